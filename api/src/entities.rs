@@ -1,7 +1,17 @@
 mod answer;
+mod challenge;
+mod privacy;
 mod question;
 mod survey;
+mod survey_event;
+mod user_status;
+mod username;
 
 pub use answer::*;
+pub use challenge::*;
+pub use privacy::*;
 pub use question::*;
 pub use survey::*;
+pub use survey_event::*;
+pub use user_status::*;
+pub use username::*;
@@ -0,0 +1,105 @@
+use sha2::{Digest, Sha256};
+
+/// Hashcash-style proof-of-work challenge a client solves before it may submit an answer.
+///
+/// A solution is a stamp `<nonce>:<counter>` whose SHA-256 hash starts with at least `difficulty` zero bits.
+/// Finding one takes about `2^difficulty` hashes, checking it takes one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Challenge {
+    pub nonce: String,
+    /// Number of leading zero bits the hash of the stamp must have.
+    pub difficulty: u8,
+    /// Unix time in seconds after which a stamp for this challenge is no longer accepted.
+    pub expires_at: u64,
+}
+
+impl Challenge {
+    /// Searches for a stamp solving this challenge.
+    #[must_use]
+    pub fn solve(&self) -> String {
+        let mut counter: u64 = 0;
+        loop {
+            let stamp = format!("{}:{counter}", self.nonce);
+            if self.is_solved_by(&stamp) {
+                return stamp;
+            }
+            counter += 1;
+        }
+    }
+
+    #[must_use]
+    pub fn is_solved_by(&self, stamp: &str) -> bool {
+        stamp_nonce(stamp) == Some(self.nonce.as_str()) && leading_zero_bits(stamp) >= u32::from(self.difficulty)
+    }
+}
+
+/// Nonce of the challenge a stamp claims to solve.
+#[must_use]
+pub fn stamp_nonce(stamp: &str) -> Option<&str> {
+    stamp.split_once(':').map(|(nonce, _counter)| nonce)
+}
+
+fn leading_zero_bits(stamp: &str) -> u32 {
+    let hash = Sha256::digest(stamp.as_bytes());
+    let mut bits = 0;
+    for byte in hash {
+        bits += byte.leading_zeros();
+        if byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn challenge(difficulty: u8) -> Challenge {
+        Challenge {
+            nonce: "5eed".to_string(),
+            difficulty,
+            expires_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_solved_stamp_is_accepted() {
+        let challenge = challenge(8);
+        let stamp = challenge.solve();
+
+        assert!(stamp.starts_with("5eed:"));
+        assert!(challenge.is_solved_by(&stamp));
+    }
+
+    #[test]
+    fn test_stamp_of_other_nonce_is_rejected() {
+        let stamp = challenge(0).solve();
+        let other = Challenge {
+            nonce: "beef".to_string(),
+            ..challenge(0)
+        };
+
+        assert!(!other.is_solved_by(&stamp));
+    }
+
+    #[test]
+    fn test_stamp_with_too_few_zero_bits_is_rejected() {
+        let challenge = challenge(12);
+        // SHA-256("5eed:0") starts with 0x83, so it has no leading zero bits.
+        let weak = "5eed:0";
+
+        assert!(!challenge.is_solved_by(weak));
+        assert!(!challenge.is_solved_by("5eed"));
+    }
+
+    #[test]
+    fn test_leading_zero_bits_counts_across_bytes() {
+        // SHA-256("5eed:7582") starts with 0x00 0x05.
+        assert_eq!(leading_zero_bits("5eed:7582"), 13);
+        // SHA-256("5eed:0") starts with 0x83.
+        assert_eq!(leading_zero_bits("5eed:0"), 0);
+    }
+}
@@ -0,0 +1,13 @@
+/// Who sees the individual answers of a survey.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum Privacy {
+    /// Answers are shown with the username of who gave them.
+    #[default]
+    Public,
+    /// Answers are shown with an alias that is the same for all answers of a user, but does not reveal the user.
+    Pseudonymous,
+    /// Only statistics of the answers are shown.
+    AggregateOnly,
+}
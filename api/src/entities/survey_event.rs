@@ -0,0 +1,15 @@
+use crate::Answer;
+
+/// Change to a survey, pushed to clients that show its results.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum SurveyEvent {
+    AnswerAdded {
+        question_id: String,
+        user: String,
+        answer: Answer,
+    },
+    /// The outcome of the question became known.
+    QuestionResolved { question_id: String, answer: Answer },
+}
@@ -0,0 +1,12 @@
+/// What is known about a username before a user signs in or answers with it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UserStatus {
+    /// The name has an account, a registered device or answers.
+    pub exists: bool,
+    /// The device asking is one of the registered devices of the name.
+    pub this_device: bool,
+    /// Surveys of which every question was answered under the name.
+    pub completed_surveys: Vec<u64>,
+}
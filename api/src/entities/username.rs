@@ -0,0 +1,160 @@
+use std::fmt;
+
+use unicase::UniCase;
+use unicode_normalization::UnicodeNormalization as _;
+use unicode_normalization::char::is_combining_mark;
+
+/// Name a user answers under, in the form usernames are stored and compared in.
+///
+/// Parsing trims the name, applies full Unicode case folding and NFC, so "Sam", "sam " and "SAM" are the same
+/// user, and so are "STRASSE" and "Straße".
+///
+/// It lives in `prono_api` rather than `prono`, as the web build of the app checks names before sending them and
+/// cannot depend on `prono`, which brings the database and Tokio along.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Username(String);
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum UsernameError {
+    #[error("Username must not be empty")]
+    Empty,
+
+    #[error("Username must not exceed {} characters", Username::MAX_LEN)]
+    TooLong,
+
+    #[error("Username must not contain {0:?}")]
+    InvalidCharacter(char),
+}
+
+impl Username {
+    /// Maximum number of characters of the folded name, surrounding whitespace excluded.
+    pub const MAX_LEN: usize = 64;
+
+    /// Characters allowed besides letters, digits and combining marks.
+    const PUNCTUATION: [char; 5] = [' ', '.', '-', '_', '\''];
+
+    /// # Errors
+    ///
+    /// Returns an error if the trimmed name is empty, longer than [`Self::MAX_LEN`] characters once folded, or
+    /// contains anything but letters, digits, combining marks, spaces and `.-_'`.
+    pub fn parse(raw: &str) -> Result<Self, UsernameError> {
+        let name: String = raw.trim().nfd().collect();
+        if name.is_empty() {
+            return Err(UsernameError::Empty);
+        }
+        if let Some(c) = name
+            .nfc()
+            .find(|&c| !(c.is_alphanumeric() || is_combining_mark(c) || Self::PUNCTUATION.contains(&c)))
+        {
+            return Err(UsernameError::InvalidCharacter(c));
+        }
+        // Folding the decomposed name, so that folding applies to accents on their own too.
+        let folded: String = UniCase::unicode(name).to_folded_case().nfc().collect();
+        if folded.chars().count() > Self::MAX_LEN {
+            return Err(UsernameError::TooLong);
+        }
+        Ok(Self(folded))
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Username {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::str::FromStr for Username {
+    type Err = UsernameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl From<Username> for String {
+    fn from(username: Username) -> Self {
+        username.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spacing_and_case_do_not_tell_users_apart() {
+        let sam = Username::parse("Sam").unwrap();
+        assert_eq!(sam.as_str(), "sam");
+        assert_eq!(Username::parse(" sam ").unwrap(), sam);
+        assert_eq!(Username::parse("SAM").unwrap(), sam);
+        assert_ne!(Username::parse("Sam J.").unwrap(), sam);
+    }
+
+    #[test]
+    fn test_case_is_folded_fully() {
+        let strasse = Username::parse("STRASSE").unwrap();
+        assert_eq!(Username::parse("Straße").unwrap(), strasse);
+        assert_eq!(Username::parse("STRAẞE").unwrap(), strasse);
+        assert_eq!(strasse.as_str(), "strasse");
+        assert_eq!(
+            Username::parse("ΟΔΥΣΣΕΥΣ").unwrap(),
+            Username::parse("Οδυσσευς").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_length_counts_the_folded_name() {
+        let half = "ß".repeat(Username::MAX_LEN / 2);
+        assert_eq!(
+            Username::parse(&half).unwrap().as_str(),
+            "ss".repeat(Username::MAX_LEN / 2)
+        );
+        assert_eq!(Username::parse(&format!("{half}ß")), Err(UsernameError::TooLong));
+    }
+
+    #[test]
+    fn test_composed_and_decomposed_accents_are_the_same_user() {
+        let composed = Username::parse("Ren\u{e9}").unwrap();
+        let decomposed = Username::parse("Rene\u{301}").unwrap();
+        assert_eq!(composed, decomposed);
+        assert_eq!(composed.as_str(), "ren\u{e9}");
+    }
+
+    #[test]
+    fn test_names_in_other_scripts_are_accepted() {
+        assert!(Username::parse("Zoë O'Neil-Smith").is_ok());
+        assert!(Username::parse("Δημήτρης").is_ok());
+        assert!(Username::parse("हिन्दी").is_ok());
+        assert!(Username::parse("山田_太郎").is_ok());
+    }
+
+    #[test]
+    fn test_invalid_names_are_rejected() {
+        assert_eq!(Username::parse(" \t "), Err(UsernameError::Empty));
+        assert_eq!(
+            Username::parse(&"x".repeat(Username::MAX_LEN + 1)),
+            Err(UsernameError::TooLong)
+        );
+        assert!(Username::parse(&format!("  {}  ", "x".repeat(Username::MAX_LEN))).is_ok());
+        assert_eq!(Username::parse("sam@home"), Err(UsernameError::InvalidCharacter('@')));
+        assert_eq!(Username::parse("sam\tj"), Err(UsernameError::InvalidCharacter('\t')));
+        assert_eq!(Username::parse("sam/../x"), Err(UsernameError::InvalidCharacter('/')));
+    }
+
+    #[test]
+    fn test_error_messages() {
+        assert_eq!(
+            UsernameError::TooLong.to_string(),
+            "Username must not exceed 64 characters"
+        );
+        assert_eq!(
+            UsernameError::InvalidCharacter('@').to_string(),
+            "Username must not contain '@'"
+        );
+    }
+}
@@ -1,5 +1,7 @@
 mod entities;
 mod ports;
+#[cfg(feature = "serde")]
+pub mod v1;
 
 pub use entities::*;
 pub use ports::*;
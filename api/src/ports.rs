@@ -1,5 +1,9 @@
+mod auth;
+mod errors;
 mod identity;
 mod surveys;
 
+pub use auth::*;
+pub use errors::*;
 pub use identity::*;
 pub use surveys::*;
@@ -0,0 +1,54 @@
+use crate::Error;
+
+/// Sign-in state of the user of the app.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum SessionState {
+    #[default]
+    SignedOut,
+    /// A login or registration request is on its way.
+    Pending,
+    SignedIn {
+        user: String,
+    },
+    Failed(Error),
+}
+
+impl SessionState {
+    #[must_use]
+    pub fn user(&self) -> Option<&str> {
+        match self {
+            SessionState::SignedIn { user } => Some(user),
+            _ => None,
+        }
+    }
+}
+
+/// Account of the user answering the survey.
+///
+/// Requests don't block, their outcome is polled with [`Auth::session`].
+#[mockall::automock]
+pub trait Auth {
+    fn register(&mut self, user: &str, password: &str);
+    fn login(&mut self, user: &str, password: &str);
+    fn logout(&mut self);
+    fn session(&self) -> SessionState;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_signed_in_state_has_user() {
+        assert_eq!(
+            SessionState::SignedIn {
+                user: "sam".to_string()
+            }
+            .user(),
+            Some("sam")
+        );
+        assert_eq!(SessionState::SignedOut.user(), None);
+        assert_eq!(SessionState::Pending.user(), None);
+        assert_eq!(SessionState::Failed(Error::InvalidCredentials).user(), None);
+    }
+}
@@ -0,0 +1,207 @@
+/// Machine-readable error codes returned by the backend REST API.
+/// The serialized names are part of the API contract and must not change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ErrorCode {
+    AnswerExists,
+    DeviceMismatch,
+    Unauthorized,
+    Forbidden,
+    InvalidCredentials,
+    UserExists,
+    ChallengeFailed,
+    SurveyClosed,
+    InvalidRequest,
+    NotFound,
+    PayloadTooLarge,
+    RateLimited,
+    UnsupportedVersion,
+    Unavailable,
+    Repository,
+    Config,
+    /// A failure inside the backend other than of the database, e.g. of its random number generator.
+    Internal,
+    /// A code introduced by a newer backend that this client does not know yet.
+    #[cfg_attr(feature = "serde", serde(other))]
+    Unknown,
+}
+
+/// JSON body of every error response of the backend REST API.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    pub message: String,
+    pub details: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Error {
+    #[error("Answer already exists")]
+    AnswerExists,
+
+    #[error("Device mismatch: username is registered to a different device")]
+    DeviceMismatch,
+
+    #[error("Survey is closed")]
+    SurveyClosed,
+
+    #[error("Not signed in or session expired")]
+    Unauthorized,
+
+    #[error("Not allowed: {0}")]
+    Forbidden(String),
+
+    #[error("Invalid username or password")]
+    InvalidCredentials,
+
+    #[error("Username is already taken")]
+    UserExists,
+
+    #[error("Proof of work rejected: {0}")]
+    ChallengeFailed(String),
+
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Too many requests: {0}")]
+    RateLimited(String),
+
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("Server error: {0}")]
+    Server(String),
+
+    #[error("Connection error: {0}")]
+    Transport(String),
+}
+
+impl From<ErrorBody> for Error {
+    fn from(body: ErrorBody) -> Self {
+        let message = match body.details {
+            Some(details) => format!("{}: {details}", body.message),
+            None => body.message,
+        };
+        match body.code {
+            ErrorCode::AnswerExists => Error::AnswerExists,
+            ErrorCode::DeviceMismatch => Error::DeviceMismatch,
+            ErrorCode::SurveyClosed => Error::SurveyClosed,
+            ErrorCode::Unauthorized => Error::Unauthorized,
+            ErrorCode::Forbidden => Error::Forbidden(message),
+            ErrorCode::InvalidCredentials => Error::InvalidCredentials,
+            ErrorCode::UserExists => Error::UserExists,
+            ErrorCode::ChallengeFailed => Error::ChallengeFailed(message),
+            ErrorCode::RateLimited => Error::RateLimited(message),
+            ErrorCode::Unavailable => Error::Unavailable(message),
+            ErrorCode::NotFound => Error::NotFound(message),
+            ErrorCode::InvalidRequest | ErrorCode::PayloadTooLarge | ErrorCode::UnsupportedVersion => {
+                Error::InvalidRequest(message)
+            }
+            ErrorCode::Repository | ErrorCode::Config | ErrorCode::Internal | ErrorCode::Unknown => {
+                Error::Server(message)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(code: ErrorCode, details: Option<&str>) -> ErrorBody {
+        ErrorBody {
+            code,
+            message: "message".to_string(),
+            details: details.map(ToString::to_string),
+        }
+    }
+
+    #[test]
+    fn test_typed_codes_map_to_their_error() {
+        assert_eq!(Error::from(body(ErrorCode::AnswerExists, None)), Error::AnswerExists);
+        assert_eq!(
+            Error::from(body(ErrorCode::DeviceMismatch, None)),
+            Error::DeviceMismatch
+        );
+        assert_eq!(Error::from(body(ErrorCode::SurveyClosed, None)), Error::SurveyClosed);
+        assert_eq!(Error::from(body(ErrorCode::Unauthorized, None)), Error::Unauthorized);
+        assert_eq!(
+            Error::from(body(ErrorCode::InvalidCredentials, None)),
+            Error::InvalidCredentials
+        );
+        assert_eq!(Error::from(body(ErrorCode::UserExists, None)), Error::UserExists);
+        assert_eq!(
+            Error::from(body(ErrorCode::Forbidden, None)),
+            Error::Forbidden("message".to_string())
+        );
+        assert_eq!(
+            Error::from(body(ErrorCode::ChallengeFailed, Some("expired"))),
+            Error::ChallengeFailed("message: expired".to_string())
+        );
+        assert_eq!(
+            Error::from(body(ErrorCode::RateLimited, Some("retry after 5 seconds"))),
+            Error::RateLimited("message: retry after 5 seconds".to_string())
+        );
+        assert_eq!(
+            Error::from(body(ErrorCode::Unavailable, Some("connecting"))),
+            Error::Unavailable("message: connecting".to_string())
+        );
+        assert_eq!(
+            Error::from(body(ErrorCode::NotFound, Some("unknown survey"))),
+            Error::NotFound("message: unknown survey".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invalid_request_keeps_message_and_details() {
+        assert_eq!(
+            Error::from(body(ErrorCode::InvalidRequest, Some("missing field `user`"))),
+            Error::InvalidRequest("message: missing field `user`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_server_side_and_unknown_codes_map_to_server_error() {
+        assert_eq!(
+            Error::from(body(ErrorCode::Repository, Some("db down"))),
+            Error::Server("message: db down".to_string())
+        );
+        assert_eq!(
+            Error::from(body(ErrorCode::Config, None)),
+            Error::Server("message".to_string())
+        );
+        assert_eq!(
+            Error::from(body(ErrorCode::Internal, None)),
+            Error::Server("message".to_string())
+        );
+        assert_eq!(
+            Error::from(body(ErrorCode::Unknown, None)),
+            Error::Server("message".to_string())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_error_body_json_shape() {
+        let json = serde_json::to_value(body(ErrorCode::AnswerExists, None)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"code": "answer_exists", "message": "message", "details": null})
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_unknown_code_deserializes_as_unknown() {
+        let body: ErrorBody =
+            serde_json::from_str(r#"{"code": "brand_new_code", "message": "m", "details": null}"#).unwrap();
+        assert_eq!(body.code, ErrorCode::Unknown);
+    }
+}
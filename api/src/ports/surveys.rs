@@ -1,9 +1,24 @@
-use crate::{Answer, Survey};
+use crate::{Answer, Error, Privacy, Survey, SurveyEvent, UserStatus};
 
 #[mockall::automock]
 pub trait Surveys {
     fn empty_survey(&self) -> Survey;
+    /// Submits the answer in the background, a failure is reported by [`Self::take_errors`].
     fn add_answer(&mut self, user: &str, question_id: String, answer: Answer);
     fn response(&self, user: &str, survey_id: u64) -> Option<Survey>;
     fn all_answers(&self, question_id: String) -> Vec<(String, Answer)>;
+    /// Who sees the individual answers of the survey. With [`Privacy::Pseudonymous`], [`Self::all_answers`] and
+    /// the events carry aliases instead of usernames.
+    fn privacy(&self, survey_id: u64) -> Privacy;
+    /// Starts looking up the username in the background, dropping the outcome of a previous lookup.
+    fn refresh_user_status(&mut self, user: &str);
+    /// Outcome of the last lookup of the username, `None` before it is refreshed or while the lookup runs. Callers
+    /// ask again on a later frame, and refresh again to retry after an error.
+    fn user_status(&self, user: &str) -> Option<Result<UserStatus, Error>>;
+    /// Events of the survey since the previous call, the first call subscribes to them.
+    /// The same answer may be reported more than once, e.g. after a reconnect.
+    fn take_events(&mut self, survey_id: u64) -> Vec<SurveyEvent>;
+    /// Failures of background requests since the previous call, e.g. an answer refused with
+    /// [`Error::DeviceMismatch`].
+    fn take_errors(&mut self) -> Vec<Error>;
 }
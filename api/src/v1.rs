@@ -0,0 +1,924 @@
+//! Wire format of version 1 of the backend REST API.
+//!
+//! These types are the JSON contract with deployed clients and are deliberately kept separate from the
+//! entities, so that the entities can evolve without breaking clients. Changing the serialized shape of
+//! any type in here requires a new API version.
+
+/// Value of the version header of this API version.
+pub static API_VERSION: &str = "1";
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::Survey))]
+pub struct Survey {
+    pub id: u64,
+    pub description: String,
+    pub questions: Vec<Question>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::Question))]
+pub struct Question {
+    pub id: String,
+    pub text: Option<String>,
+    /// Answer of the user, or an empty answer telling which kind of answer the question expects.
+    pub answer: Answer,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::Answer))]
+pub enum Answer {
+    Text { text: String },
+    PredictionDate { day: Option<u8>, month: u8, year: u16 },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::AddAnswerRequest))]
+pub struct AddAnswerRequest {
+    pub user: String,
+    pub question_id: String,
+    pub answer: Answer,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::UserAnswer))]
+pub struct UserAnswer {
+    pub user: String,
+    pub answer: Answer,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::Credentials))]
+pub struct Credentials {
+    pub user: String,
+    pub password: String,
+}
+
+/// Session issued on login or registration. The token is sent as `Authorization: Bearer <token>`.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::Session))]
+pub struct Session {
+    pub token: String,
+    pub user: String,
+    /// Unix time in seconds after which the token is no longer accepted.
+    pub expires_at: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::Device))]
+pub struct Device {
+    pub id: String,
+    /// Unix time in seconds at which the device first submitted an answer.
+    pub registered_at: u64,
+}
+
+/// One-time code moving the registration of the device that requested it to the device that redeems it.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::TransferCode))]
+pub struct TransferCode {
+    pub code: String,
+    /// Unix time in seconds after which the code is no longer accepted.
+    pub expires_at: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::CompleteTransferRequest))]
+pub struct CompleteTransferRequest {
+    pub code: String,
+}
+
+/// Result of removing all devices of a user through the admin API.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::DevicesReset))]
+pub struct DevicesReset {
+    pub removed: usize,
+}
+
+/// New name of a user renamed through the admin API.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::RenameUserRequest))]
+pub struct RenameUserRequest {
+    pub new: String,
+}
+
+/// User that another user is merged into through the admin API.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::MergeUserRequest))]
+pub struct MergeUserRequest {
+    pub into: String,
+}
+
+/// A user stored under another spelling than its normalized name, as found by the admin API.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::NormalizedUser))]
+pub struct NormalizedUser {
+    pub name: String,
+    /// Normalized name the user was merged into, `null` if it was left as it is.
+    pub merged_into: Option<String>,
+    /// Why the user was left as it is.
+    pub error: Option<String>,
+}
+
+/// An answer of a user, as listed by the answers export of the admin API.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::AnswerRecord))]
+pub struct AnswerRecord {
+    pub user: String,
+    pub question_id: String,
+    pub answer: Answer,
+    /// Unix time in seconds at which the answer was given, `null` for answers stored before it was recorded.
+    pub answered_at: Option<u64>,
+}
+
+/// Who sees the individual answers of a survey.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::Privacy))]
+pub enum Privacy {
+    /// Answers are listed with usernames.
+    Public,
+    /// Answers are listed with an alias per user instead of the username.
+    Pseudonymous,
+    /// Only the statistics of the answers are shared.
+    AggregateOnly,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::SurveyPrivacy))]
+pub struct SurveyPrivacy {
+    pub privacy: Privacy,
+}
+
+/// What is known about a username, as seen from the device asking.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::UserStatus))]
+pub struct UserStatus {
+    /// The name has an account, a registered device or answers.
+    pub exists: bool,
+    /// The `X-Device-Id` of the request is registered for the name.
+    pub this_device: bool,
+    /// Ids of the surveys of which the name answered every question.
+    pub completed_surveys: Vec<u64>,
+}
+
+/// Aggregated answers to every question of a survey, without usernames.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::SurveyStats))]
+pub struct SurveyStats {
+    pub survey_id: u64,
+    /// Statistics per question, in the order of the questions of the survey.
+    pub questions: Vec<QuestionStats>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::QuestionStats))]
+pub struct QuestionStats {
+    pub question_id: String,
+    /// Number of answers of any kind.
+    pub answers: usize,
+    /// `null` when no answer is a date, or when too few users answered to hide their individual predictions.
+    pub dates: Option<DateStats>,
+}
+
+/// Statistics of predicted dates, as `YYYY-MM-DD`. Predictions without a day count as the 15th of their month.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::DateStats))]
+pub struct DateStats {
+    pub median: String,
+    pub mean: String,
+    pub first_quartile: String,
+    pub third_quartile: String,
+    pub earliest: String,
+    pub latest: String,
+    /// Predictions per month, in order, for every month with a prediction.
+    pub histogram: Vec<MonthBucket>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::MonthBucket))]
+pub struct MonthBucket {
+    pub year: i32,
+    /// 1 to 12.
+    pub month: u32,
+    pub count: usize,
+}
+
+/// Message of the survey event stream.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::SurveyEvent))]
+pub enum SurveyEvent {
+    AnswerAdded {
+        question_id: String,
+        user: String,
+        answer: Answer,
+    },
+    QuestionResolved {
+        question_id: String,
+        answer: Answer,
+    },
+}
+
+/// Message a client sends over the `/api/v1/ws` WebSocket. The backend answers every request with a
+/// [`WsMessage`] carrying the same `id`, in the order the requests were sent.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::WsRequest))]
+pub enum WsRequest {
+    /// Signs the connection in, taking the place of the `Authorization` and `X-Device-Id` headers.
+    Authenticate {
+        id: u64,
+        token: String,
+        device_id: String,
+    },
+    Challenge {
+        id: u64,
+    },
+    EmptySurvey {
+        id: u64,
+    },
+    /// Requires an authenticated connection and a stamp solving a challenge.
+    AddAnswer {
+        id: u64,
+        answer: AddAnswerRequest,
+        proof_of_work: String,
+    },
+    Response {
+        id: u64,
+        user: String,
+        survey_id: u64,
+    },
+    AllAnswers {
+        id: u64,
+        question_id: String,
+    },
+    /// Pushes the events of the survey as [`WsMessage::Event`] from now on.
+    Subscribe {
+        id: u64,
+        survey_id: u64,
+    },
+}
+
+impl WsRequest {
+    #[must_use]
+    pub fn id(&self) -> u64 {
+        match self {
+            WsRequest::Authenticate { id, .. }
+            | WsRequest::Challenge { id }
+            | WsRequest::EmptySurvey { id }
+            | WsRequest::AddAnswer { id, .. }
+            | WsRequest::Response { id, .. }
+            | WsRequest::AllAnswers { id, .. }
+            | WsRequest::Subscribe { id, .. } => *id,
+        }
+    }
+}
+
+/// Message the backend sends over the `/api/v1/ws` WebSocket: the reply to a [`WsRequest`] or a pushed event.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = v1::WsMessage))]
+pub enum WsMessage {
+    /// Reply to a request without a result, e.g. a stored answer.
+    Done {
+        id: u64,
+    },
+    Challenge {
+        id: u64,
+        challenge: crate::Challenge,
+    },
+    /// Reply to `empty_survey` and `response`, without survey if the user has no response.
+    Survey {
+        id: u64,
+        survey: Option<Survey>,
+    },
+    Answers {
+        id: u64,
+        answers: Vec<UserAnswer>,
+    },
+    /// The request failed, `id` is missing if the request could not be read.
+    Error {
+        id: Option<u64>,
+        error: crate::ErrorBody,
+    },
+    Event {
+        event: SurveyEvent,
+    },
+}
+
+impl From<crate::Survey> for Survey {
+    fn from(survey: crate::Survey) -> Self {
+        Self {
+            id: survey.id,
+            description: survey.description,
+            questions: survey.questions.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<Survey> for crate::Survey {
+    fn from(survey: Survey) -> Self {
+        Self {
+            id: survey.id,
+            description: survey.description,
+            questions: survey.questions.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<crate::Privacy> for Privacy {
+    fn from(privacy: crate::Privacy) -> Self {
+        match privacy {
+            crate::Privacy::Public => Privacy::Public,
+            crate::Privacy::Pseudonymous => Privacy::Pseudonymous,
+            crate::Privacy::AggregateOnly => Privacy::AggregateOnly,
+        }
+    }
+}
+
+impl From<Privacy> for crate::Privacy {
+    fn from(privacy: Privacy) -> Self {
+        match privacy {
+            Privacy::Public => crate::Privacy::Public,
+            Privacy::Pseudonymous => crate::Privacy::Pseudonymous,
+            Privacy::AggregateOnly => crate::Privacy::AggregateOnly,
+        }
+    }
+}
+
+impl From<crate::UserStatus> for UserStatus {
+    fn from(status: crate::UserStatus) -> Self {
+        Self {
+            exists: status.exists,
+            this_device: status.this_device,
+            completed_surveys: status.completed_surveys,
+        }
+    }
+}
+
+impl From<UserStatus> for crate::UserStatus {
+    fn from(status: UserStatus) -> Self {
+        Self {
+            exists: status.exists,
+            this_device: status.this_device,
+            completed_surveys: status.completed_surveys,
+        }
+    }
+}
+
+impl From<crate::Question> for Question {
+    fn from(question: crate::Question) -> Self {
+        Self {
+            id: question.id,
+            text: question.text,
+            answer: question.answer.into(),
+        }
+    }
+}
+
+impl From<Question> for crate::Question {
+    fn from(question: Question) -> Self {
+        Self {
+            id: question.id,
+            answer: question.answer.into(),
+            text: question.text,
+        }
+    }
+}
+
+impl From<crate::Answer> for Answer {
+    fn from(answer: crate::Answer) -> Self {
+        match answer {
+            crate::Answer::Text(text) => Answer::Text { text },
+            crate::Answer::PredictionDate { day, month, year } => Answer::PredictionDate { day, month, year },
+        }
+    }
+}
+
+impl From<Answer> for crate::Answer {
+    fn from(answer: Answer) -> Self {
+        match answer {
+            Answer::Text { text } => crate::Answer::Text(text),
+            Answer::PredictionDate { day, month, year } => crate::Answer::PredictionDate { day, month, year },
+        }
+    }
+}
+
+impl From<(String, crate::Answer)> for UserAnswer {
+    fn from((user, answer): (String, crate::Answer)) -> Self {
+        Self {
+            user,
+            answer: answer.into(),
+        }
+    }
+}
+
+impl From<UserAnswer> for (String, crate::Answer) {
+    fn from(user_answer: UserAnswer) -> Self {
+        (user_answer.user, user_answer.answer.into())
+    }
+}
+
+impl From<crate::SurveyEvent> for SurveyEvent {
+    fn from(event: crate::SurveyEvent) -> Self {
+        match event {
+            crate::SurveyEvent::AnswerAdded {
+                question_id,
+                user,
+                answer,
+            } => SurveyEvent::AnswerAdded {
+                question_id,
+                user,
+                answer: answer.into(),
+            },
+            crate::SurveyEvent::QuestionResolved { question_id, answer } => SurveyEvent::QuestionResolved {
+                question_id,
+                answer: answer.into(),
+            },
+        }
+    }
+}
+
+impl From<SurveyEvent> for crate::SurveyEvent {
+    fn from(event: SurveyEvent) -> Self {
+        match event {
+            SurveyEvent::AnswerAdded {
+                question_id,
+                user,
+                answer,
+            } => crate::SurveyEvent::AnswerAdded {
+                question_id,
+                user,
+                answer: answer.into(),
+            },
+            SurveyEvent::QuestionResolved { question_id, answer } => crate::SurveyEvent::QuestionResolved {
+                question_id,
+                answer: answer.into(),
+            },
+        }
+    }
+}
+
+// Contract tests: the JSON below is what deployed clients send and expect, do not adapt it to code changes.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    fn round_trip<T>(value: &T, expected: &serde_json::Value)
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+    {
+        assert_eq!(&serde_json::to_value(value).unwrap(), expected);
+        assert_eq!(&serde_json::from_value::<T>(expected.clone()).unwrap(), value);
+    }
+
+    #[test]
+    fn test_text_answer_shape() {
+        round_trip(
+            &Answer::Text {
+                text: "Mars".to_string(),
+            },
+            &json!({"type": "text", "text": "Mars"}),
+        );
+    }
+
+    #[test]
+    fn test_prediction_date_answer_shape() {
+        round_trip(
+            &Answer::PredictionDate {
+                day: None,
+                month: 3,
+                year: 2027,
+            },
+            &json!({"type": "prediction_date", "day": null, "month": 3, "year": 2027}),
+        );
+    }
+
+    #[test]
+    fn test_survey_shape() {
+        round_trip(
+            &Survey {
+                id: 1,
+                description: "Starship".to_string(),
+                questions: vec![Question {
+                    id: "q1".to_string(),
+                    text: Some("When?".to_string()),
+                    answer: Answer::PredictionDate {
+                        day: Some(1),
+                        month: 1,
+                        year: 2030,
+                    },
+                }],
+            },
+            &json!({
+                "id": 1,
+                "description": "Starship",
+                "questions": [{
+                    "id": "q1",
+                    "text": "When?",
+                    "answer": {"type": "prediction_date", "day": 1, "month": 1, "year": 2030}
+                }]
+            }),
+        );
+    }
+
+    #[test]
+    fn test_add_answer_request_shape() {
+        round_trip(
+            &AddAnswerRequest {
+                user: "sam".to_string(),
+                question_id: "q1".to_string(),
+                answer: Answer::Text {
+                    text: "yes".to_string(),
+                },
+            },
+            &json!({"user": "sam", "question_id": "q1", "answer": {"type": "text", "text": "yes"}}),
+        );
+    }
+
+    #[test]
+    fn test_user_answer_shape() {
+        round_trip(
+            &UserAnswer {
+                user: "sam".to_string(),
+                answer: Answer::Text {
+                    text: "yes".to_string(),
+                },
+            },
+            &json!({"user": "sam", "answer": {"type": "text", "text": "yes"}}),
+        );
+    }
+
+    #[test]
+    fn test_credentials_shape() {
+        round_trip(
+            &Credentials {
+                user: "sam".to_string(),
+                password: "secret".to_string(),
+            },
+            &json!({"user": "sam", "password": "secret"}),
+        );
+    }
+
+    #[test]
+    fn test_session_shape() {
+        round_trip(
+            &Session {
+                token: "abc".to_string(),
+                user: "sam".to_string(),
+                expires_at: 1_900_000_000,
+            },
+            &json!({"token": "abc", "user": "sam", "expires_at": 1_900_000_000}),
+        );
+    }
+
+    #[test]
+    fn test_survey_privacy_shape() {
+        round_trip(
+            &SurveyPrivacy {
+                privacy: Privacy::AggregateOnly,
+            },
+            &json!({"privacy": "aggregate_only"}),
+        );
+        round_trip(&Privacy::Pseudonymous, &json!("pseudonymous"));
+        round_trip(&Privacy::Public, &json!("public"));
+    }
+
+    #[test]
+    fn test_user_status_shape() {
+        round_trip(
+            &UserStatus {
+                exists: true,
+                this_device: false,
+                completed_surveys: vec![1],
+            },
+            &json!({"exists": true, "this_device": false, "completed_surveys": [1]}),
+        );
+    }
+
+    #[test]
+    fn test_survey_stats_shape() {
+        round_trip(
+            &SurveyStats {
+                survey_id: 1,
+                questions: vec![
+                    QuestionStats {
+                        question_id: "q1".to_string(),
+                        answers: 2,
+                        dates: Some(DateStats {
+                            median: "2030-01-23".to_string(),
+                            mean: "2030-01-23".to_string(),
+                            first_quartile: "2030-01-08".to_string(),
+                            third_quartile: "2030-02-07".to_string(),
+                            earliest: "2029-12-24".to_string(),
+                            latest: "2030-02-22".to_string(),
+                            histogram: vec![
+                                MonthBucket {
+                                    year: 2029,
+                                    month: 12,
+                                    count: 1,
+                                },
+                                MonthBucket {
+                                    year: 2030,
+                                    month: 1,
+                                    count: 0,
+                                },
+                                MonthBucket {
+                                    year: 2030,
+                                    month: 2,
+                                    count: 1,
+                                },
+                            ],
+                        }),
+                    },
+                    QuestionStats {
+                        question_id: "q2".to_string(),
+                        answers: 0,
+                        dates: None,
+                    },
+                ],
+            },
+            &json!({
+                "survey_id": 1,
+                "questions": [
+                    {
+                        "question_id": "q1",
+                        "answers": 2,
+                        "dates": {
+                            "median": "2030-01-23",
+                            "mean": "2030-01-23",
+                            "first_quartile": "2030-01-08",
+                            "third_quartile": "2030-02-07",
+                            "earliest": "2029-12-24",
+                            "latest": "2030-02-22",
+                            "histogram": [
+                                {"year": 2029, "month": 12, "count": 1},
+                                {"year": 2030, "month": 1, "count": 0},
+                                {"year": 2030, "month": 2, "count": 1},
+                            ],
+                        },
+                    },
+                    {"question_id": "q2", "answers": 0, "dates": null},
+                ],
+            }),
+        );
+    }
+
+    #[test]
+    fn test_device_shape() {
+        round_trip(
+            &Device {
+                id: "5d1c".to_string(),
+                registered_at: 1_800_000_000,
+            },
+            &json!({"id": "5d1c", "registered_at": 1_800_000_000}),
+        );
+    }
+
+    #[test]
+    fn test_transfer_shapes() {
+        round_trip(
+            &TransferCode {
+                code: "K7QM2X9P".to_string(),
+                expires_at: 1_800_000_600,
+            },
+            &json!({"code": "K7QM2X9P", "expires_at": 1_800_000_600}),
+        );
+        round_trip(
+            &CompleteTransferRequest {
+                code: "K7QM2X9P".to_string(),
+            },
+            &json!({"code": "K7QM2X9P"}),
+        );
+    }
+
+    #[test]
+    fn test_admin_shapes() {
+        round_trip(&DevicesReset { removed: 2 }, &json!({"removed": 2}));
+        round_trip(
+            &AnswerRecord {
+                user: "sam".to_string(),
+                question_id: "q1".to_string(),
+                answer: Answer::PredictionDate {
+                    day: None,
+                    month: 5,
+                    year: 2030,
+                },
+                answered_at: Some(1_700_000_000),
+            },
+            &json!({
+                "user": "sam",
+                "question_id": "q1",
+                "answer": {"type": "prediction_date", "day": null, "month": 5, "year": 2030},
+                "answered_at": 1_700_000_000
+            }),
+        );
+    }
+
+    #[test]
+    fn test_survey_event_shapes() {
+        round_trip(
+            &SurveyEvent::AnswerAdded {
+                question_id: "q1".to_string(),
+                user: "sam".to_string(),
+                answer: Answer::Text {
+                    text: "yes".to_string(),
+                },
+            },
+            &json!({
+                "type": "answer_added",
+                "question_id": "q1",
+                "user": "sam",
+                "answer": {"type": "text", "text": "yes"}
+            }),
+        );
+        round_trip(
+            &SurveyEvent::QuestionResolved {
+                question_id: "q1".to_string(),
+                answer: Answer::PredictionDate {
+                    day: Some(3),
+                    month: 4,
+                    year: 2029,
+                },
+            },
+            &json!({
+                "type": "question_resolved",
+                "question_id": "q1",
+                "answer": {"type": "prediction_date", "day": 3, "month": 4, "year": 2029}
+            }),
+        );
+    }
+
+    #[test]
+    fn test_ws_request_shapes() {
+        round_trip(
+            &WsRequest::Authenticate {
+                id: 1,
+                token: "3q2-7w".to_string(),
+                device_id: "laptop".to_string(),
+            },
+            &json!({"op": "authenticate", "id": 1, "token": "3q2-7w", "device_id": "laptop"}),
+        );
+        round_trip(&WsRequest::Challenge { id: 2 }, &json!({"op": "challenge", "id": 2}));
+        round_trip(
+            &WsRequest::EmptySurvey { id: 3 },
+            &json!({"op": "empty_survey", "id": 3}),
+        );
+        round_trip(
+            &WsRequest::AddAnswer {
+                id: 4,
+                answer: AddAnswerRequest {
+                    user: "sam".to_string(),
+                    question_id: "q1".to_string(),
+                    answer: Answer::Text {
+                        text: "yes".to_string(),
+                    },
+                },
+                proof_of_work: "5eed:7582".to_string(),
+            },
+            &json!({
+                "op": "add_answer",
+                "id": 4,
+                "answer": {"user": "sam", "question_id": "q1", "answer": {"type": "text", "text": "yes"}},
+                "proof_of_work": "5eed:7582"
+            }),
+        );
+        round_trip(
+            &WsRequest::Response {
+                id: 5,
+                user: "sam".to_string(),
+                survey_id: 42,
+            },
+            &json!({"op": "response", "id": 5, "user": "sam", "survey_id": 42}),
+        );
+        round_trip(
+            &WsRequest::AllAnswers {
+                id: 6,
+                question_id: "q1".to_string(),
+            },
+            &json!({"op": "all_answers", "id": 6, "question_id": "q1"}),
+        );
+        round_trip(
+            &WsRequest::Subscribe { id: 7, survey_id: 42 },
+            &json!({"op": "subscribe", "id": 7, "survey_id": 42}),
+        );
+    }
+
+    #[test]
+    fn test_ws_message_shapes() {
+        round_trip(&WsMessage::Done { id: 1 }, &json!({"type": "done", "id": 1}));
+        round_trip(
+            &WsMessage::Challenge {
+                id: 2,
+                challenge: crate::Challenge {
+                    nonce: "5eed".to_string(),
+                    difficulty: 13,
+                    expires_at: 1_800_000_000,
+                },
+            },
+            &json!({
+                "type": "challenge",
+                "id": 2,
+                "challenge": {"nonce": "5eed", "difficulty": 13, "expires_at": 1_800_000_000}
+            }),
+        );
+        round_trip(
+            &WsMessage::Survey { id: 5, survey: None },
+            &json!({"type": "survey", "id": 5, "survey": null}),
+        );
+        round_trip(
+            &WsMessage::Answers {
+                id: 6,
+                answers: vec![UserAnswer {
+                    user: "sam".to_string(),
+                    answer: Answer::Text {
+                        text: "yes".to_string(),
+                    },
+                }],
+            },
+            &json!({
+                "type": "answers",
+                "id": 6,
+                "answers": [{"user": "sam", "answer": {"type": "text", "text": "yes"}}]
+            }),
+        );
+        round_trip(
+            &WsMessage::Error {
+                id: None,
+                error: crate::ErrorBody {
+                    code: crate::ErrorCode::Unauthorized,
+                    message: "Unauthorized".to_string(),
+                    details: None,
+                },
+            },
+            &json!({
+                "type": "error",
+                "id": null,
+                "error": {"code": "unauthorized", "message": "Unauthorized", "details": null}
+            }),
+        );
+        round_trip(
+            &WsMessage::Event {
+                event: SurveyEvent::QuestionResolved {
+                    question_id: "q1".to_string(),
+                    answer: Answer::Text {
+                        text: "yes".to_string(),
+                    },
+                },
+            },
+            &json!({
+                "type": "event",
+                "event": {"type": "question_resolved", "question_id": "q1", "answer": {"type": "text", "text": "yes"}}
+            }),
+        );
+    }
+
+    #[test]
+    fn test_unknown_answer_type_is_rejected() {
+        let result = serde_json::from_value::<Answer>(json!({"type": "multiple_choice", "choice": 1}));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_entity_conversion_round_trip() {
+        let answer = crate::Answer::PredictionDate {
+            day: Some(2),
+            month: 5,
+            year: 2028,
+        };
+        assert_eq!(crate::Answer::from(Answer::from(answer.clone())), answer);
+
+        let user_answer = UserAnswer::from(("sam".to_string(), crate::Answer::Text("x".to_string())));
+        assert_eq!(
+            <(String, crate::Answer)>::from(user_answer),
+            ("sam".to_string(), crate::Answer::Text("x".to_string()))
+        );
+    }
+}
@@ -1,9 +1,10 @@
 use log::debug;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Duration;
 
 use super::{error_overlay, footer, survey_ui, timeline};
-use crate::{Answer, SurveyState};
+use crate::{Answer, Survey, SurveyState};
 
 #[derive(Default, Deserialize, Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
@@ -13,17 +14,53 @@ pub struct App {
     #[serde(skip)]
     prono: Option<Box<dyn prono_api::Surveys>>,
     #[serde(skip)]
+    auth: Option<Box<dyn prono_api::Auth>>,
+    #[serde(skip)]
+    password: String,
+    #[serde(skip)]
     error_message: Option<String>,
-    /// Cached answers fetched once when survey is completed.
+    /// Cached answers fetched once when survey is completed, then kept up to date by survey events.
     #[serde(skip)]
     cached_answers: HashMap<String /*question_id*/, Vec<(String, Answer)>>,
+    /// Outcomes of resolved questions, announced by survey events.
+    #[serde(skip)]
+    resolved_answers: HashMap<String /*question_id*/, Answer>,
+    /// Who sees the answers of the completed survey, read once on submit.
+    #[serde(skip)]
+    privacy: prono_api::Privacy,
+    /// Lookup of the name being typed, until its status is known.
+    #[serde(skip)]
+    typed_name: Option<NameLookup>,
+    /// Name whose status is known, with the warning to show while it is entered.
+    #[serde(skip)]
+    checked_name: Option<(String, Option<&'static str>)>,
 }
 
+/// Status lookup of a typed name, started once typing paused and repeated after failures.
+struct NameLookup {
+    name: String,
+    /// Time in seconds at which to start the next lookup.
+    due_at: f64,
+    /// Whether the lookup started at `due_at` is running.
+    started: bool,
+    /// Delay before the next lookup if this one fails, doubled on every failure.
+    retry_delay: Duration,
+}
+
+/// Interval at which a completed survey checks for events while the user is idle.
+const EVENT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Pause in typing after which the status of the typed name is looked up, and interval at which the lookup is
+/// checked for an answer.
+const USERNAME_CHECK_DELAY: Duration = Duration::from_millis(500);
+/// Longest wait before looking up a name again after failed lookups.
+const USERNAME_RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+
 impl App {
     /// Called once before the first frame.
     pub fn new(
         cc: &eframe::CreationContext<'_>,
         prono: impl prono_api::Surveys + 'static,
+        auth: impl prono_api::Auth + 'static,
         initial_error: Option<String>,
     ) -> Self {
         // Load previous app state (if any).
@@ -33,10 +70,100 @@ impl App {
             Self::default()
         };
         app.prono = Some(Box::new(prono));
+        app.auth = Some(Box::new(auth));
         app.error_message = initial_error;
         app
     }
 
+    /// Session of the user, apps without authentication treat the entered name as signed in.
+    fn session(&self) -> prono_api::SessionState {
+        match &self.auth {
+            Some(auth) => auth.session(),
+            None => prono_api::SessionState::SignedIn {
+                user: self.user_name.clone(),
+            },
+        }
+    }
+
+    fn handle_login_action(&mut self, action: survey_ui::LoginAction) {
+        let Some(auth) = self.auth.as_mut() else {
+            return;
+        };
+        match action {
+            survey_ui::LoginAction::Login => auth.login(self.user_name.trim(), &self.password),
+            survey_ui::LoginAction::Register => auth.register(self.user_name.trim(), &self.password),
+            survey_ui::LoginAction::Logout => {
+                auth.logout();
+                self.user_name.clear();
+                self.survey_state = SurveyState::NotStarted;
+                self.cached_answers.clear();
+                self.resolved_answers.clear();
+                // The name may have answered or registered since it was checked.
+                self.checked_name = None;
+            }
+            survey_ui::LoginAction::None => return,
+        }
+        self.password.clear();
+    }
+
+    /// Looks up the status of the entered name once typing paused for [`USERNAME_CHECK_DELAY`], and again with a
+    /// growing delay after failed lookups. `now` is the time of the frame in seconds. Returns when to check again,
+    /// `None` when there is nothing to wait for.
+    fn check_username(&mut self, now: f64) -> Option<Duration> {
+        let name = self.user_name.trim();
+        if prono_api::Username::parse(name).is_err()
+            || self.checked_name.as_ref().is_some_and(|(checked, _)| checked == name)
+        {
+            self.typed_name = None;
+            return None;
+        }
+        if self.typed_name.as_ref().is_none_or(|lookup| lookup.name != name) {
+            self.typed_name = Some(NameLookup {
+                name: name.to_string(),
+                due_at: now + USERNAME_CHECK_DELAY.as_secs_f64(),
+                started: false,
+                retry_delay: USERNAME_CHECK_DELAY,
+            });
+        }
+        let lookup = self.typed_name.as_mut()?;
+        let prono = self.prono.as_mut()?;
+        if !lookup.started {
+            let remaining = Duration::from_secs_f64((lookup.due_at - now).max(0.0));
+            if !remaining.is_zero() {
+                return Some(remaining);
+            }
+            prono.refresh_user_status(&lookup.name);
+            lookup.started = true;
+        }
+        let Some(outcome) = prono.user_status(&lookup.name) else {
+            return Some(USERNAME_CHECK_DELAY);
+        };
+        match outcome {
+            Ok(status) => {
+                let warning = survey_ui::username_warning(&status, prono.empty_survey().id);
+                self.checked_name = Some((lookup.name.clone(), warning));
+                self.typed_name = None;
+                None
+            }
+            Err(e) => {
+                debug!("Looking up {} again in {:?}: {e}", lookup.name, lookup.retry_delay);
+                lookup.due_at = now + lookup.retry_delay.as_secs_f64();
+                lookup.started = false;
+                let delay = lookup.retry_delay;
+                lookup.retry_delay = (delay * 2).min(USERNAME_RETRY_MAX_DELAY);
+                Some(delay)
+            }
+        }
+    }
+
+    /// Warning about the entered name, once its status is known.
+    fn username_warning(&self) -> Option<&'static str> {
+        self.checked_name
+            .as_ref()
+            .filter(|(checked, _)| checked == self.user_name.trim())
+            .and_then(|(_, warning)| *warning)
+    }
+
     fn submit(&mut self) {
         let survey = match std::mem::replace(&mut self.survey_state, SurveyState::NotStarted) {
             SurveyState::InProgress(s) => s,
@@ -57,6 +184,11 @@ impl App {
         }
 
         self.cached_answers.clear();
+        self.privacy = prono.privacy(survey.id);
+        if self.privacy == prono_api::Privacy::AggregateOnly {
+            self.survey_state = SurveyState::Completed(survey);
+            return;
+        }
         for question in &survey.questions {
             let all_answers = prono.all_answers(question.id.clone());
             let converted: Vec<(String, Answer)> = all_answers
@@ -75,30 +207,71 @@ impl App {
                 survey.empty();
             }
             SurveyState::Completed(_) => {
-                self.user_name.clear();
+                if self.auth.is_none() {
+                    self.user_name.clear();
+                }
                 self.survey_state = SurveyState::NotStarted;
                 self.cached_answers.clear();
+                self.resolved_answers.clear();
+                // The name may have answered or registered since it was checked.
+                self.checked_name = None;
             }
             SurveyState::NotStarted => {}
         }
     }
 
+    /// Shows the failures of background requests, e.g. of answers the backend refused on submit.
+    fn receive_errors(&mut self) {
+        let Some(prono) = self.prono.as_mut() else {
+            return;
+        };
+        if let Some(e) = prono.take_errors().into_iter().next() {
+            self.error_message = Some(e.to_string());
+        }
+    }
+
+    /// Applies the events of the completed survey to the cached answers.
+    fn receive_events(&mut self) {
+        let (SurveyState::Completed(survey), Some(prono)) = (&self.survey_state, self.prono.as_mut()) else {
+            return;
+        };
+        for event in prono.take_events(survey.id) {
+            match event {
+                prono_api::SurveyEvent::AnswerAdded {
+                    question_id,
+                    user,
+                    answer,
+                } => {
+                    let answers = self.cached_answers.entry(question_id).or_default();
+                    if !answers.iter().any(|(known_user, _)| *known_user == user) {
+                        answers.push((user, answer.into()));
+                    }
+                }
+                prono_api::SurveyEvent::QuestionResolved { question_id, answer } => {
+                    self.resolved_answers.insert(question_id, answer.into());
+                }
+            }
+        }
+    }
+
     fn draw_timeline_from_answers(&self, ui: &mut egui::Ui) {
         ui.spacing();
         ui.separator();
 
         match &self.survey_state {
-            SurveyState::InProgress(survey) => {
-                ui.label("Timeline of your predictions");
-                let answers = survey.questions.iter().map(|q| (None, q.answer.clone())).collect();
-                let timeline_dates = timeline::extract_and_sort_dates(answers);
-                timeline::draw(ui, &timeline_dates);
+            SurveyState::InProgress(survey) => draw_own_predictions(ui, survey),
+            SurveyState::Completed(survey) if self.privacy == prono_api::Privacy::AggregateOnly => {
+                ui.label("Only statistics of the answers to this survey are shared");
+                draw_own_predictions(ui, survey);
             }
             SurveyState::Completed(survey) => {
                 ui.label("All answers");
                 egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
                     for question in &survey.questions {
                         ui.heading(&question.text);
+                        if let Some(resolved) = self.resolved_answers.get(&question.id) {
+                            ui.label(format!("Resolved: {resolved}"));
+                        }
 
                         // Use cached answers instead of querying database every frame
                         if let Some(cached) = self.cached_answers.get(&question.id) {
@@ -125,6 +298,13 @@ impl App {
     }
 }
 
+fn draw_own_predictions(ui: &mut egui::Ui, survey: &Survey) {
+    ui.label("Timeline of your predictions");
+    let answers = survey.questions.iter().map(|q| (None, q.answer.clone())).collect();
+    let timeline_dates = timeline::extract_and_sort_dates(answers);
+    timeline::draw(ui, &timeline_dates);
+}
+
 impl eframe::App for App {
     /// Called by the frame work to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
@@ -152,30 +332,64 @@ impl eframe::App for App {
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("Prono");
 
-            let action = ui
+            let session = self.session();
+            if let Some(user) = session.user() {
+                user.clone_into(&mut self.user_name);
+            }
+            let entering_name = matches!(self.survey_state, SurveyState::NotStarted)
+                && (self.auth.is_none()
+                    || matches!(
+                        session,
+                        prono_api::SessionState::SignedOut | prono_api::SessionState::Failed(_)
+                    ));
+            if entering_name && let Some(delay) = self.check_username(ctx.input(|i| i.time)) {
+                ctx.request_repaint_after(delay);
+            }
+            let username_warning = self.username_warning();
+
+            let (action, login_action) = ui
                 .horizontal(|ui| match &self.survey_state {
-                    SurveyState::NotStarted => {
-                        survey_ui::render_username_input(ui, &mut self.user_name);
-                        survey_ui::SurveyAction::None
-                    }
-                    SurveyState::InProgress(_) | SurveyState::Completed(_) => {
-                        survey_ui::render_survey_controls(ui, &self.survey_state)
+                    SurveyState::NotStarted if self.auth.is_none() => {
+                        survey_ui::render_username_input(ui, &mut self.user_name, username_warning);
+                        (survey_ui::SurveyAction::None, survey_ui::LoginAction::None)
                     }
+                    SurveyState::NotStarted => (
+                        survey_ui::SurveyAction::None,
+                        survey_ui::render_login(
+                            ui,
+                            &session,
+                            &mut self.user_name,
+                            username_warning,
+                            &mut self.password,
+                        ),
+                    ),
+                    SurveyState::InProgress(_) | SurveyState::Completed(_) => (
+                        survey_ui::render_survey_controls(ui, &self.survey_state),
+                        survey_ui::LoginAction::None,
+                    ),
                 })
                 .inner;
 
+            self.handle_login_action(login_action);
             match action {
                 survey_ui::SurveyAction::Reset => self.reset_survey(),
                 survey_ui::SurveyAction::Submit => self.submit(),
                 survey_ui::SurveyAction::None => {}
             }
+            self.receive_errors();
+            if matches!(self.survey_state, SurveyState::Completed(_)) {
+                self.receive_events();
+                ctx.request_repaint_after(EVENT_CHECK_INTERVAL);
+            }
 
-            survey_ui::render_survey_content(
-                ui,
-                &mut self.survey_state,
-                self.prono.as_deref(),
-                &mut self.error_message,
-            );
+            if session.user().is_some() || !matches!(self.survey_state, SurveyState::NotStarted) {
+                survey_ui::render_survey_content(
+                    ui,
+                    &mut self.survey_state,
+                    self.prono.as_deref(),
+                    &mut self.error_message,
+                );
+            }
 
             self.draw_timeline_from_answers(ui);
 
@@ -193,7 +407,7 @@ impl eframe::App for App {
 
 #[cfg(test)]
 mod tests {
-    use prono_api::MockSurveys;
+    use prono_api::{MockAuth, MockSurveys};
 
     use crate::{Question, Survey};
 
@@ -216,6 +430,7 @@ mod tests {
             })
             .return_const(());
 
+        mock_surveys.expect_privacy().return_const(prono_api::Privacy::Public);
         mock_surveys.expect_all_answers().returning(|_| {
             vec![(
                 "user1".to_string(),
@@ -239,6 +454,88 @@ mod tests {
         assert!(app.cached_answers.contains_key("q1"));
     }
 
+    #[test]
+    fn submit_does_not_fetch_answers_of_aggregate_only_survey() {
+        let mut mock_surveys = MockSurveys::new();
+        mock_surveys.expect_add_answer().return_const(());
+        mock_surveys
+            .expect_privacy()
+            .withf(|survey_id| *survey_id == 1)
+            .return_const(prono_api::Privacy::AggregateOnly);
+        mock_surveys.expect_all_answers().never();
+
+        let mut app = make_app(mock_surveys);
+        app.survey_state = SurveyState::InProgress(Survey {
+            id: 1,
+            description: "Test survey".to_string(),
+            questions: vec![Question {
+                id: "q1".to_string(),
+                text: "When will the next launch be?".to_string(),
+                answer: Answer::Text("sometime in 2025".to_string()),
+            }],
+        });
+
+        app.submit();
+        assert!(matches!(app.survey_state, SurveyState::Completed(_)));
+        assert!(app.cached_answers.is_empty());
+    }
+
+    #[test]
+    fn events_update_answers_of_completed_survey() {
+        let mut mock_surveys = MockSurveys::new();
+        mock_surveys
+            .expect_take_events()
+            .withf(|survey_id| *survey_id == 1)
+            .returning(|_| {
+                vec![
+                    prono_api::SurveyEvent::AnswerAdded {
+                        question_id: "q1".to_string(),
+                        user: "user1".to_string(),
+                        answer: prono_api::Answer::Text("again".to_owned()),
+                    },
+                    prono_api::SurveyEvent::AnswerAdded {
+                        question_id: "q1".to_string(),
+                        user: "user2".to_string(),
+                        answer: prono_api::Answer::Text("later".to_owned()),
+                    },
+                    prono_api::SurveyEvent::QuestionResolved {
+                        question_id: "q1".to_string(),
+                        answer: prono_api::Answer::Text("now".to_owned()),
+                    },
+                ]
+            });
+
+        let mut app = make_app(mock_surveys);
+        app.survey_state = SurveyState::Completed(Survey {
+            id: 1,
+            ..Survey::default()
+        });
+        app.cached_answers.insert(
+            "q1".to_string(),
+            vec![("user1".to_string(), Answer::Text("sometime in 2025".to_string()))],
+        );
+
+        app.receive_events();
+        assert_eq!(
+            app.cached_answers["q1"],
+            [
+                ("user1".to_string(), Answer::Text("sometime in 2025".to_string())),
+                ("user2".to_string(), Answer::Text("later".to_string())),
+            ]
+        );
+        assert_eq!(app.resolved_answers["q1"], Answer::Text("now".to_string()));
+    }
+
+    #[test]
+    fn events_are_not_taken_before_survey_is_completed() {
+        let mut mock_surveys = MockSurveys::new();
+        mock_surveys.expect_take_events().never();
+
+        let mut app = make_app(mock_surveys);
+        app.receive_events();
+        assert!(app.cached_answers.is_empty());
+    }
+
     #[test]
     fn submit_without_adapter_sets_error_message() {
         let mut app = App {
@@ -258,4 +555,198 @@ mod tests {
         assert!(app.error_message.is_some());
         assert!(matches!(app.survey_state, SurveyState::InProgress(_)));
     }
+
+    #[test]
+    fn login_action_sends_trimmed_name_and_forgets_password() {
+        let mut mock_auth = MockAuth::new();
+        mock_auth
+            .expect_login()
+            .withf(|user, password| user == "sam" && password == "long enough")
+            .times(1)
+            .return_const(());
+
+        let mut app = App {
+            auth: Some(Box::new(mock_auth)),
+            user_name: " sam ".to_string(),
+            password: "long enough".to_string(),
+            ..App::default()
+        };
+
+        app.handle_login_action(survey_ui::LoginAction::Login);
+        assert!(app.password.is_empty());
+    }
+
+    #[test]
+    fn logout_action_returns_to_start() {
+        let mut mock_auth = MockAuth::new();
+        mock_auth.expect_logout().times(1).return_const(());
+
+        let mut app = App {
+            auth: Some(Box::new(mock_auth)),
+            user_name: "sam".to_string(),
+            survey_state: SurveyState::Completed(Survey::default()),
+            ..App::default()
+        };
+
+        app.handle_login_action(survey_ui::LoginAction::Logout);
+        assert!(app.user_name.is_empty());
+        assert!(matches!(app.survey_state, SurveyState::NotStarted));
+    }
+
+    #[test]
+    fn app_without_auth_treats_entered_name_as_signed_in() {
+        let app = App {
+            user_name: "sam".to_string(),
+            ..App::default()
+        };
+        assert_eq!(app.session().user(), Some("sam"));
+    }
+
+    fn surveys_knowing(status: prono_api::UserStatus) -> MockSurveys {
+        let mut mock_surveys = MockSurveys::new();
+        mock_surveys.expect_empty_survey().returning(|| prono_api::Survey {
+            id: 1,
+            description: "Test survey".to_string(),
+            questions: Vec::new(),
+        });
+        mock_surveys
+            .expect_refresh_user_status()
+            .withf(|user| user == "sam")
+            .times(1)
+            .return_const(());
+        mock_surveys
+            .expect_user_status()
+            .withf(|user| user == "sam")
+            .times(1)
+            .return_const(Some(Ok(status)));
+        mock_surveys
+    }
+
+    #[test]
+    fn username_is_looked_up_once_typing_pauses() {
+        let mut app = make_app(surveys_knowing(prono_api::UserStatus {
+            exists: true,
+            this_device: false,
+            completed_surveys: Vec::new(),
+        }));
+
+        app.user_name = "sa".to_string();
+        assert_eq!(app.check_username(0.0), Some(USERNAME_CHECK_DELAY));
+        app.user_name = "sam ".to_string();
+        assert!(app.check_username(0.4).is_some());
+        assert!(app.check_username(0.8).is_some());
+        assert_eq!(app.username_warning(), None);
+
+        assert_eq!(app.check_username(1.0), None);
+        assert!(app.username_warning().unwrap().starts_with("This name is taken"));
+        assert_eq!(app.check_username(2.0), None);
+    }
+
+    #[test]
+    fn completed_survey_is_warned_about_on_own_device() {
+        let mut app = make_app(surveys_knowing(prono_api::UserStatus {
+            exists: true,
+            this_device: true,
+            completed_surveys: vec![1],
+        }));
+        app.user_name = "sam".to_string();
+
+        app.check_username(0.0);
+        app.check_username(1.0);
+
+        assert_eq!(app.username_warning(), Some("This name already filled in the survey"));
+        app.user_name = "samuel".to_string();
+        assert_eq!(app.username_warning(), None);
+    }
+
+    /// Surveys answering the lookups of "sam" with `outcomes`, one per refresh.
+    fn surveys_answering(outcomes: Vec<Result<prono_api::UserStatus, prono_api::Error>>) -> MockSurveys {
+        let mut mock_surveys = MockSurveys::new();
+        mock_surveys.expect_empty_survey().returning(|| prono_api::Survey {
+            id: 1,
+            description: "Test survey".to_string(),
+            questions: Vec::new(),
+        });
+        mock_surveys
+            .expect_refresh_user_status()
+            .withf(|user| user == "sam")
+            .times(outcomes.len())
+            .return_const(());
+        let mut outcomes = outcomes.into_iter();
+        mock_surveys
+            .expect_user_status()
+            .withf(|user| user == "sam")
+            .returning(move |_| outcomes.next());
+        mock_surveys
+    }
+
+    #[test]
+    fn failed_lookup_is_retried_with_growing_delay() {
+        let unavailable = || Err(prono_api::Error::Transport("offline".to_string()));
+        let mut app = make_app(surveys_answering(vec![
+            unavailable(),
+            unavailable(),
+            Ok(prono_api::UserStatus::default()),
+        ]));
+        app.user_name = "sam".to_string();
+
+        assert_eq!(app.check_username(0.0), Some(USERNAME_CHECK_DELAY));
+        assert_eq!(app.check_username(0.5), Some(USERNAME_CHECK_DELAY));
+        assert!(app.check_username(0.8).is_some());
+        assert_eq!(app.check_username(1.0), Some(USERNAME_CHECK_DELAY * 2));
+        assert_eq!(app.check_username(1.5), Some(USERNAME_CHECK_DELAY));
+        assert_eq!(app.username_warning(), None);
+
+        assert_eq!(app.check_username(2.0), None);
+        assert_eq!(app.check_username(3.0), None);
+    }
+
+    #[test]
+    fn name_is_looked_up_again_after_reset() {
+        let taken = prono_api::UserStatus {
+            exists: true,
+            ..prono_api::UserStatus::default()
+        };
+        let mut app = App {
+            auth: Some(Box::new(MockAuth::new())),
+            ..make_app(surveys_answering(vec![Ok(prono_api::UserStatus::default()), Ok(taken)]))
+        };
+        app.user_name = "sam".to_string();
+        app.check_username(0.0);
+        assert_eq!(app.check_username(0.5), None);
+        assert_eq!(app.username_warning(), None);
+
+        app.survey_state = SurveyState::Completed(Survey::default());
+        app.reset_survey();
+
+        assert_eq!(app.check_username(1.0), Some(USERNAME_CHECK_DELAY));
+        assert_eq!(app.check_username(1.5), None);
+        assert!(app.username_warning().unwrap().starts_with("This name is taken"));
+    }
+
+    #[test]
+    fn refused_answers_are_shown() {
+        let mut mock_surveys = MockSurveys::new();
+        mock_surveys
+            .expect_take_errors()
+            .times(1)
+            .return_const(vec![prono_api::Error::DeviceMismatch, prono_api::Error::DeviceMismatch]);
+
+        let mut app = make_app(mock_surveys);
+        app.receive_errors();
+
+        assert_eq!(app.error_message, Some(prono_api::Error::DeviceMismatch.to_string()));
+    }
+
+    #[test]
+    fn invalid_username_is_not_looked_up() {
+        let mut mock_surveys = MockSurveys::new();
+        mock_surveys.expect_refresh_user_status().never();
+        mock_surveys.expect_user_status().never();
+        let mut app = make_app(mock_surveys);
+        app.user_name = "sam!".to_string();
+
+        assert_eq!(app.check_username(0.0), None);
+        assert_eq!(app.check_username(1.0), None);
+    }
 }
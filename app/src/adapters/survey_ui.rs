@@ -86,7 +86,85 @@ pub fn render_survey_controls(ui: &mut egui::Ui, survey_state: &SurveyState) ->
     }
 }
 
-pub fn render_username_input(ui: &mut egui::Ui, user_name: &mut String) {
+/// Name field that tells what is wrong with the name while it is typed. `warning` is about a valid name, see
+/// [`username_warning`].
+pub fn render_username_input(ui: &mut egui::Ui, user_name: &mut String, warning: Option<&str>) {
     ui.label("Username:");
     ui.add(TextEdit::singleline(user_name).hint_text("Please fill in your name"));
+    if !user_name.is_empty()
+        && let Err(e) = prono_api::Username::parse(user_name)
+    {
+        ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+    } else if let Some(warning) = warning {
+        ui.colored_label(ui.visuals().warn_fg_color, warning);
+    }
+}
+
+/// Why answering survey `survey_id` under a name with `status` would be refused, `None` if nothing speaks against
+/// it.
+#[must_use]
+pub fn username_warning(status: &prono_api::UserStatus, survey_id: u64) -> Option<&'static str> {
+    if status.completed_surveys.contains(&survey_id) {
+        Some("This name already filled in the survey")
+    } else if status.exists && !status.this_device {
+        Some("This name is taken. If it is yours, transfer it from the device you used it on")
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum LoginAction {
+    None,
+    Login,
+    Register,
+    Logout,
+}
+
+pub fn render_login(
+    ui: &mut egui::Ui,
+    session: &prono_api::SessionState,
+    user_name: &mut String,
+    username_warning: Option<&str>,
+    password: &mut String,
+) -> LoginAction {
+    match session {
+        prono_api::SessionState::SignedIn { user } => {
+            ui.label(format!("Signed in as {user}"));
+            if ui.button("Log out").clicked() {
+                LoginAction::Logout
+            } else {
+                LoginAction::None
+            }
+        }
+        prono_api::SessionState::Pending => {
+            ui.spinner();
+            ui.label("Signing in...");
+            LoginAction::None
+        }
+        prono_api::SessionState::SignedOut | prono_api::SessionState::Failed(_) => {
+            ui.vertical(|ui| {
+                if let prono_api::SessionState::Failed(e) = session {
+                    ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+                }
+                ui.horizontal(|ui| render_username_input(ui, user_name, username_warning));
+                ui.horizontal(|ui| {
+                    ui.label("Password:");
+                    ui.add(TextEdit::singleline(password).password(true));
+                });
+                ui.horizontal(|ui| {
+                    let filled_in = !user_name.trim().is_empty() && !password.is_empty();
+                    if ui.add_enabled(filled_in, egui::Button::new("Log in")).clicked() {
+                        LoginAction::Login
+                    } else if ui.add_enabled(filled_in, egui::Button::new("Register")).clicked() {
+                        LoginAction::Register
+                    } else {
+                        LoginAction::None
+                    }
+                })
+                .inner
+            })
+            .inner
+        }
+    }
 }
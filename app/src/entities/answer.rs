@@ -20,6 +20,21 @@ impl Answer {
     }
 }
 
+/// Formats dates like the timeline labels, `dd/mm/yyyy` or `mm/yyyy`.
+impl std::fmt::Display for Answer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Answer::Text(text) => f.write_str(text),
+            Answer::PredictionDate {
+                day: Some(day),
+                month,
+                year,
+            } => write!(f, "{day:02}/{month:02}/{year}"),
+            Answer::PredictionDate { day: None, month, year } => write!(f, "{month:02}/{year}"),
+        }
+    }
+}
+
 #[cfg(test)]
 impl Default for Answer {
     fn default() -> Self {
@@ -59,6 +74,23 @@ mod tests {
         assert_eq!(answer, Answer::Text(String::new()));
     }
 
+    #[test]
+    fn test_display_formats_dates_like_timeline() {
+        let with_day = Answer::PredictionDate {
+            day: Some(3),
+            month: 4,
+            year: 2029,
+        };
+        let without_day = Answer::PredictionDate {
+            day: None,
+            month: 11,
+            year: 2030,
+        };
+        assert_eq!(with_day.to_string(), "03/04/2029");
+        assert_eq!(without_day.to_string(), "11/2030");
+        assert_eq!(Answer::Text("never".to_string()).to_string(), "never");
+    }
+
     #[test]
     fn test_empty_prediction_date_resets_to_current() {
         let mut answer = Answer::PredictionDate {
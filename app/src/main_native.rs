@@ -2,8 +2,12 @@ use eframe::AppCreator;
 use log::error;
 use prono::ReadConfig;
 
-fn build_app<'a>(prono: impl prono_api::Surveys + 'static, initial_error: Option<String>) -> AppCreator<'a> {
-    Box::new(|cc: &eframe::CreationContext<'_>| Ok(Box::new(crate::App::new(cc, prono, initial_error))))
+fn build_app<'a>(
+    prono: impl prono_api::Surveys + 'static,
+    auth: impl prono_api::Auth + 'static,
+    initial_error: Option<String>,
+) -> AppCreator<'a> {
+    Box::new(|cc: &eframe::CreationContext<'_>| Ok(Box::new(crate::App::new(cc, prono, auth, initial_error))))
 }
 
 /// # Panics
@@ -22,7 +26,8 @@ pub async fn main() -> eframe::Result {
 
     let config_reader = prono::factory::create_config_reader();
     let default_config_path = config_reader.default_config_path();
-    let db_config: prono_db::Config = config_reader.read(default_config_path).db.into();
+    let config = config_reader.read(default_config_path);
+    let db_config: prono_db::Config = config.db.into();
 
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
@@ -36,14 +41,22 @@ pub async fn main() -> eframe::Result {
         ..Default::default()
     };
 
-    let prono = match prono::SyncPronoAdapter::new_with_db_config::<prono_db::MysqlDb>(db_config).await {
-        Err(e) => {
-            error!("{e}");
-            return Ok(());
-        }
-        Ok(prono) => prono,
-    };
+    let prono =
+        match prono::SyncPronoAdapter::new_with_db_config::<prono_db::MysqlDb>(db_config, config.backend.alias_secret)
+            .await
+        {
+            Err(e) => {
+                error!("{e}");
+                return Ok(());
+            }
+            Ok(prono) => prono,
+        };
 
     let startup_warning = prono.startup_warning().map(String::from);
-    eframe::run_native("eframe template", native_options, build_app(prono, startup_warning))
+    let auth = prono.auth();
+    eframe::run_native(
+        "eframe template",
+        native_options,
+        build_app(prono, auth, startup_warning),
+    )
 }
@@ -4,67 +4,370 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 
-use log::error;
+use futures_channel::mpsc::UnboundedSender;
+use log::{debug, error};
+use prono_api::v1::{WsMessage, WsRequest};
 
 static BACKEND_URL: &str = "https://prono.samagali.myds.me";
+static SESSION_STORAGE_KEY: &str = "prono_session";
+/// Page query parameter selecting the WebSocket transport instead of REST requests.
+static WEBSOCKET_QUERY: &str = "transport=websocket";
+
+/// Login session shared by the survey and auth adapters, kept in local storage across visits.
+#[derive(Default)]
+struct WasmSession {
+    state: prono_api::SessionState,
+    token: Option<String>,
+}
+
+type SharedSession = Rc<RefCell<WasmSession>>;
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window().and_then(|w| w.local_storage().ok().flatten())
+}
+
+impl WasmSession {
+    /// Session of a previous visit. An expired token is dropped on the first request the backend rejects.
+    fn restore() -> Self {
+        local_storage()
+            .and_then(|storage| storage.get_item(SESSION_STORAGE_KEY).ok().flatten())
+            .and_then(|json| serde_json::from_str::<prono_api::v1::Session>(&json).ok())
+            .map_or_else(Self::default, |session| Self {
+                state: prono_api::SessionState::SignedIn { user: session.user },
+                token: Some(session.token),
+            })
+    }
+
+    fn sign_in(&mut self, session: prono_api::v1::Session) {
+        if let (Some(storage), Ok(json)) = (local_storage(), serde_json::to_string(&session)) {
+            let _ = storage.set_item(SESSION_STORAGE_KEY, &json);
+        }
+        self.state = prono_api::SessionState::SignedIn { user: session.user };
+        self.token = Some(session.token);
+    }
+
+    fn sign_out(&mut self) {
+        if let Some(storage) = local_storage() {
+            let _ = storage.remove_item(SESSION_STORAGE_KEY);
+        }
+        *self = Self::default();
+    }
+}
+
+/// Outcomes of the last lookup of every username, with the id of that lookup.
+#[derive(Default)]
+struct StatusLookups {
+    last_id: u64,
+    outcomes: HashMap<String, (u64, Option<Result<prono_api::UserStatus, prono_api::Error>>)>,
+}
+
+/// Statuses of usernames fetched from `/api/v1/users/{name}/status`, `None` while the request is on its way.
+#[derive(Default)]
+struct UserStatuses(Rc<RefCell<StatusLookups>>);
+
+impl UserStatuses {
+    /// Fetches the status of the username again. The reply to an earlier fetch of the name is dropped when it
+    /// arrives late.
+    fn refresh(&self, base_url: &str, device_id: &str, user: &str) {
+        let id = {
+            let mut lookups = self.0.borrow_mut();
+            lookups.last_id += 1;
+            let id = lookups.last_id;
+            lookups.outcomes.insert(user.to_string(), (id, None));
+            id
+        };
+
+        let url = format!("{base_url}/api/v1/users/{}/status", encode_path_segment(user));
+        let device_id = device_id.to_string();
+        let lookups = Rc::clone(&self.0);
+        let user = user.to_string();
+        wasm_bindgen_futures::spawn_local(async move {
+            let outcome = fetch_user_status(&url, &device_id).await;
+            if let Err(e) = &outcome {
+                error!("Failed to fetch status of username {user}: {e}");
+            }
+            if let Some((current, pending)) = lookups.borrow_mut().outcomes.get_mut(&user)
+                && *current == id
+            {
+                *pending = Some(outcome);
+            }
+        });
+    }
+
+    fn get(&self, user: &str) -> Option<Result<prono_api::UserStatus, prono_api::Error>> {
+        self.0
+            .borrow()
+            .outcomes
+            .get(user)
+            .and_then(|(_, outcome)| outcome.clone())
+    }
+}
+
+async fn fetch_user_status(url: &str, device_id: &str) -> Result<prono_api::UserStatus, prono_api::Error> {
+    let request = get(url)
+        .header("X-Device-Id", device_id)
+        .build()
+        .expect("Failed to build request");
+    let resp = send(request).await?;
+    resp.json::<prono_api::v1::UserStatus>()
+        .await
+        .map(Into::into)
+        .map_err(|e| prono_api::Error::Server(e.to_string()))
+}
+
+/// `text` with all but unreserved URL characters percent-encoded, to be used as a path segment.
+fn encode_path_segment(text: &str) -> String {
+    text.bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+                char::from(byte).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect()
+}
 
 struct ApiThroughRest {
     base_url: String,
     survey: prono_api::Survey,
+    privacy: prono_api::Privacy,
     device_id: String,
+    session: SharedSession,
+    user_statuses: UserStatuses,
+    /// Failures of submitted answers since the last `take_errors`.
+    errors: Rc<RefCell<Vec<prono_api::Error>>>,
     cached_all_answers: Rc<RefCell<HashMap<String, Vec<(String, prono_api::Answer)>>>>,
+    /// Survey events received since the last `take_events`.
+    events: Rc<RefCell<Vec<prono_api::SurveyEvent>>>,
+    event_source: Option<web_sys::EventSource>,
 }
 
 impl ApiThroughRest {
-    fn new(base_url: String, survey: prono_api::Survey, device_id: String) -> Self {
+    fn new(
+        base_url: String,
+        survey: prono_api::Survey,
+        privacy: prono_api::Privacy,
+        device_id: String,
+        session: SharedSession,
+    ) -> Self {
         Self {
             base_url,
             survey,
+            privacy,
             device_id,
+            session,
+            user_statuses: UserStatuses::default(),
+            errors: Rc::new(RefCell::new(Vec::new())),
             cached_all_answers: Rc::new(RefCell::new(HashMap::new())),
+            events: Rc::new(RefCell::new(Vec::new())),
+            event_source: None,
         }
     }
+
+    /// Opens the server-sent event stream of the survey. The browser reconnects on its own when the stream
+    /// drops, events sent in the meantime are missed.
+    fn subscribe(&self, survey_id: u64) -> Option<web_sys::EventSource> {
+        use eframe::wasm_bindgen::JsCast as _;
+        use eframe::wasm_bindgen::closure::Closure;
+
+        let url = format!("{}/api/v1/survey/{survey_id}/events", self.base_url);
+        let source = web_sys::EventSource::new(&url)
+            .map_err(|e| error!("Failed to subscribe to survey events: {e:?}"))
+            .ok()?;
+        let events = Rc::clone(&self.events);
+        let on_message = Closure::<dyn FnMut(web_sys::MessageEvent)>::new(move |message: web_sys::MessageEvent| {
+            let Some(data) = message.data().as_string() else {
+                return;
+            };
+            match serde_json::from_str::<prono_api::v1::SurveyEvent>(&data) {
+                Ok(event) => events.borrow_mut().push(event.into()),
+                Err(e) => error!("Failed to parse survey event: {e}"),
+            }
+        });
+        source.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        // The handler has to outlive this call, the event source is kept open as long as the page.
+        on_message.forget();
+        Some(source)
+    }
+}
+
+struct RestAuth {
+    base_url: String,
+    session: SharedSession,
+}
+
+impl RestAuth {
+    fn request_session(&mut self, action: &str, user: &str, password: &str) {
+        let url = format!("{}/api/v1/auth/{action}", self.base_url);
+        let credentials = prono_api::v1::Credentials {
+            user: user.to_string(),
+            password: password.to_string(),
+        };
+        let body_str = serde_json::to_string(&credentials).expect("Failed to serialize credentials");
+        self.session.borrow_mut().state = prono_api::SessionState::Pending;
+        let session = Rc::clone(&self.session);
+
+        wasm_bindgen_futures::spawn_local(async move {
+            let request = post(&url)
+                .header("Content-Type", "application/json")
+                .body(body_str)
+                .expect("Failed to build request body");
+            let result = match send(request).await {
+                Ok(resp) => resp
+                    .json::<prono_api::v1::Session>()
+                    .await
+                    .map_err(|e| prono_api::Error::Server(e.to_string())),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(issued) => session.borrow_mut().sign_in(issued),
+                Err(e) => {
+                    error!("Failed to sign in: {e}");
+                    session.borrow_mut().state = prono_api::SessionState::Failed(e);
+                }
+            }
+        });
+    }
+}
+
+impl prono_api::Auth for RestAuth {
+    fn register(&mut self, user: &str, password: &str) {
+        self.request_session("register", user, password);
+    }
+
+    fn login(&mut self, user: &str, password: &str) {
+        self.request_session("login", user, password);
+    }
+
+    fn logout(&mut self) {
+        let token = self.session.borrow().token.clone();
+        self.session.borrow_mut().sign_out();
+        let Some(token) = token else {
+            return;
+        };
+        let url = format!("{}/api/v1/auth/logout", self.base_url);
+        wasm_bindgen_futures::spawn_local(async move {
+            let request = post(&url)
+                .header("Authorization", &format!("Bearer {token}"))
+                .build()
+                .expect("Failed to build request");
+            if let Err(e) = send(request).await {
+                error!("Failed to log out: {e}");
+            }
+        });
+    }
+
+    fn session(&self) -> prono_api::SessionState {
+        self.session.borrow().state.clone()
+    }
+}
+
+/// Requests to the backend ask for the API version this client speaks.
+fn get(url: &str) -> gloo_net::http::RequestBuilder {
+    gloo_net::http::Request::get(url).header("X-Api-Version", prono_api::v1::API_VERSION)
+}
+
+fn post(url: &str) -> gloo_net::http::RequestBuilder {
+    gloo_net::http::Request::post(url).header("X-Api-Version", prono_api::v1::API_VERSION)
+}
+
+/// Sends the request and maps error responses of the backend to typed API errors.
+async fn send(request: gloo_net::http::Request) -> Result<gloo_net::http::Response, prono_api::Error> {
+    let resp = request
+        .send()
+        .await
+        .map_err(|e| prono_api::Error::Transport(e.to_string()))?;
+    if resp.ok() {
+        return Ok(resp);
+    }
+    match resp.json::<prono_api::ErrorBody>().await {
+        Ok(body) => Err(body.into()),
+        Err(e) => Err(prono_api::Error::Server(format!("HTTP {}: {e}", resp.status()))),
+    }
+}
+
+/// Fetches a proof-of-work challenge and solves it, which every answer submission requires.
+/// Solving runs on the UI thread, the backend keeps the default difficulty low enough for that.
+async fn solve_challenge(url: &str) -> Result<String, prono_api::Error> {
+    let resp = send(get(url).build().expect("Failed to build request")).await?;
+    let challenge = resp
+        .json::<prono_api::Challenge>()
+        .await
+        .map_err(|e| prono_api::Error::Server(e.to_string()))?;
+    Ok(challenge.solve())
+}
+
+fn copy_survey(survey: &prono_api::Survey) -> prono_api::Survey {
+    prono_api::Survey {
+        id: survey.id,
+        description: survey.description.clone(),
+        questions: survey
+            .questions
+            .iter()
+            .map(|q| prono_api::Question {
+                id: q.id.clone(),
+                answer: q.answer.clone(),
+                text: q.text.clone(),
+            })
+            .collect(),
+    }
 }
 
 impl prono_api::Surveys for ApiThroughRest {
     fn empty_survey(&self) -> prono_api::Survey {
-        prono_api::Survey {
-            id: self.survey.id,
-            description: self.survey.description.clone(),
-            questions: self
-                .survey
-                .questions
-                .iter()
-                .map(|q| prono_api::Question {
-                    id: q.id.clone(),
-                    answer: q.answer.clone(),
-                    text: q.text.clone(),
-                })
-                .collect(),
+        copy_survey(&self.survey)
+    }
+
+    fn privacy(&self, survey_id: u64) -> prono_api::Privacy {
+        if survey_id == self.survey.id {
+            self.privacy
+        } else {
+            prono_api::Privacy::default()
         }
     }
 
     fn add_answer(&mut self, user: &str, question_id: String, answer: prono_api::Answer) {
-        let url = format!("{}/api/survey/answer", self.base_url);
-        let body = serde_json::json!({
-            "user": user,
-            "question_id": question_id,
-            "answer": answer,
-        });
-        let body_str = body.to_string();
+        let url = format!("{}/api/v1/survey/answer", self.base_url);
+        let body = prono_api::v1::AddAnswerRequest {
+            user: user.to_string(),
+            question_id,
+            answer: answer.into(),
+        };
+        let body_str = serde_json::to_string(&body).expect("Failed to serialize answer");
         let device_id = self.device_id.clone();
+        let token = self.session.borrow().token.clone().unwrap_or_default();
+        let session = Rc::clone(&self.session);
+        let errors = Rc::clone(&self.errors);
+
+        let challenge_url = format!("{}/api/v1/challenge", self.base_url);
 
         wasm_bindgen_futures::spawn_local(async move {
-            let result = gloo_net::http::Request::post(&url)
+            let stamp = match solve_challenge(&challenge_url).await {
+                Ok(stamp) => stamp,
+                Err(e) => {
+                    error!("Failed to get a proof-of-work challenge: {e}");
+                    errors.borrow_mut().push(e);
+                    return;
+                }
+            };
+            let request = post(&url)
                 .header("Content-Type", "application/json")
+                .header("Authorization", &format!("Bearer {token}"))
                 .header("X-Device-Id", &device_id)
+                .header("X-Proof-Of-Work", &stamp)
                 .body(body_str)
-                .expect("Failed to build request body")
-                .send()
-                .await;
-            if let Err(e) = result {
+                .expect("Failed to build request body");
+            let Err(e) = send(request).await else {
+                return;
+            };
+            if e == prono_api::Error::Unauthorized {
+                error!("Session expired, please log in again");
+                session.borrow_mut().sign_out();
+            } else {
                 error!("Failed to submit answer: {e}");
             }
+            errors.borrow_mut().push(e);
         });
     }
 
@@ -79,14 +382,24 @@ impl prono_api::Surveys for ApiThroughRest {
         }
 
         // Spawn async fetch and cache the result
-        let url = format!("{}/api/survey/answers/{question_id}", self.base_url);
+        let url = format!("{}/api/v1/survey/answers/{question_id}", self.base_url);
         let cache = Rc::clone(&self.cached_all_answers);
+        let events = Rc::clone(&self.events);
         let qid = question_id.clone();
 
         wasm_bindgen_futures::spawn_local(async move {
-            match gloo_net::http::Request::get(&url).send().await {
-                Ok(resp) => match resp.json::<Vec<(String, prono_api::Answer)>>().await {
+            match send(get(&url).build().expect("Failed to build request")).await {
+                Ok(resp) => match resp.json::<Vec<prono_api::v1::UserAnswer>>().await {
                     Ok(answers) => {
+                        let answers: Vec<(String, prono_api::Answer)> = answers.into_iter().map(Into::into).collect();
+                        // Answers arriving after the caller asked are handed over like live answers.
+                        events.borrow_mut().extend(answers.iter().map(|(user, answer)| {
+                            prono_api::SurveyEvent::AnswerAdded {
+                                question_id: qid.clone(),
+                                user: user.clone(),
+                                answer: answer.clone(),
+                            }
+                        }));
                         cache.borrow_mut().insert(qid, answers);
                     }
                     Err(e) => error!("Failed to parse all_answers response: {e}"),
@@ -97,6 +410,293 @@ impl prono_api::Surveys for ApiThroughRest {
 
         Vec::new()
     }
+
+    fn refresh_user_status(&mut self, user: &str) {
+        self.user_statuses.refresh(&self.base_url, &self.device_id, user);
+    }
+
+    fn user_status(&self, user: &str) -> Option<Result<prono_api::UserStatus, prono_api::Error>> {
+        self.user_statuses.get(user)
+    }
+
+    fn take_events(&mut self, survey_id: u64) -> Vec<prono_api::SurveyEvent> {
+        if self.event_source.is_none() {
+            self.event_source = self.subscribe(survey_id);
+        }
+        std::mem::take(&mut *self.events.borrow_mut())
+    }
+
+    fn take_errors(&mut self) -> Vec<prono_api::Error> {
+        std::mem::take(&mut *self.errors.borrow_mut())
+    }
+}
+
+/// What to do with the reply to a request sent over the WebSocket.
+enum PendingReply {
+    Authenticate,
+    /// Solve the challenge and send this answer with the stamp.
+    Challenge(prono_api::v1::AddAnswerRequest),
+    Answer,
+    AllAnswers(String),
+    Subscribe,
+}
+
+/// State shared by [`ApiThroughWebSocket`] and the task reading its connection.
+#[derive(Default)]
+struct WsState {
+    next_id: u64,
+    pending: HashMap<u64, PendingReply>,
+    cached_all_answers: HashMap<String, Vec<(String, prono_api::Answer)>>,
+    events: Vec<prono_api::SurveyEvent>,
+    /// Failures reported by the backend since the last `take_errors`.
+    errors: Vec<prono_api::Error>,
+    /// Survey whose events are pushed, subscribed again after a reconnect.
+    subscribed: Option<u64>,
+}
+
+impl WsState {
+    fn register(&mut self, pending: PendingReply) -> u64 {
+        self.next_id += 1;
+        self.pending.insert(self.next_id, pending);
+        self.next_id
+    }
+
+    fn handle_message(&mut self, message: WsMessage, session: &SharedSession, requests: &UnboundedSender<WsRequest>) {
+        match message {
+            WsMessage::Challenge { id, challenge } => {
+                if let Some(PendingReply::Challenge(answer)) = self.pending.remove(&id) {
+                    let request = WsRequest::AddAnswer {
+                        id: self.register(PendingReply::Answer),
+                        answer,
+                        proof_of_work: challenge.solve(),
+                    };
+                    let _ = requests.unbounded_send(request);
+                }
+            }
+            WsMessage::Answers { id, answers } => {
+                if let Some(PendingReply::AllAnswers(question_id)) = self.pending.remove(&id) {
+                    let answers: Vec<(String, prono_api::Answer)> = answers.into_iter().map(Into::into).collect();
+                    // Answers arriving after the caller asked are handed over like live answers.
+                    self.events.extend(
+                        answers
+                            .iter()
+                            .map(|(user, answer)| prono_api::SurveyEvent::AnswerAdded {
+                                question_id: question_id.clone(),
+                                user: user.clone(),
+                                answer: answer.clone(),
+                            }),
+                    );
+                    self.cached_all_answers.insert(question_id, answers);
+                }
+            }
+            WsMessage::Done { id } => {
+                if let Some(PendingReply::Answer) = self.pending.remove(&id) {
+                    debug!("Answer stored");
+                }
+            }
+            WsMessage::Survey { id, .. } => {
+                self.pending.remove(&id);
+            }
+            WsMessage::Error { id, error } => {
+                if let Some(id) = id {
+                    self.pending.remove(&id);
+                }
+                let e = prono_api::Error::from(error);
+                if e == prono_api::Error::Unauthorized {
+                    error!("Session expired, please log in again");
+                    session.borrow_mut().sign_out();
+                } else {
+                    error!("WebSocket request failed: {e}");
+                }
+                self.errors.push(e);
+            }
+            WsMessage::Event { event } => self.events.push(event.into()),
+        }
+    }
+}
+
+/// [`prono_api::Surveys`] over a single WebSocket to `/api/v1/ws`. The backend acknowledges every request,
+/// failures are logged and reported by `take_errors`, and an expired session signs the user out. A dropped
+/// connection is opened again by the next request, requests waiting for a reply at that moment are lost.
+/// Usernames are looked up over REST.
+struct ApiThroughWebSocket {
+    base_url: String,
+    url: String,
+    survey: prono_api::Survey,
+    privacy: prono_api::Privacy,
+    device_id: String,
+    session: SharedSession,
+    user_statuses: UserStatuses,
+    state: Rc<RefCell<WsState>>,
+    requests: RefCell<Option<UnboundedSender<WsRequest>>>,
+}
+
+impl ApiThroughWebSocket {
+    fn new(
+        base_url: &str,
+        survey: prono_api::Survey,
+        privacy: prono_api::Privacy,
+        device_id: String,
+        session: SharedSession,
+    ) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            // http:// becomes ws:// and https:// becomes wss://
+            url: format!("{}/api/v1/ws", base_url.replacen("http", "ws", 1)),
+            survey,
+            privacy,
+            device_id,
+            session,
+            user_statuses: UserStatuses::default(),
+            state: Rc::new(RefCell::new(WsState::default())),
+            requests: RefCell::new(None),
+        }
+    }
+
+    fn send(&self, pending: PendingReply, make_request: impl FnOnce(u64) -> WsRequest) {
+        let request = make_request(self.state.borrow_mut().register(pending));
+        let mut requests = self.requests.borrow_mut();
+        let requests = match requests.as_ref() {
+            Some(requests) if !requests.is_closed() => requests,
+            _ => requests.insert(self.connect()),
+        };
+        let _ = requests.unbounded_send(request);
+    }
+
+    /// Opens the connection and spawns the task that writes requests to it and handles its messages.
+    fn connect(&self) -> UnboundedSender<WsRequest> {
+        use futures_util::future::{Either, select};
+        use futures_util::{SinkExt as _, StreamExt as _};
+        use gloo_net::websocket::Message;
+
+        let (requests, mut outgoing) = futures_channel::mpsc::unbounded::<WsRequest>();
+        if let Some(survey_id) = self.state.borrow().subscribed {
+            let id = self.state.borrow_mut().register(PendingReply::Subscribe);
+            let _ = requests.unbounded_send(WsRequest::Subscribe { id, survey_id });
+        }
+
+        let url = self.url.clone();
+        let state = Rc::clone(&self.state);
+        let session = Rc::clone(&self.session);
+        let replies = requests.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let socket = match gloo_net::websocket::futures::WebSocket::open(&url) {
+                Ok(socket) => socket,
+                Err(e) => {
+                    error!("Failed to open WebSocket to {url}: {e}");
+                    return;
+                }
+            };
+            let (mut write, mut read) = socket.split();
+            loop {
+                match select(outgoing.next(), read.next()).await {
+                    Either::Left((Some(request), _)) => {
+                        let json = serde_json::to_string(&request).expect("Failed to serialize request");
+                        if let Err(e) = write.send(Message::Text(json)).await {
+                            error!("Failed to send WebSocket request: {e}");
+                            break;
+                        }
+                    }
+                    Either::Right((Some(Ok(Message::Text(text))), _)) => match serde_json::from_str(&text) {
+                        Ok(message) => state.borrow_mut().handle_message(message, &session, &replies),
+                        Err(e) => error!("Failed to parse WebSocket message: {e}"),
+                    },
+                    Either::Right((Some(Ok(Message::Bytes(_))), _)) => {}
+                    Either::Right((Some(Err(e)), _)) => {
+                        error!("WebSocket connection failed: {e}");
+                        break;
+                    }
+                    Either::Left((None, _)) | Either::Right((None, _)) => break,
+                }
+            }
+            state.borrow_mut().pending.clear();
+        });
+        requests
+    }
+}
+
+impl prono_api::Surveys for ApiThroughWebSocket {
+    fn empty_survey(&self) -> prono_api::Survey {
+        copy_survey(&self.survey)
+    }
+
+    fn privacy(&self, survey_id: u64) -> prono_api::Privacy {
+        if survey_id == self.survey.id {
+            self.privacy
+        } else {
+            prono_api::Privacy::default()
+        }
+    }
+
+    fn add_answer(&mut self, user: &str, question_id: String, answer: prono_api::Answer) {
+        let token = self.session.borrow().token.clone().unwrap_or_default();
+        let device_id = self.device_id.clone();
+        self.send(PendingReply::Authenticate, |id| WsRequest::Authenticate {
+            id,
+            token,
+            device_id,
+        });
+        let answer = prono_api::v1::AddAnswerRequest {
+            user: user.to_string(),
+            question_id,
+            answer: answer.into(),
+        };
+        self.send(PendingReply::Challenge(answer), |id| WsRequest::Challenge { id });
+    }
+
+    fn response(&self, _user: &str, _id: u64) -> Option<prono_api::Survey> {
+        None
+    }
+
+    fn all_answers(&self, question_id: String) -> Vec<(String, prono_api::Answer)> {
+        if let Some(cached) = self.state.borrow().cached_all_answers.get(&question_id) {
+            return cached.clone();
+        }
+        self.send(PendingReply::AllAnswers(question_id.clone()), |id| {
+            WsRequest::AllAnswers { id, question_id }
+        });
+        Vec::new()
+    }
+
+    fn refresh_user_status(&mut self, user: &str) {
+        self.user_statuses.refresh(&self.base_url, &self.device_id, user);
+    }
+
+    fn user_status(&self, user: &str) -> Option<Result<prono_api::UserStatus, prono_api::Error>> {
+        self.user_statuses.get(user)
+    }
+
+    fn take_events(&mut self, survey_id: u64) -> Vec<prono_api::SurveyEvent> {
+        if self.state.borrow().subscribed != Some(survey_id) {
+            self.send(PendingReply::Subscribe, |id| WsRequest::Subscribe { id, survey_id });
+            self.state.borrow_mut().subscribed = Some(survey_id);
+        }
+        std::mem::take(&mut self.state.borrow_mut().events)
+    }
+
+    fn take_errors(&mut self) -> Vec<prono_api::Error> {
+        std::mem::take(&mut self.state.borrow_mut().errors)
+    }
+}
+
+/// Privacy of the survey, public when the backend cannot tell.
+async fn fetch_privacy(survey_id: u64) -> prono_api::Privacy {
+    let request = get(&format!("{BACKEND_URL}/api/v1/survey/{survey_id}/privacy"))
+        .build()
+        .expect("Failed to build request");
+    match send(request).await {
+        Ok(resp) => match resp.json::<prono_api::v1::SurveyPrivacy>().await {
+            Ok(body) => body.privacy.into(),
+            Err(e) => {
+                error!("Failed to parse survey privacy: {e}");
+                prono_api::Privacy::default()
+            }
+        },
+        Err(e) => {
+            error!("Failed to fetch survey privacy: {e}");
+            prono_api::Privacy::default()
+        }
+    }
 }
 
 /// # Panics
@@ -121,12 +721,12 @@ pub fn main() {
             .expect("the_canvas_id was not a HtmlCanvasElement");
 
         // Pre-fetch the survey from the backend before starting the app
-        let survey = match gloo_net::http::Request::get(&format!("{BACKEND_URL}/api/survey"))
-            .send()
-            .await
-        {
-            Ok(resp) => match resp.json::<prono_api::Survey>().await {
-                Ok(survey) => survey,
+        let request = get(&format!("{BACKEND_URL}/api/v1/survey"))
+            .build()
+            .expect("Failed to build request");
+        let survey: prono_api::Survey = match send(request).await {
+            Ok(resp) => match resp.json::<prono_api::v1::Survey>().await {
+                Ok(survey) => survey.into(),
                 Err(e) => {
                     error!("Failed to parse survey: {e}");
                     return;
@@ -138,17 +738,28 @@ pub fn main() {
             }
         };
 
+        // The backend enforces the privacy of the survey, the app only avoids asking for hidden answers
+        let privacy = fetch_privacy(survey.id).await;
+
         let identity = crate::adapters::identity_wasm::WasmIdentity::load_or_create();
         let device_id = prono_api::Identity::device_id(&identity).to_string();
-        let api = ApiThroughRest::new(BACKEND_URL.to_string(), survey, device_id);
-
-        let start_result = eframe::WebRunner::new()
-            .start(
-                canvas,
-                web_options,
-                Box::new(move |cc: &eframe::CreationContext<'_>| Ok(Box::new(crate::App::new(cc, api, None)))),
-            )
-            .await;
+        let session = Rc::new(RefCell::new(WasmSession::restore()));
+        let auth = RestAuth {
+            base_url: BACKEND_URL.to_string(),
+            session: Rc::clone(&session),
+        };
+        let use_websocket = web_sys::window()
+            .and_then(|w| w.location().search().ok())
+            .is_some_and(|query| query.contains(WEBSOCKET_QUERY));
+        let app_creator: eframe::AppCreator<'static> = if use_websocket {
+            let api = ApiThroughWebSocket::new(BACKEND_URL, survey, privacy, device_id, session);
+            Box::new(move |cc: &eframe::CreationContext<'_>| Ok(Box::new(crate::App::new(cc, api, auth, None))))
+        } else {
+            let api = ApiThroughRest::new(BACKEND_URL.to_string(), survey, privacy, device_id, session);
+            Box::new(move |cc: &eframe::CreationContext<'_>| Ok(Box::new(crate::App::new(cc, api, auth, None))))
+        };
+
+        let start_result = eframe::WebRunner::new().start(canvas, web_options, app_creator).await;
 
         // Remove the loading text and spinner:
         if let Some(loading_text) = document.get_element_by_id("loading_text") {
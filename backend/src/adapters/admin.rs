@@ -0,0 +1,762 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use actix_web::dev::Payload;
+use actix_web::{FromRequest, HttpRequest, HttpResponse, delete, get, post, put, web};
+use prono_api::v1;
+
+use crate::adapters::rest::{bearer_token, unknown_question, unknown_survey};
+use crate::adapters::rest_v1::device_to_v1;
+use crate::use_cases::{Admin, AdminService, repo_answer_to_api};
+use crate::{BackendResult, Error};
+
+/// Handlers taking an [`Admin`] only run for requests carrying the configured admin token.
+impl FromRequest for Admin {
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = BackendResult<Self>>>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let admin = req
+            .app_data::<web::Data<AdminService>>()
+            .ok_or_else(|| Error::Config("The admin API is not set up".to_string()))
+            .and_then(|admin| admin.authorize(bearer_token(req)));
+        Box::pin(async move { admin })
+    }
+}
+
+/// Users with answers, devices or an account.
+#[utoipa::path(
+    tag = "admin",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Usernames, sorted", body = Vec<String>),
+        (status = 401, description = "Missing or wrong admin token", body = prono_api::ErrorBody),
+        (status = 403, description = "The admin API is disabled", body = prono_api::ErrorBody),
+    )
+)]
+#[get("/api/admin/users")]
+pub async fn list_users(service: web::Data<AdminService>, admin: Admin) -> BackendResult<HttpResponse> {
+    Ok(HttpResponse::Ok().json(service.users(&admin).await?))
+}
+
+/// Deletes a user with their answers, devices, account and sessions.
+#[utoipa::path(
+    tag = "admin",
+    params(("user" = String, Path, description = "Username")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "User deleted"),
+        (status = 401, description = "Missing or wrong admin token", body = prono_api::ErrorBody),
+        (status = 403, description = "The admin API is disabled", body = prono_api::ErrorBody),
+    )
+)]
+#[delete("/api/admin/users/{user}")]
+pub async fn delete_user(
+    service: web::Data<AdminService>,
+    admin: Admin,
+    path: web::Path<String>,
+) -> BackendResult<HttpResponse> {
+    service.delete_user(&admin, &path.into_inner()).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Gives the answers, devices and account of a user to a name nobody uses yet, and signs the user out.
+#[utoipa::path(
+    tag = "admin",
+    params(("user" = String, Path, description = "Username")),
+    request_body = v1::RenameUserRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "User renamed"),
+        (status = 400, description = "A name is invalid, the user is unknown or already has the new name", body = prono_api::ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = prono_api::ErrorBody),
+        (status = 403, description = "The admin API is disabled", body = prono_api::ErrorBody),
+        (status = 409, description = "The new name already has answers, devices or an account", body = prono_api::ErrorBody),
+    )
+)]
+#[post("/api/admin/users/{user}/rename")]
+pub async fn rename_user(
+    service: web::Data<AdminService>,
+    admin: Admin,
+    path: web::Path<String>,
+    body: web::Json<v1::RenameUserRequest>,
+) -> BackendResult<HttpResponse> {
+    service.rename_user(&admin, &path.into_inner(), &body.new).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Moves the answers, devices and account of a user to another user, and signs the user out.
+#[utoipa::path(
+    tag = "admin",
+    params(("user" = String, Path, description = "Username")),
+    request_body = v1::MergeUserRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "User merged"),
+        (status = 400, description = "A name is invalid, the user is unknown or is the other user", body = prono_api::ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = prono_api::ErrorBody),
+        (status = 403, description = "The admin API is disabled", body = prono_api::ErrorBody),
+        (status = 409, description = "Nothing merged: `answer_exists` with the questions both answered in `details`, \
+            or `user_exists` when both have an account", body = prono_api::ErrorBody),
+    )
+)]
+#[post("/api/admin/users/{user}/merge")]
+pub async fn merge_user(
+    service: web::Data<AdminService>,
+    admin: Admin,
+    path: web::Path<String>,
+    body: web::Json<v1::MergeUserRequest>,
+) -> BackendResult<HttpResponse> {
+    service.merge_users(&admin, &path.into_inner(), &body.into).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Merges every user stored under another spelling than its normalized name into the normalized name.
+#[utoipa::path(
+    tag = "admin",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Users stored under another spelling, merged or left as they are", body = Vec<v1::NormalizedUser>),
+        (status = 401, description = "Missing or wrong admin token", body = prono_api::ErrorBody),
+        (status = 403, description = "The admin API is disabled", body = prono_api::ErrorBody),
+    )
+)]
+#[post("/api/admin/users/normalize")]
+pub async fn normalize_users(service: web::Data<AdminService>, admin: Admin) -> BackendResult<HttpResponse> {
+    let users: Vec<v1::NormalizedUser> = service
+        .normalize_users(&admin)
+        .await?
+        .into_iter()
+        .map(|(name, outcome)| match outcome {
+            Ok(normalized) => v1::NormalizedUser {
+                name,
+                merged_into: Some(normalized.as_str().to_string()),
+                error: None,
+            },
+            Err(e) => v1::NormalizedUser {
+                name,
+                merged_into: None,
+                error: Some(e.to_string()),
+            },
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(users))
+}
+
+/// Devices a user submitted answers from.
+#[utoipa::path(
+    tag = "admin",
+    params(("user" = String, Path, description = "Username")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Registered devices, oldest first", body = Vec<v1::Device>),
+        (status = 401, description = "Missing or wrong admin token", body = prono_api::ErrorBody),
+        (status = 403, description = "The admin API is disabled", body = prono_api::ErrorBody),
+    )
+)]
+#[get("/api/admin/users/{user}/devices")]
+pub async fn list_devices(
+    service: web::Data<AdminService>,
+    admin: Admin,
+    path: web::Path<String>,
+) -> BackendResult<HttpResponse> {
+    let devices: Vec<v1::Device> = service
+        .devices(&admin, &path.into_inner())
+        .await?
+        .into_iter()
+        .map(device_to_v1)
+        .collect();
+    Ok(HttpResponse::Ok().json(devices))
+}
+
+/// Removes all devices of a user, e.g. after they lost their only device.
+#[utoipa::path(
+    tag = "admin",
+    params(("user" = String, Path, description = "Username")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Devices removed", body = v1::DevicesReset),
+        (status = 401, description = "Missing or wrong admin token", body = prono_api::ErrorBody),
+        (status = 403, description = "The admin API is disabled", body = prono_api::ErrorBody),
+    )
+)]
+#[delete("/api/admin/users/{user}/devices")]
+pub async fn reset_devices(
+    service: web::Data<AdminService>,
+    admin: Admin,
+    path: web::Path<String>,
+) -> BackendResult<HttpResponse> {
+    let removed = service.reset_devices(&admin, &path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(v1::DevicesReset { removed }))
+}
+
+/// Records the outcome of a question and publishes it to the subscribers of the survey.
+#[utoipa::path(
+    tag = "admin",
+    request_body = v1::Answer,
+    params(("question_id" = String, Path, description = "Question id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Outcome published"),
+        (status = 401, description = "Missing or wrong admin token", body = prono_api::ErrorBody),
+        (status = 403, description = "The admin API is disabled", body = prono_api::ErrorBody),
+        (status = 404, description = "Question does not exist", body = prono_api::ErrorBody),
+    )
+)]
+#[post("/api/admin/questions/{question_id}/resolve")]
+pub async fn resolve_question(
+    service: web::Data<AdminService>,
+    admin: Admin,
+    path: web::Path<String>,
+    body: web::Json<v1::Answer>,
+) -> BackendResult<HttpResponse> {
+    let question_id = path.into_inner();
+    if service
+        .resolve_question(&admin, question_id.clone(), body.into_inner().into())
+        .await?
+    {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(unknown_question(&question_id))
+    }
+}
+
+async fn add_answer_for_user(
+    service: &AdminService,
+    admin: &Admin,
+    request: v1::AddAnswerRequest,
+    overwrite: bool,
+) -> BackendResult<HttpResponse> {
+    let v1::AddAnswerRequest {
+        user,
+        question_id,
+        answer,
+    } = request;
+    if service
+        .add_answer(admin, &user, question_id.clone(), answer.into(), overwrite)
+        .await?
+    {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(unknown_question(&question_id))
+    }
+}
+
+/// Stores an answer on behalf of a user, e.g. one given on paper, even when the survey is closed.
+#[utoipa::path(
+    tag = "admin",
+    request_body = v1::AddAnswerRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Answer stored"),
+        (status = 401, description = "Missing or wrong admin token", body = prono_api::ErrorBody),
+        (status = 403, description = "The admin API is disabled", body = prono_api::ErrorBody),
+        (status = 404, description = "Question does not exist", body = prono_api::ErrorBody),
+        (status = 409, description = "The user already answered the question", body = prono_api::ErrorBody),
+    )
+)]
+#[post("/api/admin/answers")]
+pub async fn add_answer(
+    service: web::Data<AdminService>,
+    admin: Admin,
+    body: web::Json<v1::AddAnswerRequest>,
+) -> BackendResult<HttpResponse> {
+    add_answer_for_user(&service, &admin, body.into_inner(), false).await
+}
+
+/// Stores an answer on behalf of a user, replacing their answer to the question if any.
+#[utoipa::path(
+    tag = "admin",
+    request_body = v1::AddAnswerRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Answer stored"),
+        (status = 401, description = "Missing or wrong admin token", body = prono_api::ErrorBody),
+        (status = 403, description = "The admin API is disabled", body = prono_api::ErrorBody),
+        (status = 404, description = "Question does not exist", body = prono_api::ErrorBody),
+    )
+)]
+#[put("/api/admin/answers")]
+pub async fn replace_answer(
+    service: web::Data<AdminService>,
+    admin: Admin,
+    body: web::Json<v1::AddAnswerRequest>,
+) -> BackendResult<HttpResponse> {
+    add_answer_for_user(&service, &admin, body.into_inner(), true).await
+}
+
+async fn set_survey_open(
+    service: &AdminService,
+    admin: &Admin,
+    survey_id: u64,
+    open: bool,
+) -> BackendResult<HttpResponse> {
+    if service.set_survey_open(admin, survey_id, open).await? {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(unknown_survey(survey_id))
+    }
+}
+
+/// Stops accepting answers to a survey, answers are then rejected with `409 survey_closed`.
+#[utoipa::path(
+    tag = "admin",
+    params(("survey_id" = u64, Path, description = "Survey id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Survey closed"),
+        (status = 401, description = "Missing or wrong admin token", body = prono_api::ErrorBody),
+        (status = 403, description = "The admin API is disabled", body = prono_api::ErrorBody),
+        (status = 404, description = "Survey does not exist", body = prono_api::ErrorBody),
+    )
+)]
+#[post("/api/admin/surveys/{survey_id}/close")]
+pub async fn close_survey(
+    service: web::Data<AdminService>,
+    admin: Admin,
+    path: web::Path<u64>,
+) -> BackendResult<HttpResponse> {
+    set_survey_open(&service, &admin, path.into_inner(), false).await
+}
+
+/// Accepts answers to a closed survey again.
+#[utoipa::path(
+    tag = "admin",
+    params(("survey_id" = u64, Path, description = "Survey id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Survey opened"),
+        (status = 401, description = "Missing or wrong admin token", body = prono_api::ErrorBody),
+        (status = 403, description = "The admin API is disabled", body = prono_api::ErrorBody),
+        (status = 404, description = "Survey does not exist", body = prono_api::ErrorBody),
+    )
+)]
+#[post("/api/admin/surveys/{survey_id}/open")]
+pub async fn open_survey(
+    service: web::Data<AdminService>,
+    admin: Admin,
+    path: web::Path<u64>,
+) -> BackendResult<HttpResponse> {
+    set_survey_open(&service, &admin, path.into_inner(), true).await
+}
+
+/// Changes who sees the individual answers of a survey.
+#[utoipa::path(
+    tag = "admin",
+    params(("survey_id" = u64, Path, description = "Survey id")),
+    request_body = v1::SurveyPrivacy,
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Privacy changed"),
+        (status = 401, description = "Missing or wrong admin token", body = prono_api::ErrorBody),
+        (status = 403, description = "The admin API is disabled", body = prono_api::ErrorBody),
+        (status = 404, description = "Survey does not exist", body = prono_api::ErrorBody),
+    )
+)]
+#[put("/api/admin/surveys/{survey_id}/privacy")]
+pub async fn set_privacy(
+    service: web::Data<AdminService>,
+    admin: Admin,
+    path: web::Path<u64>,
+    body: web::Json<v1::SurveyPrivacy>,
+) -> BackendResult<HttpResponse> {
+    let survey_id = path.into_inner();
+    let privacy = body.into_inner().privacy.into();
+    if service.set_survey_privacy(&admin, survey_id, privacy).await? {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(unknown_survey(survey_id))
+    }
+}
+
+/// Every answer to a survey with the time it was given, for analysis outside of the app.
+#[utoipa::path(
+    tag = "admin",
+    params(("survey_id" = u64, Path, description = "Survey id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Answers, oldest first", body = Vec<v1::AnswerRecord>),
+        (status = 401, description = "Missing or wrong admin token", body = prono_api::ErrorBody),
+        (status = 403, description = "The admin API is disabled", body = prono_api::ErrorBody),
+        (status = 404, description = "Survey does not exist", body = prono_api::ErrorBody),
+    )
+)]
+#[get("/api/admin/surveys/{survey_id}/answers")]
+pub async fn list_answers(
+    service: web::Data<AdminService>,
+    admin: Admin,
+    path: web::Path<u64>,
+) -> BackendResult<HttpResponse> {
+    let survey_id = path.into_inner();
+    let Some(records) = service.answer_records(&admin, survey_id).await? else {
+        return Err(unknown_survey(survey_id));
+    };
+    let records: Vec<v1::AnswerRecord> = records
+        .into_iter()
+        .map(|record| v1::AnswerRecord {
+            user: record.user,
+            question_id: record.question_id,
+            answer: repo_answer_to_api(record.answer).into(),
+            answered_at: record.answered_at,
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(records))
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use actix_web::App;
+    use actix_web::body::MessageBody;
+    use actix_web::dev::{Service, ServiceResponse};
+    use actix_web::http::{StatusCode, header};
+    use actix_web::test::{TestRequest, call_service, init_service, read_body_json};
+    use prono::fake_db::FakeRepo;
+    use prono::repo::{self, Db as _, DeviceRegistry as _};
+
+    use crate::use_cases::{Challenges, DeviceService, SurveyService};
+
+    const TOKEN: &str = "admin-secret";
+
+    /// App serving the admin routes, with its repository and the id of its survey.
+    async fn make_app(
+        token: Option<&str>,
+    ) -> (
+        impl Service<actix_http::Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error>,
+        Arc<FakeRepo>,
+        u64,
+    ) {
+        let db = Arc::new(FakeRepo::init(()).await.unwrap());
+        let devices = DeviceService::new(
+            db.clone(),
+            repo::DevicePolicy { max_devices: 2 },
+            Duration::from_secs(60),
+        );
+        let survey = Arc::new(SurveyService::new(
+            db.clone(),
+            db.clone(),
+            Arc::new(devices),
+            Challenges::new(4, Duration::from_secs(60)),
+        ));
+        let survey_id = survey.empty_survey().id;
+        let admin = AdminService::new(token, db.clone(), db.clone(), db.clone(), survey);
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(admin))
+                .service(list_users)
+                .service(normalize_users)
+                .service(rename_user)
+                .service(merge_user)
+                .service(list_devices)
+                .service(reset_devices)
+                .service(close_survey)
+                .service(open_survey)
+                .service(set_privacy)
+                .service(list_answers)
+                .service(add_answer)
+                .service(replace_answer),
+        )
+        .await;
+        (app, db, survey_id)
+    }
+
+    fn admin_request(req: TestRequest) -> actix_http::Request {
+        req.insert_header((header::AUTHORIZATION, format!("Bearer {TOKEN}")))
+            .to_request()
+    }
+
+    #[actix_web::test]
+    async fn test_requests_without_admin_token_are_unauthorized() {
+        let (app, _db, _survey_id) = make_app(Some(TOKEN)).await;
+
+        let resp = call_service(&app, TestRequest::get().uri("/api/admin/users").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = TestRequest::get()
+            .uri("/api/admin/users")
+            .insert_header((header::AUTHORIZATION, "Bearer guess"))
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_admin_api_is_forbidden_without_configured_token() {
+        let (app, _db, _survey_id) = make_app(None).await;
+
+        let resp = call_service(&app, admin_request(TestRequest::get().uri("/api/admin/users"))).await;
+
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let body: prono_api::ErrorBody = read_body_json(resp).await;
+        assert_eq!(body.code, prono_api::ErrorCode::Forbidden);
+    }
+
+    #[actix_web::test]
+    async fn test_list_and_reset_devices() {
+        let (app, db, _survey_id) = make_app(Some(TOKEN)).await;
+        db.register_device("sam", "laptop").await.unwrap();
+
+        let resp = call_service(
+            &app,
+            admin_request(TestRequest::get().uri("/api/admin/users/sam/devices")),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let devices: Vec<v1::Device> = read_body_json(resp).await;
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].id, "laptop");
+
+        let resp = call_service(
+            &app,
+            admin_request(TestRequest::delete().uri("/api/admin/users/sam/devices")),
+        )
+        .await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: v1::DevicesReset = read_body_json(resp).await;
+        assert_eq!(body, v1::DevicesReset { removed: 1 });
+    }
+
+    async fn answer(db: &FakeRepo, user: &str, text: &str) {
+        let question_id = prono::empty_survey().questions[0].id.clone();
+        repo::Surveys::add_answer(db, user, question_id, repo::Answer::Text(text.to_string()))
+            .await
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_rename_and_merge_users() {
+        let (app, db, _survey_id) = make_app(Some(TOKEN)).await;
+        answer(&db, "Sam J.", "yes").await;
+        answer(&db, "sam", "no").await;
+
+        let resp = call_service(
+            &app,
+            admin_request(
+                TestRequest::post()
+                    .uri("/api/admin/users/Sam%20J./rename")
+                    .set_json(v1::RenameUserRequest { new: "sam".to_string() }),
+            ),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let body: prono_api::ErrorBody = read_body_json(resp).await;
+        assert_eq!(body.code, prono_api::ErrorCode::UserExists);
+
+        let resp =
+            call_service(
+                &app,
+                admin_request(TestRequest::post().uri("/api/admin/users/Sam%20J./merge").set_json(
+                    v1::MergeUserRequest {
+                        into: "sam".to_string(),
+                    },
+                )),
+            )
+            .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let body: prono_api::ErrorBody = read_body_json(resp).await;
+        assert_eq!(body.code, prono_api::ErrorCode::AnswerExists);
+        assert_eq!(body.details, Some(prono::empty_survey().questions[0].id.clone()));
+
+        let resp = call_service(
+            &app,
+            admin_request(TestRequest::post().uri("/api/admin/users/Sam%20J./rename").set_json(
+                v1::RenameUserRequest {
+                    new: "Sam-J".to_string(),
+                },
+            )),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(repo::Users::all_names(db.as_ref()).await.unwrap(), ["sam", "sam-j"]);
+    }
+
+    #[actix_web::test]
+    async fn test_normalize_users() {
+        let (app, db, _survey_id) = make_app(Some(TOKEN)).await;
+        answer(&db, "Sam", "yes").await;
+        answer(&db, "sam!", "no").await;
+
+        let resp = call_service(
+            &app,
+            admin_request(TestRequest::post().uri("/api/admin/users/normalize")),
+        )
+        .await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Vec<v1::NormalizedUser> = read_body_json(resp).await;
+        assert_eq!(body.len(), 2);
+        assert_eq!(
+            body[0],
+            v1::NormalizedUser {
+                name: "Sam".to_string(),
+                merged_into: Some("sam".to_string()),
+                error: None,
+            }
+        );
+        assert_eq!(body[1].name, "sam!");
+        assert!(body[1].merged_into.is_none() && body[1].error.is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_close_and_open_survey() {
+        let (app, _db, survey_id) = make_app(Some(TOKEN)).await;
+
+        let resp = call_service(
+            &app,
+            admin_request(TestRequest::post().uri(&format!("/api/admin/surveys/{survey_id}/close"))),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+        let resp = call_service(
+            &app,
+            admin_request(TestRequest::post().uri(&format!("/api/admin/surveys/{}/open", survey_id + 1))),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let body: prono_api::ErrorBody = read_body_json(resp).await;
+        assert_eq!(body.code, prono_api::ErrorCode::NotFound);
+        assert_eq!(body.details, Some(format!("survey {} does not exist", survey_id + 1)));
+    }
+
+    #[actix_web::test]
+    async fn test_set_privacy() {
+        let (app, db, survey_id) = make_app(Some(TOKEN)).await;
+
+        let resp = call_service(
+            &app,
+            admin_request(
+                TestRequest::put()
+                    .uri(&format!("/api/admin/surveys/{survey_id}/privacy"))
+                    .set_json(v1::SurveyPrivacy {
+                        privacy: v1::Privacy::AggregateOnly,
+                    }),
+            ),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            repo::SurveyStates::privacy(db.as_ref(), survey_id).await.unwrap(),
+            repo::Privacy::AggregateOnly
+        );
+
+        let resp = call_service(
+            &app,
+            admin_request(
+                TestRequest::put()
+                    .uri(&format!("/api/admin/surveys/{}/privacy", survey_id + 1))
+                    .set_json(v1::SurveyPrivacy {
+                        privacy: v1::Privacy::Public,
+                    }),
+            ),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_list_answers() {
+        let (app, db, survey_id) = make_app(Some(TOKEN)).await;
+        let survey = prono::empty_survey();
+        let answer = repo::Answer::PredictionDate {
+            day: None,
+            month: 5,
+            year: 2030,
+        };
+        repo::Surveys::add_answer(db.as_ref(), "sam", survey.questions[0].id.clone(), answer)
+            .await
+            .unwrap();
+
+        let resp = call_service(
+            &app,
+            admin_request(TestRequest::get().uri(&format!("/api/admin/surveys/{survey_id}/answers"))),
+        )
+        .await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Vec<v1::AnswerRecord> = read_body_json(resp).await;
+        assert_eq!(body.len(), 1);
+        assert_eq!(body[0].user, "sam");
+        assert_eq!(
+            body[0].answer,
+            v1::Answer::PredictionDate {
+                day: None,
+                month: 5,
+                year: 2030
+            }
+        );
+        assert!(body[0].answered_at.is_some());
+
+        let resp = call_service(
+            &app,
+            admin_request(TestRequest::get().uri(&format!("/api/admin/surveys/{}/answers", survey_id + 1))),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_add_answer_conflicts_unless_replaced() {
+        let (app, db, _survey_id) = make_app(Some(TOKEN)).await;
+        let question_id = prono::empty_survey().questions[0].id.clone();
+        let request = |text: &str| v1::AddAnswerRequest {
+            user: "sam".to_string(),
+            question_id: question_id.clone(),
+            answer: v1::Answer::Text { text: text.to_string() },
+        };
+
+        let resp = call_service(
+            &app,
+            admin_request(TestRequest::post().uri("/api/admin/answers").set_json(request("paper"))),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+        let resp = call_service(
+            &app,
+            admin_request(TestRequest::post().uri("/api/admin/answers").set_json(request("again"))),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        let resp = call_service(
+            &app,
+            admin_request(
+                TestRequest::put()
+                    .uri("/api/admin/answers")
+                    .set_json(request("corrected")),
+            ),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            repo::Surveys::answer(db.as_ref(), "sam", question_id.clone()).await,
+            Some(repo::Answer::Text("corrected".to_string()))
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_add_answer_to_unknown_question_is_not_found() {
+        let (app, _db, _survey_id) = make_app(Some(TOKEN)).await;
+        let request = v1::AddAnswerRequest {
+            user: "sam".to_string(),
+            question_id: "unknown".to_string(),
+            answer: v1::Answer::Text {
+                text: "yes".to_string(),
+            },
+        };
+
+        let resp = call_service(
+            &app,
+            admin_request(TestRequest::post().uri("/api/admin/answers").set_json(request)),
+        )
+        .await;
+
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
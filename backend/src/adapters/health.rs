@@ -0,0 +1,193 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header;
+use actix_web::middleware::Next;
+use actix_web::{HttpResponse, get, web};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::Error;
+use crate::adapters::openapi::OPENAPI_PATH;
+use crate::use_cases::{CheckStatus, HealthService, RepoAvailability};
+
+static API_PREFIX: &str = "/api/";
+static API_DOCS_PREFIX: &str = "/api/docs";
+
+/// Liveness of the backend process.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Liveness {
+    pub alive: bool,
+}
+
+/// Whether the backend can serve requests, with the result of every check.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Readiness {
+    pub ready: bool,
+    pub db: CheckResult,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CheckResult {
+    pub up: bool,
+    /// Time the check took, in milliseconds.
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+impl From<CheckStatus> for CheckResult {
+    fn from(status: CheckStatus) -> Self {
+        Self {
+            up: status.is_up(),
+            latency_ms: u64::try_from(status.latency.as_millis()).unwrap_or(u64::MAX),
+            error: status.error,
+        }
+    }
+}
+
+/// Answers as long as the process serves HTTP, without checking its dependencies.
+#[utoipa::path(
+    tag = "health",
+    responses((status = 200, description = "Backend is running", body = Liveness))
+)]
+#[get("/healthz")]
+pub async fn healthz() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .json(Liveness { alive: true })
+}
+
+/// Checks the database connection, for load balancers and service scripts deciding whether to route requests
+/// to this instance.
+#[utoipa::path(
+    tag = "health",
+    responses(
+        (status = 200, description = "Ready to serve requests", body = Readiness),
+        (status = 503, description = "A dependency is unavailable", body = Readiness),
+    )
+)]
+#[get("/readyz")]
+pub async fn readyz(health: web::Data<HealthService>) -> HttpResponse {
+    let db = CheckResult::from(health.db_status().await);
+    let readiness = Readiness { ready: db.up, db };
+    let mut response = if readiness.ready {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::ServiceUnavailable()
+    };
+    response
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .json(readiness)
+}
+
+/// Middleware answering API requests with `503 unavailable` while the registered [`RepoAvailability`] reports
+/// the repository as not connected yet. The API documentation stays available.
+///
+/// # Errors
+///
+/// Passes on errors of the wrapped service.
+pub async fn require_repository(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let path = req.path();
+    let needs_repository = path.starts_with(API_PREFIX) && path != OPENAPI_PATH && !path.starts_with(API_DOCS_PREFIX);
+    let connecting = req
+        .app_data::<web::Data<RepoAvailability>>()
+        .is_some_and(|availability| !availability.is_available());
+    if needs_repository && connecting {
+        let err = Error::Unavailable("Connecting to the database, try again later".to_string());
+        return Ok(req.error_response(err).map_into_right_body());
+    }
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    use actix_web::App;
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::from_fn;
+    use actix_web::test::{TestRequest, call_service, init_service, read_body_json};
+    use prono::fake_db::FakeRepo;
+    use prono::repo::Db as _;
+
+    #[actix_web::test]
+    async fn healthz_is_ok_without_dependencies() {
+        let app = init_service(App::new().service(healthz)).await;
+
+        let resp = call_service(&app, TestRequest::get().uri("/healthz").to_request()).await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body, serde_json::json!({"alive": true}));
+    }
+
+    #[actix_web::test]
+    async fn readyz_reports_db_check() {
+        let health = HealthService::new(Arc::new(FakeRepo::init(()).await.unwrap()), Duration::from_secs(1));
+        let app = init_service(App::new().app_data(web::Data::new(health)).service(readyz)).await;
+
+        let resp = call_service(&app, TestRequest::get().uri("/readyz").to_request()).await;
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(header::CACHE_CONTROL).unwrap(), "no-store");
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["ready"], true);
+        assert_eq!(body["db"]["up"], true);
+        assert!(body["db"]["latency_ms"].is_u64());
+        assert!(body["db"]["error"].is_null());
+    }
+
+    #[get("/api/ping")]
+    async fn api_ping() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_web::test]
+    async fn api_is_unavailable_until_repository_connects() {
+        let connected = Arc::new(AtomicBool::new(false));
+        let availability = RepoAvailability::from_fn({
+            let connected = connected.clone();
+            move || connected.load(Ordering::SeqCst)
+        });
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(require_repository))
+                .app_data(web::Data::new(availability))
+                .service(healthz)
+                .service(crate::adapters::openapi::openapi_json())
+                .service(api_ping),
+        )
+        .await;
+
+        let resp = call_service(&app, TestRequest::get().uri("/api/ping").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: prono_api::ErrorBody = read_body_json(resp).await;
+        assert_eq!(body.code, prono_api::ErrorCode::Unavailable);
+        for uri in ["/healthz", OPENAPI_PATH] {
+            let resp = call_service(&app, TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(resp.status(), StatusCode::OK, "{uri}");
+        }
+
+        connected.store(true, Ordering::SeqCst);
+        let resp = call_service(&app, TestRequest::get().uri("/api/ping").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn failed_check_is_reported_down() {
+        let result = CheckResult::from(CheckStatus {
+            latency: Duration::from_millis(1500),
+            error: Some("connection refused".to_string()),
+        });
+
+        assert!(!result.up);
+        assert_eq!(result.latency_ms, 1500);
+        assert_eq!(result.error.as_deref(), Some("connection refused"));
+    }
+}
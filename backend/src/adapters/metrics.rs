@@ -0,0 +1,528 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header;
+use actix_web::middleware::Next;
+use actix_web::{HttpResponse, get, web};
+use async_trait::async_trait;
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+use prono::PronoResult;
+use prono::repo;
+use tokio::sync::broadcast;
+
+use crate::Error;
+
+/// Route label of requests that match no route, so that probing random paths does not create new series.
+static UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Connections of the database pool, as reported by the function given to [`Metrics::with_db_pool`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DbPoolUsage {
+    pub open: i64,
+    pub idle: i64,
+    pub max: i64,
+}
+
+/// Prometheus metrics of the backend, served by [`get_metrics`] at `GET /metrics`.
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    answers: IntCounterVec,
+    errors: IntCounterVec,
+    repo_call_duration: HistogramVec,
+    db_pool_open: IntGauge,
+    db_pool_idle: IntGauge,
+    db_pool_max: IntGauge,
+    db_pool_usage: Option<Box<dyn Fn() -> DbPoolUsage + Send + Sync>>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    /// # Panics
+    ///
+    /// Panics if a metric definition is invalid, which is a programming error.
+    #[must_use]
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let requests = IntCounterVec::new(
+            Opts::new("prono_http_requests_total", "HTTP requests by route and status"),
+            &["method", "route", "status"],
+        )
+        .expect("valid metric");
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new("prono_http_request_duration_seconds", "HTTP request latency by route"),
+            &["method", "route"],
+        )
+        .expect("valid metric");
+        let answers = IntCounterVec::new(
+            Opts::new("prono_answers_submitted_total", "Answers stored per survey"),
+            &["survey_id"],
+        )
+        .expect("valid metric");
+        let errors = IntCounterVec::new(
+            Opts::new("prono_errors_total", "Error responses by error code"),
+            &["code"],
+        )
+        .expect("valid metric");
+        let repo_call_duration = HistogramVec::new(
+            HistogramOpts::new("prono_repo_call_duration_seconds", "Duration of repository calls"),
+            &["call"],
+        )
+        .expect("valid metric");
+        let db_pool_open =
+            IntGauge::new("prono_db_pool_connections", "Open database connections").expect("valid metric");
+        let db_pool_idle =
+            IntGauge::new("prono_db_pool_idle_connections", "Idle database connections").expect("valid metric");
+        let db_pool_max =
+            IntGauge::new("prono_db_pool_max_connections", "Maximum database connections").expect("valid metric");
+
+        for collector in [
+            Box::new(requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(request_duration.clone()),
+            Box::new(answers.clone()),
+            Box::new(errors.clone()),
+            Box::new(repo_call_duration.clone()),
+            Box::new(db_pool_open.clone()),
+            Box::new(db_pool_idle.clone()),
+            Box::new(db_pool_max.clone()),
+        ] {
+            registry.register(collector).expect("metric names are unique");
+        }
+
+        Self {
+            registry,
+            requests,
+            request_duration,
+            answers,
+            errors,
+            repo_call_duration,
+            db_pool_open,
+            db_pool_idle,
+            db_pool_max,
+            db_pool_usage: None,
+        }
+    }
+
+    /// Reports the database pool usage returned by `usage` at every scrape.
+    #[must_use]
+    pub fn with_db_pool(mut self, usage: impl Fn() -> DbPoolUsage + Send + Sync + 'static) -> Self {
+        self.db_pool_usage = Some(Box::new(usage));
+        self
+    }
+
+    /// Counts the answers announced by `events` as answers of the survey, until the event channel closes.
+    pub fn count_answers(self: Arc<Self>, survey_id: u64, mut events: broadcast::Receiver<prono_api::SurveyEvent>) {
+        let answers = self.answers.with_label_values(&[survey_id.to_string()]);
+        actix_web::rt::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(prono_api::SurveyEvent::AnswerAdded { .. }) => answers.inc(),
+                    Ok(prono_api::SurveyEvent::QuestionResolved { .. }) => {}
+                    // Nearly every event is an answer, count the skipped ones rather than lose them.
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => answers.inc_by(skipped),
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+        });
+    }
+
+    fn observe_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        self.requests
+            .with_label_values(&[method, route, &status.to_string()])
+            .inc();
+        self.request_duration
+            .with_label_values(&[method, route])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    fn observe_error(&self, error: &Error) {
+        let code = serde_json::to_value(error.code())
+            .ok()
+            .and_then(|code| code.as_str().map(ToString::to_string))
+            .unwrap_or_default();
+        self.errors.with_label_values(&[code]).inc();
+    }
+
+    async fn time_repo_call<T>(&self, call: &str, future: impl Future<Output = T>) -> T {
+        let timer = self.repo_call_duration.with_label_values(&[call]).start_timer();
+        let output = future.await;
+        timer.observe_duration();
+        output
+    }
+
+    /// Metrics in the Prometheus text format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the metrics cannot be encoded.
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        if let Some(usage) = &self.db_pool_usage {
+            let usage = usage();
+            self.db_pool_open.set(usage.open);
+            self.db_pool_idle.set(usage.idle);
+            self.db_pool_max.set(usage.max);
+        }
+        TextEncoder::new().encode_to_string(&self.registry.gather())
+    }
+}
+
+/// Middleware counting requests, their latency and error responses in the registered [`Metrics`].
+///
+/// # Errors
+///
+/// Passes on errors of the wrapped service.
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let Some(metrics) = req.app_data::<web::Data<Metrics>>().cloned() else {
+        return next.call(req).await;
+    };
+    let method = req.method().to_string();
+    let route = req.match_pattern().unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+    let started = Instant::now();
+
+    let res = next.call(req).await?;
+    metrics.observe_request(&method, &route, res.status().as_u16(), started.elapsed());
+    if let Some(error) = res.response().error().and_then(|err| err.as_error::<Error>()) {
+        metrics.observe_error(error);
+    }
+    Ok(res)
+}
+
+/// Metrics of this backend instance in the Prometheus text format.
+#[get("/metrics")]
+pub async fn get_metrics(metrics: web::Data<Metrics>) -> HttpResponse {
+    match metrics.render() {
+        Ok(text) => HttpResponse::Ok()
+            .insert_header((header::CONTENT_TYPE, prometheus::TEXT_FORMAT))
+            .body(text),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
+
+/// Repository decorator timing every call in [`Metrics`].
+pub struct InstrumentedRepo<R> {
+    inner: Arc<R>,
+    metrics: Arc<Metrics>,
+}
+
+impl<R> InstrumentedRepo<R> {
+    pub fn new(inner: Arc<R>, metrics: Arc<Metrics>) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait]
+impl<R: repo::Surveys> repo::Surveys for InstrumentedRepo<R> {
+    async fn answer(&self, user: &str, question_id: String) -> Option<repo::Answer> {
+        self.metrics
+            .time_repo_call("answer", self.inner.answer(user, question_id))
+            .await
+    }
+
+    async fn response(&self, user: &str, survey_id: u64) -> Option<repo::Survey> {
+        self.metrics
+            .time_repo_call("response", self.inner.response(user, survey_id))
+            .await
+    }
+
+    async fn add_answer(&self, user: &str, question_id: String, answer: repo::Answer) -> PronoResult<()> {
+        self.metrics
+            .time_repo_call("add_answer", self.inner.add_answer(user, question_id, answer))
+            .await
+    }
+
+    async fn replace_answer(&self, user: &str, question_id: String, answer: repo::Answer) -> PronoResult<()> {
+        self.metrics
+            .time_repo_call("replace_answer", self.inner.replace_answer(user, question_id, answer))
+            .await
+    }
+
+    async fn remove_answer(&self, user: &str, question_id: String) -> PronoResult<bool> {
+        self.metrics
+            .time_repo_call("remove_answer", self.inner.remove_answer(user, question_id))
+            .await
+    }
+
+    async fn all_answers(&self, question_id: String) -> Vec<(String, repo::Answer)> {
+        self.metrics
+            .time_repo_call("all_answers", self.inner.all_answers(question_id))
+            .await
+    }
+}
+
+#[async_trait]
+impl<R: repo::SurveyStates> repo::SurveyStates for InstrumentedRepo<R> {
+    async fn is_closed(&self, survey_id: u64) -> PronoResult<bool> {
+        self.metrics
+            .time_repo_call("is_closed", self.inner.is_closed(survey_id))
+            .await
+    }
+
+    async fn set_closed(&self, survey_id: u64, closed: bool) -> PronoResult<()> {
+        self.metrics
+            .time_repo_call("set_closed", self.inner.set_closed(survey_id, closed))
+            .await
+    }
+
+    async fn privacy(&self, survey_id: u64) -> PronoResult<repo::Privacy> {
+        self.metrics
+            .time_repo_call("privacy", self.inner.privacy(survey_id))
+            .await
+    }
+
+    async fn set_privacy(&self, survey_id: u64, privacy: repo::Privacy) -> PronoResult<()> {
+        self.metrics
+            .time_repo_call("set_privacy", self.inner.set_privacy(survey_id, privacy))
+            .await
+    }
+
+    async fn resolve_question(&self, survey_id: u64, question_id: &str, answer: repo::Answer) -> PronoResult<()> {
+        self.metrics
+            .time_repo_call(
+                "resolve_question",
+                self.inner.resolve_question(survey_id, question_id, answer),
+            )
+            .await
+    }
+
+    async fn resolutions(&self, survey_id: u64) -> PronoResult<Vec<(String, repo::Answer)>> {
+        self.metrics
+            .time_repo_call("resolutions", self.inner.resolutions(survey_id))
+            .await
+    }
+}
+
+#[async_trait]
+impl<R: repo::AnswerRecords> repo::AnswerRecords for InstrumentedRepo<R> {
+    async fn answer_records(&self, survey_id: u64) -> PronoResult<Vec<repo::AnswerRecord>> {
+        self.metrics
+            .time_repo_call("answer_records", self.inner.answer_records(survey_id))
+            .await
+    }
+}
+
+#[async_trait]
+impl<R: repo::Users> repo::Users for InstrumentedRepo<R> {
+    async fn all_users(&self) -> PronoResult<Vec<String>> {
+        self.metrics.time_repo_call("all_users", self.inner.all_users()).await
+    }
+
+    async fn all_names(&self) -> PronoResult<Vec<String>> {
+        self.metrics.time_repo_call("all_names", self.inner.all_names()).await
+    }
+
+    async fn delete_user(&self, name: &str) -> PronoResult<()> {
+        self.metrics
+            .time_repo_call("delete_user", self.inner.delete_user(name))
+            .await
+    }
+
+    async fn rename_user(&self, old: &str, new: &str) -> PronoResult<()> {
+        self.metrics
+            .time_repo_call("rename_user", self.inner.rename_user(old, new))
+            .await
+    }
+
+    async fn merge_users(&self, from: &str, into: &str) -> PronoResult<()> {
+        self.metrics
+            .time_repo_call("merge_users", self.inner.merge_users(from, into))
+            .await
+    }
+}
+
+#[async_trait]
+impl<R: repo::DeviceRegistry> repo::DeviceRegistry for InstrumentedRepo<R> {
+    async fn register_device(&self, user: &str, device_id: &str) -> PronoResult<()> {
+        self.metrics
+            .time_repo_call("register_device", self.inner.register_device(user, device_id))
+            .await
+    }
+
+    async fn list_devices(&self, user: &str) -> PronoResult<Vec<repo::Device>> {
+        self.metrics
+            .time_repo_call("list_devices", self.inner.list_devices(user))
+            .await
+    }
+
+    async fn unregister_device(&self, user: &str, device_id: &str) -> PronoResult<bool> {
+        self.metrics
+            .time_repo_call("unregister_device", self.inner.unregister_device(user, device_id))
+            .await
+    }
+
+    async fn unregister_devices(&self, user: &str) -> PronoResult<usize> {
+        self.metrics
+            .time_repo_call("unregister_devices", self.inner.unregister_devices(user))
+            .await
+    }
+
+    async fn admit_device(&self, user: &str, device_id: &str, policy: repo::DevicePolicy) -> PronoResult<bool> {
+        self.metrics
+            .time_repo_call("admit_device", self.inner.admit_device(user, device_id, policy))
+            .await
+    }
+}
+
+#[async_trait]
+impl<R: repo::Accounts> repo::Accounts for InstrumentedRepo<R> {
+    async fn create_account(&self, user: &str, password_hash: &str) -> PronoResult<()> {
+        self.metrics
+            .time_repo_call("create_account", self.inner.create_account(user, password_hash))
+            .await
+    }
+
+    async fn password_hash(&self, user: &str) -> PronoResult<Option<String>> {
+        self.metrics
+            .time_repo_call("password_hash", self.inner.password_hash(user))
+            .await
+    }
+
+    async fn create_session(&self, session: &repo::Session) -> PronoResult<()> {
+        self.metrics
+            .time_repo_call("create_session", self.inner.create_session(session))
+            .await
+    }
+
+    async fn session(&self, token_hash: &str) -> PronoResult<Option<repo::Session>> {
+        self.metrics
+            .time_repo_call("session", self.inner.session(token_hash))
+            .await
+    }
+
+    async fn delete_session(&self, token_hash: &str) -> PronoResult<()> {
+        self.metrics
+            .time_repo_call("delete_session", self.inner.delete_session(token_hash))
+            .await
+    }
+}
+
+#[async_trait]
+impl<R: repo::RateLimitStore> repo::RateLimitStore for InstrumentedRepo<R> {
+    async fn take_tokens(&self, buckets: &[(String, repo::RateLimit)], now: u64) -> PronoResult<Option<Duration>> {
+        self.metrics
+            .time_repo_call("take_tokens", self.inner.take_tokens(buckets, now))
+            .await
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+
+    use actix_web::App;
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::from_fn;
+    use actix_web::test::{TestRequest, call_service, init_service, read_body};
+    use prono::fake_db::FakeRepo;
+    use prono::repo::{Db as _, Surveys as _};
+
+    #[get("/items/{id}")]
+    async fn item(path: web::Path<u64>) -> Result<HttpResponse, Error> {
+        match path.into_inner() {
+            0 => Err(Error::AnswerExists),
+            _ => Ok(HttpResponse::Ok().finish()),
+        }
+    }
+
+    async fn scrape(
+        app: &impl actix_web::dev::Service<
+            actix_http::Request,
+            Response = ServiceResponse<impl MessageBody>,
+            Error = actix_web::Error,
+        >,
+    ) -> String {
+        let resp = call_service(app, TestRequest::get().uri("/metrics").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        String::from_utf8(read_body(resp).await.to_vec()).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn requests_are_counted_per_route_pattern_and_status() {
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(track_requests))
+                .app_data(web::Data::new(Metrics::new()))
+                .service(item)
+                .service(get_metrics),
+        )
+        .await;
+
+        for uri in ["/items/1", "/items/2", "/items/0", "/nothing/here"] {
+            call_service(&app, TestRequest::get().uri(uri).to_request()).await;
+        }
+
+        let text = scrape(&app).await;
+        assert!(text.contains(r#"prono_http_requests_total{method="GET",route="/items/{id}",status="200"} 2"#));
+        assert!(text.contains(r#"prono_http_requests_total{method="GET",route="/items/{id}",status="409"} 1"#));
+        assert!(text.contains(r#"prono_http_requests_total{method="GET",route="unmatched",status="404"} 1"#));
+        assert!(text.contains(r#"prono_http_request_duration_seconds_count{method="GET",route="/items/{id}"} 3"#));
+        assert!(text.contains(r#"prono_errors_total{code="answer_exists"} 1"#));
+    }
+
+    #[actix_web::test]
+    async fn db_pool_usage_is_read_at_scrape_time() {
+        let metrics = Metrics::new().with_db_pool(|| DbPoolUsage {
+            open: 3,
+            idle: 1,
+            max: 5,
+        });
+
+        let text = metrics.render().unwrap();
+
+        assert!(text.contains("prono_db_pool_connections 3"));
+        assert!(text.contains("prono_db_pool_idle_connections 1"));
+        assert!(text.contains("prono_db_pool_max_connections 5"));
+    }
+
+    #[actix_web::test]
+    async fn answer_events_are_counted_per_survey() {
+        let metrics = Arc::new(Metrics::new());
+        let (events, receiver) = broadcast::channel(4);
+        metrics.clone().count_answers(7, receiver);
+
+        for _ in 0..2 {
+            events
+                .send(prono_api::SurveyEvent::AnswerAdded {
+                    question_id: "q1".to_string(),
+                    user: "sam".to_string(),
+                    answer: prono_api::Answer::Text("yes".to_string()),
+                })
+                .unwrap();
+        }
+        drop(events);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert!(
+            metrics
+                .render()
+                .unwrap()
+                .contains(r#"prono_answers_submitted_total{survey_id="7"} 2"#)
+        );
+    }
+
+    #[actix_web::test]
+    async fn repo_calls_are_timed() {
+        let metrics = Arc::new(Metrics::new());
+        let repo = InstrumentedRepo::new(Arc::new(FakeRepo::init(()).await.unwrap()), metrics.clone());
+
+        repo.all_answers("q1".to_string()).await;
+
+        assert!(
+            metrics
+                .render()
+                .unwrap()
+                .contains(r#"prono_repo_call_duration_seconds_count{call="all_answers"} 1"#)
+        );
+    }
+}
@@ -1 +1,8 @@
+pub mod admin;
+pub mod health;
+pub mod metrics;
+pub mod openapi;
 pub mod rest;
+pub mod rest_v1;
+pub mod tls;
+pub mod ws;
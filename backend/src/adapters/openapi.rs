@@ -0,0 +1,238 @@
+use actix_web::{HttpResponse, Resource, web};
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::openapi::{Deprecated, PathItem};
+use utoipa::{Modify, OpenApi};
+
+use crate::adapters::{admin, health, rest, rest_v1};
+
+pub static OPENAPI_PATH: &str = "/api/openapi.json";
+
+/// Specification of the REST API, generated from the handlers in [`rest`].
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Prono backend", description = "REST API of the prono survey backend"),
+    paths(
+        rest_v1::register,
+        rest_v1::login,
+        rest_v1::logout,
+        rest_v1::list_devices,
+        rest_v1::unregister_device,
+        rest_v1::start_transfer,
+        rest_v1::complete_transfer,
+        rest_v1::get_user_status,
+        rest_v1::get_challenge,
+        rest_v1::get_survey,
+        rest_v1::add_answer,
+        rest_v1::get_response,
+        rest_v1::get_all_answers,
+        rest_v1::survey_events,
+        rest_v1::get_stats,
+        rest_v1::get_privacy,
+        rest::get_survey,
+        rest::add_answer,
+        rest::get_response,
+        rest::get_all_answers,
+        rest::survey_events,
+        rest::get_stats,
+        admin::list_users,
+        admin::delete_user,
+        admin::rename_user,
+        admin::merge_user,
+        admin::normalize_users,
+        admin::list_devices,
+        admin::reset_devices,
+        admin::resolve_question,
+        admin::close_survey,
+        admin::open_survey,
+        admin::set_privacy,
+        admin::list_answers,
+        admin::add_answer,
+        admin::replace_answer,
+        health::healthz,
+        health::readyz,
+    ),
+    components(schemas(prono_api::ErrorCode, prono_api::v1::WsRequest, prono_api::v1::WsMessage)),
+    tags(
+        (name = "auth", description = "Accounts and login sessions"),
+        (name = "devices", description = "Devices users submit answers from"),
+        (name = "survey", description = "Surveys and their answers"),
+        (name = "admin", description = "Maintenance with the admin token"),
+        (name = "health", description = "Liveness and readiness probes")
+    ),
+    modifiers(&BearerAuth, &DeprecateLegacyPaths)
+)]
+pub struct ApiDoc;
+
+/// Session tokens issued by the `auth` routes.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi
+            .components
+            .get_or_insert_with(Default::default)
+            .add_security_scheme("bearer", SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)));
+    }
+}
+
+/// Marks the unversioned routes, kept for clients predating `/api/v1`, as deprecated.
+struct DeprecateLegacyPaths;
+
+impl Modify for DeprecateLegacyPaths {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let legacy = openapi
+            .paths
+            .paths
+            .iter_mut()
+            .filter(|(path, _item)| rest::is_legacy_path(path))
+            .map(|(_path, item)| item);
+        for item in legacy {
+            operations(item).for_each(|operation| operation.deprecated = Some(Deprecated::True));
+        }
+    }
+}
+
+fn operations(item: &mut PathItem) -> impl Iterator<Item = &mut utoipa::openapi::path::Operation> {
+    [&mut item.get, &mut item.post, &mut item.put, &mut item.delete]
+        .into_iter()
+        .flatten()
+}
+
+/// The OpenAPI document, served at [`OPENAPI_PATH`].
+#[must_use]
+pub fn openapi_json() -> Resource {
+    web::resource(OPENAPI_PATH).route(web::get().to(|| async { HttpResponse::Ok().json(ApiDoc::openapi()) }))
+}
+
+/// Swagger UI at `/api/docs/`, rendering the document served at [`OPENAPI_PATH`].
+#[cfg(feature = "swagger-ui")]
+#[must_use]
+pub fn swagger_ui() -> utoipa_swagger_ui::SwaggerUi {
+    utoipa_swagger_ui::SwaggerUi::new("/api/docs/{_:.*}").config(utoipa_swagger_ui::Config::from(OPENAPI_PATH))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use actix_web::App;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{TestRequest, call_service, init_service, read_body_json};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("/api/v1/auth/register", "post")]
+    #[case("/api/v1/auth/login", "post")]
+    #[case("/api/v1/auth/logout", "post")]
+    #[case("/api/v1/devices", "get")]
+    #[case("/api/v1/devices/{device_id}", "delete")]
+    #[case("/api/v1/devices/transfer", "post")]
+    #[case("/api/v1/devices/transfer/complete", "post")]
+    #[case("/api/v1/survey", "get")]
+    #[case("/api/v1/survey/answer", "post")]
+    #[case("/api/v1/survey/response/{user}/{survey_id}", "get")]
+    #[case("/api/v1/survey/answers/{question_id}", "get")]
+    #[case("/api/v1/survey/{survey_id}/events", "get")]
+    #[case("/api/v1/survey/{survey_id}/stats", "get")]
+    #[case("/api/v1/survey/{survey_id}/privacy", "get")]
+    #[case("/api/v1/challenge", "get")]
+    #[case("/api/v1/users/{name}/status", "get")]
+    #[case("/api/survey", "get")]
+    #[case("/api/survey/answer", "post")]
+    #[case("/api/survey/response/{user}/{survey_id}", "get")]
+    #[case("/api/survey/answers/{question_id}", "get")]
+    #[case("/api/survey/{survey_id}/events", "get")]
+    #[case("/api/survey/{survey_id}/stats", "get")]
+    #[case("/api/admin/users", "get")]
+    #[case("/api/admin/users/{user}", "delete")]
+    #[case("/api/admin/users/{user}/rename", "post")]
+    #[case("/api/admin/users/{user}/merge", "post")]
+    #[case("/api/admin/users/normalize", "post")]
+    #[case("/api/admin/users/{user}/devices", "get")]
+    #[case("/api/admin/users/{user}/devices", "delete")]
+    #[case("/api/admin/questions/{question_id}/resolve", "post")]
+    #[case("/api/admin/surveys/{survey_id}/close", "post")]
+    #[case("/api/admin/surveys/{survey_id}/open", "post")]
+    #[case("/api/admin/surveys/{survey_id}/privacy", "put")]
+    #[case("/api/admin/surveys/{survey_id}/answers", "get")]
+    #[case("/api/admin/answers", "post")]
+    #[case("/api/admin/answers", "put")]
+    #[case("/healthz", "get")]
+    #[case("/readyz", "get")]
+    fn document_contains_rest_handler(#[case] path: &str, #[case] method: &str) {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        assert!(doc["paths"][path][method].is_object(), "missing {method} {path}");
+    }
+
+    #[rstest]
+    #[case("Survey")]
+    #[case("Question")]
+    #[case("Answer")]
+    #[case("AddAnswerRequest")]
+    #[case("ErrorBody")]
+    #[case("ErrorCode")]
+    #[case("v1.Survey")]
+    #[case("v1.Question")]
+    #[case("v1.Answer")]
+    #[case("v1.AddAnswerRequest")]
+    #[case("v1.UserAnswer")]
+    #[case("v1.Credentials")]
+    #[case("v1.Session")]
+    #[case("Challenge")]
+    #[case("v1.Device")]
+    #[case("v1.TransferCode")]
+    #[case("v1.CompleteTransferRequest")]
+    #[case("v1.SurveyEvent")]
+    #[case("v1.SurveyStats")]
+    #[case("v1.MonthBucket")]
+    #[case("v1.SurveyPrivacy")]
+    #[case("v1.Privacy")]
+    #[case("v1.UserStatus")]
+    #[case("v1.RenameUserRequest")]
+    #[case("v1.MergeUserRequest")]
+    #[case("v1.NormalizedUser")]
+    #[case("v1.WsRequest")]
+    #[case("v1.WsMessage")]
+    #[case("Readiness")]
+    fn document_contains_schema(#[case] name: &str) {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        assert!(doc["components"]["schemas"][name].is_object(), "missing schema {name}");
+    }
+
+    #[rstest]
+    #[case("/api/survey", "get", true)]
+    #[case("/api/survey/answer", "post", true)]
+    #[case("/api/v1/survey", "get", false)]
+    #[case("/api/v1/survey/answer", "post", false)]
+    #[case("/api/v1/challenge", "get", false)]
+    #[case("/api/v1/users/{name}/status", "get", false)]
+    fn only_legacy_paths_are_deprecated(#[case] path: &str, #[case] method: &str, #[case] deprecated: bool) {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        assert_eq!(
+            doc["paths"][path][method]["deprecated"].as_bool().unwrap_or(false),
+            deprecated
+        );
+    }
+
+    #[test]
+    fn answer_routes_require_bearer_token() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        assert_eq!(doc["components"]["securitySchemes"]["bearer"]["scheme"], "bearer");
+        assert_eq!(
+            doc["paths"]["/api/v1/survey/answer"]["post"]["security"],
+            serde_json::json!([{"bearer": []}])
+        );
+    }
+
+    #[actix_web::test]
+    async fn openapi_json_is_served() {
+        let app = init_service(App::new().service(openapi_json())).await;
+        let req = TestRequest::get().uri(OPENAPI_PATH).to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let doc: serde_json::Value = read_body_json(resp).await;
+        assert!(doc["openapi"].as_str().unwrap().starts_with("3."));
+        assert_eq!(doc["info"]["title"], "Prono backend");
+    }
+}
@@ -1,52 +1,648 @@
-use actix_web::{HttpRequest, HttpResponse, get, post, web};
-use serde::Deserialize;
+use actix_cors::Cors;
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::error::JsonPayloadError;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::http::{Method, header};
+use actix_web::middleware::{Next, from_fn};
+use actix_web::{FromRequest, HttpRequest, HttpResponse, get, post, web};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-use crate::BackendResult;
 use crate::use_cases::*;
+use crate::{BackendResult, Error};
+
+/// Header with which clients request, and the backend confirms, the API version of `/api/v1` routes.
+pub static API_VERSION_HEADER: &str = "x-api-version";
+/// Header carrying the stamp that solves a challenge of `GET /api/v1/challenge`.
+pub static PROOF_OF_WORK_HEADER: &str = "x-proof-of-work";
+static V1_PREFIX: &str = "/api/v1/";
+static LEGACY_PREFIX: &str = "/api/survey";
+/// RFC 9745 `Deprecation` value of the legacy routes: deprecated since 2026-10-19, 00:00 UTC.
+static LEGACY_DEPRECATION: &str = "@1792368000";
+/// RFC 8594 `Sunset` value of the legacy routes, six months after their deprecation.
+static LEGACY_SUNSET: &str = "Mon, 19 Apr 2027 00:00:00 GMT";
+/// Interval of the messages that keep an idle event stream or WebSocket open through proxies.
+pub(crate) const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// CORS policy for the REST API, `*` in `allowed_origins` allows any origin.
+pub fn cors(allowed_origins: &[String]) -> Cors {
+    let cors = Cors::default()
+        .allowed_methods([Method::GET, Method::POST, Method::DELETE])
+        .allowed_header(header::CONTENT_TYPE)
+        .allowed_header(header::AUTHORIZATION)
+        .allowed_header("X-Device-Id")
+        .allowed_header(API_VERSION_HEADER)
+        .allowed_header(PROOF_OF_WORK_HEADER)
+        .expose_headers([
+            API_VERSION_HEADER,
+            "Deprecation",
+            "Sunset",
+            header::LINK.as_str(),
+            header::RETRY_AFTER.as_str(),
+        ])
+        .max_age(3600);
+
+    if allowed_origins.iter().any(|origin| origin == "*") {
+        return cors.allow_any_origin();
+    }
+    allowed_origins
+        .iter()
+        .fold(cors, |cors, origin| cors.allowed_origin(origin))
+}
+
+/// JSON extractor config limiting the request body to `max_body_size` bytes.
+/// Malformed bodies are reported as `invalid_request` error bodies.
+#[must_use]
+pub fn json_config(max_body_size: usize) -> web::JsonConfig {
+    web::JsonConfig::default()
+        .limit(max_body_size)
+        .error_handler(move |err, _req| match err {
+            JsonPayloadError::Overflow { .. } | JsonPayloadError::OverflowKnownLength { .. } => {
+                Error::PayloadTooLarge(max_body_size).into()
+            }
+            err => Error::InvalidRequest(err.to_string()).into(),
+        })
+}
+
+/// Path extractor config reporting unparsable path segments, e.g. a survey id that is not a number, as
+/// `invalid_request` error bodies.
+#[must_use]
+pub fn path_config() -> web::PathConfig {
+    web::PathConfig::default().error_handler(|err, _req| Error::InvalidRequest(err.to_string()).into())
+}
+
+/// Query extractor config reporting malformed query strings as `invalid_request` error bodies.
+#[must_use]
+pub fn query_config() -> web::QueryConfig {
+    web::QueryConfig::default().error_handler(|err, _req| Error::InvalidRequest(err.to_string()).into())
+}
+
+/// Middleware negotiating the API version.
+///
+/// Versioned routes reject an `X-Api-Version` request header other than the served version and confirm the
+/// served version in the response. The unversioned legacy routes carry their `Deprecation` and `Sunset` dates
+/// and point to their `/api/v1` successor.
+///
+/// # Errors
+///
+/// Passes on errors of the wrapped service.
+pub async fn api_version(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let path = req.path().to_string();
+
+    if path.starts_with(V1_PREFIX) {
+        let requested = req
+            .headers()
+            .get(API_VERSION_HEADER)
+            .map(|v| v.to_str().unwrap_or_default().to_string());
+        if let Some(requested) = requested.filter(|v| v != prono_api::v1::API_VERSION) {
+            return Ok(req
+                .error_response(Error::UnsupportedApiVersion(requested))
+                .map_into_right_body());
+        }
+        let mut res = next.call(req).await?.map_into_left_body();
+        res.headers_mut().insert(
+            HeaderName::from_static(API_VERSION_HEADER),
+            HeaderValue::from_static(prono_api::v1::API_VERSION),
+        );
+        return Ok(res);
+    }
 
-#[derive(Deserialize)]
+    let mut res = next.call(req).await?.map_into_left_body();
+    if is_legacy_path(&path) {
+        let successor = format!("</api/v1{}>; rel=\"successor-version\"", &path["/api".len()..]);
+        let headers = res.headers_mut();
+        headers.insert(
+            HeaderName::from_static("deprecation"),
+            HeaderValue::from_static(LEGACY_DEPRECATION),
+        );
+        headers.insert(
+            HeaderName::from_static("sunset"),
+            HeaderValue::from_static(LEGACY_SUNSET),
+        );
+        if let Ok(link) = HeaderValue::from_str(&successor) {
+            headers.insert(header::LINK, link);
+        }
+    }
+    Ok(res)
+}
+
+/// Middleware rate limiting the wrapped routes per client IP address and, for requests with a valid session
+/// token, per signed in user.
+///
+/// Requests pass unlimited when no [`RateLimiter`] is registered as app data.
+///
+/// # Errors
+///
+/// Passes on errors of the wrapped service.
+pub async fn rate_limit(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if let Some(limiter) = req.app_data::<web::Data<RateLimiter>>().cloned() {
+        let ip = client_ip(req.request(), limiter.trusts_forwarded_headers());
+        let principal = match bearer_token(req.request()) {
+            Some(_) => req.extract::<Principal>().await.ok(),
+            None => None,
+        };
+        let user = principal.as_ref().map(|principal| principal.user.as_str());
+        if let Err(err) = limiter.check(ip.as_deref(), user).await {
+            return Ok(req.error_response(err).map_into_right_body());
+        }
+    }
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+/// Middleware like [`rate_limit`] for the token-free legacy answer route, which limits per `X-Device-Id`
+/// instead of per signed in user.
+///
+/// # Errors
+///
+/// Passes on errors of the wrapped service.
+pub async fn rate_limit_per_device(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if let Some(limiter) = req.app_data::<web::Data<RateLimiter>>().cloned() {
+        let ip = client_ip(req.request(), limiter.trusts_forwarded_headers());
+        let device = Some(device_id(req.request())).filter(|device_id| !device_id.is_empty());
+        if let Err(err) = limiter.check_device(ip.as_deref(), device).await {
+            return Ok(req.error_response(err).map_into_right_body());
+        }
+    }
+    Ok(next.call(req).await?.map_into_left_body())
+}
+
+/// Whether `path` is one of the unversioned routes kept for clients predating `/api/v1`, `/api/survey` or below
+/// it, but not e.g. `/api/surveys`.
+pub(crate) fn is_legacy_path(path: &str) -> bool {
+    path.strip_prefix(LEGACY_PREFIX)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// IP address of the client, taken from the `Forwarded` or `X-Forwarded-For` header only when the backend
+/// runs behind a trusted reverse proxy, as clients can set these headers to anything.
+pub(crate) fn client_ip(req: &HttpRequest, trust_forwarded_headers: bool) -> Option<String> {
+    if trust_forwarded_headers {
+        return req.connection_info().realip_remote_addr().map(ToString::to_string);
+    }
+    req.peer_addr().map(|addr| addr.ip().to_string())
+}
+
+/// IP address of the client, trusting forwarded headers when the registered [`RateLimiter`] does.
+pub(crate) fn request_ip(req: &HttpRequest) -> Option<String> {
+    let trust_forwarded_headers = req
+        .app_data::<web::Data<RateLimiter>>()
+        .is_some_and(|limiter| limiter.trusts_forwarded_headers());
+    client_ip(req, trust_forwarded_headers)
+}
+
+/// Session token sent by the client as `Authorization: Bearer <token>`.
+pub(crate) fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+/// Handlers taking a [`Principal`] only run for requests with a valid session token.
+impl FromRequest for Principal {
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = BackendResult<Self>>>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let token = bearer_token(req).map(ToString::to_string);
+        let auth = req.app_data::<web::Data<AuthService>>().cloned();
+        Box::pin(async move {
+            let auth = auth.ok_or_else(|| Error::Config("Authentication is not set up".to_string()))?;
+            let token = token.ok_or(Error::Unauthorized)?;
+            auth.authenticate(&token).await
+        })
+    }
+}
+
+/// Fails unless the user named in a request body is the signed in user, spelled in any way [`prono::Username`]
+/// treats as the same.
+pub(crate) fn ensure_same_user(principal: &Principal, user: &str) -> BackendResult<()> {
+    let same = match (prono::Username::parse(&principal.user), prono::Username::parse(user)) {
+        (Ok(signed_in), Ok(named)) => signed_in == named,
+        _ => false,
+    };
+    if same {
+        Ok(())
+    } else {
+        Err(Error::Forbidden(format!(
+            "Signed in as {}, not as {user}",
+            principal.user
+        )))
+    }
+}
+
+pub(crate) fn unknown_survey(survey_id: u64) -> Error {
+    Error::NotFound(format!("survey {survey_id} does not exist"))
+}
+
+pub(crate) fn unknown_question(question_id: &str) -> Error {
+    Error::NotFound(format!("question {question_id} does not exist"))
+}
+
+pub(crate) fn no_response(user: &str, survey_id: u64) -> Error {
+    Error::NotFound(format!("{user} has no response to survey {survey_id}"))
+}
+
+/// Device id sent by the client, empty when missing.
+pub(crate) fn device_id(req: &HttpRequest) -> &str {
+    req.headers()
+        .get("X-Device-Id")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+}
+
+/// Proof-of-work stamp sent by the client, empty when missing.
+pub(crate) fn proof_of_work(req: &HttpRequest) -> &str {
+    req.headers()
+        .get(PROOF_OF_WORK_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+}
+
+/// `data` line of a server-sent event holding `event` converted by `to_message` as JSON.
+fn event_chunk<T: Serialize>(
+    event: prono_api::SurveyEvent,
+    to_message: fn(prono_api::SurveyEvent) -> T,
+) -> Option<String> {
+    let message = serde_json::to_string(&to_message(event)).ok()?;
+    Some(format!("data: {message}\n\n"))
+}
+
+/// Server-sent event stream with a `data` line per survey event, holding the event converted by `to_message`
+/// as JSON, starting with the past events of the subscription. The stream ends when the subscriber falls too
+/// far behind, clients are expected to reconnect.
+pub(crate) fn event_stream<T: Serialize + 'static>(
+    subscription: Subscription,
+    to_message: fn(prono_api::SurveyEvent) -> T,
+) -> HttpResponse {
+    let Subscription { past, events } = subscription;
+    let past = futures_util::stream::iter(
+        past.into_iter()
+            .filter_map(move |event| event_chunk(event, to_message))
+            .map(|chunk| Ok::<_, Infallible>(web::Bytes::from(chunk))),
+    );
+    let keep_alive = tokio::time::interval(KEEP_ALIVE_INTERVAL);
+    let live = futures_util::stream::unfold((events, keep_alive), move |(mut events, mut keep_alive)| async move {
+        let chunk = tokio::select! {
+            event = events.recv() => event_chunk(event.ok()?, to_message)?,
+            _ = keep_alive.tick() => ": keep-alive\n\n".to_string(),
+        };
+        Some((Ok::<_, Infallible>(web::Bytes::from(chunk)), (events, keep_alive)))
+    });
+    let stream = past.chain(live);
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(stream)
+}
+
+/// Request body of the deprecated `/api/survey/answer` route, use [`prono_api::v1::AddAnswerRequest`].
+#[derive(Deserialize, ToSchema)]
 pub struct AddAnswerRequest {
     pub user: String,
     pub question_id: String,
     pub answer: prono_api::Answer,
 }
 
+/// Empty survey with all its questions, without answers.
+#[utoipa::path(
+    tag = "survey",
+    responses((status = 200, description = "Empty survey", body = prono_api::Survey))
+)]
 #[get("/api/survey")]
 pub async fn get_survey(service: web::Data<SurveyService>) -> HttpResponse {
     let survey = service.empty_survey();
     HttpResponse::Ok().json(survey)
 }
 
-#[post("/api/survey/answer")]
+/// Stores the answer of a user to a question. Unlike its `/api/v1` successor, the route takes the user of the
+/// body without a session token, as deployed clients do not sign in. Users with an account answer through
+/// `/api/v1` only.
+#[utoipa::path(
+    tag = "survey",
+    request_body = AddAnswerRequest,
+    params(
+        ("X-Device-Id" = Option<String>, Header, description = "Device the username is bound to"),
+        ("X-Proof-Of-Work" = String, Header, description = "Stamp solving a challenge of `GET /api/v1/challenge`"),
+    ),
+    responses(
+        (status = 200, description = "Answer stored"),
+        (status = 400, description = "Malformed request body or invalid username", body = prono_api::ErrorBody),
+        (status = 401, description = "The username has an account, answer through `/api/v1` with its session token", body = prono_api::ErrorBody),
+        (status = 403, description = "Proof of work rejected, or username is registered to a different device", body = prono_api::ErrorBody),
+        (status = 409, description = "Question already answered", body = prono_api::ErrorBody),
+        (status = 413, description = "Request body too large", body = prono_api::ErrorBody),
+        (status = 429, description = "Too many requests from this IP address or device, see `Retry-After`", body = prono_api::ErrorBody),
+        (status = 500, description = "Server error", body = prono_api::ErrorBody),
+    )
+)]
+#[post("/api/survey/answer", wrap = "from_fn(rate_limit_per_device)")]
 pub async fn add_answer(
     service: web::Data<SurveyService>,
+    auth: web::Data<AuthService>,
     body: web::Json<AddAnswerRequest>,
     req: HttpRequest,
 ) -> BackendResult<HttpResponse> {
-    let device_id = req
-        .headers()
-        .get("X-Device-Id")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("");
     let body = body.into_inner();
+    let principal = auth.legacy_principal(&body.user).await?;
     service
-        .add_answer(&body.user, body.question_id, body.answer, device_id)
+        .add_answer(
+            &principal,
+            body.question_id,
+            body.answer,
+            device_id(&req),
+            proof_of_work(&req),
+            request_ip(&req).as_deref(),
+        )
         .await?;
     Ok(HttpResponse::Ok().finish())
 }
 
+/// Survey filled in with the answers of a user.
+#[utoipa::path(
+    tag = "survey",
+    params(
+        ("user" = String, Path, description = "Username"),
+        ("survey_id" = u64, Path, description = "Survey id"),
+    ),
+    responses(
+        (status = 200, description = "Survey with the answers of the user", body = prono_api::Survey),
+        (status = 403, description = "The survey is not public", body = prono_api::ErrorBody),
+        (status = 404, description = "User has no response for this survey", body = prono_api::ErrorBody),
+    )
+)]
 #[get("/api/survey/response/{user}/{survey_id}")]
-pub async fn get_response(service: web::Data<SurveyService>, path: web::Path<(String, u64)>) -> HttpResponse {
+pub async fn get_response(
+    service: web::Data<SurveyService>,
+    path: web::Path<(String, u64)>,
+) -> BackendResult<HttpResponse> {
     let (user, survey_id) = path.into_inner();
-    match service.response(&user, survey_id).await {
-        Some(survey) => HttpResponse::Ok().json(survey),
-        None => HttpResponse::NotFound().finish(),
+    match service.response(&user, survey_id).await? {
+        Some(survey) => Ok(HttpResponse::Ok().json(survey)),
+        None => Err(no_response(&user, survey_id)),
     }
 }
 
+/// Answers of all users to a question, as `[user, answer]` pairs.
+#[utoipa::path(
+    tag = "survey",
+    params(("question_id" = String, Path, description = "Question id")),
+    responses(
+        (status = 200, description = "Answers per user, or per alias in a pseudonymous survey", body = Vec<(String, prono_api::Answer)>),
+        (status = 403, description = "The survey only shares statistics", body = prono_api::ErrorBody),
+    )
+)]
 #[get("/api/survey/answers/{question_id}")]
-pub async fn get_all_answers(service: web::Data<SurveyService>, path: web::Path<String>) -> HttpResponse {
+pub async fn get_all_answers(
+    service: web::Data<SurveyService>,
+    path: web::Path<String>,
+) -> BackendResult<HttpResponse> {
     let question_id = path.into_inner();
-    let answers = service.all_answers(question_id).await;
-    HttpResponse::Ok().json(answers)
+    let answers = service.all_answers(question_id).await?;
+    Ok(HttpResponse::Ok().json(answers))
+}
+
+/// Answers added to the survey and resolved questions, as server-sent events.
+#[utoipa::path(
+    tag = "survey",
+    params(("survey_id" = u64, Path, description = "Survey id")),
+    responses(
+        (status = 200, description = "Stream of survey events", body = prono_api::SurveyEvent, content_type = "text/event-stream"),
+        (status = 404, description = "Unknown survey", body = prono_api::ErrorBody),
+    )
+)]
+#[get("/api/survey/{survey_id}/events")]
+pub async fn survey_events(service: web::Data<SurveyService>, path: web::Path<u64>) -> BackendResult<HttpResponse> {
+    let survey_id = path.into_inner();
+    match service.subscribe(survey_id).await? {
+        Some(subscription) => Ok(event_stream(subscription, std::convert::identity)),
+        None => Err(unknown_survey(survey_id)),
+    }
+}
+
+/// Statistics of the answers to each question, in the format of `GET /api/v1/survey/{survey_id}/stats`.
+#[utoipa::path(
+    tag = "survey",
+    params(("survey_id" = u64, Path, description = "Survey id")),
+    responses(
+        (status = 200, description = "Statistics per question", body = prono_api::v1::SurveyStats),
+        (status = 404, description = "Unknown survey", body = prono_api::ErrorBody),
+    )
+)]
+#[get("/api/survey/{survey_id}/stats")]
+pub async fn get_stats(service: web::Data<SurveyService>, path: web::Path<u64>) -> BackendResult<HttpResponse> {
+    let survey_id = path.into_inner();
+    match service.stats(survey_id).await {
+        Some(stats) => Ok(HttpResponse::Ok().json(crate::adapters::rest_v1::survey_stats_to_v1(survey_id, stats))),
+        None => Err(unknown_survey(survey_id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+    use actix_web::{App, test};
+
+    #[get("/ping")]
+    async fn ping() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    #[get("/api/v1/ping")]
+    async fn ping_v1() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    #[get("/api/survey/answers/{question_id}")]
+    async fn legacy_answers() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    #[post("/echo")]
+    async fn echo(body: web::Json<String>) -> HttpResponse {
+        HttpResponse::Ok().json(body.into_inner())
+    }
+
+    #[get("/items/{id}")]
+    async fn item(path: web::Path<u64>) -> HttpResponse {
+        HttpResponse::Ok().json(path.into_inner())
+    }
+
+    #[derive(Deserialize)]
+    struct Page {
+        #[allow(dead_code)]
+        page: u32,
+    }
+
+    #[get("/items")]
+    async fn items(_query: web::Query<Page>) -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    async fn allow_origin_header(allowed_origins: &[&str], origin: &str) -> Option<String> {
+        let allowed_origins: Vec<String> = allowed_origins.iter().map(ToString::to_string).collect();
+        let app = test::init_service(App::new().wrap(cors(&allowed_origins)).service(ping)).await;
+        let req = test::TestRequest::get()
+            .uri("/ping")
+            .insert_header((header::ORIGIN, origin))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        resp.headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .map(|value| value.to_str().unwrap().to_string())
+    }
+
+    #[actix_web::test]
+    async fn cors_allows_configured_origin() {
+        let header = allow_origin_header(&["https://prono.example"], "https://prono.example").await;
+        assert_eq!(header.as_deref(), Some("https://prono.example"));
+    }
+
+    #[actix_web::test]
+    async fn cors_rejects_unknown_origin() {
+        let header = allow_origin_header(&["https://prono.example"], "https://evil.example").await;
+        assert!(header.is_none());
+    }
+
+    #[actix_web::test]
+    async fn cors_wildcard_allows_any_origin() {
+        let header = allow_origin_header(&["*"], "https://anywhere.example").await;
+        assert_eq!(header.as_deref(), Some("https://anywhere.example"));
+    }
+
+    #[actix_web::test]
+    async fn json_config_rejects_bodies_over_limit() {
+        let app = test::init_service(App::new().app_data(json_config(16)).service(echo)).await;
+
+        let small = test::TestRequest::post().uri("/echo").set_json("short").to_request();
+        assert_eq!(test::call_service(&app, small).await.status(), StatusCode::OK);
+
+        let large = test::TestRequest::post()
+            .uri("/echo")
+            .set_json("this body is definitely longer than sixteen bytes")
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, large).await.status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    #[actix_web::test]
+    async fn malformed_path_and_query_are_invalid_requests() {
+        let app = test::init_service(
+            App::new()
+                .app_data(path_config())
+                .app_data(query_config())
+                .service(item)
+                .service(items),
+        )
+        .await;
+
+        for uri in ["/items/abc", "/items?page=first"] {
+            let resp = test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{uri}");
+            let body: prono_api::ErrorBody = test::read_body_json(resp).await;
+            assert_eq!(body.code, prono_api::ErrorCode::InvalidRequest);
+        }
+    }
+
+    async fn call_versioned(uri: &str, version: Option<&str>) -> ServiceResponse<impl MessageBody> {
+        let app = test::init_service(
+            App::new()
+                .wrap(actix_web::middleware::from_fn(api_version))
+                .service(ping)
+                .service(ping_v1)
+                .service(legacy_answers),
+        )
+        .await;
+        let mut req = test::TestRequest::get().uri(uri);
+        if let Some(version) = version {
+            req = req.insert_header((API_VERSION_HEADER, version));
+        }
+        test::call_service(&app, req.to_request()).await
+    }
+
+    #[actix_web::test]
+    async fn api_version_confirms_served_version() {
+        for requested in [None, Some("1")] {
+            let resp = call_versioned("/api/v1/ping", requested).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(resp.headers().get(API_VERSION_HEADER).unwrap(), "1");
+        }
+    }
+
+    #[actix_web::test]
+    async fn api_version_rejects_unsupported_version() {
+        let resp = call_versioned("/api/v1/ping", Some("2")).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        let body: prono_api::ErrorBody = test::read_body_json(resp).await;
+        assert_eq!(body.code, prono_api::ErrorCode::UnsupportedVersion);
+        assert_eq!(body.details.as_deref(), Some("2"));
+    }
+
+    #[actix_web::test]
+    async fn api_version_marks_legacy_routes_deprecated() {
+        let resp = call_versioned("/api/survey/answers/q1", None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("Deprecation").unwrap(), "@1792368000");
+        assert_eq!(resp.headers().get("Sunset").unwrap(), "Mon, 19 Apr 2027 00:00:00 GMT");
+        assert_eq!(
+            resp.headers().get(header::LINK).unwrap(),
+            "</api/v1/survey/answers/q1>; rel=\"successor-version\""
+        );
+        assert!(resp.headers().get(API_VERSION_HEADER).is_none());
+    }
+
+    #[actix_web::test]
+    async fn legacy_paths_are_matched_by_segment() {
+        assert!(is_legacy_path("/api/survey"));
+        assert!(is_legacy_path("/api/survey/answer"));
+        assert!(!is_legacy_path("/api/surveys"));
+        assert!(!is_legacy_path("/api/surveys/answer"));
+        assert!(!is_legacy_path("/api/v1/survey"));
+    }
+
+    #[actix_web::test]
+    async fn api_version_ignores_other_routes() {
+        let resp = call_versioned("/ping", Some("2")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get("Deprecation").is_none());
+        assert!(resp.headers().get(API_VERSION_HEADER).is_none());
+    }
+
+    #[actix_web::test]
+    async fn same_user_may_be_spelled_differently() {
+        let principal = Principal {
+            user: "sam".to_string(),
+        };
+
+        assert!(ensure_same_user(&principal, "Sam ").is_ok());
+        assert!(matches!(
+            ensure_same_user(&principal, "sam j"),
+            Err(Error::Forbidden(_))
+        ));
+        assert!(matches!(ensure_same_user(&principal, "sam@"), Err(Error::Forbidden(_))));
+    }
 }
@@ -0,0 +1,1086 @@
+use actix_web::middleware::from_fn;
+use actix_web::{HttpRequest, HttpResponse, Scope, delete, get, post, web};
+use prono::repo;
+use prono::stats::{DateStats, QuestionStats};
+use prono_api::v1;
+
+use crate::adapters::rest::{
+    bearer_token, device_id, ensure_same_user, event_stream, no_response, proof_of_work, rate_limit, request_ip,
+    unknown_survey,
+};
+use crate::adapters::ws;
+use crate::use_cases::*;
+use crate::{BackendResult, Error};
+
+/// Prefix of the versioned routes.
+pub static SCOPE: &str = "/api/v1";
+
+/// All versioned REST routes and the WebSocket, mounted under [`SCOPE`].
+#[must_use]
+pub fn scope() -> Scope {
+    web::scope(SCOPE)
+        .service(register)
+        .service(login)
+        .service(logout)
+        .service(list_devices)
+        .service(unregister_device)
+        .service(start_transfer)
+        .service(complete_transfer)
+        .service(get_user_status)
+        .service(get_challenge)
+        .service(get_survey)
+        .service(add_answer)
+        .service(get_response)
+        .service(get_all_answers)
+        .service(survey_events)
+        .service(get_stats)
+        .service(get_privacy)
+        .service(ws::connect)
+}
+
+impl From<IssuedSession> for v1::Session {
+    fn from(session: IssuedSession) -> Self {
+        Self {
+            token: session.token,
+            user: session.user,
+            expires_at: session.expires_at,
+        }
+    }
+}
+
+/// Creates an account and signs the new user in.
+#[utoipa::path(
+    context_path = "/api/v1",
+    tag = "auth",
+    request_body = v1::Credentials,
+    responses(
+        (status = 201, description = "Account created and signed in", body = v1::Session),
+        (status = 400, description = "Invalid username or too short password", body = prono_api::ErrorBody),
+        (status = 409, description = "Username is already taken", body = prono_api::ErrorBody),
+        (status = 429, description = "Too many requests from this IP address, see `Retry-After`", body = prono_api::ErrorBody),
+    )
+)]
+#[post("/auth/register", wrap = "from_fn(rate_limit)")]
+pub async fn register(auth: web::Data<AuthService>, body: web::Json<v1::Credentials>) -> BackendResult<HttpResponse> {
+    let session = auth.register(&body.user, &body.password).await?;
+    Ok(HttpResponse::Created().json(v1::Session::from(session)))
+}
+
+#[utoipa::path(
+    context_path = "/api/v1",
+    tag = "auth",
+    request_body = v1::Credentials,
+    responses(
+        (status = 200, description = "Signed in", body = v1::Session),
+        (status = 401, description = "Invalid username or password", body = prono_api::ErrorBody),
+        (status = 429, description = "Too many requests from this IP address, see `Retry-After`", body = prono_api::ErrorBody),
+    )
+)]
+#[post("/auth/login", wrap = "from_fn(rate_limit)")]
+pub async fn login(auth: web::Data<AuthService>, body: web::Json<v1::Credentials>) -> BackendResult<HttpResponse> {
+    let session = auth.login(&body.user, &body.password).await?;
+    Ok(HttpResponse::Ok().json(v1::Session::from(session)))
+}
+
+/// Ends the session of the sent token.
+#[utoipa::path(
+    context_path = "/api/v1",
+    tag = "auth",
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Signed out"),
+        (status = 401, description = "Missing, unknown or expired session token", body = prono_api::ErrorBody),
+    )
+)]
+#[post("/auth/logout")]
+pub async fn logout(
+    auth: web::Data<AuthService>,
+    _principal: Principal,
+    req: HttpRequest,
+) -> BackendResult<HttpResponse> {
+    if let Some(token) = bearer_token(&req) {
+        auth.logout(token).await?;
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub(crate) fn device_to_v1(device: repo::Device) -> v1::Device {
+    v1::Device {
+        id: device.id,
+        registered_at: device.registered_at,
+    }
+}
+
+impl From<TransferCode> for v1::TransferCode {
+    fn from(code: TransferCode) -> Self {
+        Self {
+            code: code.code,
+            expires_at: code.expires_at,
+        }
+    }
+}
+
+/// Devices the signed in user submitted answers from.
+#[utoipa::path(
+    context_path = "/api/v1",
+    tag = "devices",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Registered devices, oldest first", body = Vec<v1::Device>),
+        (status = 401, description = "Missing, unknown or expired session token", body = prono_api::ErrorBody),
+    )
+)]
+#[get("/devices")]
+pub async fn list_devices(devices: web::Data<DeviceService>, principal: Principal) -> BackendResult<HttpResponse> {
+    let devices: Vec<v1::Device> = devices
+        .list(&principal.user)
+        .await?
+        .into_iter()
+        .map(device_to_v1)
+        .collect();
+    Ok(HttpResponse::Ok().json(devices))
+}
+
+/// Removes a device of the signed in user, making room for another one.
+#[utoipa::path(
+    context_path = "/api/v1",
+    tag = "devices",
+    params(("device_id" = String, Path, description = "Device id")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Device removed"),
+        (status = 401, description = "Missing, unknown or expired session token", body = prono_api::ErrorBody),
+        (status = 404, description = "Device is not registered for the user", body = prono_api::ErrorBody),
+    )
+)]
+#[delete("/devices/{device_id}")]
+pub async fn unregister_device(
+    devices: web::Data<DeviceService>,
+    principal: Principal,
+    path: web::Path<String>,
+) -> BackendResult<HttpResponse> {
+    let device_id = path.into_inner();
+    if devices.unregister(&principal.user, &device_id).await? {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(Error::NotFound(format!(
+            "device {device_id} is not registered for the user"
+        )))
+    }
+}
+
+/// Issues a one-time code, sent from a registered device, to move its registration to another device.
+#[utoipa::path(
+    context_path = "/api/v1",
+    tag = "devices",
+    params(("X-Device-Id" = String, Header, description = "Registered device to move away from")),
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "Code to enter on the new device", body = v1::TransferCode),
+        (status = 401, description = "Missing, unknown or expired session token", body = prono_api::ErrorBody),
+        (status = 403, description = "The sending device is not registered for the user", body = prono_api::ErrorBody),
+    )
+)]
+#[post("/devices/transfer")]
+pub async fn start_transfer(
+    devices: web::Data<DeviceService>,
+    principal: Principal,
+    req: HttpRequest,
+) -> BackendResult<HttpResponse> {
+    let code = devices.start_transfer(&principal, device_id(&req)).await?;
+    Ok(HttpResponse::Created().json(v1::TransferCode::from(code)))
+}
+
+/// Registers the sending device in place of the device that requested the transfer code.
+#[utoipa::path(
+    context_path = "/api/v1",
+    tag = "devices",
+    request_body = v1::CompleteTransferRequest,
+    params(("X-Device-Id" = String, Header, description = "New device")),
+    security(("bearer" = [])),
+    responses(
+        (status = 204, description = "Device registration moved"),
+        (status = 400, description = "Missing device id, or unknown or expired code", body = prono_api::ErrorBody),
+        (status = 401, description = "Missing, unknown or expired session token", body = prono_api::ErrorBody),
+    )
+)]
+#[post("/devices/transfer/complete")]
+pub async fn complete_transfer(
+    devices: web::Data<DeviceService>,
+    principal: Principal,
+    body: web::Json<v1::CompleteTransferRequest>,
+    req: HttpRequest,
+) -> BackendResult<HttpResponse> {
+    devices
+        .complete_transfer(&principal, &body.code, device_id(&req))
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Whether a username is taken, registered to the device asking, and which surveys it completed, for clients to
+/// warn while the name is typed. Rate limited per IP address like signing in, as it tells whether a name exists.
+#[utoipa::path(
+    context_path = "/api/v1",
+    tag = "auth",
+    params(
+        ("name" = String, Path, description = "Username, compared in its normalized form"),
+        ("X-Device-Id" = Option<String>, Header, description = "Device to check the registrations of the name against"),
+    ),
+    responses(
+        (status = 200, description = "Status of the username", body = v1::UserStatus),
+        (status = 400, description = "Invalid username", body = prono_api::ErrorBody),
+        (status = 429, description = "Too many requests from this IP address, see `Retry-After`", body = prono_api::ErrorBody),
+        (status = 500, description = "Server error", body = prono_api::ErrorBody),
+    )
+)]
+#[get("/users/{name}/status", wrap = "from_fn(rate_limit)")]
+pub async fn get_user_status(
+    users: web::Data<UserService>,
+    path: web::Path<String>,
+    req: HttpRequest,
+) -> BackendResult<HttpResponse> {
+    let status = users.status(&path.into_inner(), device_id(&req)).await?;
+    Ok(HttpResponse::Ok().json(v1::UserStatus::from(status)))
+}
+
+/// Proof-of-work challenge. Answers are only accepted with a stamp solving a fresh challenge in the
+/// `X-Proof-Of-Work` header, see [`prono_api::Challenge`].
+#[utoipa::path(
+    context_path = "/api/v1",
+    tag = "survey",
+    responses(
+        (status = 200, description = "Challenge to solve before submitting an answer", body = prono_api::Challenge),
+        (status = 429, description = "Too many requests from this IP address or user, see `Retry-After`", body = prono_api::ErrorBody),
+    )
+)]
+#[get("/challenge", wrap = "from_fn(rate_limit)")]
+pub async fn get_challenge(service: web::Data<SurveyService>) -> BackendResult<HttpResponse> {
+    Ok(HttpResponse::Ok().json(service.challenge()?))
+}
+
+/// Empty survey with all its questions, without answers.
+#[utoipa::path(
+    context_path = "/api/v1",
+    tag = "survey",
+    responses((status = 200, description = "Empty survey", body = v1::Survey))
+)]
+#[get("/survey")]
+pub async fn get_survey(service: web::Data<SurveyService>) -> HttpResponse {
+    HttpResponse::Ok().json(v1::Survey::from(service.empty_survey()))
+}
+
+/// Stores the answer of a user to a question.
+#[utoipa::path(
+    context_path = "/api/v1",
+    tag = "survey",
+    request_body = v1::AddAnswerRequest,
+    params(
+        ("X-Device-Id" = Option<String>, Header, description = "Device the username is bound to"),
+        ("X-Proof-Of-Work" = String, Header, description = "Stamp solving a challenge of `GET /api/v1/challenge`"),
+    ),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Answer stored"),
+        (status = 400, description = "Malformed request body", body = prono_api::ErrorBody),
+        (status = 401, description = "Missing, unknown or expired session token", body = prono_api::ErrorBody),
+        (status = 403, description = "Proof of work rejected, signed in as another user, or username is registered to a different device", body = prono_api::ErrorBody),
+        (status = 409, description = "Question already answered", body = prono_api::ErrorBody),
+        (status = 413, description = "Request body too large", body = prono_api::ErrorBody),
+        (status = 429, description = "Too many requests from this IP address or user, see `Retry-After`", body = prono_api::ErrorBody),
+        (status = 500, description = "Server error", body = prono_api::ErrorBody),
+    )
+)]
+#[post("/survey/answer", wrap = "from_fn(rate_limit)")]
+pub async fn add_answer(
+    service: web::Data<SurveyService>,
+    principal: Principal,
+    body: web::Json<v1::AddAnswerRequest>,
+    req: HttpRequest,
+) -> BackendResult<HttpResponse> {
+    let body = body.into_inner();
+    ensure_same_user(&principal, &body.user)?;
+    service
+        .add_answer(
+            &principal,
+            body.question_id,
+            body.answer.into(),
+            device_id(&req),
+            proof_of_work(&req),
+            request_ip(&req).as_deref(),
+        )
+        .await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Survey filled in with the answers of a user.
+#[utoipa::path(
+    context_path = "/api/v1",
+    tag = "survey",
+    params(
+        ("user" = String, Path, description = "Username"),
+        ("survey_id" = u64, Path, description = "Survey id"),
+    ),
+    responses(
+        (status = 200, description = "Survey with the answers of the user", body = v1::Survey),
+        (status = 403, description = "The survey is not public", body = prono_api::ErrorBody),
+        (status = 404, description = "User has no response for this survey", body = prono_api::ErrorBody),
+    )
+)]
+#[get("/survey/response/{user}/{survey_id}")]
+pub async fn get_response(
+    service: web::Data<SurveyService>,
+    path: web::Path<(String, u64)>,
+) -> BackendResult<HttpResponse> {
+    let (user, survey_id) = path.into_inner();
+    match service.response(&user, survey_id).await? {
+        Some(survey) => Ok(HttpResponse::Ok().json(v1::Survey::from(survey))),
+        None => Err(no_response(&user, survey_id)),
+    }
+}
+
+/// Answers of all users to a question. A pseudonymous survey shows aliases instead of usernames.
+#[utoipa::path(
+    context_path = "/api/v1",
+    tag = "survey",
+    params(("question_id" = String, Path, description = "Question id")),
+    responses(
+        (status = 200, description = "Answers per user", body = Vec<v1::UserAnswer>),
+        (status = 403, description = "The survey only shares statistics", body = prono_api::ErrorBody),
+    )
+)]
+#[get("/survey/answers/{question_id}")]
+pub async fn get_all_answers(
+    service: web::Data<SurveyService>,
+    path: web::Path<String>,
+) -> BackendResult<HttpResponse> {
+    let answers: Vec<v1::UserAnswer> = service
+        .all_answers(path.into_inner())
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    Ok(HttpResponse::Ok().json(answers))
+}
+
+/// Who sees the individual answers of the survey.
+#[utoipa::path(
+    context_path = "/api/v1",
+    tag = "survey",
+    params(("survey_id" = u64, Path, description = "Survey id")),
+    responses(
+        (status = 200, description = "Privacy of the survey", body = v1::SurveyPrivacy),
+        (status = 404, description = "Unknown survey", body = prono_api::ErrorBody),
+    )
+)]
+#[get("/survey/{survey_id}/privacy")]
+pub async fn get_privacy(service: web::Data<SurveyService>, path: web::Path<u64>) -> BackendResult<HttpResponse> {
+    let survey_id = path.into_inner();
+    match service.privacy(survey_id).await? {
+        Some(privacy) => Ok(HttpResponse::Ok().json(v1::SurveyPrivacy {
+            privacy: privacy.into(),
+        })),
+        None => Err(unknown_survey(survey_id)),
+    }
+}
+
+pub(crate) fn survey_stats_to_v1(survey_id: u64, stats: Vec<QuestionStats>) -> v1::SurveyStats {
+    v1::SurveyStats {
+        survey_id,
+        questions: stats
+            .into_iter()
+            .map(|question| v1::QuestionStats {
+                question_id: question.question_id,
+                answers: question.answers,
+                dates: question.dates.map(date_stats_to_v1),
+            })
+            .collect(),
+    }
+}
+
+fn date_stats_to_v1(dates: DateStats) -> v1::DateStats {
+    v1::DateStats {
+        median: dates.median.to_string(),
+        mean: dates.mean.to_string(),
+        first_quartile: dates.first_quartile.to_string(),
+        third_quartile: dates.third_quartile.to_string(),
+        earliest: dates.earliest.to_string(),
+        latest: dates.latest.to_string(),
+        histogram: dates
+            .monthly_histogram
+            .into_iter()
+            .map(|(year, month, count)| v1::MonthBucket { year, month, count })
+            .collect(),
+    }
+}
+
+/// Statistics of the answers to each question, without usernames. Date statistics of questions with fewer
+/// predictions than `stats_min_answers` are left out.
+#[utoipa::path(
+    context_path = "/api/v1",
+    tag = "survey",
+    params(("survey_id" = u64, Path, description = "Survey id")),
+    responses(
+        (status = 200, description = "Statistics per question", body = v1::SurveyStats),
+        (status = 404, description = "Unknown survey", body = prono_api::ErrorBody),
+    )
+)]
+#[get("/survey/{survey_id}/stats")]
+pub async fn get_stats(service: web::Data<SurveyService>, path: web::Path<u64>) -> BackendResult<HttpResponse> {
+    let survey_id = path.into_inner();
+    match service.stats(survey_id).await {
+        Some(stats) => Ok(HttpResponse::Ok().json(survey_stats_to_v1(survey_id, stats))),
+        None => Err(unknown_survey(survey_id)),
+    }
+}
+
+/// Answers added to the survey and resolved questions, as server-sent events.
+#[utoipa::path(
+    context_path = "/api/v1",
+    tag = "survey",
+    params(("survey_id" = u64, Path, description = "Survey id")),
+    responses(
+        (status = 200, description = "Stream of survey events", body = v1::SurveyEvent, content_type = "text/event-stream"),
+        (status = 404, description = "Unknown survey", body = prono_api::ErrorBody),
+    )
+)]
+#[get("/survey/{survey_id}/events")]
+pub async fn survey_events(service: web::Data<SurveyService>, path: web::Path<u64>) -> BackendResult<HttpResponse> {
+    let survey_id = path.into_inner();
+    match service.subscribe(survey_id).await? {
+        Some(subscription) => Ok(event_stream(subscription, v1::SurveyEvent::from)),
+        None => Err(unknown_survey(survey_id)),
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+
+    use std::num::NonZeroU32;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use std::pin::pin;
+
+    use actix_web::App;
+    use actix_web::body::MessageBody;
+    use actix_web::dev::{Service, ServiceResponse};
+    use actix_web::http::{StatusCode, header};
+    use actix_web::test::{TestRequest, call_service, init_service, read_body_json};
+    use prono::fake_db::FakeRepo;
+    use prono::repo::Db as _;
+    use serde_json::json;
+
+    async fn app()
+    -> impl Service<actix_http::Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error> {
+        let db = Arc::new(FakeRepo::init(()).await.unwrap());
+        let devices = web::Data::new(DeviceService::new(
+            db.clone(),
+            repo::DevicePolicy { max_devices: 1 },
+            Duration::from_secs(60),
+        ));
+        init_service(
+            App::new()
+                .app_data(web::Data::new(SurveyService::new(
+                    db.clone(),
+                    db.clone(),
+                    devices.clone().into_inner(),
+                    Challenges::new(4, Duration::from_secs(60)),
+                )))
+                .app_data(web::Data::new(UserService::new(
+                    db.clone(),
+                    db.clone(),
+                    db.clone(),
+                    devices.clone().into_inner(),
+                )))
+                .app_data(devices)
+                .app_data(web::Data::new(AuthService::new(db, Duration::from_secs(60))))
+                .service(crate::adapters::rest::add_answer)
+                .service(scope()),
+        )
+        .await
+    }
+
+    async fn sign_up(
+        app: &impl Service<actix_http::Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error>,
+        user: &str,
+    ) -> String {
+        let req = TestRequest::post()
+            .uri("/api/v1/auth/register")
+            .set_json(json!({"user": user, "password": "long enough"}))
+            .to_request();
+        let resp = call_service(app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let session: v1::Session = read_body_json(resp).await;
+        session.token
+    }
+
+    async fn solve_challenge(
+        app: &impl Service<actix_http::Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error>,
+    ) -> String {
+        let resp = call_service(app, TestRequest::get().uri("/api/v1/challenge").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let challenge: prono_api::Challenge = read_body_json(resp).await;
+        challenge.solve()
+    }
+
+    fn answer_request(user: &str, token: Option<&str>) -> actix_http::Request {
+        answer_request_from("device-1", user, token)
+    }
+
+    fn answer_request_from(device_id: &str, user: &str, token: Option<&str>) -> actix_http::Request {
+        let mut req = TestRequest::post()
+            .uri("/api/v1/survey/answer")
+            .insert_header(("X-Device-Id", device_id))
+            .set_json(json!({"user": user, "question_id": "q1", "answer": {"type": "text", "text": "yes"}}));
+        if let Some(token) = token {
+            req = req.insert_header((header::AUTHORIZATION, format!("Bearer {token}")));
+        }
+        req.to_request()
+    }
+
+    #[actix_web::test]
+    async fn answers_round_trip_in_v1_format() {
+        let app = app().await;
+        let token = sign_up(&app, "sam").await;
+
+        let survey: serde_json::Value =
+            read_body_json(call_service(&app, TestRequest::get().uri("/api/v1/survey").to_request()).await).await;
+        let question_id = survey["questions"][0]["id"].as_str().unwrap().to_string();
+        assert!(survey["questions"][0]["answer"]["type"].is_string());
+
+        let req = TestRequest::post()
+            .uri("/api/v1/survey/answer")
+            .insert_header(("X-Device-Id", "device-1"))
+            .insert_header(("X-Proof-Of-Work", solve_challenge(&app).await))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+            .set_json(json!({"user": "sam", "question_id": question_id, "answer": {"type": "text", "text": "yes"}}))
+            .to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = TestRequest::get()
+            .uri(&format!("/api/v1/survey/answers/{question_id}"))
+            .to_request();
+        let answers: serde_json::Value = read_body_json(call_service(&app, req).await).await;
+        assert_eq!(
+            answers,
+            json!([{"user": "sam", "answer": {"type": "text", "text": "yes"}}])
+        );
+    }
+
+    #[actix_web::test]
+    async fn stats_count_answers_without_usernames() {
+        let app = app().await;
+        let token = sign_up(&app, "sam").await;
+        let survey = prono::empty_survey();
+        let question_id = survey.questions[0].id.clone();
+        let req = TestRequest::post()
+            .uri("/api/v1/survey/answer")
+            .insert_header(("X-Device-Id", "device-1"))
+            .insert_header(("X-Proof-Of-Work", solve_challenge(&app).await))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+            .set_json(json!({
+                "user": "sam",
+                "question_id": question_id,
+                "answer": {"type": "prediction_date", "day": 3, "month": 4, "year": 2030},
+            }))
+            .to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = TestRequest::get()
+            .uri(&format!("/api/v1/survey/{}/stats", survey.id))
+            .to_request();
+        let stats: v1::SurveyStats = read_body_json(call_service(&app, req).await).await;
+
+        assert_eq!(stats.survey_id, survey.id);
+        assert_eq!(stats.questions.len(), survey.questions.len());
+        assert_eq!(stats.questions[0].answers, 1);
+        let dates = stats.questions[0].dates.as_ref().unwrap();
+        assert_eq!(dates.median, "2030-04-03");
+        assert_eq!(
+            dates.histogram,
+            [v1::MonthBucket {
+                year: 2030,
+                month: 4,
+                count: 1
+            }]
+        );
+        assert!(!serde_json::to_string(&stats).unwrap().contains("sam"));
+    }
+
+    #[actix_web::test]
+    async fn stats_of_unknown_survey_are_not_found() {
+        let app = app().await;
+
+        let req = TestRequest::get().uri("/api/v1/survey/999/stats").to_request();
+
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn surveys_are_public_by_default() {
+        let app = app().await;
+        let survey = prono::empty_survey();
+
+        let req = TestRequest::get()
+            .uri(&format!("/api/v1/survey/{}/privacy", survey.id))
+            .to_request();
+        let body: v1::SurveyPrivacy = read_body_json(call_service(&app, req).await).await;
+        assert_eq!(body.privacy, v1::Privacy::Public);
+
+        let req = TestRequest::get().uri("/api/v1/survey/999/privacy").to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    async fn user_status(
+        app: &impl Service<actix_http::Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error>,
+        user: &str,
+        device_id: &str,
+    ) -> v1::UserStatus {
+        let req = TestRequest::get()
+            .uri(&format!("/api/v1/users/{user}/status"))
+            .insert_header(("X-Device-Id", device_id))
+            .to_request();
+        let resp = call_service(app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        read_body_json(resp).await
+    }
+
+    #[actix_web::test]
+    async fn user_status_tells_whether_the_name_is_taken_elsewhere() {
+        let app = app().await;
+        assert!(!user_status(&app, "sam", "device-1").await.exists);
+
+        let token = sign_up(&app, "sam").await;
+        let req = TestRequest::post()
+            .uri("/api/v1/survey/answer")
+            .insert_header(("X-Device-Id", "device-1"))
+            .insert_header(("X-Proof-Of-Work", solve_challenge(&app).await))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+            .set_json(json!({"user": "sam", "question_id": "q1", "answer": {"type": "text", "text": "yes"}}))
+            .to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+
+        let own = user_status(&app, "Sam", "device-1").await;
+        assert!(own.exists && own.this_device);
+        let other = user_status(&app, "sam", "device-2").await;
+        assert!(other.exists && !other.this_device);
+        assert!(other.completed_surveys.is_empty());
+
+        let req = TestRequest::get().uri("/api/v1/users/sam!/status").to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+    }
+
+    async fn rate_limited_app(
+        per_ip: repo::RateLimit,
+        per_user: repo::RateLimit,
+    ) -> impl Service<actix_http::Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error> {
+        let db = Arc::new(FakeRepo::init(()).await.unwrap());
+        let devices = Arc::new(DeviceService::new(
+            db.clone(),
+            repo::DevicePolicy { max_devices: 1 },
+            Duration::from_secs(60),
+        ));
+        init_service(
+            App::new()
+                .app_data(web::Data::new(SurveyService::new(
+                    db.clone(),
+                    db.clone(),
+                    devices.clone(),
+                    Challenges::new(4, Duration::from_secs(60)),
+                )))
+                .app_data(web::Data::new(UserService::new(
+                    db.clone(),
+                    db.clone(),
+                    db.clone(),
+                    devices,
+                )))
+                .app_data(web::Data::new(AuthService::new(db, Duration::from_secs(60))))
+                .app_data(web::Data::new(RateLimiter::new(
+                    Arc::new(MemoryRateLimitStore::default()),
+                    per_ip,
+                    per_user,
+                )))
+                .service(crate::adapters::rest::add_answer)
+                .service(scope()),
+        )
+        .await
+    }
+
+    #[actix_web::test]
+    async fn answers_beyond_the_user_limit_are_rejected_with_retry_after() {
+        let per_ip = repo::RateLimit {
+            burst: 10,
+            per_minute: NonZeroU32::new(10).unwrap(),
+        };
+        let per_user = repo::RateLimit {
+            burst: 1,
+            per_minute: NonZeroU32::new(2).unwrap(),
+        };
+        let app = rate_limited_app(per_ip, per_user).await;
+        let sam = sign_up(&app, "sam").await;
+        let alex = sign_up(&app, "alex").await;
+
+        let resp = call_service(&app, answer_request("sam", Some(&sam))).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        // Another device id does not get the user a new bucket.
+        let resp = call_service(&app, answer_request_from("device-2", "sam", Some(&sam))).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers().get(header::RETRY_AFTER).unwrap(), "30");
+        let body: prono_api::ErrorBody = read_body_json(resp).await;
+        assert_eq!(body.code, prono_api::ErrorCode::RateLimited);
+
+        let resp = call_service(&app, answer_request("alex", Some(&alex))).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn legacy_answers_are_rate_limited_per_device() {
+        let per_ip = repo::RateLimit {
+            burst: 10,
+            per_minute: NonZeroU32::new(10).unwrap(),
+        };
+        let per_user = repo::RateLimit {
+            burst: 1,
+            per_minute: NonZeroU32::new(2).unwrap(),
+        };
+        let app = rate_limited_app(per_ip, per_user).await;
+        let legacy_answer = |device_id: &str, user: &str| {
+            TestRequest::post()
+                .uri("/api/survey/answer")
+                .insert_header(("X-Device-Id", device_id))
+                .set_json(json!({"user": user, "question_id": "q1", "answer": {"Text": "yes"}}))
+                .to_request()
+        };
+
+        let resp = call_service(&app, legacy_answer("device-1", "sam")).await;
+        assert_ne!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Another name does not get the device a new bucket.
+        let resp = call_service(&app, legacy_answer("device-1", "alex")).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers().get(header::RETRY_AFTER).unwrap(), "30");
+
+        let resp = call_service(&app, legacy_answer("device-2", "sam")).await;
+        assert_ne!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[actix_web::test]
+    async fn challenges_and_sign_ins_are_rate_limited_per_ip() {
+        let per_ip = repo::RateLimit {
+            burst: 2,
+            per_minute: NonZeroU32::MIN,
+        };
+        let app = rate_limited_app(per_ip, per_ip).await;
+        let from = |req: TestRequest, ip: &str| req.peer_addr(format!("{ip}:40000").parse().unwrap()).to_request();
+        let credentials = json!({"user": "sam", "password": "long enough"});
+
+        let resp = call_service(&app, from(TestRequest::get().uri("/api/v1/challenge"), "10.0.0.1")).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let login_req = TestRequest::post().uri("/api/v1/auth/login").set_json(&credentials);
+        let resp = call_service(&app, from(login_req, "10.0.0.1")).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let register_req = TestRequest::post().uri("/api/v1/auth/register").set_json(&credentials);
+        let resp = call_service(&app, from(register_req, "10.0.0.1")).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        let register_req = TestRequest::post().uri("/api/v1/auth/register").set_json(&credentials);
+        let resp = call_service(&app, from(register_req, "10.0.0.2")).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    #[actix_web::test]
+    async fn user_status_is_rate_limited_per_ip() {
+        let per_ip = repo::RateLimit {
+            burst: 1,
+            per_minute: NonZeroU32::MIN,
+        };
+        let app = rate_limited_app(per_ip, per_ip).await;
+        let from = |ip: &str| {
+            TestRequest::get()
+                .uri("/api/v1/users/sam/status")
+                .peer_addr(format!("{ip}:40000").parse().unwrap())
+                .to_request()
+        };
+
+        assert_eq!(call_service(&app, from("10.0.0.1")).await.status(), StatusCode::OK);
+        let resp = call_service(&app, from("10.0.0.1")).await;
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(resp.headers().contains_key(header::RETRY_AFTER));
+        assert_eq!(call_service(&app, from("10.0.0.2")).await.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn legacy_answer_format_is_rejected() {
+        let app = app().await;
+        let token = sign_up(&app, "sam").await;
+
+        let req = TestRequest::post()
+            .uri("/api/v1/survey/answer")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+            .set_json(json!({"user": "sam", "question_id": "q1", "answer": {"Text": "yes"}}))
+            .to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn answer_without_token_is_unauthorized() {
+        let app = app().await;
+
+        let resp = call_service(&app, answer_request("sam", None)).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let resp = call_service(&app, answer_request("sam", Some("forged"))).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn legacy_answer_route_needs_no_token() {
+        let app = app().await;
+
+        let req = TestRequest::post()
+            .uri("/api/survey/answer")
+            .insert_header(("X-Device-Id", "device-1"))
+            .insert_header(("X-Proof-Of-Work", solve_challenge(&app).await))
+            .set_json(json!({"user": " Sam", "question_id": "q1", "answer": {"Text": "yes"}}))
+            .to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = TestRequest::get().uri("/api/v1/survey/answers/q1").to_request();
+        let answers: serde_json::Value = read_body_json(call_service(&app, req).await).await;
+        assert_eq!(
+            answers,
+            json!([{"user": "sam", "answer": {"type": "text", "text": "yes"}}])
+        );
+    }
+
+    #[actix_web::test]
+    async fn legacy_answer_route_refuses_names_with_an_account() {
+        let app = app().await;
+        sign_up(&app, "sam").await;
+
+        let req = TestRequest::post()
+            .uri("/api/survey/answer")
+            .insert_header(("X-Device-Id", "device-2"))
+            .insert_header(("X-Proof-Of-Work", solve_challenge(&app).await))
+            .set_json(json!({"user": "Sam", "question_id": "q1", "answer": {"Text": "yes"}}))
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        let body: prono_api::ErrorBody = read_body_json(resp).await;
+        assert_eq!(body.code, prono_api::ErrorCode::Unauthorized);
+
+        let req = TestRequest::get().uri("/api/v1/survey/answers/q1").to_request();
+        let answers: serde_json::Value = read_body_json(call_service(&app, req).await).await;
+        assert_eq!(answers, json!([]));
+    }
+
+    #[actix_web::test]
+    async fn answer_for_other_user_is_forbidden() {
+        let app = app().await;
+        let token = sign_up(&app, "sam").await;
+
+        let resp = call_service(&app, answer_request("alex", Some(&token))).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let body: prono_api::ErrorBody = read_body_json(resp).await;
+        assert_eq!(body.code, prono_api::ErrorCode::Forbidden);
+    }
+
+    #[actix_web::test]
+    async fn answer_without_proof_of_work_is_forbidden() {
+        let app = app().await;
+        let token = sign_up(&app, "sam").await;
+
+        let resp = call_service(&app, answer_request("sam", Some(&token))).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let body: prono_api::ErrorBody = read_body_json(resp).await;
+        assert_eq!(body.code, prono_api::ErrorCode::ChallengeFailed);
+
+        // A used stamp is refused for another question too.
+        let stamp = solve_challenge(&app).await;
+        for (question_id, expected) in [("q1", StatusCode::OK), ("q2", StatusCode::FORBIDDEN)] {
+            let req = TestRequest::post()
+                .uri("/api/v1/survey/answer")
+                .insert_header(("X-Device-Id", "device-1"))
+                .insert_header(("X-Proof-Of-Work", stamp.as_str()))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+                .set_json(json!({"user": "sam", "question_id": question_id, "answer": {"type": "text", "text": "yes"}}))
+                .to_request();
+            assert_eq!(call_service(&app, req).await.status(), expected);
+        }
+    }
+
+    #[actix_web::test]
+    async fn login_and_logout() {
+        let app = app().await;
+        sign_up(&app, "sam").await;
+
+        let req = TestRequest::post()
+            .uri("/api/v1/auth/login")
+            .set_json(json!({"user": "sam", "password": "wrong password"}))
+            .to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+        let req = TestRequest::post()
+            .uri("/api/v1/auth/login")
+            .set_json(json!({"user": "sam", "password": "long enough"}))
+            .to_request();
+        let session: v1::Session = read_body_json(call_service(&app, req).await).await;
+        assert_eq!(session.user, "sam");
+
+        let req = TestRequest::post()
+            .uri("/api/v1/auth/logout")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", session.token)))
+            .to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+
+        let resp = call_service(&app, answer_request("sam", Some(&session.token))).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    async fn answer_with_proof_of_work(
+        app: &impl Service<actix_http::Request, Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error>,
+        device_id: &str,
+        token: &str,
+        question_id: &str,
+    ) -> StatusCode {
+        let req = TestRequest::post()
+            .uri("/api/v1/survey/answer")
+            .insert_header(("X-Device-Id", device_id))
+            .insert_header(("X-Proof-Of-Work", solve_challenge(app).await))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+            .set_json(json!({"user": "sam", "question_id": question_id, "answer": {"type": "text", "text": "yes"}}))
+            .to_request();
+        call_service(app, req).await.status()
+    }
+
+    #[actix_web::test]
+    async fn transfer_code_moves_answers_to_new_device() {
+        let app = app().await;
+        let token = sign_up(&app, "sam").await;
+        assert_eq!(
+            answer_with_proof_of_work(&app, "old", &token, "q1").await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            answer_with_proof_of_work(&app, "new", &token, "q2").await,
+            StatusCode::FORBIDDEN
+        );
+
+        let req = TestRequest::post()
+            .uri("/api/v1/devices/transfer")
+            .insert_header(("X-Device-Id", "old"))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let code: v1::TransferCode = read_body_json(resp).await;
+
+        let req = TestRequest::post()
+            .uri("/api/v1/devices/transfer/complete")
+            .insert_header(("X-Device-Id", "new"))
+            .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+            .set_json(json!({"code": code.code}))
+            .to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+
+        assert_eq!(
+            answer_with_proof_of_work(&app, "new", &token, "q2").await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            answer_with_proof_of_work(&app, "old", &token, "q3").await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[actix_web::test]
+    async fn devices_are_listed_and_removed() {
+        let app = app().await;
+        let token = sign_up(&app, "sam").await;
+        assert_eq!(
+            answer_with_proof_of_work(&app, "old", &token, "q1").await,
+            StatusCode::OK
+        );
+
+        let req = TestRequest::get()
+            .uri("/api/v1/devices")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+            .to_request();
+        let devices: Vec<v1::Device> = read_body_json(call_service(&app, req).await).await;
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].id, "old");
+
+        for expected in [StatusCode::NO_CONTENT, StatusCode::NOT_FOUND] {
+            let req = TestRequest::delete()
+                .uri("/api/v1/devices/old")
+                .insert_header((header::AUTHORIZATION, format!("Bearer {token}")))
+                .to_request();
+            let resp = call_service(&app, req).await;
+            assert_eq!(resp.status(), expected);
+            if expected == StatusCode::NOT_FOUND {
+                let body: prono_api::ErrorBody = read_body_json(resp).await;
+                assert_eq!(body.code, prono_api::ErrorCode::NotFound);
+            }
+        }
+        assert_eq!(
+            answer_with_proof_of_work(&app, "new", &token, "q2").await,
+            StatusCode::OK
+        );
+    }
+
+    #[actix_web::test]
+    async fn survey_events_stream_added_answers() {
+        let app = app().await;
+        let token = sign_up(&app, "sam").await;
+
+        let survey: v1::Survey =
+            read_body_json(call_service(&app, TestRequest::get().uri("/api/v1/survey").to_request()).await).await;
+
+        let req = TestRequest::get()
+            .uri(&format!("/api/v1/survey/{}/events", survey.id))
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get(header::CONTENT_TYPE).unwrap(), "text/event-stream");
+        let mut body = pin!(resp.into_body());
+        let mut next_chunk = async || {
+            let Some(Ok(chunk)) = std::future::poll_fn(|cx| body.as_mut().poll_next(cx)).await else {
+                panic!("event stream ended");
+            };
+            String::from_utf8(chunk.to_vec()).unwrap()
+        };
+        assert_eq!(next_chunk().await, ": keep-alive\n\n");
+
+        assert_eq!(
+            answer_with_proof_of_work(&app, "device-1", &token, "q1").await,
+            StatusCode::OK
+        );
+        let chunk = next_chunk().await;
+        let event: v1::SurveyEvent = serde_json::from_str(chunk.strip_prefix("data: ").unwrap().trim_end()).unwrap();
+        assert_eq!(
+            event,
+            v1::SurveyEvent::AnswerAdded {
+                question_id: "q1".to_string(),
+                user: "sam".to_string(),
+                answer: v1::Answer::Text {
+                    text: "yes".to_string()
+                },
+            }
+        );
+    }
+
+    #[actix_web::test]
+    async fn events_of_unknown_survey_are_not_found() {
+        let app = app().await;
+        let survey: v1::Survey =
+            read_body_json(call_service(&app, TestRequest::get().uri("/api/v1/survey").to_request()).await).await;
+
+        let req = TestRequest::get()
+            .uri(&format!("/api/v1/survey/{}/events", survey.id + 1))
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,310 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+
+use actix_web::{HttpRequest, HttpResponse, http::header, web};
+use log::{error, info};
+use rustls::crypto::{CryptoProvider, ring};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+
+use crate::{BackendResult, Error};
+
+/// Port the HTTPS server listens on, used as target of the plain HTTP redirect.
+#[derive(Clone, Copy)]
+pub struct HttpsPort(pub u16);
+
+/// Certificate resolver that serves the certificate from the configured PEM files
+/// and swaps it in place when the files are renewed on disk.
+#[derive(Debug)]
+pub struct CertReloader {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    provider: Arc<CryptoProvider>,
+    current: RwLock<LoadedCert>,
+}
+
+#[derive(Debug)]
+struct LoadedCert {
+    certified_key: Arc<CertifiedKey>,
+    pem: PemFiles,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct PemFiles {
+    cert: Vec<u8>,
+    key: Vec<u8>,
+}
+
+impl CertReloader {
+    /// # Errors
+    ///
+    /// Returns an error if the certificate or key file cannot be read or parsed,
+    /// or if the key does not belong to the certificate.
+    pub fn load(cert_path: impl Into<PathBuf>, key_path: impl Into<PathBuf>) -> BackendResult<Self> {
+        let cert_path = cert_path.into();
+        let key_path = key_path.into();
+        let provider = Arc::new(ring::default_provider());
+        let current = load_cert(&cert_path, &key_path, &provider)?;
+        info!("Loaded TLS certificate from {}", cert_path.display());
+
+        Ok(Self {
+            cert_path,
+            key_path,
+            provider,
+            current: RwLock::new(current),
+        })
+    }
+
+    /// Reloads the certificate when the content of the PEM files changed.
+    /// Returns whether a new certificate is now being served.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the renewed files cannot be read or parsed, in which
+    /// case the previous certificate stays in use.
+    pub fn reload_if_changed(&self) -> BackendResult<bool> {
+        let pem = read_pem_files(&self.cert_path, &self.key_path)?;
+        if self.current.read().unwrap_or_else(PoisonError::into_inner).pem == pem {
+            return Ok(false);
+        }
+
+        let reloaded = load_cert(&self.cert_path, &self.key_path, &self.provider)?;
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) = reloaded;
+        info!("Reloaded TLS certificate from {}", self.cert_path.display());
+        Ok(true)
+    }
+
+    #[must_use]
+    pub fn certified_key(&self) -> Arc<CertifiedKey> {
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .certified_key
+            .clone()
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the crypto provider does not support the default protocol versions.
+    pub fn server_config(self: &Arc<Self>) -> BackendResult<rustls::ServerConfig> {
+        let config = rustls::ServerConfig::builder_with_provider(self.provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| Error::Config(format!("TLS setup failed: {e}")))?
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone());
+        Ok(config)
+    }
+
+    /// Periodically checks the certificate files for changes on the current actix runtime.
+    pub fn spawn_reload_task(self: &Arc<Self>, interval: Duration) {
+        let reloader = self.clone();
+        actix_web::rt::spawn(async move {
+            let mut ticker = actix_web::rt::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = reloader.reload_if_changed() {
+                    error!("Keeping current TLS certificate: {e}");
+                }
+            }
+        });
+    }
+}
+
+impl ResolvesServerCert for CertReloader {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.certified_key())
+    }
+}
+
+fn read_pem_files(cert_path: &Path, key_path: &Path) -> BackendResult<PemFiles> {
+    let read = |path: &Path| fs::read(path).map_err(|e| Error::Config(format!("Cannot read {}: {e}", path.display())));
+    Ok(PemFiles {
+        cert: read(cert_path)?,
+        key: read(key_path)?,
+    })
+}
+
+fn load_cert(cert_path: &Path, key_path: &Path, provider: &CryptoProvider) -> BackendResult<LoadedCert> {
+    let pem = read_pem_files(cert_path, key_path)?;
+    let certs = CertificateDer::pem_slice_iter(&pem.cert)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| Error::Config(format!("Invalid certificate {}: {e}", cert_path.display())))?;
+    if certs.is_empty() {
+        return Err(Error::Config(format!(
+            "No certificate found in {}",
+            cert_path.display()
+        )));
+    }
+    let key = PrivateKeyDer::from_pem_slice(&pem.key)
+        .map_err(|e| Error::Config(format!("Invalid private key {}: {e}", key_path.display())))?;
+    let certified_key = CertifiedKey::from_der(certs, key, provider)
+        .map_err(|e| Error::Config(format!("Unusable TLS certificate: {e}")))?;
+
+    Ok(LoadedCert {
+        certified_key: Arc::new(certified_key),
+        pem,
+    })
+}
+
+fn https_location(host: &str, https_port: u16, path_and_query: &str) -> String {
+    let host = host
+        .rsplit_once(':')
+        .filter(|(_name, port)| !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()))
+        .map_or(host, |(name, _port)| name);
+    if https_port == 443 {
+        format!("https://{host}{path_and_query}")
+    } else {
+        format!("https://{host}:{https_port}{path_and_query}")
+    }
+}
+
+/// Default service of the plain HTTP listener, permanently redirecting every request to HTTPS.
+#[allow(clippy::unused_async)] // actix handlers must be async
+pub async fn redirect_to_https(req: HttpRequest, https_port: web::Data<HttpsPort>) -> HttpResponse {
+    let path_and_query = req.uri().path_and_query().map_or("/", |pq| pq.as_str());
+    let location = https_location(req.connection_info().host(), https_port.0, path_and_query);
+    HttpResponse::PermanentRedirect()
+        .insert_header((header::LOCATION, location))
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+    use rstest::rstest;
+
+    struct TempCert {
+        dir: PathBuf,
+    }
+
+    impl TempCert {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("prono-tls-{name}-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let cert = Self { dir };
+            cert.renew();
+            cert
+        }
+
+        fn renew(&self) {
+            let generated = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+            fs::write(self.cert_path(), generated.cert.pem()).unwrap();
+            fs::write(self.key_path(), generated.signing_key.serialize_pem()).unwrap();
+        }
+
+        fn cert_path(&self) -> PathBuf {
+            self.dir.join("cert.pem")
+        }
+
+        fn key_path(&self) -> PathBuf {
+            self.dir.join("key.pem")
+        }
+    }
+
+    impl Drop for TempCert {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn load_self_signed_certificate() {
+        let cert = TempCert::new("load");
+        let reloader = CertReloader::load(cert.cert_path(), cert.key_path()).unwrap();
+        assert_eq!(reloader.certified_key().cert.len(), 1);
+    }
+
+    #[test]
+    fn load_missing_certificate_is_config_error() {
+        let result = CertReloader::load("does_not_exist.pem", "does_not_exist.key");
+        assert!(matches!(result, Err(Error::Config(msg)) if msg.contains("does_not_exist.pem")));
+    }
+
+    #[test]
+    fn load_rejects_key_of_other_certificate() {
+        let cert = TempCert::new("mismatch");
+        let other = TempCert::new("mismatch-other");
+        let result = CertReloader::load(cert.cert_path(), other.key_path());
+        assert!(matches!(result, Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn reload_without_changes_keeps_certificate() {
+        let cert = TempCert::new("unchanged");
+        let reloader = CertReloader::load(cert.cert_path(), cert.key_path()).unwrap();
+        let before = reloader.certified_key();
+
+        assert!(!reloader.reload_if_changed().unwrap());
+        assert!(Arc::ptr_eq(&before, &reloader.certified_key()));
+    }
+
+    #[test]
+    fn reload_picks_up_renewed_certificate() {
+        let cert = TempCert::new("renewed");
+        let reloader = CertReloader::load(cert.cert_path(), cert.key_path()).unwrap();
+        let before = reloader.certified_key();
+
+        cert.renew();
+
+        assert!(reloader.reload_if_changed().unwrap());
+        assert_ne!(before.cert, reloader.certified_key().cert);
+    }
+
+    #[test]
+    fn reload_of_broken_files_keeps_previous_certificate() {
+        let cert = TempCert::new("broken");
+        let reloader = CertReloader::load(cert.cert_path(), cert.key_path()).unwrap();
+        let before = reloader.certified_key();
+
+        fs::write(cert.key_path(), "not a key").unwrap();
+
+        assert!(reloader.reload_if_changed().is_err());
+        assert!(Arc::ptr_eq(&before, &reloader.certified_key()));
+    }
+
+    #[test]
+    fn server_config_uses_reloader() {
+        let cert = TempCert::new("server-config");
+        let reloader = Arc::new(CertReloader::load(cert.cert_path(), cert.key_path()).unwrap());
+        assert!(reloader.server_config().is_ok());
+    }
+
+    #[rstest]
+    #[case("example.com", 443, "/api/survey", "https://example.com/api/survey")]
+    #[case("example.com:80", 443, "/", "https://example.com/")]
+    #[case("[::1]", 443, "/", "https://[::1]/")]
+    #[case("[::1]:8080", 8081, "/", "https://[::1]:8081/")]
+    #[case("localhost:8080", 8081, "/api/survey?x=1", "https://localhost:8081/api/survey?x=1")]
+    fn https_location_replaces_scheme_and_port(
+        #[case] host: &str,
+        #[case] https_port: u16,
+        #[case] path_and_query: &str,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(https_location(host, https_port, path_and_query), expected);
+    }
+
+    #[actix_web::test]
+    async fn redirect_to_https_is_permanent_redirect() {
+        let req = TestRequest::get()
+            .uri("/api/survey")
+            .insert_header((header::HOST, "prono.example:8080"))
+            .to_http_request();
+
+        let resp = redirect_to_https(req, web::Data::new(HttpsPort(8081))).await;
+
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            resp.headers().get(header::LOCATION).unwrap(),
+            "https://prono.example:8081/api/survey"
+        );
+    }
+}
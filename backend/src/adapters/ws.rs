@@ -0,0 +1,478 @@
+use std::collections::VecDeque;
+
+use actix_web::{HttpRequest, HttpResponse, get, web};
+use actix_ws::{AggregatedMessage, AggregatedMessageStream, CloseCode, CloseReason, Session};
+use log::warn;
+use prono_api::v1;
+use tokio::sync::broadcast;
+
+use crate::adapters::rest::{KEEP_ALIVE_INTERVAL, ensure_same_user, request_ip, unknown_survey};
+use crate::use_cases::*;
+use crate::{BackendResult, Error};
+
+/// Largest request accepted over the WebSocket, in bytes.
+const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+/// WebSocket carrying the survey API as [`v1::WsRequest`] and [`v1::WsMessage`] JSON text messages.
+///
+/// Requests on one connection are handled one at a time, in order. Subscribed survey events are pushed
+/// between replies.
+#[get("/ws")]
+pub async fn connect(
+    req: HttpRequest,
+    body: web::Payload,
+    service: web::Data<SurveyService>,
+    auth: web::Data<AuthService>,
+) -> actix_web::Result<HttpResponse> {
+    let (response, session, messages) = actix_ws::handle(&req, body)?;
+    let messages = messages
+        .aggregate_continuations()
+        .max_continuation_size(MAX_MESSAGE_SIZE);
+    let rate_limiter = req.app_data::<web::Data<RateLimiter>>().cloned();
+    let client_ip = request_ip(&req);
+    let connection = Connection {
+        service,
+        auth,
+        rate_limiter,
+        client_ip,
+        credentials: None,
+        past_events: VecDeque::new(),
+        events: None,
+    };
+    actix_web::rt::spawn(connection.run(session, messages));
+    Ok(response)
+}
+
+struct Connection {
+    service: web::Data<SurveyService>,
+    auth: web::Data<AuthService>,
+    rate_limiter: Option<web::Data<RateLimiter>>,
+    client_ip: Option<String>,
+    /// Session token and device id sent with the last `authenticate` request.
+    credentials: Option<(String, String)>,
+    /// Events of the subscribed survey from before subscribing, sent before new ones.
+    past_events: VecDeque<prono_api::SurveyEvent>,
+    events: Option<broadcast::Receiver<prono_api::SurveyEvent>>,
+}
+
+fn error_message(id: Option<u64>, error: &Error) -> v1::WsMessage {
+    v1::WsMessage::Error {
+        id,
+        error: error.body(),
+    }
+}
+
+impl Connection {
+    async fn run(mut self, mut session: Session, mut messages: AggregatedMessageStream) {
+        let mut keep_alive = tokio::time::interval(KEEP_ALIVE_INTERVAL);
+        loop {
+            let outgoing = tokio::select! {
+                message = messages.recv() => match message {
+                    Some(Ok(AggregatedMessage::Text(text))) => self.reply(&text).await,
+                    Some(Ok(AggregatedMessage::Binary(_))) => {
+                        error_message(None, &Error::InvalidRequest("Expected a JSON text message".to_string()))
+                    }
+                    Some(Ok(AggregatedMessage::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                    Some(Ok(AggregatedMessage::Pong(_))) => continue,
+                    Some(Ok(AggregatedMessage::Close(reason))) => {
+                        let _ = session.close(reason).await;
+                        return;
+                    }
+                    Some(Err(e)) => {
+                        warn!("Closing WebSocket after protocol error: {e}");
+                        let reason = CloseReason {
+                            code: CloseCode::Protocol,
+                            description: Some(e.to_string()),
+                        };
+                        let _ = session.close(Some(reason)).await;
+                        return;
+                    }
+                    None => return,
+                },
+                Some(event) = self.next_event() => v1::WsMessage::Event { event: event.into() },
+                _ = keep_alive.tick() => {
+                    if session.ping(b"").await.is_err() {
+                        return;
+                    }
+                    continue;
+                }
+            };
+            let Ok(json) = serde_json::to_string(&outgoing) else {
+                continue;
+            };
+            if session.text(json).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Next event of the subscribed survey, never resolves without subscription.
+    async fn next_event(&mut self) -> Option<prono_api::SurveyEvent> {
+        if let Some(event) = self.past_events.pop_front() {
+            return Some(event);
+        }
+        let Some(events) = self.events.as_mut() else {
+            return std::future::pending().await;
+        };
+        loop {
+            match events.recv().await {
+                Ok(event) => return Some(event),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("WebSocket subscriber missed {missed} survey events");
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    self.events = None;
+                    return None;
+                }
+            }
+        }
+    }
+
+    async fn reply(&mut self, text: &str) -> v1::WsMessage {
+        match serde_json::from_str::<v1::WsRequest>(text) {
+            Ok(request) => {
+                let id = request.id();
+                self.handle(request)
+                    .await
+                    .unwrap_or_else(|e| error_message(Some(id), &e))
+            }
+            Err(e) => error_message(None, &Error::InvalidRequest(e.to_string())),
+        }
+    }
+
+    async fn handle(&mut self, request: v1::WsRequest) -> BackendResult<v1::WsMessage> {
+        match request {
+            v1::WsRequest::Authenticate { id, token, device_id } => {
+                self.auth.authenticate(&token).await?;
+                self.credentials = Some((token, device_id));
+                Ok(v1::WsMessage::Done { id })
+            }
+            v1::WsRequest::Challenge { id } => {
+                if let Some(limiter) = &self.rate_limiter {
+                    limiter.check(self.client_ip.as_deref(), None).await?;
+                }
+                Ok(v1::WsMessage::Challenge {
+                    id,
+                    challenge: self.service.challenge()?,
+                })
+            }
+            v1::WsRequest::EmptySurvey { id } => Ok(v1::WsMessage::Survey {
+                id,
+                survey: Some(self.service.empty_survey().into()),
+            }),
+            v1::WsRequest::AddAnswer {
+                id,
+                answer,
+                proof_of_work,
+            } => {
+                let (token, device_id) = self.credentials.as_ref().ok_or(Error::Unauthorized)?;
+                let principal = self.auth.authenticate(token).await?;
+                if let Some(limiter) = &self.rate_limiter {
+                    limiter.check(self.client_ip.as_deref(), Some(&principal.user)).await?;
+                }
+                ensure_same_user(&principal, &answer.user)?;
+                self.service
+                    .add_answer(
+                        &principal,
+                        answer.question_id,
+                        answer.answer.into(),
+                        device_id,
+                        &proof_of_work,
+                        self.client_ip.as_deref(),
+                    )
+                    .await?;
+                Ok(v1::WsMessage::Done { id })
+            }
+            v1::WsRequest::Response { id, user, survey_id } => Ok(v1::WsMessage::Survey {
+                id,
+                survey: self.service.response(&user, survey_id).await?.map(Into::into),
+            }),
+            v1::WsRequest::AllAnswers { id, question_id } => Ok(v1::WsMessage::Answers {
+                id,
+                answers: self
+                    .service
+                    .all_answers(question_id)
+                    .await?
+                    .into_iter()
+                    .map(Into::into)
+                    .collect(),
+            }),
+            v1::WsRequest::Subscribe { id, survey_id } => {
+                let subscription = self
+                    .service
+                    .subscribe(survey_id)
+                    .await?
+                    .ok_or_else(|| unknown_survey(survey_id))?;
+                self.past_events = subscription.past.into();
+                self.events = Some(subscription.events);
+                Ok(v1::WsMessage::Done { id })
+            }
+        }
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use actix_web::App;
+    use actix_web::http::{StatusCode, header};
+    use actix_web::test::{TestRequest, call_service, init_service};
+    use prono::fake_db::FakeRepo;
+    use prono::repo::{self, Db as _};
+    use serde_json::json;
+
+    async fn connection() -> Connection {
+        let db = Arc::new(FakeRepo::init(()).await.unwrap());
+        let devices = DeviceService::new(
+            db.clone(),
+            repo::DevicePolicy { max_devices: 1 },
+            Duration::from_secs(60),
+        );
+        Connection {
+            service: web::Data::new(SurveyService::new(
+                db.clone(),
+                db.clone(),
+                Arc::new(devices),
+                Challenges::new(4, Duration::from_secs(60)),
+            )),
+            auth: web::Data::new(AuthService::new(db, Duration::from_secs(60))),
+            rate_limiter: None,
+            client_ip: None,
+            credentials: None,
+            past_events: VecDeque::new(),
+            events: None,
+        }
+    }
+
+    async fn send(connection: &mut Connection, request: serde_json::Value) -> v1::WsMessage {
+        connection.reply(&request.to_string()).await
+    }
+
+    async fn stamp(connection: &mut Connection) -> String {
+        match send(connection, json!({"op": "challenge", "id": 1})).await {
+            v1::WsMessage::Challenge { challenge, .. } => challenge.solve(),
+            other => panic!("expected a challenge, got {other:?}"),
+        }
+    }
+
+    async fn add_answer(connection: &mut Connection, user: &str) -> v1::WsMessage {
+        let stamp = stamp(connection).await;
+        send(
+            connection,
+            json!({
+                "op": "add_answer",
+                "id": 7,
+                "answer": {"user": user, "question_id": "q1", "answer": {"type": "text", "text": "yes"}},
+                "proof_of_work": stamp
+            }),
+        )
+        .await
+    }
+
+    fn error_code(message: &v1::WsMessage) -> Option<prono_api::ErrorCode> {
+        match message {
+            v1::WsMessage::Error { error, .. } => Some(error.code),
+            _ => None,
+        }
+    }
+
+    #[actix_web::test]
+    async fn subscribers_first_receive_past_resolutions() {
+        let mut connection = connection().await;
+        let survey_id = connection.service.empty_survey().id;
+        let answer = prono_api::Answer::Text("yes".to_string());
+        connection
+            .service
+            .resolve_question("q1".to_string(), answer.clone())
+            .await
+            .unwrap();
+
+        let subscribed = send(
+            &mut connection,
+            json!({"op": "subscribe", "id": 2, "survey_id": survey_id}),
+        )
+        .await;
+
+        assert_eq!(subscribed, v1::WsMessage::Done { id: 2 });
+        assert_eq!(
+            connection.next_event().await,
+            Some(prono_api::SurveyEvent::QuestionResolved {
+                question_id: "q1".to_string(),
+                answer,
+            })
+        );
+    }
+
+    #[actix_web::test]
+    async fn answer_is_stored_and_pushed_to_subscribers() {
+        let mut connection = connection().await;
+        let session = connection.auth.register("sam", "long enough").await.unwrap();
+        let survey_id = connection.service.empty_survey().id;
+
+        let subscribed = send(
+            &mut connection,
+            json!({"op": "subscribe", "id": 2, "survey_id": survey_id}),
+        )
+        .await;
+        assert_eq!(subscribed, v1::WsMessage::Done { id: 2 });
+        let authenticated = send(
+            &mut connection,
+            json!({"op": "authenticate", "id": 3, "token": session.token, "device_id": "laptop"}),
+        )
+        .await;
+        assert_eq!(authenticated, v1::WsMessage::Done { id: 3 });
+        assert_eq!(add_answer(&mut connection, "sam").await, v1::WsMessage::Done { id: 7 });
+
+        let answer = v1::Answer::Text {
+            text: "yes".to_string(),
+        };
+        assert_eq!(
+            connection.next_event().await.map(v1::SurveyEvent::from),
+            Some(v1::SurveyEvent::AnswerAdded {
+                question_id: "q1".to_string(),
+                user: "sam".to_string(),
+                answer: answer.clone(),
+            })
+        );
+        assert_eq!(
+            send(
+                &mut connection,
+                json!({"op": "all_answers", "id": 8, "question_id": "q1"})
+            )
+            .await,
+            v1::WsMessage::Answers {
+                id: 8,
+                answers: vec![v1::UserAnswer {
+                    user: "sam".to_string(),
+                    answer,
+                }],
+            }
+        );
+    }
+
+    #[actix_web::test]
+    async fn answers_of_a_user_are_rate_limited() {
+        let mut connection = connection().await;
+        let limit = repo::RateLimit {
+            burst: 1,
+            per_minute: std::num::NonZeroU32::MIN,
+        };
+        connection.rate_limiter = Some(web::Data::new(RateLimiter::new(
+            Arc::new(MemoryRateLimitStore::default()),
+            limit,
+            limit,
+        )));
+        let session = connection.auth.register("sam", "long enough").await.unwrap();
+        send(
+            &mut connection,
+            json!({"op": "authenticate", "id": 3, "token": session.token, "device_id": "laptop"}),
+        )
+        .await;
+
+        assert_eq!(add_answer(&mut connection, "sam").await, v1::WsMessage::Done { id: 7 });
+        send(
+            &mut connection,
+            json!({"op": "authenticate", "id": 4, "token": session.token, "device_id": "phone"}),
+        )
+        .await;
+        let reply = add_answer(&mut connection, "sam").await;
+        assert_eq!(error_code(&reply), Some(prono_api::ErrorCode::RateLimited));
+    }
+
+    #[actix_web::test]
+    async fn answer_requires_authenticated_connection() {
+        let mut connection = connection().await;
+
+        let reply = add_answer(&mut connection, "sam").await;
+        assert_eq!(error_code(&reply), Some(prono_api::ErrorCode::Unauthorized));
+
+        let reply = send(
+            &mut connection,
+            json!({"op": "authenticate", "id": 3, "token": "forged", "device_id": "laptop"}),
+        )
+        .await;
+        assert_eq!(error_code(&reply), Some(prono_api::ErrorCode::Unauthorized));
+        assert!(connection.credentials.is_none());
+    }
+
+    #[actix_web::test]
+    async fn answer_for_other_user_is_forbidden() {
+        let mut connection = connection().await;
+        let session = connection.auth.register("sam", "long enough").await.unwrap();
+        send(
+            &mut connection,
+            json!({"op": "authenticate", "id": 3, "token": session.token, "device_id": "laptop"}),
+        )
+        .await;
+
+        let reply = add_answer(&mut connection, "alex").await;
+        assert_eq!(error_code(&reply), Some(prono_api::ErrorCode::Forbidden));
+    }
+
+    #[actix_web::test]
+    async fn malformed_request_is_reported_without_id() {
+        let mut connection = connection().await;
+
+        let reply = connection.reply("{\"op\": \"launch\", \"id\": 1}").await;
+        assert!(matches!(
+            reply,
+            v1::WsMessage::Error { id: None, ref error } if error.code == prono_api::ErrorCode::InvalidRequest
+        ));
+    }
+
+    #[actix_web::test]
+    async fn survey_requests_are_answered_with_their_id() {
+        let mut connection = connection().await;
+        let survey_id = connection.service.empty_survey().id;
+
+        let reply = send(&mut connection, json!({"op": "empty_survey", "id": 4})).await;
+        assert!(matches!(reply, v1::WsMessage::Survey { id: 4, survey: Some(ref s) } if s.id == survey_id));
+
+        let reply = send(
+            &mut connection,
+            json!({"op": "response", "id": 5, "user": "nobody", "survey_id": survey_id}),
+        )
+        .await;
+        assert_eq!(reply, v1::WsMessage::Survey { id: 5, survey: None });
+
+        let reply = send(
+            &mut connection,
+            json!({"op": "subscribe", "id": 6, "survey_id": survey_id + 1}),
+        )
+        .await;
+        assert_eq!(error_code(&reply), Some(prono_api::ErrorCode::NotFound));
+    }
+
+    #[actix_web::test]
+    async fn connect_upgrades_to_websocket() {
+        let connection = connection().await;
+        let app = init_service(
+            App::new()
+                .app_data(connection.service)
+                .app_data(connection.auth)
+                .service(web::scope(crate::adapters::rest_v1::SCOPE).service(connect)),
+        )
+        .await;
+
+        let req = TestRequest::get()
+            .uri("/api/v1/ws")
+            .insert_header((header::CONNECTION, "upgrade"))
+            .insert_header((header::UPGRADE, "websocket"))
+            .insert_header((header::SEC_WEBSOCKET_VERSION, "13"))
+            .insert_header((header::SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ=="))
+            .to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::SWITCHING_PROTOCOLS);
+
+        let plain = TestRequest::get().uri("/api/v1/ws").to_request();
+        assert_eq!(call_service(&app, plain).await.status(), StatusCode::BAD_REQUEST);
+    }
+}
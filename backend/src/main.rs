@@ -1,13 +1,28 @@
 use std::sync::Arc;
+use std::time::Duration;
 
-use actix_cors::Cors;
+use actix_web::middleware::from_fn;
 use actix_web::{App, HttpServer, web};
-use log::info;
+use log::{info, warn};
 use prono::ReadConfig;
-use prono::repo::Db;
+use prono::backend_config::RateLimitStoreKind;
+use prono::repo;
 
-use prono_backend::adapters::rest;
-use prono_backend::use_cases::SurveyService;
+use prono_backend::adapters::metrics::{self, DbPoolUsage, InstrumentedRepo, Metrics};
+use prono_backend::adapters::{admin, health, openapi, rest, rest_v1, tls};
+use prono_backend::use_cases::{
+    AdminService, AuthService, Backoff, Challenges, DeferredRepo, DeviceService, HealthService, MemoryRateLimitStore,
+    RateLimiter, SurveyService, UserService,
+};
+
+/// Time the database has to answer a readiness probe.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Delays between attempts to connect to a database that is not up yet, e.g. while the NAS boots.
+const RECONNECT_BACKOFF: Backoff = Backoff {
+    initial: Duration::from_secs(1),
+    max: Duration::from_secs(30),
+};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -15,37 +30,161 @@ async fn main() -> std::io::Result<()> {
 
     let config_reader = prono::factory::create_config_reader();
     let default_config_path = config_reader.default_config_path();
-    let db_config: prono_db::Config = config_reader.read(default_config_path).db.into();
-
-    let db = prono_db::MysqlDb::init(db_config)
-        .await
-        .expect("Failed to initialize database");
-
-    let db = Arc::new(db);
-    let service = web::Data::new(SurveyService::new(db.clone(), db));
+    let config = config_reader.read(default_config_path);
+    let db_config: prono_db::Config = config.db.into();
+    let backend_config = config.backend;
 
-    info!("Starting backend server on 0.0.0.0:8081");
+    let db = Arc::new(DeferredRepo::<prono_db::MysqlDb>::connect(db_config, RECONNECT_BACKOFF));
+    let availability = web::Data::new(db.availability());
+    let health = web::Data::new(HealthService::new(db.clone(), HEALTH_CHECK_TIMEOUT));
+    let pool = db.clone();
+    let metrics = web::Data::new(Metrics::new().with_db_pool(move || {
+        let usage = pool.current().map(prono_db::MysqlDb::pool_usage).unwrap_or_default();
+        DbPoolUsage {
+            open: usage.open.into(),
+            idle: i64::try_from(usage.idle).unwrap_or(i64::MAX),
+            max: usage.max.into(),
+        }
+    }));
+    let db = Arc::new(InstrumentedRepo::new(db, metrics.clone().into_inner()));
+    let challenges = Challenges::new(
+        backend_config.challenge_difficulty,
+        Duration::from_secs(backend_config.challenge_ttl_secs),
+    );
+    let devices = web::Data::new(DeviceService::new(
+        db.clone(),
+        prono::repo::DevicePolicy {
+            max_devices: backend_config.max_devices_per_user,
+        },
+        Duration::from_secs(backend_config.transfer_code_ttl_secs),
+    ));
+    let mut service = SurveyService::new(db.clone(), db.clone(), devices.clone().into_inner(), challenges)
+        .hiding_stats_below(backend_config.stats_min_answers);
+    if let Some(secret) = backend_config.alias_secret.clone() {
+        service = service.aliasing_with(secret);
+    } else {
+        warn!("No alias secret configured, pseudonymous aliases change on every restart");
+    }
+    let service = web::Data::new(service);
+    let users = web::Data::new(UserService::new(
+        db.clone(),
+        db.clone(),
+        db.clone(),
+        devices.clone().into_inner(),
+    ));
+    let admin = web::Data::new(AdminService::new(
+        backend_config.admin_token.as_deref(),
+        db.clone(),
+        db.clone(),
+        db.clone(),
+        service.clone().into_inner(),
+    ));
+    let survey_id = service.empty_survey().id;
+    if let Some(events) = service.subscribe_unfiltered(survey_id) {
+        metrics.clone().into_inner().count_answers(survey_id, events);
+    }
+    let rate_limit = backend_config.rate_limit;
+    let rate_limiter = rate_limit.enabled.then(|| {
+        let store: Arc<dyn repo::RateLimitStore> = match rate_limit.store {
+            RateLimitStoreKind::Memory => Arc::new(MemoryRateLimitStore::default()),
+            RateLimitStoreKind::Database => db.clone(),
+        };
+        web::Data::new(
+            RateLimiter::new(store, rate_limit.per_ip, rate_limit.per_user)
+                .trusting_forwarded_headers(rate_limit.trust_forwarded_headers),
+        )
+    });
+    let auth = web::Data::new(AuthService::new(
+        db,
+        Duration::from_secs(backend_config.session_ttl_secs),
+    ));
 
-    HttpServer::new(move || {
-        let cors = Cors::default()
-            .allowed_origin("https://jaques-sam.github.io")
-            .allowed_origin("https://prono-app.samagali.myds.me")
-            .allowed_origin("http://127.0.0.1:8080")
-            .allowed_origin("http://localhost:8080")
-            .allowed_methods(vec!["GET", "POST"])
-            .allowed_header(actix_web::http::header::CONTENT_TYPE)
-            .allowed_header("X-Device-Id")
-            .max_age(3600);
-
-        App::new()
-            .wrap(cors)
+    let allowed_origins = backend_config.allowed_origins;
+    let max_body_size = backend_config.max_body_size;
+    let mut server = HttpServer::new(move || {
+        let app = App::new()
+            .wrap(from_fn(health::require_repository))
+            .wrap(from_fn(rest::api_version))
+            .wrap(rest::cors(&allowed_origins))
+            .wrap(from_fn(metrics::track_requests))
+            .app_data(rest::json_config(max_body_size))
+            .app_data(rest::path_config())
+            .app_data(rest::query_config())
+            .app_data(metrics.clone())
+            .app_data(health.clone())
+            .app_data(availability.clone())
             .app_data(service.clone())
+            .app_data(admin.clone())
+            .app_data(auth.clone())
+            .app_data(devices.clone())
+            .app_data(users.clone());
+        let app = match &rate_limiter {
+            Some(rate_limiter) => app.app_data(rate_limiter.clone()),
+            None => app,
+        };
+        let app = app
+            .service(rest_v1::scope())
+            .service(admin::list_users)
+            .service(admin::normalize_users)
+            .service(admin::rename_user)
+            .service(admin::merge_user)
+            .service(admin::list_devices)
+            .service(admin::reset_devices)
+            .service(admin::delete_user)
+            .service(admin::resolve_question)
+            .service(admin::close_survey)
+            .service(admin::open_survey)
+            .service(admin::set_privacy)
+            .service(admin::list_answers)
+            .service(admin::add_answer)
+            .service(admin::replace_answer)
             .service(rest::get_survey)
             .service(rest::add_answer)
             .service(rest::get_response)
             .service(rest::get_all_answers)
+            .service(rest::survey_events)
+            .service(rest::get_stats)
+            .service(health::healthz)
+            .service(health::readyz)
+            .service(metrics::get_metrics)
+            .service(openapi::openapi_json());
+        #[cfg(feature = "swagger-ui")]
+        let app = app.service(openapi::swagger_ui());
+        app
+    });
+    if let Some(workers) = backend_config.workers {
+        server = server.workers(workers);
+    }
+
+    let listen_address = backend_config.listen_address;
+    let port = backend_config.port;
+    let Some(tls_config) = backend_config.tls else {
+        info!("Starting backend server on http://{listen_address}:{port}");
+        return server.bind((listen_address.as_str(), port))?.run().await;
+    };
+
+    let reloader =
+        Arc::new(tls::CertReloader::load(&tls_config.cert, &tls_config.key).expect("Failed to load TLS certificate"));
+    reloader.spawn_reload_task(Duration::from_secs(tls_config.reload_interval_secs));
+    let server_config = reloader.server_config().expect("Failed to set up TLS");
+
+    info!("Starting backend server on https://{listen_address}:{port}");
+    let https_server = server
+        .bind_rustls_0_23((listen_address.as_str(), port), server_config)?
+        .run();
+
+    let Some(redirect_port) = tls_config.http_redirect_port else {
+        return https_server.await;
+    };
+
+    info!("Redirecting http://{listen_address}:{redirect_port} to HTTPS");
+    let redirect_server = HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::new(tls::HttpsPort(port)))
+            .default_service(web::to(tls::redirect_to_https))
     })
-    .bind(("0.0.0.0", 8081))?
-    .run()
-    .await
+    .bind((listen_address.as_str(), redirect_port))?
+    .run();
+
+    tokio::try_join!(https_server, redirect_server).map(|_| ())
 }
@@ -1,4 +1,6 @@
 use actix_web::HttpResponse;
+use actix_web::http::{StatusCode, header};
+use prono_api::{ErrorBody, ErrorCode};
 
 #[derive(Debug, thiserror::Error)]
 #[cfg_attr(test, derive(PartialEq, Eq))]
@@ -9,11 +11,56 @@ pub enum Error {
     #[error("Answer already exists")]
     AnswerExists,
 
+    /// Carries the ids of the questions both users answered, when merging them.
+    #[error("Both users answered questions {}", .0.join(", "))]
+    AnswersConflict(Vec<String>),
+
     #[error("Configuration error: {0}")]
     Config(String),
 
     #[error("Device mismatch: username is registered to a different device")]
     DeviceMismatch,
+
+    #[error("Survey is closed")]
+    SurveyClosed,
+
+    #[error("Not signed in or session expired")]
+    Unauthorized,
+
+    #[error("Not allowed: {0}")]
+    Forbidden(String),
+
+    #[error("Invalid username or password")]
+    InvalidCredentials,
+
+    #[error("Username is already taken")]
+    UserExists,
+
+    #[error("Proof of work rejected: {0}")]
+    ChallengeFailed(String),
+
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Request body exceeds {0} bytes")]
+    PayloadTooLarge(usize),
+
+    /// Carries the number of seconds after which the client may retry.
+    #[error("Too many requests, retry after {0} seconds")]
+    RateLimited(u64),
+
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
+
+    /// A failure inside the backend that is neither the database nor the request, only logged.
+    #[error("Internal error: {0}")]
+    Internal(String),
+
+    #[error("Unsupported API version {0}, supported: {supported}", supported = prono_api::v1::API_VERSION)]
+    UnsupportedApiVersion(String),
 }
 
 pub type BackendResult<T> = std::result::Result<T, Error>;
@@ -23,25 +70,117 @@ impl From<prono::Error> for Error {
         match err {
             prono::Error::Repository(msg) => Error::Repository(msg),
             prono::Error::AnswerExists => Error::AnswerExists,
+            prono::Error::AnswersConflict(questions) => Error::AnswersConflict(questions),
             prono::Error::DeviceMismatch => Error::DeviceMismatch,
+            prono::Error::UserExists => Error::UserExists,
+            prono::Error::InvalidCredentials => Error::InvalidCredentials,
+            prono::Error::InvalidInput(msg) => Error::InvalidRequest(msg),
+            prono::Error::Unavailable(msg) => Error::Unavailable(msg),
+            err @ prono::Error::RandomFailed => Error::Internal(err.to_string()),
+        }
+    }
+}
+
+impl Error {
+    #[must_use]
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::Repository(_) => ErrorCode::Repository,
+            Error::AnswerExists | Error::AnswersConflict(_) => ErrorCode::AnswerExists,
+            Error::Config(_) => ErrorCode::Config,
+            Error::DeviceMismatch => ErrorCode::DeviceMismatch,
+            Error::SurveyClosed => ErrorCode::SurveyClosed,
+            Error::Unauthorized => ErrorCode::Unauthorized,
+            Error::Forbidden(_) => ErrorCode::Forbidden,
+            Error::InvalidCredentials => ErrorCode::InvalidCredentials,
+            Error::UserExists => ErrorCode::UserExists,
+            Error::ChallengeFailed(_) => ErrorCode::ChallengeFailed,
+            Error::InvalidRequest(_) => ErrorCode::InvalidRequest,
+            Error::NotFound(_) => ErrorCode::NotFound,
+            Error::PayloadTooLarge(_) => ErrorCode::PayloadTooLarge,
+            Error::RateLimited(_) => ErrorCode::RateLimited,
+            Error::UnsupportedApiVersion(_) => ErrorCode::UnsupportedVersion,
+            Error::Unavailable(_) => ErrorCode::Unavailable,
+            Error::Internal(_) => ErrorCode::Internal,
+        }
+    }
+
+    /// The JSON body sent to clients. Variants carrying a message put it in `details`,
+    /// so `message` stays stable for a given `code`. Repository messages may reveal the database, they are only
+    /// logged.
+    #[must_use]
+    pub fn body(&self) -> ErrorBody {
+        let (message, details) = match self {
+            Error::Repository(_) => ("Repository error".to_string(), None),
+            Error::Internal(_) => ("Internal error".to_string(), None),
+            Error::Config(msg) => ("Configuration error".to_string(), Some(msg.clone())),
+            Error::InvalidRequest(msg) => ("Invalid request".to_string(), Some(msg.clone())),
+            Error::NotFound(msg) => ("Not found".to_string(), Some(msg.clone())),
+            Error::Forbidden(msg) => ("Not allowed".to_string(), Some(msg.clone())),
+            Error::ChallengeFailed(msg) => ("Proof of work rejected".to_string(), Some(msg.clone())),
+            Error::Unavailable(msg) => ("Service unavailable".to_string(), Some(msg.clone())),
+            Error::AnswersConflict(questions) => (Error::AnswerExists.to_string(), Some(questions.join(", "))),
+            Error::RateLimited(secs) => (
+                "Too many requests".to_string(),
+                Some(format!("retry after {secs} seconds")),
+            ),
+            Error::UnsupportedApiVersion(requested) => (
+                format!("Unsupported API version, supported: {}", prono_api::v1::API_VERSION),
+                Some(requested.clone()),
+            ),
+            Error::AnswerExists
+            | Error::DeviceMismatch
+            | Error::SurveyClosed
+            | Error::Unauthorized
+            | Error::InvalidCredentials
+            | Error::UserExists
+            | Error::PayloadTooLarge(_) => (self.to_string(), None),
+        };
+        ErrorBody {
+            code: self.code(),
+            message,
+            details,
         }
     }
 }
 
 impl actix_web::ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Error::AnswerExists | Error::AnswersConflict(_) | Error::UserExists | Error::SurveyClosed => {
+                StatusCode::CONFLICT
+            }
+            Error::DeviceMismatch | Error::Forbidden(_) | Error::ChallengeFailed(_) => StatusCode::FORBIDDEN,
+            Error::Unauthorized | Error::InvalidCredentials => StatusCode::UNAUTHORIZED,
+            Error::InvalidRequest(_) | Error::UnsupportedApiVersion(_) => StatusCode::BAD_REQUEST,
+            Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Error::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            Error::Repository(_) | Error::Config(_) | Error::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
     fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
         match self {
-            Error::AnswerExists => HttpResponse::Conflict().json(self.to_string()),
-            Error::DeviceMismatch => HttpResponse::Forbidden().json(self.to_string()),
-            Error::Repository(msg) | Error::Config(msg) => HttpResponse::InternalServerError().json(msg.clone()),
+            Error::Repository(_) | Error::Internal(_) => log::error!("{self}"),
+            Error::Unauthorized => {
+                response.insert_header((header::WWW_AUTHENTICATE, "Bearer"));
+            }
+            Error::RateLimited(secs) => {
+                response.insert_header((header::RETRY_AFTER, secs.to_string()));
+            }
+            _ => {}
         }
+        response.json(self.body())
     }
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use actix_web::http::header;
-    use actix_web::{body::to_bytes, http::StatusCode};
+    use actix_web::body::to_bytes;
     use rstest::rstest;
 
     #[test]
@@ -56,6 +195,15 @@ mod tests {
         assert_eq!(err, Error::AnswerExists);
     }
 
+    #[actix_web::test]
+    async fn random_failures_are_internal_server_errors() {
+        let err: Error = prono::Error::RandomFailed.into();
+        let (status, body) = response_body(&err).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body.code, ErrorCode::Internal);
+        assert_eq!(body.details, None);
+    }
+
     #[test]
     fn from_prono_error_maps_device_mismatch() {
         let err: Error = prono::Error::DeviceMismatch.into();
@@ -77,57 +225,168 @@ mod tests {
             Error::DeviceMismatch.to_string(),
             "Device mismatch: username is registered to a different device"
         );
+        assert_eq!(
+            Error::InvalidRequest("missing field".to_string()).to_string(),
+            "Invalid request: missing field"
+        );
     }
 
-    async fn assert_response(err: Error, expected_status: StatusCode, expected_json_string: &str) {
-        let resp = actix_web::ResponseError::error_response(&err);
-        assert_eq!(resp.status(), expected_status);
+    async fn response_body(err: &Error) -> (StatusCode, ErrorBody) {
+        let resp = actix_web::ResponseError::error_response(err);
+        let status = resp.status();
 
         let body = to_bytes(resp.into_body())
             .await
             .expect("response body should be readable");
-        let body = std::str::from_utf8(&body).expect("response body should be valid UTF-8");
+        let body = serde_json::from_slice(&body).expect("response body should be an error body");
 
-        assert_eq!(body, format!("\"{expected_json_string}\""));
+        (status, body)
     }
 
     #[actix_web::test]
     async fn error_response_answer_exists_is_conflict() {
-        assert_response(Error::AnswerExists, StatusCode::CONFLICT, "Answer already exists").await;
+        let (status, body) = response_body(&Error::AnswerExists).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(
+            body,
+            ErrorBody {
+                code: ErrorCode::AnswerExists,
+                message: "Answer already exists".to_string(),
+                details: None,
+            }
+        );
     }
 
     #[actix_web::test]
     async fn error_response_device_mismatch_is_forbidden() {
-        assert_response(
-            Error::DeviceMismatch,
-            StatusCode::FORBIDDEN,
-            "Device mismatch: username is registered to a different device",
-        )
-        .await;
+        let (status, body) = response_body(&Error::DeviceMismatch).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(
+            body,
+            ErrorBody {
+                code: ErrorCode::DeviceMismatch,
+                message: "Device mismatch: username is registered to a different device".to_string(),
+                details: None,
+            }
+        );
+    }
+
+    #[actix_web::test]
+    async fn error_response_repository_is_internal_server_error_without_raw_message() {
+        let (status, body) =
+            response_body(&Error::Repository("Table 'prono.Accounts' doesn't exist".to_string())).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            body,
+            ErrorBody {
+                code: ErrorCode::Repository,
+                message: "Repository error".to_string(),
+                details: None,
+            }
+        );
+    }
+
+    #[actix_web::test]
+    async fn error_response_config_is_internal_server_error_with_raw_message_as_details() {
+        let (status, body) = response_body(&Error::Config("missing SECRET_KEY".to_string())).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body.code, ErrorCode::Config);
+        assert_eq!(body.details.as_deref(), Some("missing SECRET_KEY"));
+    }
+
+    #[actix_web::test]
+    async fn error_response_unsupported_api_version_names_supported_version_and_requested_as_details() {
+        let (status, body) = response_body(&Error::UnsupportedApiVersion("2".to_string())).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body,
+            ErrorBody {
+                code: ErrorCode::UnsupportedVersion,
+                message: "Unsupported API version, supported: 1".to_string(),
+                details: Some("2".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn error_response_unauthorized_asks_for_bearer_token() {
+        let resp = actix_web::ResponseError::error_response(&Error::Unauthorized);
+        assert_eq!(resp.headers().get(header::WWW_AUTHENTICATE).unwrap(), "Bearer");
+    }
+
+    #[actix_web::test]
+    async fn error_response_rate_limited_tells_when_to_retry() {
+        let resp = actix_web::ResponseError::error_response(&Error::RateLimited(7));
+        assert_eq!(resp.headers().get(header::RETRY_AFTER).unwrap(), "7");
+
+        let (status, body) = response_body(&Error::RateLimited(7)).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            body,
+            ErrorBody {
+                code: ErrorCode::RateLimited,
+                message: "Too many requests".to_string(),
+                details: Some("retry after 7 seconds".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn from_prono_error_maps_account_errors() {
+        assert_eq!(Error::from(prono::Error::UserExists), Error::UserExists);
+        assert_eq!(
+            Error::from(prono::Error::AnswersConflict(vec!["q1".to_string()])),
+            Error::AnswersConflict(vec!["q1".to_string()])
+        );
+        assert_eq!(Error::from(prono::Error::InvalidCredentials), Error::InvalidCredentials);
+        assert_eq!(
+            Error::from(prono::Error::InvalidInput("too short".to_string())),
+            Error::InvalidRequest("too short".to_string())
+        );
+        assert_eq!(
+            Error::from(prono::Error::Unavailable("connecting".to_string())),
+            Error::Unavailable("connecting".to_string())
+        );
     }
 
     #[actix_web::test]
-    async fn error_response_repository_is_internal_server_error_and_uses_raw_message() {
-        assert_response(
-            Error::Repository("storage unavailable".to_string()),
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "storage unavailable",
-        )
-        .await;
+    async fn error_response_invalid_request_is_bad_request() {
+        let (status, body) = response_body(&Error::InvalidRequest("missing field `user`".to_string())).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body.code, ErrorCode::InvalidRequest);
+        assert_eq!(body.details.as_deref(), Some("missing field `user`"));
     }
 
     #[actix_web::test]
-    async fn error_response_config_is_internal_server_error_and_uses_raw_message() {
-        assert_response(
-            Error::Config("missing SECRET_KEY".to_string()),
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "missing SECRET_KEY",
-        )
-        .await;
+    async fn answers_conflict_lists_the_questions_in_details() {
+        let err = Error::AnswersConflict(vec!["q1".to_string(), "q2".to_string()]);
+        let (status, body) = response_body(&err).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(
+            body,
+            ErrorBody {
+                code: ErrorCode::AnswerExists,
+                message: "Answer already exists".to_string(),
+                details: Some("q1, q2".to_string()),
+            }
+        );
     }
+
     #[rstest]
     #[case(Error::AnswerExists, StatusCode::CONFLICT)]
     #[case(Error::DeviceMismatch, StatusCode::FORBIDDEN)]
+    #[case(Error::InvalidRequest("bad".to_string()), StatusCode::BAD_REQUEST)]
+    #[case(Error::NotFound("unknown survey".to_string()), StatusCode::NOT_FOUND)]
+    #[case(Error::PayloadTooLarge(16), StatusCode::PAYLOAD_TOO_LARGE)]
+    #[case(Error::UnsupportedApiVersion("2".to_string()), StatusCode::BAD_REQUEST)]
+    #[case(Error::Unauthorized, StatusCode::UNAUTHORIZED)]
+    #[case(Error::InvalidCredentials, StatusCode::UNAUTHORIZED)]
+    #[case(Error::Forbidden("other user".to_string()), StatusCode::FORBIDDEN)]
+    #[case(Error::UserExists, StatusCode::CONFLICT)]
+    #[case(Error::SurveyClosed, StatusCode::CONFLICT)]
+    #[case(Error::ChallengeFailed("expired".to_string()), StatusCode::FORBIDDEN)]
+    #[case(Error::RateLimited(1), StatusCode::TOO_MANY_REQUESTS)]
+    #[case(Error::Unavailable("connecting".to_string()), StatusCode::SERVICE_UNAVAILABLE)]
     #[case(
     Error::Repository("storage unavailable".to_string()),
     StatusCode::INTERNAL_SERVER_ERROR
@@ -144,6 +403,9 @@ mod tests {
     #[rstest]
     #[case(Error::AnswerExists)]
     #[case(Error::DeviceMismatch)]
+    #[case(Error::InvalidRequest("bad".to_string()))]
+    #[case(Error::NotFound("unknown survey".to_string()))]
+    #[case(Error::PayloadTooLarge(16))]
     #[case(Error::Repository("db".to_string()))]
     #[case(Error::Config("env".to_string()))]
     fn error_response_sets_json_content_type(#[case] err: Error) {
@@ -166,22 +428,24 @@ mod tests {
     }
 
     #[actix_web::test]
-    async fn repository_error_response_does_not_use_display_prefix() {
-        let err = Error::Repository("storage unavailable".to_string());
-        let resp = actix_web::ResponseError::error_response(&err);
-
-        let body = to_bytes(resp.into_body())
-            .await
-            .expect("response body should be readable");
-        let body = std::str::from_utf8(&body).expect("response body should be valid UTF-8");
-
-        assert_eq!(body, "\"storage unavailable\"");
-        assert_ne!(body, "\"Repository error: storage unavailable\"");
+    async fn error_body_maps_back_to_typed_api_error() {
+        for (err, expected) in [
+            (Error::AnswerExists, prono_api::Error::AnswerExists),
+            (Error::DeviceMismatch, prono_api::Error::DeviceMismatch),
+            (Error::SurveyClosed, prono_api::Error::SurveyClosed),
+            (
+                Error::Repository("db".to_string()),
+                prono_api::Error::Server("Repository error".to_string()),
+            ),
+        ] {
+            let (_status, body) = response_body(&err).await;
+            assert_eq!(prono_api::Error::from(body), expected);
+        }
     }
 
     #[actix_web::test]
-    async fn repository_error_response_escapes_special_characters_as_json() {
-        let err = Error::Repository("line1\n\"quoted\"".to_string());
+    async fn error_response_escapes_special_characters_of_details_as_json() {
+        let err = Error::InvalidRequest("line1\n\"quoted\"".to_string());
         let resp = actix_web::ResponseError::error_response(&err);
 
         let body = to_bytes(resp.into_body())
@@ -189,6 +453,6 @@ mod tests {
             .expect("response body should be readable");
         let body = std::str::from_utf8(&body).expect("response body should be valid UTF-8");
 
-        assert_eq!(body, "\"line1\\n\\\"quoted\\\"\"");
+        assert!(body.contains(r#""details":"line1\n\"quoted\"""#));
     }
 }
@@ -1,3 +1,19 @@
+mod admin_service;
+mod auth_service;
+mod challenges;
+mod deferred_repo;
+mod device_service;
+mod health_service;
+mod rate_limiter;
 mod survey_service;
+mod user_service;
 
+pub use admin_service::*;
+pub use auth_service::*;
+pub use challenges::*;
+pub use deferred_repo::*;
+pub use device_service::*;
+pub use health_service::*;
+pub use rate_limiter::*;
 pub use survey_service::*;
+pub use user_service::*;
@@ -0,0 +1,383 @@
+use std::sync::Arc;
+
+use prono::{credentials, repo};
+
+use crate::use_cases::SurveyService;
+use crate::{BackendResult, Error};
+
+/// Proof that a request carries the admin token, only handed out by [`AdminService::authorize`].
+#[derive(Debug)]
+pub struct Admin(());
+
+/// Maintenance of users, devices and surveys, so that administrators do not need database credentials.
+pub struct AdminService {
+    token_hash: Option<String>,
+    users: Arc<dyn repo::Users>,
+    devices: Arc<dyn repo::DeviceRegistry>,
+    answers: Arc<dyn repo::AnswerRecords>,
+    survey: Arc<SurveyService>,
+}
+
+impl AdminService {
+    /// Without a `token`, every request is refused.
+    pub fn new(
+        token: Option<&str>,
+        users: Arc<dyn repo::Users>,
+        devices: Arc<dyn repo::DeviceRegistry>,
+        answers: Arc<dyn repo::AnswerRecords>,
+        survey: Arc<SurveyService>,
+    ) -> Self {
+        Self {
+            token_hash: token.filter(|token| !token.is_empty()).map(credentials::token_hash),
+            users,
+            devices,
+            answers,
+            survey,
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns [`Error::Forbidden`] if no admin token is configured, or [`Error::Unauthorized`] if `token` is
+    /// missing or not the admin token.
+    pub fn authorize(&self, token: Option<&str>) -> BackendResult<Admin> {
+        let Some(expected) = &self.token_hash else {
+            return Err(Error::Forbidden("The admin API is disabled".to_string()));
+        };
+        // Comparing hashes keeps the time taken independent of how much of the token is right.
+        match token {
+            Some(token) if credentials::token_hash(token) == *expected => Ok(Admin(())),
+            _ => Err(Error::Unauthorized),
+        }
+    }
+
+    /// Users with answers, devices or an account, sorted by name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a repository error occurs.
+    pub async fn users(&self, _admin: &Admin) -> BackendResult<Vec<String>> {
+        Ok(self.users.all_names().await?)
+    }
+
+    /// Deletes the user with their answers, devices, account and sessions.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a repository error occurs.
+    pub async fn delete_user(&self, _admin: &Admin, user: &str) -> BackendResult<()> {
+        self.users.delete_user(user).await?;
+        self.survey.forget_stats();
+        Ok(())
+    }
+
+    /// Gives the answers, devices and account of `old` to `new`, see [`repo::Users::rename_user`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UserExists`] if `new` is already used, [`Error::InvalidRequest`] if a name is invalid or
+    /// `old` is unknown, or an error if a repository error occurs.
+    pub async fn rename_user(&self, _admin: &Admin, old: &str, new: &str) -> BackendResult<()> {
+        self.users.rename_user(old, new).await?;
+        self.survey.forget_stats();
+        Ok(())
+    }
+
+    /// Moves the answers, devices and account of `from` to `into`, see [`repo::Users::merge_users`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AnswersConflict`] if both answered the same question, [`Error::UserExists`] if both have
+    /// an account, [`Error::InvalidRequest`] if a name is invalid or `from` is unknown, or an error if a
+    /// repository error occurs.
+    pub async fn merge_users(&self, _admin: &Admin, from: &str, into: &str) -> BackendResult<()> {
+        self.users.merge_users(from, into).await?;
+        self.survey.forget_stats();
+        Ok(())
+    }
+
+    /// Merges every user stored under another spelling into its normalized name, see
+    /// [`prono::users::normalize_names`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the names cannot be read.
+    pub async fn normalize_users(
+        &self,
+        _admin: &Admin,
+    ) -> BackendResult<Vec<(String, prono::PronoResult<prono::Username>)>> {
+        let outcomes = prono::users::normalize_names(self.users.as_ref()).await?;
+        if outcomes.iter().any(|(_, outcome)| outcome.is_ok()) {
+            self.survey.forget_stats();
+        }
+        Ok(outcomes)
+    }
+
+    /// Devices the user submitted answers from, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a repository error occurs.
+    pub async fn devices(&self, _admin: &Admin, user: &str) -> BackendResult<Vec<repo::Device>> {
+        Ok(self.devices.list_devices(user).await?)
+    }
+
+    /// Removes all devices of the user, e.g. after they lost their only device. Returns how many were removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a repository error occurs.
+    pub async fn reset_devices(&self, _admin: &Admin, user: &str) -> BackendResult<usize> {
+        Ok(self.devices.unregister_devices(user).await?)
+    }
+
+    fn has_question(&self, question_id: &str) -> bool {
+        self.survey
+            .empty_survey()
+            .questions
+            .iter()
+            .any(|question| question.id == question_id)
+    }
+
+    /// Records and announces the outcome of a question, returns `false` if the survey has no such question.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a repository error occurs.
+    pub async fn resolve_question(
+        &self,
+        _admin: &Admin,
+        question_id: String,
+        answer: prono_api::Answer,
+    ) -> BackendResult<bool> {
+        if !self.has_question(&question_id) {
+            return Ok(false);
+        }
+        self.survey.resolve_question(question_id, answer).await?;
+        Ok(true)
+    }
+
+    /// Stores an answer on behalf of a user, replacing their answer if `overwrite` is set. Returns `false` if the
+    /// survey has no such question.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::AnswerExists`] if the user already answered and `overwrite` is not set, or an error if a
+    /// repository error occurs.
+    pub async fn add_answer(
+        &self,
+        _admin: &Admin,
+        user: &str,
+        question_id: String,
+        answer: prono_api::Answer,
+        overwrite: bool,
+    ) -> BackendResult<bool> {
+        let known = self.has_question(&question_id);
+        if known {
+            self.survey.import_answer(user, question_id, answer, overwrite).await?;
+        }
+        Ok(known)
+    }
+
+    /// Opens or closes the survey to new answers, returns `false` if the survey does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a repository error occurs.
+    pub async fn set_survey_open(&self, _admin: &Admin, survey_id: u64, open: bool) -> BackendResult<bool> {
+        self.survey.set_open(survey_id, open).await
+    }
+
+    /// Changes who sees the individual answers of the survey, returns `false` if the survey does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a repository error occurs.
+    pub async fn set_survey_privacy(
+        &self,
+        _admin: &Admin,
+        survey_id: u64,
+        privacy: prono_api::Privacy,
+    ) -> BackendResult<bool> {
+        self.survey.set_privacy(survey_id, privacy).await
+    }
+
+    /// Every answer to a question of the survey, oldest first, `None` if the survey does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a repository error occurs.
+    pub async fn answer_records(
+        &self,
+        _admin: &Admin,
+        survey_id: u64,
+    ) -> BackendResult<Option<Vec<repo::AnswerRecord>>> {
+        let survey = self.survey.empty_survey();
+        if survey_id != survey.id {
+            return Ok(None);
+        }
+        let mut records = self.answers.answer_records(survey_id).await?;
+        records.retain(|record| {
+            survey
+                .questions
+                .iter()
+                .any(|question| question.id == record.question_id)
+        });
+        Ok(Some(records))
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use prono::fake_db::FakeRepo;
+    use prono::repo::{Db as _, DeviceRegistry as _, Surveys as _};
+
+    use crate::use_cases::{AuthService, Challenges, DeviceService, Principal};
+
+    const TOKEN: &str = "admin-secret";
+
+    async fn make_service(token: Option<&str>) -> (AdminService, Arc<FakeRepo>) {
+        let db = Arc::new(FakeRepo::init(()).await.unwrap());
+        let devices = DeviceService::new(
+            db.clone(),
+            repo::DevicePolicy { max_devices: 2 },
+            Duration::from_secs(60),
+        );
+        let survey = SurveyService::new(
+            db.clone(),
+            db.clone(),
+            Arc::new(devices),
+            Challenges::new(4, Duration::from_secs(60)),
+        );
+        (
+            AdminService::new(token, db.clone(), db.clone(), db.clone(), Arc::new(survey)),
+            db,
+        )
+    }
+
+    async fn answer(service: &AdminService, user: &str) {
+        let survey = &service.survey;
+        survey
+            .add_answer(
+                &Principal { user: user.to_string() },
+                survey.empty_survey().questions[0].id.clone(),
+                prono_api::Answer::Text("yes".to_string()),
+                "device-1",
+                &survey.challenge().unwrap().solve(),
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_authorize_checks_token() {
+        let (service, _db) = make_service(Some(TOKEN)).await;
+
+        assert!(service.authorize(Some(TOKEN)).is_ok());
+        assert!(matches!(service.authorize(Some("guess")), Err(Error::Unauthorized)));
+        assert!(matches!(service.authorize(None), Err(Error::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn test_admin_api_is_disabled_without_token() {
+        for token in [None, Some("")] {
+            let (service, _db) = make_service(token).await;
+
+            assert!(matches!(service.authorize(Some("")), Err(Error::Forbidden(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deleted_user_cannot_sign_in_and_is_not_listed() {
+        let (service, db) = make_service(Some(TOKEN)).await;
+        let admin = service.authorize(Some(TOKEN)).unwrap();
+        let auth = AuthService::new(db.clone(), Duration::from_secs(60));
+        let session = auth.register("sam", "long enough").await.unwrap();
+        answer(&service, "sam").await;
+        assert_eq!(service.users(&admin).await.unwrap(), ["sam"]);
+
+        service.delete_user(&admin, "sam").await.unwrap();
+
+        assert!(matches!(
+            auth.login("sam", "long enough").await,
+            Err(Error::InvalidCredentials)
+        ));
+        assert!(matches!(
+            auth.authenticate(&session.token).await,
+            Err(Error::Unauthorized)
+        ));
+        assert!(db.list_devices("sam").await.unwrap().is_empty());
+        assert!(service.users(&admin).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reset_devices_removes_all_devices_of_user() {
+        let (service, db) = make_service(Some(TOKEN)).await;
+        let admin = service.authorize(Some(TOKEN)).unwrap();
+        db.register_device("sam", "laptop").await.unwrap();
+        db.register_device("sam", "phone").await.unwrap();
+        db.register_device("alex", "phone").await.unwrap();
+
+        assert_eq!(service.reset_devices(&admin, "sam").await.unwrap(), 2);
+
+        assert!(db.list_devices("sam").await.unwrap().is_empty());
+        assert_eq!(db.list_devices("alex").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_answer_records_skip_questions_outside_survey() {
+        let (service, db) = make_service(Some(TOKEN)).await;
+        let admin = service.authorize(Some(TOKEN)).unwrap();
+        answer(&service, "sam").await;
+        db.add_answer("sam", "retired".to_string(), repo::Answer::Text("no".to_string()))
+            .await
+            .unwrap();
+        let survey = service.survey.empty_survey();
+
+        let records = service.answer_records(&admin, survey.id).await.unwrap().unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].user, "sam");
+        assert_eq!(records[0].question_id, survey.questions[0].id);
+        assert!(records[0].answered_at.is_some());
+        assert!(service.answer_records(&admin, survey.id + 1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_question_publishes_known_questions_only() {
+        let (service, _db) = make_service(Some(TOKEN)).await;
+        let admin = service.authorize(Some(TOKEN)).unwrap();
+        let survey = service.survey.empty_survey();
+        let mut events = service.survey.subscribe(survey.id).await.unwrap().unwrap().events;
+        let answer = prono_api::Answer::Text("yes".to_string());
+
+        assert!(
+            !service
+                .resolve_question(&admin, "unknown".to_string(), answer.clone())
+                .await
+                .unwrap()
+        );
+        assert!(
+            service
+                .resolve_question(&admin, survey.questions[0].id.clone(), answer.clone())
+                .await
+                .unwrap()
+        );
+
+        assert_eq!(
+            events.try_recv().unwrap(),
+            prono_api::SurveyEvent::QuestionResolved {
+                question_id: survey.questions[0].id.clone(),
+                answer,
+            }
+        );
+        assert!(events.try_recv().is_err());
+        let past = service.survey.subscribe(survey.id).await.unwrap().unwrap().past;
+        assert_eq!(past.len(), 1);
+    }
+}
@@ -0,0 +1,207 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use prono::{credentials, repo};
+
+use crate::{BackendResult, Error};
+
+/// User on whose behalf a request is made, established from its session token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Principal {
+    pub user: String,
+}
+
+/// Session handed out on login. Only the hash of `token` is stored.
+#[derive(Debug)]
+pub struct IssuedSession {
+    pub token: String,
+    pub user: String,
+    pub expires_at: u64,
+}
+
+pub struct AuthService {
+    accounts: Arc<dyn repo::Accounts + Send + Sync>,
+    session_ttl: Duration,
+}
+
+impl AuthService {
+    pub fn new(accounts: Arc<dyn repo::Accounts + Send + Sync>, session_ttl: Duration) -> Self {
+        Self { accounts, session_ttl }
+    }
+
+    /// Creates the account and signs the new user in, under the normalized form of `user`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the username or password is invalid, the username is taken,
+    /// or if a repository error occurs.
+    pub async fn register(&self, user: &str, password: &str) -> BackendResult<IssuedSession> {
+        let username = credentials::register(self.accounts.as_ref(), user, password).await?;
+        self.issue_session(username.as_str()).await
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the credentials are invalid or if a repository error occurs.
+    pub async fn login(&self, user: &str, password: &str) -> BackendResult<IssuedSession> {
+        let username = credentials::verify_login(self.accounts.as_ref(), user, password).await?;
+        self.issue_session(username.as_str()).await
+    }
+
+    /// # Errors
+    ///
+    /// Returns [`Error::Unauthorized`] if the token is unknown or expired, or a repository error.
+    pub async fn authenticate(&self, token: &str) -> BackendResult<Principal> {
+        let token_hash = credentials::token_hash(token);
+        let Some(session) = self.accounts.session(&token_hash).await? else {
+            return Err(Error::Unauthorized);
+        };
+        if session.is_expired(unix_now()) {
+            self.accounts.delete_session(&token_hash).await?;
+            return Err(Error::Unauthorized);
+        }
+        Ok(Principal { user: session.user })
+    }
+
+    /// User of the token-free legacy answer route, the normalized form of `user`. A name with an account only
+    /// answers with its session token, otherwise anyone could answer as it from a new device.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Unauthorized`] if `user` has an account, [`Error::InvalidRequest`] for an invalid username,
+    /// or a repository error.
+    pub async fn legacy_principal(&self, user: &str) -> BackendResult<Principal> {
+        let username = credentials::parse_username(user)?;
+        if self.accounts.password_hash(username.as_str()).await?.is_some() {
+            return Err(Error::Unauthorized);
+        }
+        Ok(Principal { user: username.into() })
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if a repository error occurs.
+    pub async fn logout(&self, token: &str) -> BackendResult<()> {
+        self.accounts.delete_session(&credentials::token_hash(token)).await?;
+        Ok(())
+    }
+
+    async fn issue_session(&self, user: &str) -> BackendResult<IssuedSession> {
+        let token = credentials::new_token()?;
+        let session = repo::Session {
+            token_hash: credentials::token_hash(&token),
+            user: user.to_string(),
+            expires_at: unix_now().saturating_add(self.session_ttl.as_secs()),
+        };
+        self.accounts.create_session(&session).await?;
+        Ok(IssuedSession {
+            token,
+            user: session.user,
+            expires_at: session.expires_at,
+        })
+    }
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+
+    use prono::fake_db::FakeRepo;
+    use prono::repo::Db as _;
+
+    async fn make_service(session_ttl: Duration) -> AuthService {
+        AuthService::new(Arc::new(FakeRepo::init(()).await.unwrap()), session_ttl)
+    }
+
+    #[tokio::test]
+    async fn test_registered_user_is_signed_in() {
+        let service = make_service(Duration::from_secs(60)).await;
+
+        let session = service.register("sam", "long enough").await.unwrap();
+
+        assert_eq!(session.user, "sam");
+        assert!(session.expires_at > unix_now());
+        assert_eq!(
+            service.authenticate(&session.token).await.unwrap(),
+            Principal {
+                user: "sam".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_login_checks_password() {
+        let service = make_service(Duration::from_secs(60)).await;
+        service.register("sam", "long enough").await.unwrap();
+
+        assert!(service.login("sam", "long enough").await.is_ok());
+        assert_eq!(
+            service.login("sam", "wrong password").await.unwrap_err(),
+            Error::InvalidCredentials
+        );
+    }
+
+    #[tokio::test]
+    async fn test_register_taken_name_fails() {
+        let service = make_service(Duration::from_secs(60)).await;
+        service.register("sam", "long enough").await.unwrap();
+
+        assert_eq!(
+            service.register("sam", "other password").await.unwrap_err(),
+            Error::UserExists
+        );
+    }
+
+    #[tokio::test]
+    async fn test_usernames_are_normalized() {
+        let service = make_service(Duration::from_secs(60)).await;
+        let session = service.register(" Sam", "long enough").await.unwrap();
+        assert_eq!(session.user, "sam");
+
+        assert_eq!(service.login("SAM", "long enough").await.unwrap().user, "sam");
+        assert_eq!(
+            service.register("sam ", "other password").await.unwrap_err(),
+            Error::UserExists
+        );
+        assert!(matches!(
+            service.register("sam@home", "long enough").await,
+            Err(Error::InvalidRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_token_is_unauthorized() {
+        let service = make_service(Duration::from_secs(60)).await;
+        assert_eq!(service.authenticate("forged").await.unwrap_err(), Error::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn test_logged_out_token_is_unauthorized() {
+        let service = make_service(Duration::from_secs(60)).await;
+        let session = service.register("sam", "long enough").await.unwrap();
+
+        service.logout(&session.token).await.unwrap();
+
+        assert_eq!(
+            service.authenticate(&session.token).await.unwrap_err(),
+            Error::Unauthorized
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_unauthorized() {
+        let service = make_service(Duration::ZERO).await;
+        let session = service.register("sam", "long enough").await.unwrap();
+
+        assert_eq!(
+            service.authenticate(&session.token).await.unwrap_err(),
+            Error::Unauthorized
+        );
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use prono::credentials;
+
+use crate::use_cases::unix_now;
+use crate::{BackendResult, Error};
+
+/// Most redeemed challenges remembered per client until they expire. Beyond it, answers of the client are turned
+/// away until its oldest expire, so the memory taken stays bounded however many stamps one client solves.
+const MAX_REDEEMED_PER_CLIENT: usize = 1_000;
+
+/// Issues proof-of-work challenges and accepts each solved challenge once.
+///
+/// Challenges are stateless: the nonce carries its expiry time and a signature over it, so issuing one takes no
+/// memory. Only redeemed nonces are remembered, until they expire. The signing secret is generated at start, so a
+/// backend restart invalidates the challenges clients are working on.
+pub struct Challenges {
+    difficulty: u8,
+    ttl: Duration,
+    secret: String,
+    redeemed: Mutex<Redeemed>,
+}
+
+/// Redeemed and not yet expired challenges.
+#[derive(Default)]
+struct Redeemed {
+    /// Expiry time and client of every challenge, by nonce.
+    by_nonce: HashMap<String, (u64, String)>,
+    /// Number of challenges of each client in `by_nonce`.
+    per_client: HashMap<String, usize>,
+}
+
+impl Redeemed {
+    fn forget_expired(&mut self, now: u64) {
+        let per_client = &mut self.per_client;
+        self.by_nonce.retain(|_nonce, (expires_at, client)| {
+            let keep = *expires_at >= now;
+            if !keep && let Some(count) = per_client.get_mut(client.as_str()) {
+                *count -= 1;
+                if *count == 0 {
+                    per_client.remove(client.as_str());
+                }
+            }
+            keep
+        });
+    }
+
+    fn insert(&mut self, nonce: &str, expires_at: u64, client: &str) {
+        self.by_nonce
+            .insert(nonce.to_string(), (expires_at, client.to_string()));
+        *self.per_client.entry(client.to_string()).or_default() += 1;
+    }
+}
+
+impl Challenges {
+    /// # Panics
+    ///
+    /// Panics if the system random number generator fails.
+    #[must_use]
+    pub fn new(difficulty: u8, ttl: Duration) -> Self {
+        Self {
+            difficulty,
+            ttl,
+            secret: credentials::new_token().expect("Failed to generate a challenge secret"),
+            redeemed: Mutex::new(Redeemed::default()),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if no random nonce can be generated.
+    pub fn issue(&self) -> BackendResult<prono_api::Challenge> {
+        let expires_at = unix_now().saturating_add(self.ttl.as_secs());
+        let random = credentials::new_token()?;
+        let signature = credentials::sign(&self.secret, &self.signed_part(&random, expires_at));
+        Ok(prono_api::Challenge {
+            nonce: format!("{random}-{expires_at}-{signature}"),
+            difficulty: self.difficulty,
+            expires_at,
+        })
+    }
+
+    /// Accepts a stamp solving an issued challenge, once. `client` is the IP address the stamp comes from, if
+    /// known.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ChallengeFailed`] if the challenge was not issued by this backend, is already used or
+    /// expired, or if the stamp does not solve it. Returns [`Error::RateLimited`] while too many challenges
+    /// redeemed by `client` are remembered.
+    pub fn redeem(&self, stamp: &str, client: Option<&str>) -> BackendResult<()> {
+        let nonce = prono_api::stamp_nonce(stamp)
+            .ok_or_else(|| Error::ChallengeFailed("missing or malformed stamp".to_string()))?;
+        let expires_at = self
+            .verified_expiry(nonce)
+            .ok_or_else(|| Error::ChallengeFailed("unknown challenge".to_string()))?;
+        let now = unix_now();
+        if expires_at < now {
+            return Err(Error::ChallengeFailed("challenge expired".to_string()));
+        }
+
+        let challenge = prono_api::Challenge {
+            nonce: nonce.to_string(),
+            difficulty: self.difficulty,
+            expires_at,
+        };
+        if !challenge.is_solved_by(stamp) {
+            return Err(Error::ChallengeFailed("stamp does not solve the challenge".to_string()));
+        }
+
+        let client = client.unwrap_or_default();
+        let mut redeemed = self.redeemed.lock().unwrap_or_else(PoisonError::into_inner);
+        redeemed.forget_expired(now);
+        if redeemed.by_nonce.contains_key(nonce) {
+            return Err(Error::ChallengeFailed("challenge already used".to_string()));
+        }
+        if redeemed.per_client.get(client).copied().unwrap_or_default() >= MAX_REDEEMED_PER_CLIENT {
+            let first_expiry = redeemed
+                .by_nonce
+                .values()
+                .filter(|(_, redeemed_by)| redeemed_by == client)
+                .map(|(expires_at, _)| *expires_at)
+                .min()
+                .unwrap_or(now);
+            return Err(Error::RateLimited(first_expiry.saturating_sub(now).max(1)));
+        }
+        redeemed.insert(nonce, expires_at, client);
+        Ok(())
+    }
+
+    fn signed_part(&self, random: &str, expires_at: u64) -> String {
+        format!("{random}-{expires_at}-{}", self.difficulty)
+    }
+
+    /// Expiry time of a nonce issued by [`Self::issue`], `None` for a forged or malformed nonce.
+    fn verified_expiry(&self, nonce: &str) -> Option<u64> {
+        let mut parts = nonce.split('-');
+        let (Some(random), Some(expires_at), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return None;
+        };
+        let expires_at = expires_at.parse().ok()?;
+        credentials::verify_signature(&self.secret, &self.signed_part(random, expires_at), signature)
+            .then_some(expires_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn challenges() -> Challenges {
+        Challenges::new(4, Duration::from_secs(60))
+    }
+
+    fn signed_nonce(challenges: &Challenges, random: &str, expires_at: u64) -> String {
+        let signature = credentials::sign(&challenges.secret, &challenges.signed_part(random, expires_at));
+        format!("{random}-{expires_at}-{signature}")
+    }
+
+    #[test]
+    fn test_solved_challenge_is_accepted_once() {
+        let challenges = challenges();
+        let stamp = challenges.issue().unwrap().solve();
+
+        assert_eq!(challenges.redeem(&stamp, None), Ok(()));
+        assert_eq!(
+            challenges.redeem(&stamp, None),
+            Err(Error::ChallengeFailed("challenge already used".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_issued_challenge_uses_configured_difficulty() {
+        let challenge = challenges().issue().unwrap();
+
+        assert_eq!(challenge.difficulty, 4);
+        assert!(challenge.expires_at > unix_now());
+    }
+
+    #[test]
+    fn test_issuing_keeps_no_state() {
+        let challenges = challenges();
+        for _ in 0..10 {
+            challenges.issue().unwrap();
+        }
+
+        assert!(challenges.redeemed.lock().unwrap().by_nonce.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_challenge_is_rejected() {
+        let challenges = challenges();
+        let signed_for_past = signed_nonce(&challenges, "r", 0);
+        let extended = signed_for_past.replacen("-0-", &format!("-{}-", u64::MAX), 1);
+
+        for nonce in ["forged".to_string(), extended, challenges.issue().unwrap().nonce + "0"] {
+            let forged = prono_api::Challenge {
+                nonce,
+                difficulty: 4,
+                expires_at: u64::MAX,
+            };
+            assert_eq!(
+                challenges.redeem(&forged.solve(), None),
+                Err(Error::ChallengeFailed("unknown challenge".to_string()))
+            );
+        }
+        assert!(matches!(challenges.redeem("", None), Err(Error::ChallengeFailed(_))));
+    }
+
+    #[test]
+    fn test_challenge_of_another_backend_is_rejected() {
+        let stamp = challenges().issue().unwrap().solve();
+
+        assert!(matches!(
+            challenges().redeem(&stamp, None),
+            Err(Error::ChallengeFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_unsolved_challenge_is_rejected() {
+        let challenges = Challenges::new(32, Duration::from_secs(60));
+        let challenge = challenges.issue().unwrap();
+        let stamp = format!("{}:0", challenge.nonce);
+
+        assert_eq!(
+            challenges.redeem(&stamp, None),
+            Err(Error::ChallengeFailed("stamp does not solve the challenge".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_expired_challenge_is_rejected() {
+        let challenges = Challenges::new(0, Duration::ZERO);
+        let nonce = signed_nonce(&challenges, "r", 0);
+
+        assert_eq!(
+            challenges.redeem(&format!("{nonce}:0"), None),
+            Err(Error::ChallengeFailed("challenge expired".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_redeemed_challenges_are_bounded_per_client() {
+        let challenges = challenges();
+        let expires_at = unix_now() + 30;
+        {
+            let mut redeemed = challenges.redeemed.lock().unwrap();
+            for i in 0..MAX_REDEEMED_PER_CLIENT {
+                redeemed.insert(&i.to_string(), expires_at, "10.0.0.1");
+            }
+        }
+
+        assert!(matches!(
+            challenges.redeem(&challenges.issue().unwrap().solve(), Some("10.0.0.1")),
+            Err(Error::RateLimited(secs)) if (1..=30).contains(&secs)
+        ));
+        assert_eq!(
+            challenges.redeem(&challenges.issue().unwrap().solve(), Some("10.0.0.2")),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_expired_challenges_no_longer_count_for_their_client() {
+        let challenges = challenges();
+        {
+            let mut redeemed = challenges.redeemed.lock().unwrap();
+            for i in 0..MAX_REDEEMED_PER_CLIENT {
+                redeemed.insert(&i.to_string(), 0, "10.0.0.1");
+            }
+        }
+
+        assert_eq!(
+            challenges.redeem(&challenges.issue().unwrap().solve(), Some("10.0.0.1")),
+            Ok(())
+        );
+        let redeemed = challenges.redeemed.lock().unwrap();
+        assert_eq!(redeemed.by_nonce.len(), 1);
+        assert_eq!(redeemed.per_client.get("10.0.0.1"), Some(&1));
+    }
+}
@@ -0,0 +1,411 @@
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use log::{info, warn};
+use prono::{Error, PronoResult, repo};
+
+/// Delays between connection attempts, doubling from `initial` up to `max`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Backoff {
+    fn next(self, delay: Duration) -> Duration {
+        delay.saturating_mul(2).min(self.max)
+    }
+}
+
+/// Whether a [`DeferredRepo`] is connected, readable without knowing its repository type.
+#[derive(Clone)]
+pub struct RepoAvailability(Arc<dyn Fn() -> bool + Send + Sync>);
+
+impl RepoAvailability {
+    pub fn from_fn(is_available: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(is_available))
+    }
+
+    #[must_use]
+    pub fn is_available(&self) -> bool {
+        (self.0)()
+    }
+}
+
+/// Repository that connects in the background, so that the backend starts while the database is still down.
+///
+/// Until the connection succeeds, calls fail with [`Error::Unavailable`], or return nothing for calls that
+/// cannot fail. Connection failures after that are left to the repository.
+pub struct DeferredRepo<D> {
+    db: Arc<OnceLock<D>>,
+}
+
+impl<D> DeferredRepo<D>
+where
+    D: repo::Db + 'static,
+    D::Config: Clone + Send + Sync,
+{
+    /// Starts connecting on the current Tokio runtime, retrying with `backoff` until it succeeds.
+    pub fn connect(config: D::Config, backoff: Backoff) -> Self {
+        let db = Arc::new(OnceLock::new());
+        let connected = db.clone();
+        tokio::spawn(async move {
+            let mut delay = backoff.initial;
+            loop {
+                match D::init(config.clone()).await {
+                    Ok(db) => {
+                        info!("Repository connected");
+                        let _ = connected.set(db);
+                        return;
+                    }
+                    Err(err) => {
+                        warn!("Repository unavailable, retrying in {} s: {err}", delay.as_secs_f32());
+                        tokio::time::sleep(delay).await;
+                        delay = backoff.next(delay);
+                    }
+                }
+            }
+        });
+        Self { db }
+    }
+}
+
+impl<D: Send + Sync + 'static> DeferredRepo<D> {
+    /// The connected repository, `None` while connecting.
+    #[must_use]
+    pub fn current(&self) -> Option<&D> {
+        self.db.get()
+    }
+
+    #[must_use]
+    pub fn availability(&self) -> RepoAvailability {
+        let db = self.db.clone();
+        RepoAvailability::from_fn(move || db.get().is_some())
+    }
+
+    fn get(&self) -> PronoResult<&D> {
+        self.current()
+            .ok_or_else(|| Error::Unavailable("Connecting to the database".to_string()))
+    }
+}
+
+#[async_trait]
+impl<D> repo::Db for DeferredRepo<D>
+where
+    D: repo::Db + 'static,
+    D::Config: Clone + Send + Sync,
+{
+    type Config = (D::Config, Backoff);
+
+    async fn init((config, backoff): Self::Config) -> PronoResult<Self> {
+        Ok(Self::connect(config, backoff))
+    }
+
+    async fn health_check(&self) -> PronoResult<()> {
+        self.get()?.health_check().await
+    }
+}
+
+#[async_trait]
+impl<D: repo::Surveys + 'static> repo::Surveys for DeferredRepo<D> {
+    async fn answer(&self, user: &str, question_id: String) -> Option<repo::Answer> {
+        self.current()?.answer(user, question_id).await
+    }
+
+    async fn response(&self, user: &str, survey_id: u64) -> Option<repo::Survey> {
+        self.current()?.response(user, survey_id).await
+    }
+
+    async fn add_answer(&self, user: &str, question_id: String, answer: repo::Answer) -> PronoResult<()> {
+        self.get()?.add_answer(user, question_id, answer).await
+    }
+
+    async fn replace_answer(&self, user: &str, question_id: String, answer: repo::Answer) -> PronoResult<()> {
+        self.get()?.replace_answer(user, question_id, answer).await
+    }
+
+    async fn remove_answer(&self, user: &str, question_id: String) -> PronoResult<bool> {
+        self.get()?.remove_answer(user, question_id).await
+    }
+
+    async fn all_answers(&self, question_id: String) -> Vec<(String, repo::Answer)> {
+        match self.current() {
+            Some(db) => db.all_answers(question_id).await,
+            None => Vec::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl<D: repo::SurveyStates + 'static> repo::SurveyStates for DeferredRepo<D> {
+    async fn is_closed(&self, survey_id: u64) -> PronoResult<bool> {
+        self.get()?.is_closed(survey_id).await
+    }
+
+    async fn set_closed(&self, survey_id: u64, closed: bool) -> PronoResult<()> {
+        self.get()?.set_closed(survey_id, closed).await
+    }
+
+    async fn privacy(&self, survey_id: u64) -> PronoResult<repo::Privacy> {
+        self.get()?.privacy(survey_id).await
+    }
+
+    async fn set_privacy(&self, survey_id: u64, privacy: repo::Privacy) -> PronoResult<()> {
+        self.get()?.set_privacy(survey_id, privacy).await
+    }
+
+    async fn resolve_question(&self, survey_id: u64, question_id: &str, answer: repo::Answer) -> PronoResult<()> {
+        self.get()?.resolve_question(survey_id, question_id, answer).await
+    }
+
+    async fn resolutions(&self, survey_id: u64) -> PronoResult<Vec<(String, repo::Answer)>> {
+        self.get()?.resolutions(survey_id).await
+    }
+}
+
+#[async_trait]
+impl<D: repo::AnswerRecords + 'static> repo::AnswerRecords for DeferredRepo<D> {
+    async fn answer_records(&self, survey_id: u64) -> PronoResult<Vec<repo::AnswerRecord>> {
+        self.get()?.answer_records(survey_id).await
+    }
+}
+
+#[async_trait]
+impl<D: repo::Users + 'static> repo::Users for DeferredRepo<D> {
+    async fn all_users(&self) -> PronoResult<Vec<String>> {
+        self.get()?.all_users().await
+    }
+
+    async fn all_names(&self) -> PronoResult<Vec<String>> {
+        self.get()?.all_names().await
+    }
+
+    async fn delete_user(&self, name: &str) -> PronoResult<()> {
+        self.get()?.delete_user(name).await
+    }
+
+    async fn rename_user(&self, old: &str, new: &str) -> PronoResult<()> {
+        self.get()?.rename_user(old, new).await
+    }
+
+    async fn merge_users(&self, from: &str, into: &str) -> PronoResult<()> {
+        self.get()?.merge_users(from, into).await
+    }
+}
+
+#[async_trait]
+impl<D: repo::DeviceRegistry + 'static> repo::DeviceRegistry for DeferredRepo<D> {
+    async fn register_device(&self, user: &str, device_id: &str) -> PronoResult<()> {
+        self.get()?.register_device(user, device_id).await
+    }
+
+    async fn list_devices(&self, user: &str) -> PronoResult<Vec<repo::Device>> {
+        self.get()?.list_devices(user).await
+    }
+
+    async fn unregister_device(&self, user: &str, device_id: &str) -> PronoResult<bool> {
+        self.get()?.unregister_device(user, device_id).await
+    }
+
+    async fn unregister_devices(&self, user: &str) -> PronoResult<usize> {
+        self.get()?.unregister_devices(user).await
+    }
+
+    async fn admit_device(&self, user: &str, device_id: &str, policy: repo::DevicePolicy) -> PronoResult<bool> {
+        self.get()?.admit_device(user, device_id, policy).await
+    }
+}
+
+#[async_trait]
+impl<D: repo::Accounts + 'static> repo::Accounts for DeferredRepo<D> {
+    async fn create_account(&self, user: &str, password_hash: &str) -> PronoResult<()> {
+        self.get()?.create_account(user, password_hash).await
+    }
+
+    async fn password_hash(&self, user: &str) -> PronoResult<Option<String>> {
+        self.get()?.password_hash(user).await
+    }
+
+    async fn create_session(&self, session: &repo::Session) -> PronoResult<()> {
+        self.get()?.create_session(session).await
+    }
+
+    async fn session(&self, token_hash: &str) -> PronoResult<Option<repo::Session>> {
+        self.get()?.session(token_hash).await
+    }
+
+    async fn delete_session(&self, token_hash: &str) -> PronoResult<()> {
+        self.get()?.delete_session(token_hash).await
+    }
+}
+
+#[async_trait]
+impl<D: repo::RateLimitStore + 'static> repo::RateLimitStore for DeferredRepo<D> {
+    async fn take_tokens(&self, buckets: &[(String, repo::RateLimit)], now: u64) -> PronoResult<Option<Duration>> {
+        self.get()?.take_tokens(buckets, now).await
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use prono::fake_db::FakeRepo;
+    use prono::repo::{Accounts as _, Db as _, Surveys as _};
+    use tokio::sync::Semaphore;
+
+    const BACKOFF: Backoff = Backoff {
+        initial: Duration::from_millis(1),
+        max: Duration::from_millis(4),
+    };
+
+    /// Connection attempts of a [`FlakyDb`] wait until the test releases them.
+    #[derive(Clone)]
+    struct FlakyConfig {
+        failures_left: Arc<AtomicUsize>,
+        release: Arc<Semaphore>,
+    }
+
+    impl FlakyConfig {
+        fn failing(failures: usize) -> Self {
+            Self {
+                failures_left: Arc::new(AtomicUsize::new(failures)),
+                release: Arc::new(Semaphore::new(0)),
+            }
+        }
+
+        fn release(&self) {
+            self.release.add_permits(Semaphore::MAX_PERMITS);
+        }
+    }
+
+    /// Fails to connect as many times as the counter in its config says, once connection attempts are released.
+    struct FlakyDb(FakeRepo);
+
+    #[async_trait]
+    impl repo::Db for FlakyDb {
+        type Config = FlakyConfig;
+
+        async fn init(config: Self::Config) -> PronoResult<Self> {
+            let _released = config.release.acquire().await;
+            let failed = config
+                .failures_left
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            if failed {
+                return Err(Error::Repository("connection refused".to_string()));
+            }
+            Ok(Self(FakeRepo::init(()).await?))
+        }
+
+        async fn health_check(&self) -> PronoResult<()> {
+            self.0.health_check().await
+        }
+    }
+
+    #[async_trait]
+    impl repo::Surveys for FlakyDb {
+        async fn answer(&self, user: &str, question_id: String) -> Option<repo::Answer> {
+            self.0.answer(user, question_id).await
+        }
+
+        async fn response(&self, user: &str, survey_id: u64) -> Option<repo::Survey> {
+            self.0.response(user, survey_id).await
+        }
+
+        async fn add_answer(&self, user: &str, question_id: String, answer: repo::Answer) -> PronoResult<()> {
+            self.0.add_answer(user, question_id, answer).await
+        }
+
+        async fn replace_answer(&self, user: &str, question_id: String, answer: repo::Answer) -> PronoResult<()> {
+            self.0.replace_answer(user, question_id, answer).await
+        }
+
+        async fn remove_answer(&self, user: &str, question_id: String) -> PronoResult<bool> {
+            self.0.remove_answer(user, question_id).await
+        }
+
+        async fn all_answers(&self, question_id: String) -> Vec<(String, repo::Answer)> {
+            self.0.all_answers(question_id).await
+        }
+    }
+
+    #[async_trait]
+    impl repo::Accounts for FlakyDb {
+        async fn create_account(&self, user: &str, password_hash: &str) -> PronoResult<()> {
+            self.0.create_account(user, password_hash).await
+        }
+
+        async fn password_hash(&self, user: &str) -> PronoResult<Option<String>> {
+            self.0.password_hash(user).await
+        }
+
+        async fn create_session(&self, session: &repo::Session) -> PronoResult<()> {
+            self.0.create_session(session).await
+        }
+
+        async fn session(&self, token_hash: &str) -> PronoResult<Option<repo::Session>> {
+            self.0.session(token_hash).await
+        }
+
+        async fn delete_session(&self, token_hash: &str) -> PronoResult<()> {
+            self.0.delete_session(token_hash).await
+        }
+    }
+
+    async fn wait_until_available(availability: &RepoAvailability) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !availability.is_available() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("repository should connect");
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        assert_eq!(BACKOFF.next(Duration::from_millis(1)), Duration::from_millis(2));
+        assert_eq!(BACKOFF.next(Duration::from_millis(2)), Duration::from_millis(4));
+        assert_eq!(BACKOFF.next(Duration::from_millis(4)), Duration::from_millis(4));
+    }
+
+    #[tokio::test]
+    async fn test_calls_fail_as_unavailable_until_connected() {
+        let config = FlakyConfig::failing(3);
+        let repo = DeferredRepo::<FlakyDb>::connect(config.clone(), BACKOFF);
+        let availability = repo.availability();
+
+        assert!(!availability.is_available());
+        assert!(matches!(repo.password_hash("sam").await, Err(Error::Unavailable(_))));
+        assert!(matches!(repo.health_check().await, Err(Error::Unavailable(_))));
+        assert!(repo.all_answers("q1".to_string()).await.is_empty());
+
+        config.release();
+        wait_until_available(&availability).await;
+        assert_eq!(config.failures_left.load(Ordering::SeqCst), 0);
+        repo.create_account("sam", "hash").await.unwrap();
+        assert_eq!(repo.password_hash("sam").await.unwrap().as_deref(), Some("hash"));
+        repo.health_check().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_repository_is_unavailable_while_connecting() {
+        let config = FlakyConfig::failing(usize::MAX);
+        let repo = DeferredRepo::<FlakyDb>::connect(config.clone(), BACKOFF);
+
+        config.release();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert!(!repo.availability().is_available());
+        assert!(repo.current().is_none());
+        assert!(matches!(
+            repo.add_answer("sam", "q1".to_string(), repo::Answer::Text("yes".to_string()))
+                .await,
+            Err(Error::Unavailable(_))
+        ));
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use prono::{credentials, repo};
+
+use crate::use_cases::{Principal, unix_now};
+use crate::{BackendResult, Error};
+
+/// Code handed out to move a device registration to another device.
+#[derive(Debug)]
+pub struct TransferCode {
+    pub code: String,
+    pub expires_at: u64,
+}
+
+struct PendingTransfer {
+    user: String,
+    from_device: String,
+    expires_at: u64,
+}
+
+/// Devices users submit answers from, limited by a [`repo::DevicePolicy`].
+///
+/// A user moves to a new device with a one-time transfer code requested from a registered device.
+/// Pending transfers are kept in memory, a backend restart invalidates them.
+pub struct DeviceService {
+    devices: Arc<dyn repo::DeviceRegistry + Send + Sync>,
+    policy: repo::DevicePolicy,
+    transfer_code_ttl: Duration,
+    /// Pending transfers by hash of their code.
+    transfers: Mutex<HashMap<String, PendingTransfer>>,
+}
+
+impl DeviceService {
+    pub fn new(
+        devices: Arc<dyn repo::DeviceRegistry + Send + Sync>,
+        policy: repo::DevicePolicy,
+        transfer_code_ttl: Duration,
+    ) -> Self {
+        Self {
+            devices,
+            policy,
+            transfer_code_ttl,
+            transfers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Registers the device for the user unless the policy forbids it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::DeviceMismatch`] if the user already has the maximum number of other devices,
+    /// or a repository error.
+    pub async fn admit(&self, user: &str, device_id: &str) -> BackendResult<()> {
+        if self.devices.admit_device(user, device_id, self.policy).await? {
+            Ok(())
+        } else {
+            Err(Error::DeviceMismatch)
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if a repository error occurs.
+    pub async fn list(&self, user: &str) -> BackendResult<Vec<repo::Device>> {
+        Ok(self.devices.list_devices(user).await?)
+    }
+
+    /// Returns whether the device was registered for the user.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a repository error occurs.
+    pub async fn unregister(&self, user: &str, device_id: &str) -> BackendResult<bool> {
+        Ok(self.devices.unregister_device(user, device_id).await?)
+    }
+
+    /// Issues a one-time code that moves the registration of `device_id` to the device redeeming it.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::DeviceMismatch`] if `device_id` is not registered for the user,
+    /// or a repository error.
+    pub async fn start_transfer(&self, principal: &Principal, device_id: &str) -> BackendResult<TransferCode> {
+        let devices = self.devices.list_devices(&principal.user).await?;
+        if !devices.iter().any(|device| device.id == device_id) {
+            return Err(Error::DeviceMismatch);
+        }
+
+        let now = unix_now();
+        let code = TransferCode {
+            code: credentials::new_one_time_code()?,
+            expires_at: now.saturating_add(self.transfer_code_ttl.as_secs()),
+        };
+        let mut transfers = self.transfers.lock().unwrap_or_else(PoisonError::into_inner);
+        transfers.retain(|_hash, transfer| transfer.expires_at >= now);
+        transfers.insert(
+            credentials::token_hash(&code.code),
+            PendingTransfer {
+                user: principal.user.clone(),
+                from_device: device_id.to_string(),
+                expires_at: code.expires_at,
+            },
+        );
+        Ok(code)
+    }
+
+    /// Registers `device_id` in place of the device that requested the code. The code is used up,
+    /// whether the transfer succeeds or not.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidRequest`] if the device id is missing, or the code is unknown, expired or
+    /// issued to another user, or a repository error.
+    pub async fn complete_transfer(&self, principal: &Principal, code: &str, device_id: &str) -> BackendResult<()> {
+        if device_id.is_empty() {
+            return Err(Error::InvalidRequest("Missing device id".to_string()));
+        }
+        let code_hash = credentials::token_hash(&code.trim().to_uppercase());
+        let transfer = self
+            .transfers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&code_hash)
+            .filter(|transfer| transfer.user == principal.user && transfer.expires_at >= unix_now())
+            .ok_or_else(|| Error::InvalidRequest("Unknown or expired transfer code".to_string()))?;
+
+        self.devices
+            .unregister_device(&transfer.user, &transfer.from_device)
+            .await?;
+        self.devices.register_device(&transfer.user, device_id).await?;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+
+    use prono::fake_db::FakeRepo;
+    use prono::repo::Db as _;
+
+    fn principal(user: &str) -> Principal {
+        Principal { user: user.to_string() }
+    }
+
+    async fn make_service(max_devices: usize) -> DeviceService {
+        DeviceService::new(
+            Arc::new(FakeRepo::init(()).await.unwrap()),
+            repo::DevicePolicy { max_devices },
+            Duration::from_secs(60),
+        )
+    }
+
+    async fn device_ids(service: &DeviceService, user: &str) -> Vec<String> {
+        service
+            .list(user)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|device| device.id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_admit_allows_devices_up_to_the_limit() {
+        let service = make_service(2).await;
+
+        service.admit("sam", "laptop").await.unwrap();
+        service.admit("sam", "phone").await.unwrap();
+        service.admit("sam", "laptop").await.unwrap();
+
+        assert_eq!(service.admit("sam", "tablet").await.unwrap_err(), Error::DeviceMismatch);
+        assert_eq!(device_ids(&service, "sam").await, ["laptop", "phone"]);
+    }
+
+    #[tokio::test]
+    async fn test_unregister_makes_room_for_another_device() {
+        let service = make_service(1).await;
+        service.admit("sam", "laptop").await.unwrap();
+
+        assert!(service.unregister("sam", "laptop").await.unwrap());
+        service.admit("sam", "phone").await.unwrap();
+
+        assert_eq!(device_ids(&service, "sam").await, ["phone"]);
+    }
+
+    #[tokio::test]
+    async fn test_transfer_moves_registration_once() {
+        let service = make_service(1).await;
+        service.admit("sam", "old-laptop").await.unwrap();
+
+        let code = service.start_transfer(&principal("sam"), "old-laptop").await.unwrap();
+        service
+            .complete_transfer(&principal("sam"), &code.code.to_lowercase(), "new-laptop")
+            .await
+            .unwrap();
+
+        assert_eq!(device_ids(&service, "sam").await, ["new-laptop"]);
+        assert!(matches!(
+            service
+                .complete_transfer(&principal("sam"), &code.code, "third-laptop")
+                .await,
+            Err(Error::InvalidRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_transfer_requires_a_registered_device() {
+        let service = make_service(1).await;
+        service.admit("sam", "old-laptop").await.unwrap();
+
+        assert_eq!(
+            service
+                .start_transfer(&principal("sam"), "new-laptop")
+                .await
+                .unwrap_err(),
+            Error::DeviceMismatch
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transfer_code_of_other_user_is_rejected() {
+        let service = make_service(1).await;
+        service.admit("sam", "laptop").await.unwrap();
+        let code = service.start_transfer(&principal("sam"), "laptop").await.unwrap();
+
+        assert!(matches!(
+            service.complete_transfer(&principal("alex"), &code.code, "phone").await,
+            Err(Error::InvalidRequest(_))
+        ));
+        assert_eq!(device_ids(&service, "sam").await, ["laptop"]);
+        assert!(device_ids(&service, "alex").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expired_transfer_code_is_rejected() {
+        let service = DeviceService::new(
+            Arc::new(FakeRepo::init(()).await.unwrap()),
+            repo::DevicePolicy { max_devices: 1 },
+            Duration::ZERO,
+        );
+        service.admit("sam", "laptop").await.unwrap();
+        let code = service.start_transfer(&principal("sam"), "laptop").await.unwrap();
+        service
+            .transfers
+            .lock()
+            .unwrap()
+            .values_mut()
+            .for_each(|t| t.expires_at = 0);
+
+        assert!(matches!(
+            service.complete_transfer(&principal("sam"), &code.code, "phone").await,
+            Err(Error::InvalidRequest(_))
+        ));
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures_util::future::BoxFuture;
+use prono::{PronoResult, repo};
+
+type HealthCheck = Box<dyn Fn() -> BoxFuture<'static, PronoResult<()>> + Send + Sync>;
+
+/// Outcome of a single health check.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CheckStatus {
+    pub latency: Duration,
+    /// Why the check failed, `None` if it passed.
+    pub error: Option<String>,
+}
+
+impl CheckStatus {
+    #[must_use]
+    pub fn is_up(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Checks whether the backend can serve requests, for readiness probes.
+pub struct HealthService {
+    db_check: HealthCheck,
+    timeout: Duration,
+}
+
+impl HealthService {
+    /// A check taking longer than `timeout` fails.
+    pub fn new<D: repo::Db + 'static>(db: Arc<D>, timeout: Duration) -> Self {
+        Self::with_db_check(
+            Box::new(move || {
+                let db = db.clone();
+                Box::pin(async move { db.health_check().await })
+            }),
+            timeout,
+        )
+    }
+
+    fn with_db_check(db_check: HealthCheck, timeout: Duration) -> Self {
+        Self { db_check, timeout }
+    }
+
+    /// Pings the repository.
+    pub async fn db_status(&self) -> CheckStatus {
+        let started = Instant::now();
+        let error = match tokio::time::timeout(self.timeout, (self.db_check)()).await {
+            Ok(Ok(())) => None,
+            Ok(Err(err)) => Some(err.to_string()),
+            Err(_elapsed) => Some(format!("No reply within {} ms", self.timeout.as_millis())),
+        };
+        CheckStatus {
+            latency: started.elapsed(),
+            error,
+        }
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+
+    use prono::fake_db::FakeRepo;
+    use prono::repo::Db as _;
+
+    #[tokio::test]
+    async fn test_reachable_db_is_up() {
+        let service = HealthService::new(Arc::new(FakeRepo::init(()).await.unwrap()), Duration::from_secs(1));
+
+        let status = service.db_status().await;
+
+        assert!(status.is_up());
+        assert!(status.latency < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_failing_db_is_down_with_reason() {
+        let service = HealthService::with_db_check(
+            Box::new(|| Box::pin(async { Err(prono::Error::Repository("connection refused".to_string())) })),
+            Duration::from_secs(1),
+        );
+
+        let status = service.db_status().await;
+
+        assert!(!status.is_up());
+        assert!(status.error.unwrap().contains("connection refused"));
+    }
+
+    #[tokio::test]
+    async fn test_slow_db_is_down_after_timeout() {
+        let service =
+            HealthService::with_db_check(Box::new(|| Box::pin(std::future::pending())), Duration::from_millis(10));
+
+        let status = service.db_status().await;
+
+        assert_eq!(status.error.as_deref(), Some("No reply within 10 ms"));
+        assert!(status.latency >= Duration::from_millis(10));
+    }
+}
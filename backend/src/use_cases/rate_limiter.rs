@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use prono::PronoResult;
+use prono::repo::{self, RateLimit, TokenBucket};
+
+use crate::{BackendResult, Error};
+
+/// Number of buckets above which buckets that refilled completely are forgotten.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Token buckets kept in memory, every backend instance limits on its own.
+#[derive(Default)]
+pub struct MemoryRateLimitStore {
+    buckets: Mutex<HashMap<String, (TokenBucket, RateLimit)>>,
+}
+
+#[async_trait]
+impl repo::RateLimitStore for MemoryRateLimitStore {
+    async fn take_tokens(&self, keys: &[(String, RateLimit)], now: u64) -> PronoResult<Option<Duration>> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        if buckets.len() >= PRUNE_THRESHOLD {
+            buckets.retain(|_key, (bucket, limit)| !bucket.is_full_at(*limit, now));
+        }
+        let states: Vec<_> = keys
+            .iter()
+            .map(|(key, limit)| {
+                let bucket = buckets
+                    .get(key)
+                    .map_or_else(|| TokenBucket::full(*limit, now), |(bucket, _limit)| *bucket);
+                (bucket, *limit)
+            })
+            .collect();
+        match TokenBucket::take_all(&states, now) {
+            Ok(taken) => {
+                for ((key, limit), bucket) in keys.iter().zip(taken) {
+                    buckets.insert(key.clone(), (bucket, *limit));
+                }
+                Ok(None)
+            }
+            Err(wait) => Ok(Some(wait)),
+        }
+    }
+}
+
+/// Limits how often a client IP address and a signed in user, or on the token-free legacy answer route a device,
+/// may call the rate limited routes.
+pub struct RateLimiter {
+    store: Arc<dyn repo::RateLimitStore>,
+    per_ip: RateLimit,
+    per_user: RateLimit,
+    trust_forwarded_headers: bool,
+}
+
+impl RateLimiter {
+    pub fn new(store: Arc<dyn repo::RateLimitStore>, per_ip: RateLimit, per_user: RateLimit) -> Self {
+        Self {
+            store,
+            per_ip,
+            per_user,
+            trust_forwarded_headers: false,
+        }
+    }
+
+    /// Takes the client IP address from forwarding headers set by a reverse proxy.
+    #[must_use]
+    pub fn trusting_forwarded_headers(mut self, trust: bool) -> Self {
+        self.trust_forwarded_headers = trust;
+        self
+    }
+
+    #[must_use]
+    pub fn trusts_forwarded_headers(&self) -> bool {
+        self.trust_forwarded_headers
+    }
+
+    /// Takes a token from the bucket of the IP address and from the bucket of the signed in user, when known,
+    /// or from neither when one of them is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::RateLimited`] if either bucket is empty, or a repository error.
+    pub async fn check(&self, ip: Option<&str>, user: Option<&str>) -> BackendResult<()> {
+        self.take(ip, user.map(|user| format!("user:{user}"))).await
+    }
+
+    /// Like [`Self::check`] for requests without a session token, which are limited per `X-Device-Id` with the
+    /// limit of a user instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::RateLimited`] if either bucket is empty, or a repository error.
+    pub async fn check_device(&self, ip: Option<&str>, device_id: Option<&str>) -> BackendResult<()> {
+        self.take(ip, device_id.map(|device_id| format!("device:{device_id}")))
+            .await
+    }
+
+    async fn take(&self, ip: Option<&str>, client: Option<String>) -> BackendResult<()> {
+        let buckets: Vec<_> = [
+            ip.map(|ip| (format!("ip:{ip}"), self.per_ip)),
+            client.map(|client| (client, self.per_user)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if buckets.is_empty() {
+            return Ok(());
+        }
+        match self.store.take_tokens(&buckets, unix_now_millis()).await? {
+            Some(wait) => {
+                let retry_after = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+                Err(Error::RateLimited(retry_after.max(1)))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+fn unix_now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::*;
+
+    const PER_IP: RateLimit = RateLimit {
+        burst: 3,
+        per_minute: NonZeroU32::MIN,
+    };
+    const PER_USER: RateLimit = RateLimit {
+        burst: 1,
+        per_minute: NonZeroU32::MIN,
+    };
+
+    fn make_limiter() -> RateLimiter {
+        RateLimiter::new(Arc::new(MemoryRateLimitStore::default()), PER_IP, PER_USER)
+    }
+
+    #[tokio::test]
+    async fn test_user_is_limited_separately_from_ip() {
+        let limiter = make_limiter();
+
+        limiter.check(Some("10.0.0.1"), Some("sam")).await.unwrap();
+        let err = limiter.check(Some("10.0.0.1"), Some("sam")).await.unwrap_err();
+        assert!(matches!(err, Error::RateLimited(secs) if secs > 0 && secs <= 60));
+
+        limiter.check(Some("10.0.0.1"), Some("alex")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ip_is_limited_across_users() {
+        let limiter = make_limiter();
+
+        for user in ["a", "b", "c"] {
+            limiter.check(Some("10.0.0.1"), Some(user)).await.unwrap();
+        }
+
+        assert!(matches!(
+            limiter.check(Some("10.0.0.1"), Some("d")).await,
+            Err(Error::RateLimited(_))
+        ));
+        limiter.check(Some("10.0.0.2"), Some("d")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejected_request_takes_no_token_from_the_other_bucket() {
+        let limiter = make_limiter();
+
+        limiter.check(Some("10.0.0.1"), Some("sam")).await.unwrap();
+        for _ in 0..5 {
+            assert!(limiter.check(Some("10.0.0.1"), Some("sam")).await.is_err());
+        }
+
+        limiter.check(Some("10.0.0.1"), None).await.unwrap();
+        limiter.check(Some("10.0.0.1"), None).await.unwrap();
+        assert!(limiter.check(Some("10.0.0.1"), None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_device_is_limited_separately_from_user_of_the_same_name() {
+        let limiter = make_limiter();
+
+        limiter.check_device(Some("10.0.0.1"), Some("sam")).await.unwrap();
+        assert!(matches!(
+            limiter.check_device(Some("10.0.0.1"), Some("sam")).await,
+            Err(Error::RateLimited(_))
+        ));
+
+        limiter.check(Some("10.0.0.2"), Some("sam")).await.unwrap();
+        limiter.check_device(Some("10.0.0.2"), Some("phone")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unknown_ip_and_user_are_not_limited() {
+        let limiter = make_limiter();
+
+        for _ in 0..5 {
+            limiter.check(None, None).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_forgets_refilled_buckets() {
+        let store = MemoryRateLimitStore::default();
+        for i in 0..PRUNE_THRESHOLD {
+            repo::RateLimitStore::take_tokens(&store, &[(i.to_string(), PER_USER)], 0)
+                .await
+                .unwrap();
+        }
+
+        repo::RateLimitStore::take_tokens(&store, &[("late".to_string(), PER_USER)], 60_000)
+            .await
+            .unwrap();
+
+        assert_eq!(store.buckets.lock().unwrap().len(), 1);
+    }
+}
@@ -1,12 +1,49 @@
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
 
-use prono::repo;
+use prono::stats::QuestionStats;
+use prono::{credentials, privacy, repo};
+use tokio::sync::broadcast;
 
-use crate::BackendResult;
+use crate::use_cases::{Challenges, DeviceService, Principal};
+use crate::{BackendResult, Error};
+
+/// Events buffered per subscriber, a subscriber that falls further behind is disconnected.
+const EVENT_CAPACITY: usize = 256;
+
+/// Age after which cached statistics are computed again, so that answers stored by other processes, e.g. by
+/// `prono-cli answers import`, show up in them.
+const STATS_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// Events of a survey for one subscriber.
+pub struct Subscription {
+    /// Questions resolved before subscribing, in the order they were resolved. A question resolved while
+    /// subscribing may also be received as a new event.
+    pub past: Vec<prono_api::SurveyEvent>,
+    pub events: broadcast::Receiver<prono_api::SurveyEvent>,
+}
+
+/// Statistics of each survey, computed at most once per change of the answers.
+#[derive(Default)]
+struct StatsCache {
+    /// Increased on every change of the answers, so statistics computed meanwhile are not cached.
+    generation: u64,
+    surveys: HashMap<u64, (Instant, Vec<QuestionStats>)>,
+}
 
 pub struct SurveyService {
     db: Arc<dyn repo::Surveys + Send + Sync>,
-    devices: Arc<dyn repo::DeviceRegistry + Send + Sync>,
+    states: Arc<dyn repo::SurveyStates>,
+    devices: Arc<DeviceService>,
+    challenges: Challenges,
+    /// Events as the privacy of the survey allows clients to see them.
+    events: broadcast::Sender<prono_api::SurveyEvent>,
+    /// Events with the usernames of all answers, for the backend itself.
+    unfiltered_events: broadcast::Sender<prono_api::SurveyEvent>,
+    stats_min_answers: usize,
+    stats_cache: Mutex<StatsCache>,
+    alias_secret: String,
 }
 
 fn api_answer_to_repo(answer: prono_api::Answer) -> repo::Answer {
@@ -16,7 +53,7 @@ fn api_answer_to_repo(answer: prono_api::Answer) -> repo::Answer {
     }
 }
 
-fn repo_answer_to_api(answer: repo::Answer) -> prono_api::Answer {
+pub(crate) fn repo_answer_to_api(answer: repo::Answer) -> prono_api::Answer {
     match answer {
         repo::Answer::Text(text) => prono_api::Answer::Text(text),
         repo::Answer::PredictionDate { day, month, year } => prono_api::Answer::PredictionDate { day, month, year },
@@ -40,8 +77,42 @@ fn repo_survey_to_api(survey: repo::Survey) -> prono_api::Survey {
 }
 
 impl SurveyService {
-    pub fn new(db: Arc<dyn repo::Surveys + Send + Sync>, devices: Arc<dyn repo::DeviceRegistry + Send + Sync>) -> Self {
-        Self { db, devices }
+    /// # Panics
+    ///
+    /// Panics if the system random number generator fails.
+    pub fn new(
+        db: Arc<dyn repo::Surveys + Send + Sync>,
+        states: Arc<dyn repo::SurveyStates>,
+        devices: Arc<DeviceService>,
+        challenges: Challenges,
+    ) -> Self {
+        Self {
+            db,
+            states,
+            devices,
+            challenges,
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            unfiltered_events: broadcast::channel(EVENT_CAPACITY).0,
+            stats_min_answers: 0,
+            stats_cache: Mutex::default(),
+            alias_secret: credentials::new_token().expect("Failed to generate an alias secret"),
+        }
+    }
+
+    /// Derives the aliases of users in pseudonymous surveys from `secret`, instead of a secret that changes every
+    /// time the backend starts.
+    #[must_use]
+    pub fn aliasing_with(mut self, secret: String) -> Self {
+        self.alias_secret = secret;
+        self
+    }
+
+    /// Leaves the date statistics of questions with fewer than `min_answers` predictions out of [`Self::stats`], as
+    /// they would give away the predictions of the few users who answered.
+    #[must_use]
+    pub fn hiding_stats_below(mut self, min_answers: usize) -> Self {
+        self.stats_min_answers = min_answers;
+        self
     }
 
     #[must_use]
@@ -49,38 +120,265 @@ impl SurveyService {
         prono::empty_survey()
     }
 
+    /// Proof-of-work challenge to solve before calling [`Self::add_answer`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no challenge can be generated.
+    pub fn challenge(&self) -> BackendResult<prono_api::Challenge> {
+        self.challenges.issue()
+    }
+
+    /// Stores an answer, provided `stamp` solves a challenge issued by [`Self::challenge`]. The stamp is only used
+    /// up by an answer that passes the other checks, `client` is the IP address it comes from, if known.
+    ///
     /// # Errors
     ///
-    /// Returns an error if the device verification fails, the answer already exists,
-    /// or if a repository error occurs.
+    /// Returns [`Error::InvalidRequest`] for a predicted date that does not exist or is out of range, an error if
+    /// the survey is closed, the device verification fails, the answer already exists, the proof of work is
+    /// rejected, or if a repository error occurs.
     pub async fn add_answer(
         &self,
-        user: &str,
+        principal: &Principal,
         question_id: String,
         answer: prono_api::Answer,
         device_id: &str,
+        stamp: &str,
+        client: Option<&str>,
+    ) -> BackendResult<()> {
+        let repo_answer = api_answer_to_repo(answer.clone());
+        prono::stats::check_answer(&repo_answer)?;
+        if self.states.is_closed(self.empty_survey().id).await? {
+            return Err(Error::SurveyClosed);
+        }
+        let user = principal.user.as_str();
+        self.devices.admit(user, device_id).await?;
+        if self.db.answer(user, question_id.clone()).await.is_some() {
+            return Err(Error::AnswerExists);
+        }
+        self.challenges.redeem(stamp, client)?;
+        self.db.add_answer(user, question_id.clone(), repo_answer).await?;
+        self.publish_answer(question_id, user, answer).await;
+        Ok(())
+    }
+
+    /// Stores an answer on behalf of a user, e.g. one given on paper, even when the survey is closed. With
+    /// `overwrite`, an existing answer of the user is replaced.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidRequest`] for an invalid username or a predicted date that does not exist or is out
+    /// of range, an error if the answer already exists and
+    /// `overwrite` is not set, or if a repository error occurs.
+    pub async fn import_answer(
+        &self,
+        user: &str,
+        question_id: String,
+        answer: prono_api::Answer,
+        overwrite: bool,
     ) -> BackendResult<()> {
-        if !self.devices.verify_device(user, device_id).await? {
-            return Err(crate::Error::DeviceMismatch);
+        let username = credentials::parse_username(user)?;
+        let user = username.as_str();
+        let repo_answer = api_answer_to_repo(answer.clone());
+        prono::stats::check_answer(&repo_answer)?;
+        if overwrite {
+            self.db.replace_answer(user, question_id.clone(), repo_answer).await?;
+        } else {
+            self.db.add_answer(user, question_id.clone(), repo_answer).await?;
+        }
+        self.publish_answer(question_id, user, answer).await;
+        Ok(())
+    }
+
+    /// Opens or closes the survey to new answers, returns `false` if the survey does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a repository error occurs.
+    pub async fn set_open(&self, survey_id: u64, open: bool) -> BackendResult<bool> {
+        if survey_id != self.empty_survey().id {
+            return Ok(false);
+        }
+        self.states.set_closed(survey_id, !open).await?;
+        Ok(true)
+    }
+
+    /// Who sees the individual answers of the survey, `None` if the survey does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a repository error occurs.
+    pub async fn privacy(&self, survey_id: u64) -> BackendResult<Option<prono_api::Privacy>> {
+        if survey_id != self.empty_survey().id {
+            return Ok(None);
+        }
+        Ok(Some(self.states.privacy(survey_id).await?.into()))
+    }
+
+    /// Changes who sees the individual answers of the survey, returns `false` if the survey does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a repository error occurs.
+    pub async fn set_privacy(&self, survey_id: u64, privacy: prono_api::Privacy) -> BackendResult<bool> {
+        if survey_id != self.empty_survey().id {
+            return Ok(false);
         }
-        self.devices.register_device(user, device_id).await?;
-        self.db
-            .add_answer(user, question_id, api_answer_to_repo(answer))
+        self.states.set_privacy(survey_id, privacy.into()).await?;
+        Ok(true)
+    }
+
+    /// Records the outcome of a question and announces it to the subscribers of the survey.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a repository error occurs, the outcome is not announced then.
+    pub async fn resolve_question(&self, question_id: String, answer: prono_api::Answer) -> BackendResult<()> {
+        self.states
+            .resolve_question(self.empty_survey().id, &question_id, api_answer_to_repo(answer.clone()))
             .await?;
+        let event = prono_api::SurveyEvent::QuestionResolved { question_id, answer };
+        // Sending only fails when nobody is subscribed.
+        let _ = self.unfiltered_events.send(event.clone());
+        let _ = self.events.send(event);
         Ok(())
     }
 
-    pub async fn response(&self, user: &str, survey_id: u64) -> Option<prono_api::Survey> {
-        self.db.response(user, survey_id).await.map(repo_survey_to_api)
+    /// The questions of the survey resolved so far and the events from now on, `None` if the survey does not
+    /// exist. Answers are reported as the privacy of the survey allows.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the resolved questions cannot be read.
+    pub async fn subscribe(&self, survey_id: u64) -> BackendResult<Option<Subscription>> {
+        if survey_id != self.empty_survey().id {
+            return Ok(None);
+        }
+        // Subscribing first, so that no resolution is missed between reading the past ones and subscribing.
+        let events = self.events.subscribe();
+        let past = self
+            .states
+            .resolutions(survey_id)
+            .await?
+            .into_iter()
+            .map(|(question_id, answer)| prono_api::SurveyEvent::QuestionResolved {
+                question_id,
+                answer: repo_answer_to_api(answer),
+            })
+            .collect();
+        Ok(Some(Subscription { past, events }))
     }
 
-    pub async fn all_answers(&self, question_id: String) -> Vec<(String, prono_api::Answer)> {
-        self.db
+    /// Like [`Self::subscribe`], but with every answer and its username whatever the privacy of the survey. Not
+    /// to be handed to clients.
+    #[must_use]
+    pub fn subscribe_unfiltered(&self, survey_id: u64) -> Option<broadcast::Receiver<prono_api::SurveyEvent>> {
+        (survey_id == self.empty_survey().id).then(|| self.unfiltered_events.subscribe())
+    }
+
+    async fn publish_answer(&self, question_id: String, user: &str, answer: prono_api::Answer) {
+        self.forget_stats();
+        let _ = self.unfiltered_events.send(prono_api::SurveyEvent::AnswerAdded {
+            question_id: question_id.clone(),
+            user: user.to_string(),
+            answer: answer.clone(),
+        });
+        let answers = vec![(user.to_string(), answer)];
+        if let Some((user, answer)) = self.visible_answers(answers).await.into_iter().flatten().next() {
+            let _ = self.events.send(prono_api::SurveyEvent::AnswerAdded {
+                question_id,
+                user,
+                answer,
+            });
+        }
+    }
+
+    /// `answers` to the survey as its privacy allows to show them, `None` when only statistics are shown. The
+    /// answers are hidden when the privacy cannot be read.
+    async fn visible_answers<T>(&self, answers: Vec<(String, T)>) -> Option<Vec<(String, T)>> {
+        let survey_id = self.empty_survey().id;
+        let privacy = self.states.privacy(survey_id).await.unwrap_or_else(|e| {
+            log::error!("Failed to read the privacy of survey [{survey_id}]: {e}");
+            repo::Privacy::AggregateOnly
+        });
+        privacy::visible_answers(privacy, &self.alias_secret, survey_id, answers)
+    }
+
+    /// Survey filled in with the answers of a user. Only public surveys show the response of a user.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Forbidden`] if the survey is not public, or an error if a repository error occurs.
+    pub async fn response(&self, user: &str, survey_id: u64) -> BackendResult<Option<prono_api::Survey>> {
+        if survey_id == self.empty_survey().id && self.states.privacy(survey_id).await? != repo::Privacy::Public {
+            return Err(Error::Forbidden("Responses of this survey are private".to_string()));
+        }
+        let Ok(username) = prono::Username::parse(user) else {
+            return Ok(None);
+        };
+        Ok(self
+            .db
+            .response(username.as_str(), survey_id)
+            .await
+            .map(repo_survey_to_api))
+    }
+
+    /// Drops the cached statistics, for when answers change.
+    pub(crate) fn forget_stats(&self) {
+        let mut cache = self.stats_cache.lock().unwrap_or_else(PoisonError::into_inner);
+        cache.generation += 1;
+        cache.surveys.clear();
+    }
+
+    /// Statistics of the answers to each question of the survey, `None` if the survey does not exist. The
+    /// statistics are cached until the answers change, for at most [`STATS_MAX_AGE`].
+    pub async fn stats(&self, survey_id: u64) -> Option<Vec<QuestionStats>> {
+        let survey = self.empty_survey();
+        if survey_id != survey.id {
+            return None;
+        }
+        let generation = {
+            let cache = self.stats_cache.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some((computed_at, stats)) = cache.surveys.get(&survey_id)
+                && computed_at.elapsed() < STATS_MAX_AGE
+            {
+                return Some(stats.clone());
+            }
+            cache.generation
+        };
+        let mut stats = prono::stats::survey_stats(self.db.as_ref(), &survey).await;
+        for question in &mut stats {
+            if question
+                .dates
+                .as_ref()
+                .is_some_and(|dates| dates.predictions < self.stats_min_answers)
+            {
+                question.dates = None;
+            }
+        }
+        let mut cache = self.stats_cache.lock().unwrap_or_else(PoisonError::into_inner);
+        if cache.generation == generation {
+            cache.surveys.insert(survey_id, (Instant::now(), stats.clone()));
+        }
+        Some(stats)
+    }
+
+    /// Answers of all users to a question, with aliases instead of usernames when the survey is pseudonymous.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Forbidden`] if the survey only shares statistics of its answers.
+    pub async fn all_answers(&self, question_id: String) -> BackendResult<Vec<(String, prono_api::Answer)>> {
+        let answers = self
+            .db
             .all_answers(question_id)
             .await
             .into_iter()
             .map(|(user, answer)| (user, repo_answer_to_api(answer)))
-            .collect()
+            .collect();
+        self.visible_answers(answers)
+            .await
+            .ok_or_else(|| Error::Forbidden("Answers of this survey are only shared as statistics".to_string()))
     }
 }
 
@@ -88,12 +386,32 @@ impl SurveyService {
 mod tests {
     use super::*;
 
+    use std::time::Duration;
+
     use prono::fake_db::FakeRepo;
     use prono::repo::Db as _;
 
+    fn principal(user: &str) -> Principal {
+        Principal { user: user.to_string() }
+    }
+
     async fn make_service() -> SurveyService {
         let db = Arc::new(FakeRepo::init(()).await.unwrap());
-        SurveyService::new(db.clone(), db)
+        let devices = DeviceService::new(
+            db.clone(),
+            repo::DevicePolicy { max_devices: 1 },
+            Duration::from_secs(60),
+        );
+        SurveyService::new(
+            db.clone(),
+            db,
+            Arc::new(devices),
+            Challenges::new(4, Duration::from_secs(60)),
+        )
+    }
+
+    fn stamp(service: &SurveyService) -> String {
+        service.challenge().unwrap().solve()
     }
 
     #[tokio::test]
@@ -111,11 +429,18 @@ mod tests {
 
         let answer = prono_api::Answer::Text("test answer".to_string());
         service
-            .add_answer("testuser", question_id.clone(), answer, "device-1")
+            .add_answer(
+                &principal("testuser"),
+                question_id.clone(),
+                answer,
+                "device-1",
+                &stamp(&service),
+                None,
+            )
             .await
             .unwrap();
 
-        let all = service.all_answers(question_id).await;
+        let all = service.all_answers(question_id).await.unwrap();
         assert_eq!(all.len(), 1);
         assert_eq!(all[0].0, "testuser");
     }
@@ -128,14 +453,61 @@ mod tests {
 
         let answer = prono_api::Answer::Text("answer".to_string());
         service
-            .add_answer("user1", question_id.clone(), answer.clone(), "device-1")
+            .add_answer(
+                &principal("user1"),
+                question_id.clone(),
+                answer.clone(),
+                "device-1",
+                &stamp(&service),
+                None,
+            )
             .await
             .unwrap();
 
-        let result = service.add_answer("user1", question_id, answer, "device-1").await;
+        let result = service
+            .add_answer(
+                &principal("user1"),
+                question_id,
+                answer,
+                "device-1",
+                &stamp(&service),
+                None,
+            )
+            .await;
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_rejected_answer_leaves_the_stamp_unused() {
+        let service = make_service().await;
+        let survey = service.empty_survey();
+        let q1 = survey.questions[0].id.clone();
+        let answer = prono_api::Answer::Text("answer".to_string());
+        service
+            .add_answer(
+                &principal("user1"),
+                q1.clone(),
+                answer.clone(),
+                "device-1",
+                &stamp(&service),
+                None,
+            )
+            .await
+            .unwrap();
+        let stamp = stamp(&service);
+
+        let result = service
+            .add_answer(&principal("user1"), q1, answer.clone(), "device-1", &stamp, None)
+            .await;
+        assert!(matches!(result, Err(crate::Error::AnswerExists)));
+
+        let q2 = survey.questions[1].id.clone();
+        service
+            .add_answer(&principal("user1"), q2, answer, "device-1", &stamp, None)
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn test_add_answer_device_mismatch() {
         let service = make_service().await;
@@ -148,11 +520,20 @@ mod tests {
 
         let answer = prono_api::Answer::Text("answer".to_string());
         service
-            .add_answer("user1", q1, answer.clone(), "device-1")
+            .add_answer(
+                &principal("user1"),
+                q1,
+                answer.clone(),
+                "device-1",
+                &stamp(&service),
+                None,
+            )
             .await
             .unwrap();
 
-        let result = service.add_answer("user1", q2, answer, "device-2").await;
+        let result = service
+            .add_answer(&principal("user1"), q2, answer, "device-2", &stamp(&service), None)
+            .await;
         assert!(result.is_err());
     }
 
@@ -164,20 +545,462 @@ mod tests {
 
         let answer = prono_api::Answer::Text("my answer".to_string());
         service
-            .add_answer("user1", question_id, answer, "device-1")
+            .add_answer(
+                &principal("user1"),
+                question_id,
+                answer,
+                "device-1",
+                &stamp(&service),
+                None,
+            )
             .await
             .unwrap();
 
-        let response = service.response("user1", 0).await;
+        let response = service.response("user1", 0).await.unwrap();
         assert!(response.is_some());
         let response = response.unwrap();
         assert_eq!(response.questions.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_add_answer_without_proof_of_work_fails() {
+        let service = make_service().await;
+        let question_id = service.empty_survey().questions[0].id.clone();
+        let answer = prono_api::Answer::Text("answer".to_string());
+
+        let result = service
+            .add_answer(&principal("user1"), question_id.clone(), answer, "device-1", "", None)
+            .await;
+
+        assert!(matches!(result, Err(crate::Error::ChallengeFailed(_))));
+        assert!(service.all_answers(question_id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_receive_added_answers_and_resolutions() {
+        let service = make_service().await;
+        let question_id = service.empty_survey().questions[0].id.clone();
+        let mut events = service
+            .subscribe(service.empty_survey().id)
+            .await
+            .unwrap()
+            .unwrap()
+            .events;
+        let answer = prono_api::Answer::Text("answer".to_string());
+
+        service
+            .add_answer(
+                &principal("user1"),
+                question_id.clone(),
+                answer.clone(),
+                "device-1",
+                &stamp(&service),
+                None,
+            )
+            .await
+            .unwrap();
+        service
+            .resolve_question(question_id.clone(), answer.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            events.try_recv().unwrap(),
+            prono_api::SurveyEvent::AnswerAdded {
+                question_id: question_id.clone(),
+                user: "user1".to_string(),
+                answer: answer.clone(),
+            }
+        );
+        assert_eq!(
+            events.try_recv().unwrap(),
+            prono_api::SurveyEvent::QuestionResolved { question_id, answer }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_new_subscribers_receive_past_resolutions() {
+        let service = make_service().await;
+        let survey = service.empty_survey();
+        let first = prono_api::Answer::Text("first".to_string());
+        let second = prono_api::Answer::Text("second".to_string());
+
+        for (question, answer) in [(0, &first), (1, &first), (0, &second)] {
+            service
+                .resolve_question(survey.questions[question].id.clone(), answer.clone())
+                .await
+                .unwrap();
+        }
+        let subscription = service.subscribe(survey.id).await.unwrap().unwrap();
+
+        assert_eq!(
+            subscription.past,
+            vec![
+                prono_api::SurveyEvent::QuestionResolved {
+                    question_id: survey.questions[1].id.clone(),
+                    answer: first,
+                },
+                prono_api::SurveyEvent::QuestionResolved {
+                    question_id: survey.questions[0].id.clone(),
+                    answer: second,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejected_answer_is_not_published() {
+        let service = make_service().await;
+        let question_id = service.empty_survey().questions[0].id.clone();
+        let mut events = service
+            .subscribe(service.empty_survey().id)
+            .await
+            .unwrap()
+            .unwrap()
+            .events;
+
+        let _ = service
+            .add_answer(
+                &principal("user1"),
+                question_id,
+                prono_api::Answer::Text("answer".to_string()),
+                "device-1",
+                "",
+                None,
+            )
+            .await;
+
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pseudonymous_survey_aliases_answers_and_events() {
+        let service = make_service().await.aliasing_with("secret".to_string());
+        let survey_id = service.empty_survey().id;
+        let question_id = service.empty_survey().questions[0].id.clone();
+        let mut events = service.subscribe(survey_id).await.unwrap().unwrap().events;
+        let answer = prono_api::Answer::Text("answer".to_string());
+        assert!(
+            service
+                .set_privacy(survey_id, prono_api::Privacy::Pseudonymous)
+                .await
+                .unwrap()
+        );
+
+        service
+            .add_answer(
+                &principal("user1"),
+                question_id.clone(),
+                answer.clone(),
+                "device-1",
+                &stamp(&service),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let alias = privacy::alias("secret", survey_id, "user1");
+        assert_eq!(
+            service.all_answers(question_id.clone()).await.unwrap(),
+            vec![(alias.clone(), answer.clone())]
+        );
+        assert_eq!(
+            events.try_recv().unwrap(),
+            prono_api::SurveyEvent::AnswerAdded {
+                question_id,
+                user: alias,
+                answer,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_aggregate_only_survey_hides_answers_from_clients() {
+        let service = make_service().await;
+        let survey_id = service.empty_survey().id;
+        let question_id = service.empty_survey().questions[0].id.clone();
+        let mut events = service.subscribe(survey_id).await.unwrap().unwrap().events;
+        let mut unfiltered = service.subscribe_unfiltered(survey_id).unwrap();
+        service
+            .set_privacy(survey_id, prono_api::Privacy::AggregateOnly)
+            .await
+            .unwrap();
+
+        service
+            .add_answer(
+                &principal("user1"),
+                question_id.clone(),
+                prono_api::Answer::Text("answer".to_string()),
+                "device-1",
+                &stamp(&service),
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            service.all_answers(question_id).await,
+            Err(Error::Forbidden(_))
+        ));
+        assert!(events.try_recv().is_err());
+        assert!(matches!(
+            unfiltered.try_recv().unwrap(),
+            prono_api::SurveyEvent::AnswerAdded { user, .. } if user == "user1"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_response_is_forbidden_unless_public() {
+        let service = make_service().await;
+        let survey_id = service.empty_survey().id;
+        assert_eq!(
+            service.privacy(survey_id).await.unwrap(),
+            Some(prono_api::Privacy::Public)
+        );
+
+        service
+            .set_privacy(survey_id, prono_api::Privacy::Pseudonymous)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            service.response("user1", survey_id).await,
+            Err(Error::Forbidden(_))
+        ));
+        assert!(
+            !service
+                .set_privacy(survey_id + 1, prono_api::Privacy::Public)
+                .await
+                .unwrap()
+        );
+        assert_eq!(service.privacy(survey_id + 1).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_closed_survey_rejects_answers_until_reopened() {
+        let service = make_service().await;
+        let survey_id = service.empty_survey().id;
+        let question_id = service.empty_survey().questions[0].id.clone();
+        let answer = prono_api::Answer::Text("answer".to_string());
+
+        assert!(service.set_open(survey_id, false).await.unwrap());
+        let result = service
+            .add_answer(
+                &principal("user1"),
+                question_id.clone(),
+                answer.clone(),
+                "device-1",
+                &stamp(&service),
+                None,
+            )
+            .await;
+        assert!(matches!(result, Err(Error::SurveyClosed)));
+        assert!(service.all_answers(question_id.clone()).await.unwrap().is_empty());
+
+        assert!(service.set_open(survey_id, true).await.unwrap());
+        service
+            .add_answer(
+                &principal("user1"),
+                question_id,
+                answer,
+                "device-1",
+                &stamp(&service),
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_import_answer_replaces_only_with_overwrite() {
+        let service = make_service().await;
+        let survey_id = service.empty_survey().id;
+        let question_id = service.empty_survey().questions[0].id.clone();
+        let paper = prono_api::Answer::Text("on paper".to_string());
+        let corrected = prono_api::Answer::Text("corrected".to_string());
+        assert!(service.set_open(survey_id, false).await.unwrap());
+
+        service
+            .import_answer("user1", question_id.clone(), paper.clone(), false)
+            .await
+            .unwrap();
+        let result = service
+            .import_answer("user1", question_id.clone(), corrected.clone(), false)
+            .await;
+        assert!(matches!(result, Err(Error::AnswerExists)));
+        assert_eq!(
+            service.all_answers(question_id.clone()).await.unwrap(),
+            vec![("user1".to_string(), paper)]
+        );
+
+        service
+            .import_answer("user1", question_id.clone(), corrected.clone(), true)
+            .await
+            .unwrap();
+        assert_eq!(
+            service.all_answers(question_id).await.unwrap(),
+            vec![("user1".to_string(), corrected)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stats_hide_dates_of_few_predictions() {
+        let service = make_service().await.hiding_stats_below(2);
+        let survey = service.empty_survey();
+        let question_id = survey.questions[0].id.clone();
+        let prediction = |year| prono_api::Answer::PredictionDate {
+            day: None,
+            month: 6,
+            year,
+        };
+
+        service
+            .import_answer("user1", question_id.clone(), prediction(2030), false)
+            .await
+            .unwrap();
+        let stats = service.stats(survey.id).await.unwrap();
+        assert_eq!(stats[0].answers, 1);
+        assert_eq!(stats[0].dates, None);
+
+        service
+            .import_answer("user2", question_id, prediction(2032), false)
+            .await
+            .unwrap();
+        let stats = service.stats(survey.id).await.unwrap();
+        assert_eq!(stats[0].answers, 2);
+        assert_eq!(stats[0].dates.as_ref().unwrap().histogram, [(2030, 1), (2032, 1)]);
+        assert_eq!(stats.len(), survey.questions.len());
+    }
+
+    #[tokio::test]
+    async fn test_stats_are_cached_until_answers_change() {
+        let db = Arc::new(FakeRepo::init(()).await.unwrap());
+        let devices = DeviceService::new(
+            db.clone(),
+            repo::DevicePolicy { max_devices: 1 },
+            Duration::from_secs(60),
+        );
+        let service = SurveyService::new(
+            db.clone(),
+            db.clone(),
+            Arc::new(devices),
+            Challenges::new(4, Duration::from_secs(60)),
+        );
+        let survey = service.empty_survey();
+        let question_id = survey.questions[0].id.clone();
+        let prediction = |year| prono_api::Answer::PredictionDate {
+            day: None,
+            month: 6,
+            year,
+        };
+        service
+            .import_answer("user1", question_id.clone(), prediction(2030), false)
+            .await
+            .unwrap();
+        assert_eq!(service.stats(survey.id).await.unwrap()[0].answers, 1);
+
+        // Answers stored behind the back of the service only show up once the cached statistics expire.
+        repo::Surveys::add_answer(
+            db.as_ref(),
+            "user2",
+            question_id.clone(),
+            repo::Answer::Text("?".into()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(service.stats(survey.id).await.unwrap()[0].answers, 1);
+
+        service
+            .import_answer("user1", question_id, prediction(2032), true)
+            .await
+            .unwrap();
+        let stats = service.stats(survey.id).await.unwrap();
+        assert_eq!(stats[0].answers, 2);
+        assert_eq!(stats[0].dates.as_ref().unwrap().histogram, [(2032, 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_out_of_range_predictions_are_rejected() {
+        let service = make_service().await;
+        let question_id = service.empty_survey().questions[0].id.clone();
+        let principal = principal("user1");
+        let far_future = prono_api::Answer::PredictionDate {
+            day: Some(31),
+            month: 12,
+            year: u16::MAX,
+        };
+
+        let result = service
+            .add_answer(
+                &principal,
+                question_id.clone(),
+                far_future.clone(),
+                "device1",
+                &stamp(&service),
+                None,
+            )
+            .await;
+        assert!(matches!(result, Err(Error::InvalidRequest(_))));
+        let result = service
+            .import_answer("user1", question_id.clone(), far_future, true)
+            .await;
+        assert!(matches!(result, Err(Error::InvalidRequest(_))));
+        assert!(service.all_answers(question_id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stats_of_unknown_survey_fail() {
+        let service = make_service().await;
+
+        assert!(service.stats(service.empty_survey().id + 1).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_opening_unknown_survey_fails() {
+        let service = make_service().await;
+
+        assert!(!service.set_open(service.empty_survey().id + 1, false).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_to_unknown_survey_fails() {
+        let service = make_service().await;
+
+        assert!(
+            service
+                .subscribe(service.empty_survey().id + 1)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_imported_answers_are_stored_under_normalized_username() {
+        let service = make_service().await;
+        let question_id = service.empty_survey().questions[0].id.clone();
+        let answer = prono_api::Answer::Text("paper".to_string());
+
+        service
+            .import_answer(" Sam", question_id.clone(), answer.clone(), false)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            service.all_answers(question_id.clone()).await.unwrap(),
+            vec![("sam".to_string(), answer.clone())]
+        );
+        assert!(service.response("SAM", 0).await.unwrap().is_some());
+        assert!(matches!(
+            service.import_answer("", question_id, answer, false).await,
+            Err(Error::InvalidRequest(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_response_returns_none_for_unknown_user() {
         let service = make_service().await;
-        let response = service.response("nobody", 0).await;
+        let response = service.response("nobody", 0).await.unwrap();
         assert!(response.is_none());
     }
 }
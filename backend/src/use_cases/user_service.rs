@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use prono::{credentials, repo};
+
+use crate::BackendResult;
+use crate::use_cases::DeviceService;
+
+/// What is known about usernames, for clients to warn about a name while it is typed instead of on submit.
+pub struct UserService {
+    accounts: Arc<dyn repo::Accounts + Send + Sync>,
+    db: Arc<dyn repo::Surveys + Send + Sync>,
+    states: Arc<dyn repo::SurveyStates>,
+    devices: Arc<DeviceService>,
+}
+
+impl UserService {
+    pub fn new(
+        accounts: Arc<dyn repo::Accounts + Send + Sync>,
+        db: Arc<dyn repo::Surveys + Send + Sync>,
+        states: Arc<dyn repo::SurveyStates>,
+        devices: Arc<DeviceService>,
+    ) -> Self {
+        Self {
+            accounts,
+            db,
+            states,
+            devices,
+        }
+    }
+
+    /// Status of `user` as seen from `device_id`. Completing a survey that is not public is only reported to
+    /// the devices of the user, it would tell others who took part.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::Error::InvalidRequest`] for an invalid username, or an error if a repository error occurs.
+    pub async fn status(&self, user: &str, device_id: &str) -> BackendResult<prono_api::UserStatus> {
+        let username = credentials::parse_username(user)?;
+        let user = username.as_str();
+        let survey = prono::empty_survey();
+        let mut status = prono::users::user_status(self.accounts.as_ref(), self.db.as_ref(), &survey, user).await?;
+        let devices = self.devices.list(user).await?;
+        status.exists |= !devices.is_empty();
+        status.this_device = !device_id.is_empty() && devices.iter().any(|device| device.id == device_id);
+        if !status.this_device && self.states.privacy(survey.id).await? != repo::Privacy::Public {
+            status.completed_surveys.clear();
+        }
+        Ok(status)
+    }
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use prono::fake_db::FakeRepo;
+    use prono::repo::{Accounts as _, Db as _, DeviceRegistry as _, SurveyStates as _, Surveys as _};
+
+    use crate::Error;
+
+    async fn make_service() -> (UserService, Arc<FakeRepo>) {
+        let repo = Arc::new(FakeRepo::init(()).await.unwrap());
+        let devices = DeviceService::new(
+            repo.clone(),
+            repo::DevicePolicy { max_devices: 1 },
+            Duration::from_secs(60),
+        );
+        let service = UserService::new(repo.clone(), repo.clone(), repo.clone(), Arc::new(devices));
+        (service, repo)
+    }
+
+    async fn complete_survey(repo: &FakeRepo, user: &str) {
+        for question in prono::empty_survey().questions {
+            repo.add_answer(user, question.id, repo::Answer::Text("never".to_string()))
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_name_is_free() {
+        let (service, _repo) = make_service().await;
+
+        let status = service.status("sam", "laptop").await.unwrap();
+
+        assert_eq!(status, prono_api::UserStatus::default());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_name_is_rejected() {
+        let (service, _repo) = make_service().await;
+
+        assert!(matches!(
+            service.status("sam!", "laptop").await,
+            Err(Error::InvalidRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_name_is_normalized() {
+        let (service, repo) = make_service().await;
+        repo.create_account("sam", "hash").await.unwrap();
+
+        assert!(service.status(" Sam ", "laptop").await.unwrap().exists);
+    }
+
+    #[tokio::test]
+    async fn test_registered_device_is_recognized() {
+        let (service, repo) = make_service().await;
+        repo.register_device("sam", "laptop").await.unwrap();
+
+        let own = service.status("sam", "laptop").await.unwrap();
+        let other = service.status("sam", "phone").await.unwrap();
+        let anonymous = service.status("sam", "").await.unwrap();
+
+        assert!(own.exists && own.this_device);
+        assert!(other.exists && !other.this_device);
+        assert!(!anonymous.this_device);
+    }
+
+    #[tokio::test]
+    async fn test_completed_public_survey_is_reported_to_anyone() {
+        let (service, repo) = make_service().await;
+        complete_survey(&repo, "sam").await;
+
+        let status = service.status("sam", "phone").await.unwrap();
+
+        assert_eq!(status.completed_surveys, vec![prono::empty_survey().id]);
+    }
+
+    #[tokio::test]
+    async fn test_completed_private_survey_is_only_reported_to_own_devices() {
+        let (service, repo) = make_service().await;
+        let survey_id = prono::empty_survey().id;
+        repo.set_privacy(survey_id, repo::Privacy::Pseudonymous).await.unwrap();
+        repo.register_device("sam", "laptop").await.unwrap();
+        complete_survey(&repo, "sam").await;
+
+        let own = service.status("sam", "laptop").await.unwrap();
+        let other = service.status("sam", "phone").await.unwrap();
+
+        assert_eq!(own.completed_surveys, vec![survey_id]);
+        assert!(other.exists);
+        assert!(other.completed_surveys.is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use prono::credentials;
+use prono::repo::{self, AnswerRecords, DeviceRegistry, Surveys, Users};
+use prono::stats::{self, QuestionStats};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Repository(#[from] prono::Error),
+
+    #[error(transparent)]
+    Api(#[from] prono_api::Error),
+
+    #[error("Request to the backend failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Backend answered {0}")]
+    Status(reqwest::StatusCode),
+
+    #[error("Invalid backend URL: {0}")]
+    InvalidUrl(String),
+}
+
+impl Error {
+    /// Whether the user already answered, as reported by the database or the backend.
+    #[must_use]
+    pub fn is_answer_exists(&self) -> bool {
+        matches!(
+            self,
+            Error::Repository(prono::Error::AnswerExists) | Error::Api(prono_api::Error::AnswerExists)
+        )
+    }
+
+    /// Whether a name is already taken, as reported by the database or the backend.
+    #[must_use]
+    pub fn is_user_exists(&self) -> bool {
+        matches!(
+            self,
+            Error::Repository(prono::Error::UserExists) | Error::Api(prono_api::Error::UserExists)
+        )
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// What the subcommands do, either on the database or through the admin API of a backend.
+#[async_trait]
+pub trait Admin: Send + Sync {
+    async fn users(&self) -> Result<Vec<String>>;
+    async fn delete_user(&self, name: &str) -> Result<()>;
+    /// Gives the answers, devices and account of `old` to `new`, who must not have any yet.
+    async fn rename_user(&self, old: &str, new: &str) -> Result<()>;
+    /// Moves the answers, devices and account of `from` to `into`, nothing changes if both answered the same
+    /// question or both have an account.
+    async fn merge_users(&self, from: &str, into: &str) -> Result<()>;
+    /// Merges every name stored in another spelling than its normalized one into the normalized name, see
+    /// [`prono::users::normalize_names`]. Returns each such name with the name it was merged into, or why it was
+    /// left as it is.
+    async fn normalize_users(&self) -> Result<Vec<(String, std::result::Result<String, String>)>>;
+    async fn devices(&self, user: &str) -> Result<Vec<repo::Device>>;
+    /// Returns how many devices were removed.
+    async fn reset_devices(&self, user: &str) -> Result<usize>;
+    /// Answers to the survey, oldest first.
+    async fn answer_records(&self, survey_id: u64) -> Result<Vec<repo::AnswerRecord>>;
+    /// Stores an answer of the user, replacing their previous answer if `overwrite` is set. Fails with an
+    /// error for which [`Error::is_answer_exists`] holds if they already answered and `overwrite` is not set.
+    async fn add_answer(&self, user: &str, question_id: &str, answer: repo::Answer, overwrite: bool) -> Result<()>;
+    /// Statistics of the answers to each question of `survey`.
+    async fn stats(&self, survey: &prono_api::Survey) -> Result<Vec<QuestionStats>>;
+}
+
+/// Works on the database directly, with the credentials from the config file.
+pub struct Database<D>(pub D);
+
+#[async_trait]
+impl<D: Users + DeviceRegistry + AnswerRecords + Surveys> Admin for Database<D> {
+    async fn users(&self) -> Result<Vec<String>> {
+        Ok(self.0.all_names().await?)
+    }
+
+    async fn delete_user(&self, name: &str) -> Result<()> {
+        Ok(self.0.delete_user(name).await?)
+    }
+
+    async fn rename_user(&self, old: &str, new: &str) -> Result<()> {
+        Ok(self.0.rename_user(old, new).await?)
+    }
+
+    async fn merge_users(&self, from: &str, into: &str) -> Result<()> {
+        Ok(self.0.merge_users(from, into).await?)
+    }
+
+    async fn normalize_users(&self) -> Result<Vec<(String, std::result::Result<String, String>)>> {
+        let outcomes = prono::users::normalize_names(&self.0).await?;
+        Ok(outcomes
+            .into_iter()
+            .map(|(name, outcome)| (name, outcome.map(|n| n.as_str().to_string()).map_err(|e| e.to_string())))
+            .collect())
+    }
+
+    async fn devices(&self, user: &str) -> Result<Vec<repo::Device>> {
+        Ok(self.0.list_devices(user).await?)
+    }
+
+    async fn reset_devices(&self, user: &str) -> Result<usize> {
+        Ok(self.0.unregister_devices(user).await?)
+    }
+
+    async fn answer_records(&self, survey_id: u64) -> Result<Vec<repo::AnswerRecord>> {
+        Ok(self.0.answer_records(survey_id).await?)
+    }
+
+    async fn add_answer(&self, user: &str, question_id: &str, answer: repo::Answer, overwrite: bool) -> Result<()> {
+        let user = credentials::parse_username(user)?;
+        let user = user.as_str();
+        stats::check_answer(&answer)?;
+        if overwrite {
+            Ok(self.0.replace_answer(user, question_id.to_string(), answer).await?)
+        } else {
+            Ok(self.0.add_answer(user, question_id.to_string(), answer).await?)
+        }
+    }
+
+    async fn stats(&self, survey: &prono_api::Survey) -> Result<Vec<QuestionStats>> {
+        Ok(stats::survey_stats(&self.0, survey).await)
+    }
+}
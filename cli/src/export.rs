@@ -0,0 +1,252 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use chrono::{DateTime, SecondsFormat};
+use prono::repo;
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Comma-separated values with a header row
+    Csv,
+    /// One JSON array of rows
+    Json,
+    /// One JSON object per line
+    Jsonl,
+}
+
+impl Format {
+    /// Format of a file named `path`, from its extension.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "csv" => Some(Format::Csv),
+            "json" => Some(Format::Json),
+            "jsonl" | "ndjson" => Some(Format::Jsonl),
+            _ => None,
+        }
+    }
+}
+
+/// One answer, flattened so that it can be analysed without knowing how answers are stored.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Row {
+    pub user: String,
+    pub question_id: String,
+    pub question_text: String,
+    /// `text` or `prediction_date`.
+    pub answer_kind: &'static str,
+    /// The text, or the predicted date as `YYYY-MM-DD`, or `YYYY-MM` when no day was picked.
+    pub answer_value: String,
+    /// RFC 3339 time in UTC, empty for answers stored before the time was recorded.
+    pub answered_at: Option<String>,
+}
+
+/// Rows of the `records` answering a question of `survey`, in the order of `records`.
+pub fn rows(survey: &prono_api::Survey, records: Vec<repo::AnswerRecord>) -> Vec<Row> {
+    records
+        .into_iter()
+        .filter_map(|record| {
+            let question = survey
+                .questions
+                .iter()
+                .find(|question| question.id == record.question_id)?;
+            let (answer_kind, answer_value) = match record.answer {
+                repo::Answer::Text(text) => ("text", text),
+                repo::Answer::PredictionDate {
+                    day: Some(day),
+                    month,
+                    year,
+                } => ("prediction_date", format!("{year:04}-{month:02}-{day:02}")),
+                repo::Answer::PredictionDate { day: None, month, year } => {
+                    ("prediction_date", format!("{year:04}-{month:02}"))
+                }
+            };
+            Some(Row {
+                user: record.user,
+                question_id: record.question_id,
+                question_text: question.text.clone().unwrap_or_default(),
+                answer_kind,
+                answer_value,
+                answered_at: record
+                    .answered_at
+                    .and_then(|secs| DateTime::from_timestamp(i64::try_from(secs).ok()?, 0))
+                    .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            })
+        })
+        .collect()
+}
+
+/// # Errors
+///
+/// Returns an error if writing to `writer` fails.
+pub fn write(rows: &[Row], format: Format, mut writer: impl Write) -> io::Result<()> {
+    match format {
+        Format::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()
+        }
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut writer, rows)?;
+            writeln!(writer)
+        }
+        Format::Jsonl => {
+            for row in rows {
+                serde_json::to_writer(&mut writer, row)?;
+                writeln!(writer)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn survey() -> prono_api::Survey {
+        prono_api::Survey {
+            id: 1,
+            description: "Test survey".to_string(),
+            questions: vec![
+                prono_api::Question {
+                    id: "q1".to_string(),
+                    text: Some("When, with \"quotes\"?".to_string()),
+                    answer: prono_api::Answer::PredictionDate {
+                        day: None,
+                        month: 1,
+                        year: 2030,
+                    },
+                },
+                prono_api::Question {
+                    id: "q2".to_string(),
+                    text: Some("Why?".to_string()),
+                    answer: prono_api::Answer::Text(String::new()),
+                },
+            ],
+        }
+    }
+
+    fn record(user: &str, question_id: &str, answer: repo::Answer, answered_at: Option<u64>) -> repo::AnswerRecord {
+        repo::AnswerRecord {
+            user: user.to_string(),
+            question_id: question_id.to_string(),
+            answer,
+            answered_at,
+        }
+    }
+
+    fn sample_rows() -> Vec<Row> {
+        rows(
+            &survey(),
+            vec![
+                record(
+                    "sam",
+                    "q1",
+                    repo::Answer::PredictionDate {
+                        day: Some(3),
+                        month: 4,
+                        year: 2029,
+                    },
+                    Some(1_700_000_000),
+                ),
+                record("alex", "q2", repo::Answer::Text("Because, well".to_string()), None),
+            ],
+        )
+    }
+
+    fn written(format: Format) -> String {
+        let mut out = Vec::new();
+        write(&sample_rows(), format, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn format_from_path_uses_extension() {
+        assert_eq!(Format::from_path(Path::new("answers.CSV")), Some(Format::Csv));
+        assert_eq!(Format::from_path(Path::new("out/answers.json")), Some(Format::Json));
+        assert_eq!(Format::from_path(Path::new("answers.ndjson")), Some(Format::Jsonl));
+        assert_eq!(Format::from_path(Path::new("answers")), None);
+    }
+
+    #[test]
+    fn rows_decode_answers_and_times() {
+        let rows = sample_rows();
+
+        assert_eq!(
+            rows[0],
+            Row {
+                user: "sam".to_string(),
+                question_id: "q1".to_string(),
+                question_text: "When, with \"quotes\"?".to_string(),
+                answer_kind: "prediction_date",
+                answer_value: "2029-04-03".to_string(),
+                answered_at: Some("2023-11-14T22:13:20Z".to_string()),
+            }
+        );
+        assert_eq!(rows[1].answer_kind, "text");
+        assert_eq!(rows[1].answer_value, "Because, well");
+        assert_eq!(rows[1].answered_at, None);
+    }
+
+    #[test]
+    fn rows_without_day_have_month_precision() {
+        let rows = rows(
+            &survey(),
+            vec![record(
+                "sam",
+                "q1",
+                repo::Answer::PredictionDate {
+                    day: None,
+                    month: 12,
+                    year: 2030,
+                },
+                None,
+            )],
+        );
+
+        assert_eq!(rows[0].answer_value, "2030-12");
+    }
+
+    #[test]
+    fn rows_skip_questions_outside_survey() {
+        let rows = rows(
+            &survey(),
+            vec![record("sam", "retired", repo::Answer::Text("no".to_string()), None)],
+        );
+
+        assert!(rows.is_empty());
+    }
+
+    #[test]
+    fn csv_has_header_and_quotes_fields() {
+        assert_eq!(
+            written(Format::Csv),
+            "user,question_id,question_text,answer_kind,answer_value,answered_at\n\
+             sam,q1,\"When, with \"\"quotes\"\"?\",prediction_date,2029-04-03,2023-11-14T22:13:20Z\n\
+             alex,q2,Why?,text,\"Because, well\",\n"
+        );
+    }
+
+    #[test]
+    fn jsonl_has_one_object_per_line() {
+        let out = written(Format::Jsonl);
+        let lines: Vec<serde_json::Value> = out.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["answer_value"], "2029-04-03");
+        assert_eq!(lines[1]["answered_at"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn json_is_an_array_of_rows() {
+        let rows: Vec<serde_json::Value> = serde_json::from_str(&written(Format::Json)).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1]["user"], "alex");
+        assert_eq!(rows[1]["question_text"], "Why?");
+    }
+}
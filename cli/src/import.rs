@@ -0,0 +1,521 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+
+use chrono::NaiveDate;
+use prono::repo;
+use serde::Deserialize;
+
+use crate::admin::{self, Admin};
+use crate::export::Format;
+
+/// What to do with an answer to a question the user already answered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum OnConflict {
+    /// Keep the stored answer
+    Skip,
+    /// Replace the stored answer
+    Overwrite,
+    /// Import nothing
+    Fail,
+}
+
+/// A row of an import file. Exports can be imported as they are, their `answered_at` is ignored.
+#[derive(Debug, Default, Deserialize)]
+pub struct Row {
+    pub user: String,
+    /// Takes precedence over `question_text`.
+    pub question_id: Option<String>,
+    pub question_text: Option<String>,
+    /// Checked against the survey definition when given.
+    pub answer_kind: Option<String>,
+    pub answer_value: String,
+}
+
+/// An answer of the import file, valid for the survey definition.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// Number of the row in the file, the first row after the header being row 1.
+    pub row: usize,
+    pub user: String,
+    pub question_id: String,
+    pub answer: repo::Answer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Add,
+    Overwrite,
+    Skip,
+    /// The user already answered and the conflict policy is [`OnConflict::Fail`].
+    Conflict,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Action::Add => "add",
+            Action::Overwrite => "overwrite",
+            Action::Skip => "skip, already answered",
+            Action::Conflict => "conflict, already answered",
+        })
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub added: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
+}
+
+impl Summary {
+    /// What importing with `actions` would do.
+    #[must_use]
+    pub fn of(actions: &[Action]) -> Self {
+        let count = |action| actions.iter().filter(|&&planned| planned == action).count();
+        Self {
+            added: count(Action::Add),
+            overwritten: count(Action::Overwrite),
+            skipped: count(Action::Skip),
+        }
+    }
+}
+
+/// # Errors
+///
+/// Returns an error if `reader` fails or its content is not valid `format`.
+pub fn parse(reader: impl Read, format: Format) -> io::Result<Vec<Row>> {
+    match format {
+        Format::Csv => csv::Reader::from_reader(reader)
+            .deserialize()
+            .map(|row| row.map_err(io::Error::from))
+            .collect(),
+        Format::Json => Ok(serde_json::from_reader(reader)?),
+        Format::Jsonl => BufReader::new(reader)
+            .lines()
+            .filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect(),
+    }
+}
+
+/// Entries of the `rows`, or for each invalid row its number and what is wrong with it.
+///
+/// # Errors
+///
+/// Returns the invalid rows if any row does not fit `survey`, or answers a question a previous row answered.
+pub fn validate(survey: &prono_api::Survey, rows: Vec<Row>) -> Result<Vec<Entry>, Vec<(usize, String)>> {
+    let mut entries = Vec::new();
+    let mut invalid = Vec::new();
+    let mut first_rows = HashMap::new();
+    for (index, row) in rows.into_iter().enumerate() {
+        let number = index + 1;
+        match validate_row(survey, number, &row) {
+            Ok(entry) => {
+                let key = (entry.user.clone(), entry.question_id.clone());
+                if let Some(first) = first_rows.insert(key, number) {
+                    invalid.push((
+                        number,
+                        format!("'{}' already answers this question in row {first}", entry.user),
+                    ));
+                } else {
+                    entries.push(entry);
+                }
+            }
+            Err(message) => invalid.push((number, message)),
+        }
+    }
+    if invalid.is_empty() { Ok(entries) } else { Err(invalid) }
+}
+
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|value| !value.is_empty())
+}
+
+fn validate_row(survey: &prono_api::Survey, number: usize, row: &Row) -> Result<Entry, String> {
+    let user = non_empty(Some(&row.user)).ok_or("missing user")?;
+    let question = match (
+        non_empty(row.question_id.as_deref()),
+        non_empty(row.question_text.as_deref()),
+    ) {
+        (Some(id), _) => survey
+            .questions
+            .iter()
+            .find(|question| question.id == id)
+            .ok_or_else(|| format!("unknown question id '{id}'"))?,
+        (None, Some(text)) => survey
+            .questions
+            .iter()
+            .find(|question| question.text.as_deref().is_some_and(|known| known.trim() == text))
+            .ok_or_else(|| format!("no question reads '{text}'"))?,
+        (None, None) => return Err("missing question_id or question_text".to_string()),
+    };
+    let expected_kind = match question.answer {
+        prono_api::Answer::Text(_) => "text",
+        prono_api::Answer::PredictionDate { .. } => "prediction_date",
+    };
+    if let Some(kind) = non_empty(row.answer_kind.as_deref())
+        && kind != expected_kind
+    {
+        return Err(format!("question expects a {expected_kind} answer, not {kind}"));
+    }
+    let value = non_empty(Some(&row.answer_value)).ok_or("missing answer_value")?;
+    let answer = match question.answer {
+        prono_api::Answer::Text(_) => repo::Answer::Text(value.to_string()),
+        prono_api::Answer::PredictionDate { .. } => parse_date(value)
+            .ok_or_else(|| format!("'{value}' is not a date like 2030-05-31, 2030-05, 31/05/2030 or 05/2030"))?,
+    };
+    Ok(Entry {
+        row: number,
+        user: user.to_string(),
+        question_id: question.id.clone(),
+        answer,
+    })
+}
+
+/// Parses `YYYY-MM-DD` and `YYYY-MM` as written by exports, and `DD/MM/YYYY` and `MM/YYYY` as written on paper.
+fn parse_date(value: &str) -> Option<repo::Answer> {
+    let numbers = |separator| {
+        value
+            .split(separator)
+            .map(|part| part.parse::<u16>().ok())
+            .collect::<Option<Vec<u16>>>()
+    };
+    let (day, month, year) = if value.contains('-') {
+        match numbers('-')?[..] {
+            [year, month, day] => (Some(day), month, year),
+            [year, month] => (None, month, year),
+            _ => return None,
+        }
+    } else {
+        match numbers('/')?[..] {
+            [day, month, year] => (Some(day), month, year),
+            [month, year] => (None, month, year),
+            _ => return None,
+        }
+    };
+    let month = u8::try_from(month).ok().filter(|month| (1..=12).contains(month))?;
+    let day = match day {
+        Some(day) => {
+            let day = u8::try_from(day).ok()?;
+            NaiveDate::from_ymd_opt(i32::from(year), u32::from(month), u32::from(day))?;
+            Some(day)
+        }
+        None => None,
+    };
+    Some(repo::Answer::PredictionDate { day, month, year })
+}
+
+/// What to do with each of the `entries`, given the answers that are `existing` by user and question id.
+#[must_use]
+pub fn plan(entries: &[Entry], existing: &HashSet<(String, String)>, on_conflict: OnConflict) -> Vec<Action> {
+    entries
+        .iter()
+        .map(|entry| {
+            if !existing.contains(&(entry.user.clone(), entry.question_id.clone())) {
+                return Action::Add;
+            }
+            match on_conflict {
+                OnConflict::Skip => Action::Skip,
+                OnConflict::Overwrite => Action::Overwrite,
+                OnConflict::Fail => Action::Conflict,
+            }
+        })
+        .collect()
+}
+
+/// Stores the `entries` as `actions` planned. An answer given since planning is skipped with
+/// [`OnConflict::Skip`], and fails the import otherwise.
+///
+/// # Errors
+///
+/// Returns an error if storing an answer fails, the answers stored before are kept.
+pub async fn apply(
+    admin: &dyn Admin,
+    entries: Vec<Entry>,
+    actions: &[Action],
+    on_conflict: OnConflict,
+) -> admin::Result<Summary> {
+    let mut summary = Summary::default();
+    for (entry, action) in entries.into_iter().zip(actions) {
+        let overwrite = match action {
+            Action::Add => false,
+            Action::Overwrite => true,
+            Action::Skip | Action::Conflict => {
+                summary.skipped += 1;
+                continue;
+            }
+        };
+        match admin
+            .add_answer(&entry.user, &entry.question_id, entry.answer, overwrite)
+            .await
+        {
+            Ok(()) if overwrite => summary.overwritten += 1,
+            Ok(()) => summary.added += 1,
+            Err(err) if err.is_answer_exists() && on_conflict == OnConflict::Skip => summary.skipped += 1,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+
+    use super::*;
+
+    fn survey() -> prono_api::Survey {
+        prono_api::Survey {
+            id: 1,
+            description: "Test survey".to_string(),
+            questions: vec![
+                prono_api::Question {
+                    id: "q1".to_string(),
+                    text: Some("When?".to_string()),
+                    answer: prono_api::Answer::PredictionDate {
+                        day: None,
+                        month: 1,
+                        year: 2030,
+                    },
+                },
+                prono_api::Question {
+                    id: "q2".to_string(),
+                    text: Some("Why?".to_string()),
+                    answer: prono_api::Answer::Text(String::new()),
+                },
+            ],
+        }
+    }
+
+    fn row(user: &str, question_id: &str, answer_value: &str) -> Row {
+        Row {
+            user: user.to_string(),
+            question_id: Some(question_id.to_string()),
+            answer_value: answer_value.to_string(),
+            ..Row::default()
+        }
+    }
+
+    fn entry(row: usize, user: &str, question_id: &str) -> Entry {
+        Entry {
+            row,
+            user: user.to_string(),
+            question_id: question_id.to_string(),
+            answer: repo::Answer::Text("because".to_string()),
+        }
+    }
+
+    /// Answers by user and question id, failing like the repository when asked to add an existing one.
+    #[derive(Default)]
+    struct Answers(Mutex<HashMap<(String, String), repo::Answer>>);
+
+    /// Outcome of the calls imports do not make.
+    fn not_imported<T>() -> admin::Result<T> {
+        Err(prono::Error::Repository("not used by imports".to_string()).into())
+    }
+
+    #[async_trait]
+    impl Admin for Answers {
+        async fn users(&self) -> admin::Result<Vec<String>> {
+            not_imported()
+        }
+
+        async fn delete_user(&self, _name: &str) -> admin::Result<()> {
+            not_imported()
+        }
+
+        async fn rename_user(&self, _old: &str, _new: &str) -> admin::Result<()> {
+            not_imported()
+        }
+
+        async fn merge_users(&self, _from: &str, _into: &str) -> admin::Result<()> {
+            not_imported()
+        }
+
+        async fn normalize_users(&self) -> admin::Result<Vec<(String, Result<String, String>)>> {
+            not_imported()
+        }
+
+        async fn devices(&self, _user: &str) -> admin::Result<Vec<repo::Device>> {
+            not_imported()
+        }
+
+        async fn reset_devices(&self, _user: &str) -> admin::Result<usize> {
+            not_imported()
+        }
+
+        async fn answer_records(&self, _survey_id: u64) -> admin::Result<Vec<repo::AnswerRecord>> {
+            not_imported()
+        }
+
+        async fn stats(&self, _survey: &prono_api::Survey) -> admin::Result<Vec<prono::stats::QuestionStats>> {
+            not_imported()
+        }
+
+        async fn add_answer(
+            &self,
+            user: &str,
+            question_id: &str,
+            answer: repo::Answer,
+            overwrite: bool,
+        ) -> admin::Result<()> {
+            let mut answers = self.0.lock().unwrap();
+            let key = (user.to_string(), question_id.to_string());
+            if answers.contains_key(&key) && !overwrite {
+                return Err(prono::Error::AnswerExists.into());
+            }
+            answers.insert(key, answer);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn parse_reads_exports() {
+        let csv = "user,question_id,question_text,answer_kind,answer_value,answered_at\n\
+                   sam,q1,When?,prediction_date,2029-04-03,2023-11-14T22:13:20Z\n\
+                   alex,,Why?,text,\"Because, well\",\n";
+        let rows = parse(csv.as_bytes(), Format::Csv).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].question_id, None);
+        assert_eq!(rows[1].answer_value, "Because, well");
+
+        let jsonl = "{\"user\": \"sam\", \"question_text\": \"Why?\", \"answer_value\": \"yes\"}\n\n";
+        let rows = parse(jsonl.as_bytes(), Format::Jsonl).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].question_text.as_deref(), Some("Why?"));
+
+        let json = "[{\"user\": \"sam\", \"question_id\": \"q2\", \"answer_value\": \"yes\"}]";
+        assert_eq!(parse(json.as_bytes(), Format::Json).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn parse_rejects_rows_without_answer_value() {
+        assert!(parse("user,question_id\nsam,q1\n".as_bytes(), Format::Csv).is_err());
+    }
+
+    #[test]
+    fn validate_maps_question_text_to_id() {
+        let rows = vec![Row {
+            user: " sam ".to_string(),
+            question_text: Some("Why?".to_string()),
+            answer_value: "because".to_string(),
+            ..Row::default()
+        }];
+
+        assert_eq!(validate(&survey(), rows).unwrap(), vec![entry(1, "sam", "q2")]);
+    }
+
+    #[test]
+    fn validate_parses_dates_of_exports_and_paper() {
+        let rows = vec![
+            row("a", "q1", "2030-05-31"),
+            row("b", "q1", "2030-05"),
+            row("c", "q1", "31/05/2030"),
+            row("d", "q1", "05/2030"),
+        ];
+
+        let answers: Vec<repo::Answer> = validate(&survey(), rows)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.answer)
+            .collect();
+
+        let full = repo::Answer::PredictionDate {
+            day: Some(31),
+            month: 5,
+            year: 2030,
+        };
+        let month = repo::Answer::PredictionDate {
+            day: None,
+            month: 5,
+            year: 2030,
+        };
+        assert_eq!(answers, [full.clone(), month.clone(), full, month]);
+    }
+
+    #[test]
+    fn validate_reports_every_invalid_row() {
+        let rows = vec![
+            row("sam", "q2", "because"),
+            row("sam", "unknown", "yes"),
+            row("sam", "q1", "31/02/2030"),
+            row("", "q2", "because"),
+            row("sam", "q2", "again"),
+            Row {
+                answer_kind: Some("text".to_string()),
+                ..row("alex", "q1", "2030-05")
+            },
+        ];
+
+        let invalid = validate(&survey(), rows).unwrap_err();
+
+        let numbers: Vec<usize> = invalid.iter().map(|(number, _message)| *number).collect();
+        assert_eq!(numbers, [2, 3, 4, 5, 6]);
+        assert_eq!(invalid[3].1, "'sam' already answers this question in row 1");
+    }
+
+    #[test]
+    fn plan_follows_conflict_policy() {
+        let entries = [entry(1, "sam", "q1"), entry(2, "alex", "q1")];
+        let existing = HashSet::from([("sam".to_string(), "q1".to_string())]);
+
+        assert_eq!(plan(&entries, &existing, OnConflict::Skip), [Action::Skip, Action::Add]);
+        assert_eq!(
+            plan(&entries, &existing, OnConflict::Overwrite),
+            [Action::Overwrite, Action::Add]
+        );
+        assert_eq!(
+            plan(&entries, &existing, OnConflict::Fail),
+            [Action::Conflict, Action::Add]
+        );
+    }
+
+    #[tokio::test]
+    async fn apply_stores_planned_answers() {
+        let admin = Answers::default();
+        admin
+            .add_answer("sam", "q1", repo::Answer::Text("old".to_string()), false)
+            .await
+            .unwrap();
+        let entries = vec![entry(1, "sam", "q1"), entry(2, "alex", "q1")];
+        let actions = [Action::Overwrite, Action::Add];
+
+        let summary = apply(&admin, entries, &actions, OnConflict::Overwrite).await.unwrap();
+
+        assert_eq!(
+            summary,
+            Summary {
+                added: 1,
+                overwritten: 1,
+                skipped: 0
+            }
+        );
+        assert_eq!(Summary::of(&actions), summary);
+        let answers = admin.0.lock().unwrap();
+        assert_eq!(
+            answers[&("sam".to_string(), "q1".to_string())],
+            repo::Answer::Text("because".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn apply_handles_answers_given_since_planning() {
+        let admin = Answers::default();
+        admin
+            .add_answer("sam", "q1", repo::Answer::Text("meanwhile".to_string()), false)
+            .await
+            .unwrap();
+
+        let summary = apply(&admin, vec![entry(1, "sam", "q1")], &[Action::Add], OnConflict::Skip)
+            .await
+            .unwrap();
+        assert_eq!(summary.skipped, 1);
+
+        let result = apply(&admin, vec![entry(1, "sam", "q1")], &[Action::Add], OnConflict::Fail).await;
+        assert!(result.is_err_and(|err| err.is_answer_exists()));
+    }
+}
@@ -1,10 +1,30 @@
+mod admin;
+mod export;
+mod import;
+mod remote;
+mod stats;
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
 use clap::{Parser, Subcommand};
 use prono::ReadConfig;
-use prono::repo::{Db, Users};
+use prono::repo::Db;
+
+use crate::admin::{Admin, Database};
+use crate::export::Format;
+use crate::import::{Action, OnConflict, Summary};
+use crate::remote::Remote;
 
 #[derive(Debug, Parser)]
 #[command(name = "prono-cli", about = "Prono database management CLI")]
 struct Cli {
+    /// Manage the backend at this URL through its admin API instead of the database, overrides `remote` in
+    /// the `[cli]` config section
+    #[arg(long, global = true, value_name = "URL")]
+    remote: Option<String>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -16,6 +36,22 @@ enum Commands {
         #[command(subcommand)]
         action: UserAction,
     },
+    /// Manage the devices users submit answers from
+    Devices {
+        #[command(subcommand)]
+        action: DeviceAction,
+    },
+    /// Work with the answers of all users
+    Answers {
+        #[command(subcommand)]
+        action: AnswerAction,
+    },
+    /// Show statistics of the answers to each question
+    Stats {
+        /// Id of the survey, by default the survey of the survey definition
+        #[arg(long)]
+        survey: Option<u64>,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -27,6 +63,65 @@ enum UserAction {
         /// Username to delete
         name: String,
     },
+    /// Give the answers and devices of a user to a new username
+    Rename {
+        /// Current username
+        old: String,
+        /// Username not used yet
+        new: String,
+    },
+    /// Move the answers and devices of a user to another user, e.g. after typing their name differently
+    Merge {
+        /// Username to merge, it disappears
+        from: String,
+        /// Username to keep
+        into: String,
+    },
+    /// Merge every user stored under another spelling than the normalized one, e.g. `Sam` into `sam`
+    Normalize,
+}
+
+#[derive(Debug, Subcommand)]
+enum DeviceAction {
+    /// Show the registered devices of a user
+    List {
+        /// Username
+        user: String,
+    },
+    /// Remove all devices of a user, e.g. after losing their only device
+    Reset {
+        /// Username
+        user: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum AnswerAction {
+    /// Export every answer to a survey, one row per answer
+    Export {
+        /// Id of the survey
+        #[arg(long)]
+        survey: u64,
+        #[arg(long, value_enum)]
+        format: Format,
+        /// File to write to instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Import answers given outside the app, e.g. on paper, into the survey
+    Import {
+        /// CSV, JSON or JSON Lines file with the columns of an export
+        file: PathBuf,
+        /// Format of the file, by default told from its extension
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+        /// Report what would be imported without storing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// What to do with answers to questions the user already answered
+        #[arg(long, value_enum, default_value_t = OnConflict::Fail)]
+        on_conflict: OnConflict,
+    },
 }
 
 #[tokio::main]
@@ -34,17 +129,31 @@ async fn main() {
     env_logger::init();
     let cli = Cli::parse();
 
-    let config_reader = prono::factory::create_config_reader();
-    let default_config_path = config_reader.default_config_path();
-    let db_config: prono_db::Config = config_reader.read(default_config_path).db.into();
-    let db = prono_db::MysqlDb::init(db_config)
-        .await
-        .expect("Failed to initialize database");
+    let cli_config_reader = prono::factory::create_cli_config_reader();
+    let cli_config = cli_config_reader.read(cli_config_reader.default_config_path());
+    let admin: Box<dyn Admin> = if let Some(url) = cli.remote.or(cli_config.remote) {
+        let token = cli_config
+            .admin_token
+            .expect("Remote mode needs the admin token, set PRONO_CLI_ADMIN_TOKEN or admin_token in [cli]");
+        Box::new(Remote::new(&url, token).expect("Failed to set up remote mode"))
+    } else {
+        let config_reader = prono::factory::create_config_reader();
+        let default_config_path = config_reader.default_config_path();
+        let db_config: prono_db::Config = config_reader.read(default_config_path).db.into();
+        let db = prono_db::MysqlDb::init(db_config)
+            .await
+            .expect("Failed to initialize database");
+        Box::new(Database(db))
+    };
+
+    run(admin.as_ref(), cli.command).await;
+}
 
-    match cli.command {
+async fn run(admin: &dyn Admin, command: Commands) {
+    match command {
         Commands::Users { action } => match action {
             UserAction::Show => {
-                let users = db.all_users().await.expect("Failed to fetch users");
+                let users = admin.users().await.expect("Failed to fetch users");
                 if users.is_empty() {
                     println!("No users found.");
                 } else {
@@ -54,11 +163,172 @@ async fn main() {
                 }
             }
             UserAction::Delete { name } => {
-                db.delete_user(&name).await.expect("Failed to delete user");
+                admin.delete_user(&name).await.expect("Failed to delete user");
                 println!("User '{name}' deleted.");
             }
+            UserAction::Rename { old, new } => match admin.rename_user(&old, &new).await {
+                Ok(()) => println!("User '{old}' renamed to '{new}'."),
+                Err(e) if e.is_user_exists() => {
+                    eprintln!("'{new}' already exists, merge the users instead.");
+                    std::process::exit(1);
+                }
+                Err(e) => panic!("Failed to rename user: {e}"),
+            },
+            UserAction::Merge { from, into } => match admin.merge_users(&from, &into).await {
+                Ok(()) => println!("User '{from}' merged into '{into}'."),
+                Err(admin::Error::Repository(prono::Error::AnswersConflict(questions))) => {
+                    for question_id in questions {
+                        println!("Both answered {question_id}");
+                    }
+                    eprintln!("Nothing merged, delete one of the answers to each question above first.");
+                    std::process::exit(1);
+                }
+                Err(e) if e.is_user_exists() => {
+                    eprintln!("Both '{from}' and '{into}' have an account, nothing merged.");
+                    std::process::exit(1);
+                }
+                Err(e) => panic!("Failed to merge users: {e}"),
+            },
+            UserAction::Normalize => normalize_users(admin).await,
         },
+        Commands::Devices { action } => match action {
+            DeviceAction::List { user } => {
+                let devices = admin.devices(&user).await.expect("Failed to fetch devices");
+                if devices.is_empty() {
+                    println!("No devices registered for '{user}'.");
+                } else {
+                    for device in &devices {
+                        println!("{}\tregistered at {} (Unix time)", device.id, device.registered_at);
+                    }
+                }
+            }
+            DeviceAction::Reset { user } => {
+                let removed = admin.reset_devices(&user).await.expect("Failed to remove devices");
+                println!("Removed {removed} device(s) of '{user}'.");
+            }
+        },
+        Commands::Answers {
+            action: AnswerAction::Export { survey, format, output },
+        } => {
+            let definition = survey_definition(survey);
+            let records = admin.answer_records(survey).await.expect("Failed to fetch answers");
+            let rows = export::rows(&definition, records);
+            if let Some(path) = output {
+                let file = File::create(&path).expect("Failed to create output file");
+                export::write(&rows, format, file).expect("Failed to write answers");
+                println!("Exported {} answer(s) to {}.", rows.len(), path.display());
+            } else {
+                export::write(&rows, format, io::stdout().lock()).expect("Failed to write answers");
+            }
+        }
+        Commands::Answers {
+            action:
+                AnswerAction::Import {
+                    file,
+                    format,
+                    dry_run,
+                    on_conflict,
+                },
+        } => import_answers(admin, &file, format, dry_run, on_conflict).await,
+        Commands::Stats { survey } => {
+            let definition = survey.map_or_else(prono::empty_survey, survey_definition);
+            let stats = admin.stats(&definition).await.expect("Failed to fetch answers");
+            print!("{}", stats::render(&definition, &stats));
+        }
+    }
+}
+
+/// The survey definition, exits if it is not the survey `survey_id`.
+/// Merges the users stored under other spellings into their normalized names, exits with an error when some are left
+/// as they are.
+async fn normalize_users(admin: &dyn Admin) {
+    let outcomes = admin.normalize_users().await.expect("Failed to normalize users");
+    let mut failed = false;
+    for (name, outcome) in outcomes {
+        match outcome {
+            Ok(normalized) => println!("User '{name}' merged into '{normalized}'."),
+            Err(e) => {
+                failed = true;
+                eprintln!("User '{name}' left as it is: {e}");
+            }
+        }
+    }
+    if failed {
+        eprintln!("Rename, merge or delete the users left as they are by hand.");
+        std::process::exit(1);
+    }
+}
+
+fn survey_definition(survey_id: u64) -> prono_api::Survey {
+    let definition = prono::empty_survey();
+    if survey_id != definition.id {
+        eprintln!(
+            "Unknown survey {survey_id}, the survey definition has id {}.",
+            definition.id
+        );
+        std::process::exit(1);
     }
+    definition
+}
+
+async fn import_answers(
+    admin: &dyn Admin,
+    file: &Path,
+    format: Option<Format>,
+    dry_run: bool,
+    on_conflict: OnConflict,
+) {
+    let Some(format) = format.or_else(|| Format::from_path(file)) else {
+        eprintln!("Cannot tell the format of {}, pass --format.", file.display());
+        std::process::exit(1);
+    };
+    let reader = File::open(file).expect("Failed to open import file");
+    let rows = import::parse(reader, format).unwrap_or_else(|err| {
+        eprintln!("Failed to read {}: {err}", file.display());
+        std::process::exit(1);
+    });
+    let definition = prono::empty_survey();
+    let entries = import::validate(&definition, rows).unwrap_or_else(|invalid| {
+        for (row, message) in invalid {
+            eprintln!("Row {row}: {message}");
+        }
+        eprintln!("Nothing imported, fix the rows above first.");
+        std::process::exit(1);
+    });
+    let existing: HashSet<(String, String)> = admin
+        .answer_records(definition.id)
+        .await
+        .expect("Failed to fetch answers")
+        .into_iter()
+        .map(|record| (record.user, record.question_id))
+        .collect();
+    let actions = import::plan(&entries, &existing, on_conflict);
+    for (entry, action) in entries.iter().zip(&actions) {
+        if dry_run || *action == Action::Conflict {
+            println!("Row {}: {action} '{}' -> {}", entry.row, entry.user, entry.question_id);
+        }
+    }
+    if actions.contains(&Action::Conflict) {
+        eprintln!("Nothing imported, some users already answered. Pass --on-conflict skip or overwrite.");
+        std::process::exit(1);
+    }
+    if dry_run {
+        let Summary {
+            added,
+            overwritten,
+            skipped,
+        } = Summary::of(&actions);
+        println!("Would add {added}, overwrite {overwritten} and skip {skipped} answer(s).");
+        return;
+    }
+    let Summary {
+        added,
+        overwritten,
+        skipped,
+    } = import::apply(admin, entries, &actions, on_conflict)
+        .await
+        .expect("Failed to import answers");
+    println!("Added {added}, overwrote {overwritten} and skipped {skipped} answer(s).");
 }
 
 #[cfg(test)]
@@ -85,10 +355,201 @@ mod tests {
             Commands::Users {
                 action: UserAction::Delete { name },
             } => assert_eq!(name, "alice"),
-            Commands::Users { .. } => panic!("Expected Users Delete command"),
+            Commands::Users { .. } | Commands::Devices { .. } | Commands::Answers { .. } | Commands::Stats { .. } => {
+                panic!("Expected Users Delete command")
+            }
+        }
+    }
+
+    #[test]
+    fn parse_users_rename() {
+        let cli = Cli::try_parse_from(["prono-cli", "users", "rename", "Sam", "sam"]).unwrap();
+        match cli.command {
+            Commands::Users {
+                action: UserAction::Rename { old, new },
+            } => assert_eq!((old.as_str(), new.as_str()), ("Sam", "sam")),
+            Commands::Users { .. } | Commands::Devices { .. } | Commands::Answers { .. } | Commands::Stats { .. } => {
+                panic!("Expected Users Rename command")
+            }
+        }
+    }
+
+    #[test]
+    fn parse_users_merge() {
+        let cli = Cli::try_parse_from(["prono-cli", "users", "merge", "Sam J.", "sam"]).unwrap();
+        match cli.command {
+            Commands::Users {
+                action: UserAction::Merge { from, into },
+            } => assert_eq!((from.as_str(), into.as_str()), ("Sam J.", "sam")),
+            Commands::Users { .. } | Commands::Devices { .. } | Commands::Answers { .. } | Commands::Stats { .. } => {
+                panic!("Expected Users Merge command")
+            }
         }
     }
 
+    #[test]
+    fn parse_users_normalize() {
+        let cli = Cli::try_parse_from(["prono-cli", "users", "normalize"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Users {
+                action: UserAction::Normalize
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_users_merge_missing_target_fails() {
+        assert!(Cli::try_parse_from(["prono-cli", "users", "merge", "Sam"]).is_err());
+    }
+
+    #[test]
+    fn parse_devices_list() {
+        let cli = Cli::try_parse_from(["prono-cli", "devices", "list", "alice"]).unwrap();
+        match cli.command {
+            Commands::Devices {
+                action: DeviceAction::List { user },
+            } => assert_eq!(user, "alice"),
+            Commands::Users { .. } | Commands::Devices { .. } | Commands::Answers { .. } | Commands::Stats { .. } => {
+                panic!("Expected Devices List command")
+            }
+        }
+    }
+
+    #[test]
+    fn parse_devices_reset() {
+        let cli = Cli::try_parse_from(["prono-cli", "devices", "reset", "alice"]).unwrap();
+        match cli.command {
+            Commands::Devices {
+                action: DeviceAction::Reset { user },
+            } => assert_eq!(user, "alice"),
+            Commands::Users { .. } | Commands::Devices { .. } | Commands::Answers { .. } | Commands::Stats { .. } => {
+                panic!("Expected Devices Reset command")
+            }
+        }
+    }
+
+    #[test]
+    fn parse_answers_export() {
+        let cli = Cli::try_parse_from([
+            "prono-cli",
+            "answers",
+            "export",
+            "--survey",
+            "1",
+            "--format",
+            "jsonl",
+            "--output",
+            "answers.jsonl",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Answers {
+                action: AnswerAction::Export { survey, format, output },
+            } => {
+                assert_eq!(survey, 1);
+                assert_eq!(format, Format::Jsonl);
+                assert_eq!(output, Some(PathBuf::from("answers.jsonl")));
+            }
+            Commands::Users { .. } | Commands::Devices { .. } | Commands::Answers { .. } | Commands::Stats { .. } => {
+                panic!("Expected Answers Export command")
+            }
+        }
+    }
+
+    #[test]
+    fn parse_answers_export_defaults_to_stdout() {
+        let cli = Cli::try_parse_from(["prono-cli", "answers", "export", "--survey", "1", "--format", "csv"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Answers {
+                action: AnswerAction::Export { output: None, .. }
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_answers_export_rejects_unknown_format() {
+        assert!(Cli::try_parse_from(["prono-cli", "answers", "export", "--survey", "1", "--format", "xml"]).is_err());
+    }
+
+    #[test]
+    fn parse_answers_import() {
+        let cli = Cli::try_parse_from([
+            "prono-cli",
+            "answers",
+            "import",
+            "paper.csv",
+            "--dry-run",
+            "--on-conflict",
+            "overwrite",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Answers {
+                action:
+                    AnswerAction::Import {
+                        file,
+                        format,
+                        dry_run,
+                        on_conflict,
+                    },
+            } => {
+                assert_eq!(file, PathBuf::from("paper.csv"));
+                assert_eq!(format, None);
+                assert!(dry_run);
+                assert_eq!(on_conflict, OnConflict::Overwrite);
+            }
+            Commands::Users { .. } | Commands::Devices { .. } | Commands::Answers { .. } | Commands::Stats { .. } => {
+                panic!("Expected Answers Import command")
+            }
+        }
+    }
+
+    #[test]
+    fn parse_answers_import_fails_on_conflict_by_default() {
+        let cli = Cli::try_parse_from(["prono-cli", "answers", "import", "paper.json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Answers {
+                action: AnswerAction::Import {
+                    dry_run: false,
+                    on_conflict: OnConflict::Fail,
+                    ..
+                }
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_stats() {
+        let cli = Cli::try_parse_from(["prono-cli", "stats"]).unwrap();
+        assert!(matches!(cli.command, Commands::Stats { survey: None }));
+
+        let cli = Cli::try_parse_from(["prono-cli", "stats", "--survey", "1"]).unwrap();
+        assert!(matches!(cli.command, Commands::Stats { survey: Some(1) }));
+    }
+
+    #[test]
+    fn parse_remote_before_and_after_subcommand() {
+        let cli = Cli::try_parse_from(["prono-cli", "--remote", "https://nas.local:8081", "users", "show"]).unwrap();
+        assert_eq!(cli.remote.as_deref(), Some("https://nas.local:8081"));
+
+        let cli = Cli::try_parse_from(["prono-cli", "users", "show", "--remote", "https://nas.local:8081"]).unwrap();
+        assert_eq!(cli.remote.as_deref(), Some("https://nas.local:8081"));
+    }
+
+    #[test]
+    fn parse_without_remote_is_local() {
+        let cli = Cli::try_parse_from(["prono-cli", "users", "show"]).unwrap();
+        assert!(cli.remote.is_none());
+    }
+
+    #[test]
+    fn parse_devices_reset_missing_user_fails() {
+        assert!(Cli::try_parse_from(["prono-cli", "devices", "reset"]).is_err());
+    }
+
     #[test]
     fn parse_missing_subcommand_fails() {
         let result = Cli::try_parse_from(["prono-cli"]);
@@ -0,0 +1,249 @@
+use async_trait::async_trait;
+use prono::repo;
+use prono::stats::QuestionStats;
+use prono_api::v1;
+use reqwest::{RequestBuilder, Response, Url};
+
+use crate::admin::{Admin, Error, Result};
+
+/// Works through the `/api/admin` routes of a backend, authenticated with its admin token.
+pub struct Remote {
+    client: reqwest::Client,
+    base_url: Url,
+    token: String,
+}
+
+impl Remote {
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidUrl`] if `base_url` is not an `http` or `https` URL.
+    pub fn new(base_url: &str, token: String) -> Result<Self> {
+        let base_url = Url::parse(base_url).map_err(|e| Error::InvalidUrl(format!("{base_url}: {e}")))?;
+        if !matches!(base_url.scheme(), "http" | "https") {
+            return Err(Error::InvalidUrl(format!("{base_url}: not an http(s) URL")));
+        }
+        Ok(Self {
+            client: reqwest::Client::new(),
+            base_url,
+            token,
+        })
+    }
+
+    /// URL of the admin route made of `segments`, each percent-encoded.
+    fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .expect("http(s) URLs have a path")
+            .pop_if_empty()
+            .extend(["api", "admin"])
+            .extend(segments);
+        url
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let response = request.bearer_auth(&self.token).send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        match response.json::<prono_api::ErrorBody>().await {
+            Ok(body) => Err(api_error(body)),
+            Err(_) => Err(Error::Status(status)),
+        }
+    }
+}
+
+/// The error in an error response. A merge refused with `answer_exists` lists the questions both users answered
+/// in `details`, it becomes the same error as from the database so that they can be shown.
+fn api_error(body: prono_api::ErrorBody) -> Error {
+    match (body.code, body.details) {
+        (prono_api::ErrorCode::AnswerExists, Some(details)) => {
+            prono::Error::AnswersConflict(details.split(", ").map(ToString::to_string).collect()).into()
+        }
+        (code, details) => prono_api::Error::from(prono_api::ErrorBody {
+            code,
+            message: body.message,
+            details,
+        })
+        .into(),
+    }
+}
+
+fn answer_to_v1(answer: repo::Answer) -> v1::Answer {
+    match answer {
+        repo::Answer::Text(text) => v1::Answer::Text { text },
+        repo::Answer::PredictionDate { day, month, year } => v1::Answer::PredictionDate { day, month, year },
+    }
+}
+
+fn answer_from_v1(answer: v1::Answer) -> repo::Answer {
+    match answer {
+        v1::Answer::Text { text } => repo::Answer::Text(text),
+        v1::Answer::PredictionDate { day, month, year } => repo::Answer::PredictionDate { day, month, year },
+    }
+}
+
+#[async_trait]
+impl Admin for Remote {
+    async fn users(&self) -> Result<Vec<String>> {
+        let request = self.client.get(self.url(&["users"]));
+        Ok(self.send(request).await?.json().await?)
+    }
+
+    async fn delete_user(&self, name: &str) -> Result<()> {
+        self.send(self.client.delete(self.url(&["users", name]))).await?;
+        Ok(())
+    }
+
+    async fn rename_user(&self, old: &str, new: &str) -> Result<()> {
+        let body = v1::RenameUserRequest { new: new.to_string() };
+        let request = self.client.post(self.url(&["users", old, "rename"])).json(&body);
+        self.send(request).await?;
+        Ok(())
+    }
+
+    async fn merge_users(&self, from: &str, into: &str) -> Result<()> {
+        let body = v1::MergeUserRequest { into: into.to_string() };
+        let request = self.client.post(self.url(&["users", from, "merge"])).json(&body);
+        self.send(request).await?;
+        Ok(())
+    }
+
+    async fn normalize_users(&self) -> Result<Vec<(String, std::result::Result<String, String>)>> {
+        let request = self.client.post(self.url(&["users", "normalize"]));
+        let users: Vec<v1::NormalizedUser> = self.send(request).await?.json().await?;
+        Ok(users
+            .into_iter()
+            .map(|user| {
+                let outcome = match (user.merged_into, user.error) {
+                    (Some(normalized), _) => Ok(normalized),
+                    (None, error) => Err(error.unwrap_or_default()),
+                };
+                (user.name, outcome)
+            })
+            .collect())
+    }
+
+    async fn devices(&self, user: &str) -> Result<Vec<repo::Device>> {
+        let request = self.client.get(self.url(&["users", user, "devices"]));
+        let devices: Vec<v1::Device> = self.send(request).await?.json().await?;
+        Ok(devices
+            .into_iter()
+            .map(|device| repo::Device {
+                id: device.id,
+                registered_at: device.registered_at,
+            })
+            .collect())
+    }
+
+    async fn reset_devices(&self, user: &str) -> Result<usize> {
+        let request = self.client.delete(self.url(&["users", user, "devices"]));
+        let reset: v1::DevicesReset = self.send(request).await?.json().await?;
+        Ok(reset.removed)
+    }
+
+    async fn answer_records(&self, survey_id: u64) -> Result<Vec<repo::AnswerRecord>> {
+        let request = self
+            .client
+            .get(self.url(&["surveys", &survey_id.to_string(), "answers"]));
+        let records: Vec<v1::AnswerRecord> = self.send(request).await?.json().await?;
+        Ok(records
+            .into_iter()
+            .map(|record| repo::AnswerRecord {
+                user: record.user,
+                question_id: record.question_id,
+                answer: answer_from_v1(record.answer),
+                answered_at: record.answered_at,
+            })
+            .collect())
+    }
+
+    async fn add_answer(&self, user: &str, question_id: &str, answer: repo::Answer, overwrite: bool) -> Result<()> {
+        let url = self.url(&["answers"]);
+        let request = if overwrite {
+            self.client.put(url)
+        } else {
+            self.client.post(url)
+        };
+        let body = v1::AddAnswerRequest {
+            user: user.to_string(),
+            question_id: question_id.to_string(),
+            answer: answer_to_v1(answer),
+        };
+        self.send(request.json(&body)).await?;
+        Ok(())
+    }
+
+    async fn stats(&self, survey: &prono_api::Survey) -> Result<Vec<QuestionStats>> {
+        let records = self.answer_records(survey.id).await?;
+        Ok(survey
+            .questions
+            .iter()
+            .map(|question| {
+                let answers: Vec<repo::Answer> = records
+                    .iter()
+                    .filter(|record| record.question_id == question.id)
+                    .map(|record| record.answer.clone())
+                    .collect();
+                QuestionStats::new(question.id.clone(), &answers)
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(base_url: &str) -> Remote {
+        Remote::new(base_url, "token".to_string()).unwrap()
+    }
+
+    #[test]
+    fn url_appends_admin_route_to_base_path() {
+        assert_eq!(
+            remote("https://nas.local:8081").url(&["users"]).as_str(),
+            "https://nas.local:8081/api/admin/users"
+        );
+        assert_eq!(
+            remote("https://example.com/prono/")
+                .url(&["users", "sam", "devices"])
+                .as_str(),
+            "https://example.com/prono/api/admin/users/sam/devices"
+        );
+    }
+
+    #[test]
+    fn url_encodes_user_names() {
+        assert_eq!(
+            remote("http://localhost:8081").url(&["users", "a b/c"]).as_str(),
+            "http://localhost:8081/api/admin/users/a%20b%2Fc"
+        );
+    }
+
+    #[test]
+    fn conflicting_answers_are_listed_like_from_the_database() {
+        let body = |details: Option<&str>| prono_api::ErrorBody {
+            code: prono_api::ErrorCode::AnswerExists,
+            message: "Answer already exists".to_string(),
+            details: details.map(ToString::to_string),
+        };
+        assert!(matches!(
+            api_error(body(Some("q1, q2"))),
+            Error::Repository(prono::Error::AnswersConflict(questions)) if questions == ["q1", "q2"]
+        ));
+        assert!(api_error(body(None)).is_answer_exists());
+    }
+
+    #[test]
+    fn new_rejects_non_http_urls() {
+        assert!(matches!(
+            Remote::new("nas.local:8081", String::new()),
+            Err(Error::InvalidUrl(_))
+        ));
+        assert!(matches!(
+            Remote::new("not a url", String::new()),
+            Err(Error::InvalidUrl(_))
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt::Write;
+
+use prono::stats::QuestionStats;
+
+/// Width of the longest bar of a histogram.
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// Statistics of each question of `survey` as text, in the order of `stats`.
+pub fn render(survey: &prono_api::Survey, stats: &[QuestionStats]) -> String {
+    let mut out = String::new();
+    for question_stats in stats {
+        let text = survey
+            .questions
+            .iter()
+            .find(|question| question.id == question_stats.question_id)
+            .and_then(|question| question.text.as_deref())
+            .unwrap_or_default();
+        // Writing to a String never fails.
+        let _ = writeln!(out, "{text} ({})", question_stats.question_id);
+        let _ = writeln!(out, "  answers: {}", question_stats.answers);
+        if let Some(dates) = &question_stats.dates {
+            let _ = writeln!(out, "  median: {}  mean: {}", dates.median, dates.mean);
+            let _ = writeln!(
+                out,
+                "  interquartile range: {} to {} ({} days)",
+                dates.first_quartile,
+                dates.third_quartile,
+                dates.interquartile_days()
+            );
+            let _ = writeln!(out, "  earliest: {}  latest: {}", dates.earliest, dates.latest);
+            let most = dates.histogram.iter().map(|(_year, count)| *count).max().unwrap_or(0);
+            for (year, count) in &dates.histogram {
+                let _ = writeln!(
+                    out,
+                    "  {year} | {:<HISTOGRAM_WIDTH$} {count}",
+                    "#".repeat(bar(*count, most))
+                );
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Length of the bar of `count` when the bar of `most` is [`HISTOGRAM_WIDTH`] long, at least 1 for any answer.
+fn bar(count: usize, most: usize) -> usize {
+    if most == 0 {
+        return 0;
+    }
+    (count * HISTOGRAM_WIDTH).div_ceil(most)
+}
+
+#[cfg(test)]
+mod tests {
+    use prono::repo;
+
+    use super::*;
+
+    fn survey() -> prono_api::Survey {
+        prono_api::Survey {
+            id: 1,
+            description: "Test survey".to_string(),
+            questions: vec![
+                prono_api::Question {
+                    id: "q1".to_string(),
+                    text: Some("When?".to_string()),
+                    answer: prono_api::Answer::PredictionDate {
+                        day: None,
+                        month: 1,
+                        year: 2030,
+                    },
+                },
+                prono_api::Question {
+                    id: "q2".to_string(),
+                    text: Some("Why?".to_string()),
+                    answer: prono_api::Answer::Text(String::new()),
+                },
+            ],
+        }
+    }
+
+    fn prediction(month: u8, year: u16) -> repo::Answer {
+        repo::Answer::PredictionDate {
+            day: Some(1),
+            month,
+            year,
+        }
+    }
+
+    #[test]
+    fn render_prints_dates_and_histogram() {
+        let stats = [
+            QuestionStats::new(
+                "q1".to_string(),
+                &[prediction(1, 2030), prediction(7, 2030), prediction(1, 2032)],
+            ),
+            QuestionStats::new("q2".to_string(), &[repo::Answer::Text("fun".to_string())]),
+        ];
+
+        let out = render(&survey(), &stats);
+
+        let expected = format!(
+            "When? (q1)\n  \
+               answers: 3\n  \
+               median: 2030-07-01  mean: 2030-11-01\n  \
+               interquartile range: 2030-04-02 to 2031-04-02 (365 days)\n  \
+               earliest: 2030-01-01  latest: 2032-01-01\n  \
+               2030 | {} 2\n  \
+               2032 | {} 1\n\
+             \n\
+             Why? (q2)\n  \
+               answers: 1\n\
+             \n",
+            "#".repeat(40),
+            "#".repeat(20) + &" ".repeat(20),
+        );
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn bar_is_visible_for_any_answer() {
+        assert_eq!(bar(1, 1000), 1);
+        assert_eq!(bar(0, 1000), 0);
+        assert_eq!(bar(500, 1000), 20);
+        assert_eq!(bar(0, 0), 0);
+    }
+}
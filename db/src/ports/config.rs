@@ -1,13 +1,33 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use prono::db_config::SslMode;
 use secure_string::SecureString;
+use sqlx::mysql::{MySqlConnectOptions, MySqlSslMode};
 
 pub static DB_NAME: &str = "db_prono";
 
+#[derive(Clone)]
 pub struct Config {
     pub host: SecureString,
     pub port: SecureString,
     pub user: SecureString,
     pub pass: SecureString,
     pub db_name: String,
+    pub ssl_mode: SslMode,
+    pub ca_cert: Option<PathBuf>,
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+}
+
+fn mysql_ssl_mode(ssl_mode: SslMode) -> MySqlSslMode {
+    match ssl_mode {
+        SslMode::Disabled => MySqlSslMode::Disabled,
+        SslMode::Preferred => MySqlSslMode::Preferred,
+        SslMode::Required => MySqlSslMode::Required,
+        SslMode::VerifyCa => MySqlSslMode::VerifyCa,
+        SslMode::VerifyIdentity => MySqlSslMode::VerifyIdentity,
+    }
 }
 
 impl Config {
@@ -23,6 +43,26 @@ impl Config {
 
         format!("{DB_PROTOCOL}://{user}:{pass}@{host}:{port}/{db_name}").into()
     }
+
+    /// Connect options built from the URL, extended with the TLS settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the constructed database URL cannot be parsed.
+    pub fn connect_options(&self) -> Result<MySqlConnectOptions, sqlx::Error> {
+        let mut options =
+            MySqlConnectOptions::from_str(self.construct_url().unsecure())?.ssl_mode(mysql_ssl_mode(self.ssl_mode));
+        if let Some(ca_cert) = &self.ca_cert {
+            options = options.ssl_ca(ca_cert);
+        }
+        if let Some(client_cert) = &self.client_cert {
+            options = options.ssl_client_cert(client_cert);
+        }
+        if let Some(client_key) = &self.client_key {
+            options = options.ssl_client_key(client_key);
+        }
+        Ok(options)
+    }
 }
 
 impl From<prono::db_config::Config> for Config {
@@ -33,12 +73,17 @@ impl From<prono::db_config::Config> for Config {
             user: db_config.user,
             pass: db_config.pass,
             db_name: DB_NAME.to_string(),
+            ssl_mode: db_config.ssl_mode,
+            ca_cert: db_config.ca_cert,
+            client_cert: db_config.client_cert,
+            client_key: db_config.client_key,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use rstest::rstest;
     use secure_string::SecureString;
 
     use super::*;
@@ -51,6 +96,10 @@ mod tests {
             user: SecureString::from("user"),
             pass: SecureString::from("password"),
             db_name: "db_name".to_string(),
+            ssl_mode: SslMode::default(),
+            ca_cert: None,
+            client_cert: None,
+            client_key: None,
         };
 
         let expected_host = db_config.host.unsecure();
@@ -71,6 +120,10 @@ mod tests {
             port: SecureString::from("3306"),
             user: SecureString::from("root"),
             pass: SecureString::from("secret"),
+            ssl_mode: SslMode::VerifyIdentity,
+            ca_cert: Some(PathBuf::from("ca.pem")),
+            client_cert: None,
+            client_key: None,
         };
 
         let config: Config = prono_config.into();
@@ -80,5 +133,36 @@ mod tests {
         assert_eq!(config.user.unsecure(), "root");
         assert_eq!(config.pass.unsecure(), "secret");
         assert_eq!(config.db_name, DB_NAME);
+        assert_eq!(config.ssl_mode, SslMode::VerifyIdentity);
+        assert_eq!(config.ca_cert, Some(PathBuf::from("ca.pem")));
+    }
+
+    #[rstest]
+    #[case(SslMode::Disabled)]
+    #[case(SslMode::Preferred)]
+    #[case(SslMode::Required)]
+    #[case(SslMode::VerifyCa)]
+    #[case(SslMode::VerifyIdentity)]
+    fn test_connect_options_use_ssl_mode(#[case] ssl_mode: SslMode) {
+        let db_config = Config {
+            host: SecureString::from("localhost"),
+            port: SecureString::from("3306"),
+            user: SecureString::from("user"),
+            pass: SecureString::from("password"),
+            db_name: DB_NAME.to_string(),
+            ssl_mode,
+            ca_cert: Some(PathBuf::from("ca.pem")),
+            client_cert: Some(PathBuf::from("client.pem")),
+            client_key: Some(PathBuf::from("client.key")),
+        };
+
+        let options = db_config.connect_options().unwrap();
+
+        assert_eq!(options.get_host(), "localhost");
+        assert_eq!(options.get_port(), 3306);
+        assert_eq!(
+            std::mem::discriminant(&options.get_ssl_mode()),
+            std::mem::discriminant(&mysql_ssl_mode(ssl_mode))
+        );
     }
 }
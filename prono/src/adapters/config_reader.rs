@@ -3,31 +3,114 @@ use std::{fs, path};
 use crate::ReadConfig;
 use log::{debug, info, warn};
 
-use crate::SecureConfig;
+use crate::{SecureConfig, cli_config};
 
 static HOST_OVERRIDE_ENV_VAR: &str = "PRONO_DB_HOST";
 static PORT_OVERRIDE_ENV_VAR: &str = "PRONO_DB_PORT";
 static USER_OVERRIDE_ENV_VAR: &str = "PRONO_DB_USER";
 static PASS_OVERRIDE_ENV_VAR: &str = "PRONO_DB_PASS";
+static SSL_MODE_OVERRIDE_ENV_VAR: &str = "PRONO_DB_SSL_MODE";
+static CA_CERT_OVERRIDE_ENV_VAR: &str = "PRONO_DB_CA_CERT";
+static CLIENT_CERT_OVERRIDE_ENV_VAR: &str = "PRONO_DB_CLIENT_CERT";
+static CLIENT_KEY_OVERRIDE_ENV_VAR: &str = "PRONO_DB_CLIENT_KEY";
+static LISTEN_ADDRESS_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_LISTEN_ADDRESS";
+static BACKEND_PORT_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_PORT";
+static ALLOWED_ORIGINS_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_ALLOWED_ORIGINS";
+static MAX_BODY_SIZE_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_MAX_BODY_SIZE";
+static WORKERS_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_WORKERS";
+static SESSION_TTL_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_SESSION_TTL_SECS";
+static CHALLENGE_DIFFICULTY_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_CHALLENGE_DIFFICULTY";
+static CHALLENGE_TTL_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_CHALLENGE_TTL_SECS";
+static MAX_DEVICES_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_MAX_DEVICES_PER_USER";
+static TRANSFER_CODE_TTL_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_TRANSFER_CODE_TTL_SECS";
+static ADMIN_TOKEN_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_ADMIN_TOKEN";
+static STATS_MIN_ANSWERS_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_STATS_MIN_ANSWERS";
+static ALIAS_SECRET_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_ALIAS_SECRET";
+static RATE_LIMIT_ENABLED_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_RATE_LIMIT_ENABLED";
+static RATE_LIMIT_STORE_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_RATE_LIMIT_STORE";
+static TLS_CERT_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_TLS_CERT";
+static TLS_KEY_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_TLS_KEY";
+static HTTP_REDIRECT_PORT_OVERRIDE_ENV_VAR: &str = "PRONO_BACKEND_HTTP_REDIRECT_PORT";
+static CLI_REMOTE_OVERRIDE_ENV_VAR: &str = "PRONO_CLI_REMOTE";
+static CLI_ADMIN_TOKEN_OVERRIDE_ENV_VAR: &str = "PRONO_CLI_ADMIN_TOKEN";
 static CONFIG_FILENAME: &str = "secure_config.toml";
 
 #[derive(Default)]
 pub struct ConfigReader {}
 
+/// Parses a non-empty environment variable, an unparsable value is logged and ignored.
+fn parse_env_var<T>(name: &str) -> Option<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    parse_override(name, std::env::var(name).ok())
+}
+
+/// Parses the `value` of the override `name` when it is not empty, an unparsable value is logged and ignored.
+fn parse_override<T>(name: &str, value: Option<String>) -> Option<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    let value = value.filter(|s| !s.is_empty())?;
+    value.parse().map_err(|e| warn!("Ignoring {name}: {e}")).ok()
+}
+
+fn default_config_path() -> path::PathBuf {
+    let path = dirs::config_dir().unwrap().join("prono").join(CONFIG_FILENAME);
+    debug!("Default config path: {}", path.display());
+    path
+}
+
 impl ReadConfig<SecureConfig> for ConfigReader {
     fn default_config_path(&self) -> path::PathBuf {
-        let path = dirs::config_dir().unwrap().join("prono").join(CONFIG_FILENAME);
-        debug!("Default config path: {}", path.display());
-        path
+        default_config_path()
     }
 
     fn read<P: AsRef<path::Path>>(&self, config: P) -> SecureConfig {
         let non_empty = |s: String| if s.is_empty() { None } else { Some(s.into()) };
+        let non_empty_path = |s: String| if s.is_empty() { None } else { Some(s.into()) };
         let overrides = crate::db_config::Overrides {
             host: std::env::var(HOST_OVERRIDE_ENV_VAR).ok().and_then(non_empty),
             port: std::env::var(PORT_OVERRIDE_ENV_VAR).ok().and_then(non_empty),
             user: std::env::var(USER_OVERRIDE_ENV_VAR).ok().and_then(non_empty),
             pass: std::env::var(PASS_OVERRIDE_ENV_VAR).ok().and_then(non_empty),
+            ssl_mode: parse_env_var(SSL_MODE_OVERRIDE_ENV_VAR),
+            ca_cert: std::env::var(CA_CERT_OVERRIDE_ENV_VAR).ok().and_then(non_empty_path),
+            client_cert: std::env::var(CLIENT_CERT_OVERRIDE_ENV_VAR)
+                .ok()
+                .and_then(non_empty_path),
+            client_key: std::env::var(CLIENT_KEY_OVERRIDE_ENV_VAR).ok().and_then(non_empty_path),
+        };
+        let backend_overrides = crate::backend_config::Overrides {
+            listen_address: std::env::var(LISTEN_ADDRESS_OVERRIDE_ENV_VAR)
+                .ok()
+                .filter(|s| !s.is_empty()),
+            port: parse_env_var(BACKEND_PORT_OVERRIDE_ENV_VAR),
+            allowed_origins: std::env::var(ALLOWED_ORIGINS_OVERRIDE_ENV_VAR)
+                .ok()
+                .filter(|s| !s.is_empty())
+                .map(|s| s.split(',').map(|origin| origin.trim().to_string()).collect()),
+            max_body_size: parse_env_var(MAX_BODY_SIZE_OVERRIDE_ENV_VAR),
+            workers: parse_env_var(WORKERS_OVERRIDE_ENV_VAR),
+            session_ttl_secs: parse_env_var(SESSION_TTL_OVERRIDE_ENV_VAR),
+            challenge_difficulty: parse_env_var(CHALLENGE_DIFFICULTY_OVERRIDE_ENV_VAR),
+            challenge_ttl_secs: parse_env_var(CHALLENGE_TTL_OVERRIDE_ENV_VAR),
+            max_devices_per_user: parse_env_var(MAX_DEVICES_OVERRIDE_ENV_VAR),
+            transfer_code_ttl_secs: parse_env_var(TRANSFER_CODE_TTL_OVERRIDE_ENV_VAR),
+            admin_token: std::env::var(ADMIN_TOKEN_OVERRIDE_ENV_VAR)
+                .ok()
+                .filter(|s| !s.is_empty()),
+            stats_min_answers: parse_env_var(STATS_MIN_ANSWERS_OVERRIDE_ENV_VAR),
+            alias_secret: std::env::var(ALIAS_SECRET_OVERRIDE_ENV_VAR)
+                .ok()
+                .filter(|s| !s.is_empty()),
+            rate_limit_enabled: parse_env_var(RATE_LIMIT_ENABLED_OVERRIDE_ENV_VAR),
+            rate_limit_store: parse_env_var(RATE_LIMIT_STORE_OVERRIDE_ENV_VAR),
+            tls_cert: std::env::var(TLS_CERT_OVERRIDE_ENV_VAR).ok().and_then(non_empty_path),
+            tls_key: std::env::var(TLS_KEY_OVERRIDE_ENV_VAR).ok().and_then(non_empty_path),
+            http_redirect_port: parse_env_var(HTTP_REDIRECT_PORT_OVERRIDE_ENV_VAR),
         };
 
         let config_path = config.as_ref();
@@ -48,32 +131,110 @@ impl ReadConfig<SecureConfig> for ConfigReader {
 
         if let Some(secure_config) = parsed {
             info!("Some or no secret environment vars are set. Read remaining config from secure_config.toml");
-            secure_config.override_db_config(overrides)
+            secure_config
+                .override_db_config(overrides)
+                .override_backend_config(backend_overrides)
         } else {
             info!("No/invalid secure config file, read secret environment vars...");
             SecureConfig {
                 db: overrides
                     .try_into()
                     .expect("expect all overrides are set through env vars"),
+                backend: backend_overrides.into(),
             }
         }
     }
 }
 
+/// Reads the `[cli]` section only, so that remote mode works without database settings.
+impl ReadConfig<cli_config::Config> for ConfigReader {
+    fn default_config_path(&self) -> path::PathBuf {
+        default_config_path()
+    }
+
+    fn read<P: AsRef<path::Path>>(&self, config: P) -> cli_config::Config {
+        let overrides = cli_config::Overrides {
+            remote: std::env::var(CLI_REMOTE_OVERRIDE_ENV_VAR)
+                .ok()
+                .filter(|s| !s.is_empty()),
+            admin_token: std::env::var(CLI_ADMIN_TOKEN_OVERRIDE_ENV_VAR)
+                .ok()
+                .filter(|s| !s.is_empty()),
+        };
+
+        let mut cli_config = fs::read_to_string(config.as_ref())
+            .ok()
+            .and_then(|s| {
+                toml::from_str::<cli_config::ConfigFile>(&s)
+                    .map_err(|e| warn!("Failed to parse cli config: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default()
+            .cli;
+        cli_config.apply_overrides(overrides);
+        cli_config
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use std::path::Path;
 
-    #[test]
-    fn test_read_valid_config_file() {
-        let filename = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/.."))
+    fn test_config_path() -> std::path::PathBuf {
+        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/.."))
             .join(file!())
             .parent()
             .unwrap()
-            .join("test_config.toml");
-        ConfigReader {}.read(&filename);
+            .join("test_config.toml")
+    }
+
+    #[test]
+    fn test_read_valid_config_file() {
+        let _config: SecureConfig = ConfigReader {}.read(test_config_path());
+    }
+
+    #[test]
+    fn test_read_tls_options_from_config_file() {
+        let config: SecureConfig = ConfigReader {}.read(test_config_path());
+
+        assert_eq!(config.db.ssl_mode, crate::db_config::SslMode::VerifyCa);
+        assert_eq!(config.db.ca_cert, Some(Path::new("/etc/prono/ca.pem").to_path_buf()));
+        assert!(config.db.client_cert.is_none());
+    }
+
+    #[test]
+    fn test_read_backend_section_from_config_file() {
+        let config: SecureConfig = ConfigReader {}.read(test_config_path());
+
+        assert_eq!(config.backend.port, 9000);
+        assert_eq!(config.backend.allowed_origins, ["https://prono.example"]);
+        let tls = config.backend.tls.expect("tls section should be parsed");
+        assert_eq!(tls.cert, Path::new("/etc/prono/cert.pem"));
+        assert_eq!(tls.key, Path::new("/etc/prono/key.pem"));
+        assert_eq!(tls.http_redirect_port, Some(8080));
+        assert_eq!(
+            config.backend.rate_limit.per_ip,
+            crate::repo::RateLimit {
+                burst: 20,
+                per_minute: std::num::NonZeroU32::new(10).unwrap()
+            }
+        );
+    }
+
+    #[test]
+    fn test_read_cli_section_from_config_file() {
+        let config: cli_config::Config = ConfigReader {}.read(test_config_path());
+
+        assert_eq!(config.remote.as_deref(), Some("https://nas.local:8081"));
+    }
+
+    #[test]
+    fn test_read_cli_config_without_config_file_is_local() {
+        let config: cli_config::Config = ConfigReader {}.read(Path::new("file_does_not_exist.toml"));
+
+        assert!(config.remote.is_none());
     }
 
     #[test]
@@ -81,6 +242,14 @@ mod tests {
     fn test_read_without_config_file_fails() {
         generic::add_panic_hook();
 
-        ConfigReader {}.read(Path::new("file_does_not_exist.toml"));
+        let _config: SecureConfig = ConfigReader {}.read(Path::new("file_does_not_exist.toml"));
+    }
+
+    #[test]
+    fn test_parse_override_ignores_missing_empty_and_invalid_values() {
+        assert_eq!(parse_override::<u16>("PORT", Some("42".to_string())), Some(42));
+        assert_eq!(parse_override::<u16>("PORT", Some("forty-two".to_string())), None);
+        assert_eq!(parse_override::<u16>("PORT", Some(String::new())), None);
+        assert_eq!(parse_override::<u16>("PORT", None), None);
     }
 }
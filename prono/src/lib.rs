@@ -12,8 +12,24 @@ use tokio::spawn;
 
 static SURVEY_CONFIG: &str = include_str!("./surveys/survey_spacex_starship.json");
 
-use std::sync::mpsc::{self, Receiver, Sender};
-
+/// The survey of [`SURVEY_CONFIG`], parsed once.
+static EMPTY_SURVEY: LazyLock<prono_api::Survey> = LazyLock::new(|| {
+    let survey: Survey = FileSurvey::create_from_file(SURVEY_CONFIG).into();
+    survey.into()
+});
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::time::{Duration, Instant};
+
+// Defined in `prono_api` for the web build of the app, which cannot depend on this crate.
+pub use prono_api::{Username, UsernameError};
+pub use use_cases::credentials;
+pub use use_cases::privacy;
+pub use use_cases::stats;
+pub use use_cases::users;
 #[cfg(debug_assertions)]
 pub use use_cases::*;
 
@@ -28,8 +44,43 @@ use crate::repo::Db;
 pub struct SyncPronoAdapter {
     req_tx: Sender<Request>,
     startup_warning: Option<String>,
+    event_poll: EventPoll,
+    user_statuses: RefCell<HashMap<String, UserStatusLookup>>,
+    /// Receivers of the outcomes of submitted answers, until [`prono_api::Surveys::take_errors`] sees them.
+    pending_answers: Vec<Receiver<PronoResult<()>>>,
+}
+
+/// Interval at which [`prono_api::Surveys::take_events`] checks the database for new answers and resolutions.
+const EVENT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+type UserAnswers = Vec<(String, Answer)>;
+
+/// Answers and resolutions seen so far by [`prono_api::Surveys::take_events`]. Without a backend to push
+/// changes, they are found by polling the answers of every question and the resolved questions.
+#[derive(Default)]
+struct EventPoll {
+    last_poll: Option<Instant>,
+    /// Question id and receiver of its answers, for every question of a running poll.
+    pending: Vec<(String, Receiver<UserAnswers>)>,
+    /// Receiver of the resolved questions during a running poll.
+    pending_resolutions: Option<Receiver<Vec<(String, Answer)>>>,
+    /// Question id and user of every reported answer.
+    seen: HashSet<(String, String)>,
+    /// Reported outcome of every resolved question.
+    resolved: HashMap<String, Answer>,
 }
 
+/// Last lookup of a username started by [`prono_api::Surveys::refresh_user_status`].
+enum UserStatusLookup {
+    Pending(Receiver<PronoResult<prono_api::UserStatus>>),
+    Done(Result<prono_api::UserStatus, prono_api::Error>),
+}
+
+/// Repository the adapter's background thread works on.
+trait Repo: repo::Surveys + repo::Accounts + repo::SurveyStates {}
+
+impl<T: repo::Surveys + repo::Accounts + repo::SurveyStates> Repo for T {}
+
 enum Request {
     AddAnswer {
         user: String,
@@ -42,10 +93,32 @@ enum Request {
         survey_id: u64,
         resp: Sender<Option<Survey>>,
     },
+    /// Answers as the privacy of the survey allows to show them.
     AllAnswers {
         question_id: String,
         resp: Sender<Vec<(String, Answer)>>,
     },
+    Privacy {
+        resp: Sender<repo::Privacy>,
+    },
+    /// Outcomes of the resolved questions of the survey.
+    Resolutions {
+        resp: Sender<Vec<(String, Answer)>>,
+    },
+    UserStatus {
+        user: String,
+        resp: Sender<PronoResult<prono_api::UserStatus>>,
+    },
+    Register {
+        user: String,
+        password: String,
+        resp: Sender<PronoResult<Username>>,
+    },
+    Login {
+        user: String,
+        password: String,
+        resp: Sender<PronoResult<Username>>,
+    },
 }
 
 impl SyncPronoAdapter {
@@ -58,21 +131,29 @@ impl SyncPronoAdapter {
     /// on the adapter's background runtime using the provided `config`.
     ///
     /// Call sites supply the concrete DB implementation type as a type
-    /// parameter, e.g. `SyncPronoAdapter::new_with_db_config::<prono_db::MysqlDb>(cfg)`.
+    /// parameter, e.g. `SyncPronoAdapter::new_with_db_config::<prono_db::MysqlDb>(cfg, secret)`.
+    ///
+    /// Aliases of pseudonymous surveys are derived from `alias_secret`, the `alias_secret` of the backend config,
+    /// so they match the backend's. Without it, they only stay the same while the app runs.
     ///
     /// # Errors
     ///
-    /// This function will return an error if the database initialization fails.
-    pub async fn new_with_db_config<D>(config: D::Config) -> PronoResult<Self>
+    /// This function will return an error if the database initialization fails, or no alias secret can be
+    /// generated.
+    pub async fn new_with_db_config<D>(config: D::Config, alias_secret: Option<String>) -> PronoResult<Self>
     where
-        D: repo::Db + 'static,
+        D: repo::Db + repo::Accounts + repo::SurveyStates + 'static,
         D::Config: Send + 'static,
     {
         let (req_tx, req_rx) = mpsc::channel::<Request>();
+        let survey_id = empty_survey().id;
+        let alias_secret = match alias_secret {
+            Some(secret) => secret,
+            None => credentials::new_token()?,
+        };
 
-        #[allow(unused_mut)]
         let mut startup_warning = None;
-        let db: Box<dyn repo::Surveys + Send + Sync> = match D::init(config).await {
+        let db: Box<dyn Repo> = match D::init(config).await {
             Ok(db) => Box::new(db),
             #[allow(unused)]
             Err(err) => {
@@ -82,6 +163,9 @@ impl SyncPronoAdapter {
                     return Ok(Self {
                         req_tx,
                         startup_warning,
+                        event_poll: EventPoll::default(),
+                        user_statuses: RefCell::default(),
+                        pending_answers: Vec::new(),
                     });
                 }
                 #[cfg(debug_assertions)]
@@ -93,6 +177,7 @@ impl SyncPronoAdapter {
                 }
             }
         };
+        startup_warning = add_privacy_warning(db.as_ref(), survey_id, startup_warning).await;
 
         // Task not 100% needed if the app requires a database connection
         spawn(async move {
@@ -117,7 +202,37 @@ impl SyncPronoAdapter {
                     Request::AllAnswers { question_id, resp } => {
                         let result = db.all_answers(question_id).await;
                         let converted = result.into_iter().map(|(u, a)| (u, a.into())).collect();
-                        let _ = resp.send(converted);
+                        let privacy = survey_privacy(db.as_ref(), survey_id).await;
+                        let visible = privacy::visible_answers(privacy, &alias_secret, survey_id, converted);
+                        let _ = resp.send(visible.unwrap_or_default());
+                    }
+                    Request::Privacy { resp } => {
+                        let _ = resp.send(survey_privacy(db.as_ref(), survey_id).await);
+                    }
+                    Request::Resolutions { resp } => match db.resolutions(survey_id).await {
+                        Ok(resolutions) => {
+                            let _ = resp.send(resolutions.into_iter().map(|(id, a)| (id, a.into())).collect());
+                        }
+                        Err(e) => error!("Failed to read the resolved questions of survey [{survey_id}]: {e}"),
+                    },
+                    Request::UserStatus { user, resp } => {
+                        let result = match credentials::parse_username(&user) {
+                            Ok(username) => {
+                                users::user_status(db.as_ref(), db.as_ref(), &empty_survey(), username.as_str()).await
+                            }
+                            Err(e) => Err(e),
+                        };
+                        // Answers are stored without binding names to devices, so any name is this device's own.
+                        let _ = resp.send(result.map(|status| prono_api::UserStatus {
+                            this_device: status.exists,
+                            ..status
+                        }));
+                    }
+                    Request::Register { user, password, resp } => {
+                        let _ = resp.send(credentials::register(db.as_ref(), &user, &password).await);
+                    }
+                    Request::Login { user, password, resp } => {
+                        let _ = resp.send(credentials::verify_login(db.as_ref(), &user, &password).await);
                     }
                 }
             }
@@ -126,11 +241,15 @@ impl SyncPronoAdapter {
         Ok(Self {
             req_tx,
             startup_warning,
+            event_poll: EventPoll::default(),
+            user_statuses: RefCell::default(),
+            pending_answers: Vec::new(),
         })
     }
 
     /// Returns a warning message if the database connection failed at startup
-    /// and a fallback was used (debug builds only).
+    /// and a fallback was used (debug builds only), or the privacy of the
+    /// survey could not be read, so answers of others are hidden.
     #[must_use]
     pub fn startup_warning(&self) -> Option<&str> {
         self.startup_warning.as_deref()
@@ -168,13 +287,115 @@ impl SyncPronoAdapter {
         let _ = self.req_tx.send(Request::AllAnswers { question_id, resp: tx });
         rx
     }
+
+    /// Request the resolved questions of the survey; returns a receiver you can `try_recv` on.
+    #[must_use]
+    pub fn request_resolutions(&self) -> Receiver<Vec<(String, Answer)>> {
+        let (tx, rx) = mpsc::channel();
+        let _ = self.req_tx.send(Request::Resolutions { resp: tx });
+        rx
+    }
+
+    /// Request the status of a username; returns a receiver you can `try_recv` on.
+    #[must_use]
+    pub fn request_user_status(&self, user: &str) -> Receiver<PronoResult<prono_api::UserStatus>> {
+        let (tx, rx) = mpsc::channel();
+        let _ = self.req_tx.send(Request::UserStatus {
+            user: user.to_string(),
+            resp: tx,
+        });
+        rx
+    }
+
+    /// Sign-in against the accounts in the same database.
+    #[must_use]
+    pub fn auth(&self) -> SyncAuthAdapter {
+        SyncAuthAdapter {
+            req_tx: self.req_tx.clone(),
+            state: RefCell::new(prono_api::SessionState::SignedOut),
+            pending: RefCell::new(None),
+        }
+    }
+}
+
+/// Privacy of the survey, answers are hidden when it cannot be read.
+async fn survey_privacy(db: &dyn Repo, survey_id: u64) -> repo::Privacy {
+    db.privacy(survey_id).await.unwrap_or_else(|e| {
+        error!("Failed to read the privacy of survey [{survey_id}]: {e}");
+        repo::Privacy::AggregateOnly
+    })
+}
+
+/// `warning` extended with a warning when the privacy of the survey cannot be read, as its answers are then
+/// hidden by [`survey_privacy`].
+async fn add_privacy_warning(db: &dyn Repo, survey_id: u64, warning: Option<String>) -> Option<String> {
+    let Err(e) = db.privacy(survey_id).await else {
+        return warning;
+    };
+    let msg = format!("Failed to read the privacy of the survey: {e}. Answers of others are hidden.");
+    error!("{msg}");
+    Some(match warning {
+        Some(warning) => format!("{warning} {msg}"),
+        None => msg,
+    })
+}
+
+/// [`prono_api::Auth`] for the native app, which checks credentials directly in the database.
+pub struct SyncAuthAdapter {
+    req_tx: Sender<Request>,
+    state: RefCell<prono_api::SessionState>,
+    pending: RefCell<Option<Receiver<PronoResult<Username>>>>,
+}
+
+impl SyncAuthAdapter {
+    fn request(&mut self, make_request: impl FnOnce(Sender<PronoResult<Username>>) -> Request) {
+        let (tx, rx) = mpsc::channel();
+        let _ = self.req_tx.send(make_request(tx));
+        *self.pending.get_mut() = Some(rx);
+        *self.state.get_mut() = prono_api::SessionState::Pending;
+    }
+}
+
+impl prono_api::Auth for SyncAuthAdapter {
+    fn register(&mut self, user: &str, password: &str) {
+        self.request(|resp| Request::Register {
+            user: user.to_string(),
+            password: password.to_string(),
+            resp,
+        });
+    }
+
+    fn login(&mut self, user: &str, password: &str) {
+        self.request(|resp| Request::Login {
+            user: user.to_string(),
+            password: password.to_string(),
+            resp,
+        });
+    }
+
+    fn logout(&mut self) {
+        *self.pending.get_mut() = None;
+        *self.state.get_mut() = prono_api::SessionState::SignedOut;
+    }
+
+    fn session(&self) -> prono_api::SessionState {
+        let mut pending = self.pending.borrow_mut();
+        let outcome = pending.as_ref().and_then(|rx| rx.try_recv().ok());
+        if let Some(result) = outcome {
+            *pending = None;
+            *self.state.borrow_mut() = match result {
+                Ok(username) => prono_api::SessionState::SignedIn { user: username.into() },
+                Err(e) => prono_api::SessionState::Failed(e.into()),
+            };
+        }
+        self.state.borrow().clone()
+    }
 }
 
 /// Returns an empty survey template parsed from the embedded survey JSON.
 #[must_use]
 pub fn empty_survey() -> prono_api::Survey {
-    let survey: Survey = FileSurvey::create_from_file(SURVEY_CONFIG).into();
-    survey.into()
+    EMPTY_SURVEY.clone()
 }
 
 // It will issue requests to the background thread and try to `try_recv` the per-call
@@ -182,16 +403,12 @@ pub fn empty_survey() -> prono_api::Survey {
 // This keeps the GUI thread non-blocking while allowing callers to poll for results.
 impl prono_api::Surveys for SyncPronoAdapter {
     fn empty_survey(&self) -> prono_api::Survey {
-        let survey: Survey = FileSurvey::create_from_file(SURVEY_CONFIG).into();
-
-        survey.into()
+        empty_survey()
     }
 
     fn add_answer(&mut self, user: &str, question_id: String, answer: prono_api::Answer) {
         let rx = self.request_add_answer(user, question_id, answer.into());
-        if let Ok(Err(e)) = rx.try_recv() {
-            error!("Failed to add answer: {e}");
-        }
+        self.pending_answers.push(rx);
     }
 
     fn response(&self, user: &str, id: u64) -> Option<prono_api::Survey> {
@@ -220,6 +437,110 @@ impl prono_api::Surveys for SyncPronoAdapter {
             }
         }
     }
+
+    fn privacy(&self, survey_id: u64) -> prono_api::Privacy {
+        if survey_id != empty_survey().id {
+            return prono_api::Privacy::default();
+        }
+        let (tx, rx) = mpsc::channel();
+        let _ = self.req_tx.send(Request::Privacy { resp: tx });
+        rx.recv().unwrap_or(repo::Privacy::AggregateOnly).into()
+    }
+
+    fn refresh_user_status(&mut self, user: &str) {
+        let lookup = UserStatusLookup::Pending(self.request_user_status(user));
+        self.user_statuses.get_mut().insert(user.to_string(), lookup);
+    }
+
+    fn user_status(&self, user: &str) -> Option<Result<prono_api::UserStatus, prono_api::Error>> {
+        let mut lookups = self.user_statuses.borrow_mut();
+        let lookup = lookups.get_mut(user)?;
+        if let UserStatusLookup::Pending(rx) = lookup {
+            let outcome = match rx.try_recv() {
+                Ok(outcome) => outcome.map_err(prono_api::Error::from),
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => {
+                    Err(prono_api::Error::Unavailable("the database thread stopped".to_string()))
+                }
+            };
+            if let Err(e) = &outcome {
+                error!("Failed to look up username {user}: {e}");
+            }
+            *lookup = UserStatusLookup::Done(outcome);
+        }
+        match lookup {
+            UserStatusLookup::Done(outcome) => Some(outcome.clone()),
+            UserStatusLookup::Pending(_) => None,
+        }
+    }
+
+    fn take_events(&mut self, survey_id: u64) -> Vec<prono_api::SurveyEvent> {
+        if EMPTY_SURVEY.id != survey_id {
+            return Vec::new();
+        }
+        let poll_due = self
+            .event_poll
+            .last_poll
+            .is_none_or(|last_poll| last_poll.elapsed() >= EVENT_POLL_INTERVAL);
+        if self.event_poll.pending.is_empty() && self.event_poll.pending_resolutions.is_none() && poll_due {
+            self.event_poll.last_poll = Some(Instant::now());
+            self.event_poll.pending = EMPTY_SURVEY
+                .questions
+                .iter()
+                .map(|question| (question.id.clone(), self.request_all_answers(question.id.clone())))
+                .collect();
+            self.event_poll.pending_resolutions = Some(self.request_resolutions());
+        }
+
+        let mut events = Vec::new();
+        let seen = &mut self.event_poll.seen;
+        self.event_poll.pending.retain(|(question_id, rx)| match rx.try_recv() {
+            Ok(answers) => {
+                for (user, answer) in answers {
+                    if seen.insert((question_id.clone(), user.clone())) {
+                        events.push(prono_api::SurveyEvent::AnswerAdded {
+                            question_id: question_id.clone(),
+                            user,
+                            answer: answer.into(),
+                        });
+                    }
+                }
+                false
+            }
+            Err(TryRecvError::Empty) => true,
+            Err(TryRecvError::Disconnected) => false,
+        });
+        let resolutions = match self.event_poll.pending_resolutions.as_ref().map(Receiver::try_recv) {
+            Some(Ok(resolutions)) => resolutions,
+            Some(Err(TryRecvError::Empty)) | None => return events,
+            Some(Err(TryRecvError::Disconnected)) => Vec::new(),
+        };
+        self.event_poll.pending_resolutions = None;
+        for (question_id, answer) in resolutions {
+            if self.event_poll.resolved.get(&question_id) != Some(&answer) {
+                self.event_poll.resolved.insert(question_id.clone(), answer.clone());
+                events.push(prono_api::SurveyEvent::QuestionResolved {
+                    question_id,
+                    answer: answer.into(),
+                });
+            }
+        }
+        events
+    }
+
+    fn take_errors(&mut self) -> Vec<prono_api::Error> {
+        let mut errors = Vec::new();
+        self.pending_answers.retain(|rx| match rx.try_recv() {
+            Err(TryRecvError::Empty) => true,
+            Ok(Err(e)) => {
+                error!("Failed to add answer: {e}");
+                errors.push(e.into());
+                false
+            }
+            Ok(Ok(())) | Err(TryRecvError::Disconnected) => false,
+        });
+        errors
+    }
 }
 
 #[cfg(test)]
@@ -236,7 +557,7 @@ mod tests {
     #[cfg(debug_assertions)]
     #[tokio::test]
     async fn test_sync_prono_adapter_with_fake_db() {
-        let adapter = SyncPronoAdapter::new_with_db_config::<fake_db::FakeRepo>(())
+        let adapter = SyncPronoAdapter::new_with_db_config::<fake_db::FakeRepo>((), None)
             .await
             .unwrap();
 
@@ -249,7 +570,7 @@ mod tests {
     #[cfg(debug_assertions)]
     #[tokio::test(flavor = "multi_thread")]
     async fn test_sync_prono_adapter_add_and_retrieve() {
-        let mut adapter = SyncPronoAdapter::new_with_db_config::<fake_db::FakeRepo>(())
+        let mut adapter = SyncPronoAdapter::new_with_db_config::<fake_db::FakeRepo>((), None)
             .await
             .unwrap();
 
@@ -269,5 +590,153 @@ mod tests {
         let answers = prono_api::Surveys::all_answers(&adapter, qid);
         assert_eq!(answers.len(), 1);
         assert_eq!(answers[0].0, "testuser");
+        assert_eq!(
+            prono_api::Surveys::privacy(&adapter, survey.id),
+            prono_api::Privacy::Public
+        );
+    }
+
+    /// Events of one poll of the adapter, waiting for all its answers to arrive.
+    #[cfg(debug_assertions)]
+    fn take_polled_events(adapter: &mut SyncPronoAdapter, survey_id: u64) -> Vec<prono_api::SurveyEvent> {
+        let mut events = prono_api::Surveys::take_events(adapter, survey_id);
+        while !adapter.event_poll.pending.is_empty() || adapter.event_poll.pending_resolutions.is_some() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            events.extend(prono_api::Surveys::take_events(adapter, survey_id));
+        }
+        events
+    }
+
+    #[cfg(debug_assertions)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_sync_prono_adapter_reports_new_answers_once() {
+        use prono_api::Surveys;
+
+        let mut adapter = SyncPronoAdapter::new_with_db_config::<fake_db::FakeRepo>((), None)
+            .await
+            .unwrap();
+        let survey = adapter.empty_survey();
+        let qid = survey.questions[0].id.clone();
+        let answer = prono_api::Answer::Text("hello".to_string());
+        adapter.add_answer("testuser", qid.clone(), answer.clone());
+
+        assert_eq!(
+            take_polled_events(&mut adapter, survey.id),
+            [prono_api::SurveyEvent::AnswerAdded {
+                question_id: qid,
+                user: "testuser".to_string(),
+                answer,
+            }]
+        );
+
+        adapter.event_poll.last_poll = None;
+        assert!(take_polled_events(&mut adapter, survey.id).is_empty());
+        assert!(adapter.take_events(survey.id + 1).is_empty());
+    }
+
+    /// Errors of the answers submitted to the adapter, waiting for all of them to be stored or refused.
+    #[cfg(debug_assertions)]
+    fn take_answer_errors(adapter: &mut SyncPronoAdapter) -> Vec<prono_api::Error> {
+        let mut errors = prono_api::Surveys::take_errors(adapter);
+        while !adapter.pending_answers.is_empty() {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            errors.extend(prono_api::Surveys::take_errors(adapter));
+        }
+        errors
+    }
+
+    #[cfg(debug_assertions)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_sync_prono_adapter_reports_refused_answers() {
+        use prono_api::Surveys;
+
+        let mut adapter = SyncPronoAdapter::new_with_db_config::<fake_db::FakeRepo>((), None)
+            .await
+            .unwrap();
+        let qid = adapter.empty_survey().questions[0].id.clone();
+        let answer = prono_api::Answer::Text("hello".to_string());
+
+        adapter.add_answer("testuser", qid.clone(), answer.clone());
+        assert!(take_answer_errors(&mut adapter).is_empty());
+        adapter.add_answer("testuser", qid, answer);
+        assert_eq!(take_answer_errors(&mut adapter), [prono_api::Error::AnswerExists]);
+        assert!(adapter.take_errors().is_empty());
+    }
+
+    #[cfg(debug_assertions)]
+    fn wait_for_user_status(
+        adapter: &SyncPronoAdapter,
+        user: &str,
+    ) -> Option<Result<prono_api::UserStatus, prono_api::Error>> {
+        for _ in 0..100 {
+            if let Some(outcome) = prono_api::Surveys::user_status(adapter, user) {
+                return Some(outcome);
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        None
+    }
+
+    #[cfg(debug_assertions)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_sync_prono_adapter_looks_up_user_status_again_on_refresh() {
+        use prono_api::Surveys;
+
+        let mut adapter = SyncPronoAdapter::new_with_db_config::<fake_db::FakeRepo>((), None)
+            .await
+            .unwrap();
+        assert_eq!(adapter.user_status("testuser"), None);
+
+        adapter.refresh_user_status("testuser");
+        assert!(!wait_for_user_status(&adapter, "testuser").unwrap().unwrap().exists);
+
+        let qid = adapter.empty_survey().questions[0].id.clone();
+        adapter.add_answer("testuser", qid, prono_api::Answer::Text("hello".to_string()));
+        assert!(take_answer_errors(&mut adapter).is_empty());
+        assert!(!adapter.user_status("testuser").unwrap().unwrap().exists);
+
+        adapter.refresh_user_status("testuser");
+        assert!(wait_for_user_status(&adapter, "testuser").unwrap().unwrap().exists);
+    }
+
+    #[cfg(debug_assertions)]
+    fn wait_for_session(auth: &SyncAuthAdapter) -> prono_api::SessionState {
+        for _ in 0..100 {
+            let state = prono_api::Auth::session(auth);
+            if state != prono_api::SessionState::Pending {
+                return state;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        prono_api::SessionState::Pending
+    }
+
+    #[cfg(debug_assertions)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_sync_auth_adapter_register_login_and_logout() {
+        use prono_api::{Auth, SessionState};
+
+        let adapter = SyncPronoAdapter::new_with_db_config::<fake_db::FakeRepo>((), None)
+            .await
+            .unwrap();
+        let mut auth = adapter.auth();
+        assert_eq!(auth.session(), SessionState::SignedOut);
+
+        auth.register("sam", "long enough");
+        assert_eq!(
+            wait_for_session(&auth),
+            SessionState::SignedIn {
+                user: "sam".to_string()
+            }
+        );
+
+        auth.logout();
+        assert_eq!(auth.session(), SessionState::SignedOut);
+
+        auth.login("sam", "wrong password");
+        assert_eq!(
+            wait_for_session(&auth),
+            SessionState::Failed(prono_api::Error::InvalidCredentials)
+        );
     }
 }
@@ -1,3 +1,5 @@
+pub mod backend_config;
+pub mod cli_config;
 mod config_read;
 pub mod db_config;
 mod errors;
@@ -0,0 +1,476 @@
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::repo::RateLimit;
+
+static DEFAULT_LISTEN_ADDRESS: &str = "0.0.0.0";
+static DEFAULT_PORT: u16 = 8081;
+/// Where the web app is published, and where `trunk serve` serves it during development.
+static DEFAULT_ALLOWED_ORIGINS: [&str; 4] = [
+    "https://jaques-sam.github.io",
+    "https://prono-app.samagali.myds.me",
+    "http://127.0.0.1:8080",
+    "http://localhost:8080",
+];
+static DEFAULT_MAX_BODY_SIZE: usize = 16 * 1024;
+static DEFAULT_CERT_RELOAD_INTERVAL_SECS: u64 = 60;
+static DEFAULT_SESSION_TTL_SECS: u64 = 30 * 24 * 60 * 60;
+static DEFAULT_CHALLENGE_DIFFICULTY: u8 = 16;
+static DEFAULT_CHALLENGE_TTL_SECS: u64 = 5 * 60;
+static DEFAULT_MAX_DEVICES_PER_USER: usize = 3;
+static DEFAULT_TRANSFER_CODE_TTL_SECS: u64 = 10 * 60;
+/// Below this, the median of a question is close to the prediction of a single, often known, user.
+static DEFAULT_STATS_MIN_ANSWERS: usize = 5;
+/// Every answer, challenge, registration and login takes a token, so a client IP may fill in a few complete
+/// surveys at once.
+static DEFAULT_RATE_LIMIT_PER_IP: RateLimit = RateLimit {
+    burst: 60,
+    per_minute: NonZeroU32::new(30).unwrap(),
+};
+static DEFAULT_RATE_LIMIT_PER_USER: RateLimit = RateLimit {
+    burst: 10,
+    per_minute: NonZeroU32::new(4).unwrap(),
+};
+
+#[derive(Deserialize)]
+#[serde(default)]
+#[cfg_attr(test, derive(Clone, Debug, PartialEq, Eq))]
+pub struct Config {
+    pub listen_address: String,
+    pub port: u16,
+    /// Origins allowed by CORS, `*` allows any origin.
+    pub allowed_origins: Vec<String>,
+    /// Maximum size in bytes of a JSON request body.
+    pub max_body_size: usize,
+    /// Number of HTTP worker threads, defaults to the number of physical CPU cores.
+    pub workers: Option<usize>,
+    /// Lifetime of a login session token.
+    pub session_ttl_secs: u64,
+    /// Leading zero bits of the proof of work required to submit an answer, every bit doubles the work.
+    pub challenge_difficulty: u8,
+    /// Time a client has to solve a proof-of-work challenge.
+    pub challenge_ttl_secs: u64,
+    /// Number of devices a user may submit answers from.
+    pub max_devices_per_user: usize,
+    /// Time a device transfer code stays valid.
+    pub transfer_code_ttl_secs: u64,
+    /// Bearer token of the `/api/admin` routes, the admin API is disabled when not set.
+    pub admin_token: Option<String>,
+    /// Predictions a question needs before the survey statistics include its date statistics.
+    pub stats_min_answers: usize,
+    /// Secret the aliases of users in pseudonymous surveys are derived from. Without it, aliases change every time
+    /// the backend starts.
+    pub alias_secret: Option<String>,
+    pub rate_limit: RateLimitConfig,
+    pub tls: Option<TlsConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            listen_address: DEFAULT_LISTEN_ADDRESS.to_string(),
+            port: DEFAULT_PORT,
+            allowed_origins: DEFAULT_ALLOWED_ORIGINS.iter().map(ToString::to_string).collect(),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            workers: None,
+            session_ttl_secs: DEFAULT_SESSION_TTL_SECS,
+            challenge_difficulty: DEFAULT_CHALLENGE_DIFFICULTY,
+            challenge_ttl_secs: DEFAULT_CHALLENGE_TTL_SECS,
+            max_devices_per_user: DEFAULT_MAX_DEVICES_PER_USER,
+            transfer_code_ttl_secs: DEFAULT_TRANSFER_CODE_TTL_SECS,
+            admin_token: None,
+            stats_min_answers: DEFAULT_STATS_MIN_ANSWERS,
+            alias_secret: None,
+            rate_limit: RateLimitConfig::default(),
+            tls: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct TlsConfig {
+    /// PEM file with the server certificate chain.
+    pub cert: PathBuf,
+    /// PEM file with the private key belonging to `cert`.
+    pub key: PathBuf,
+    /// Plain HTTP port that redirects every request to HTTPS, disabled when not set.
+    pub http_redirect_port: Option<u16>,
+    /// Interval at which the certificate files are checked for changes.
+    #[serde(default = "default_cert_reload_interval_secs")]
+    pub reload_interval_secs: u64,
+}
+
+fn default_cert_reload_interval_secs() -> u64 {
+    DEFAULT_CERT_RELOAD_INTERVAL_SECS
+}
+
+/// Rate limits of answer submissions, challenges, registrations and logins, requests over a limit are rejected
+/// with `429 Too Many Requests`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub store: RateLimitStoreKind,
+    /// Take the client IP address from the `Forwarded` or `X-Forwarded-For` header. Only enable this behind
+    /// a reverse proxy that sets the header, clients can send any value.
+    pub trust_forwarded_headers: bool,
+    pub per_ip: RateLimit,
+    /// Limit per signed in user, and per `X-Device-Id` on the token-free legacy answer route. `per_device` is the
+    /// name of older configs.
+    #[serde(alias = "per_device")]
+    pub per_user: RateLimit,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            store: RateLimitStoreKind::default(),
+            trust_forwarded_headers: false,
+            per_ip: DEFAULT_RATE_LIMIT_PER_IP,
+            per_user: DEFAULT_RATE_LIMIT_PER_USER,
+        }
+    }
+}
+
+/// Where the token buckets of the rate limits are kept.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitStoreKind {
+    /// In the memory of every backend instance, each instance limits on its own.
+    #[default]
+    Memory,
+    /// In the database, shared by all backend instances.
+    Database,
+}
+
+impl FromStr for RateLimitStoreKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "memory" => Ok(Self::Memory),
+            "database" => Ok(Self::Database),
+            _ => Err(format!("unknown rate limit store '{s}'")),
+        }
+    }
+}
+
+impl Config {
+    pub fn apply_overrides(&mut self, overrides: Overrides) {
+        if let Some(listen_address) = overrides.listen_address {
+            self.listen_address = listen_address;
+        }
+        if let Some(port) = overrides.port {
+            self.port = port;
+        }
+        if let Some(allowed_origins) = overrides.allowed_origins {
+            self.allowed_origins = allowed_origins;
+        }
+        if let Some(max_body_size) = overrides.max_body_size {
+            self.max_body_size = max_body_size;
+        }
+        if let Some(workers) = overrides.workers {
+            self.workers = Some(workers);
+        }
+        if let Some(session_ttl_secs) = overrides.session_ttl_secs {
+            self.session_ttl_secs = session_ttl_secs;
+        }
+        if let Some(challenge_difficulty) = overrides.challenge_difficulty {
+            self.challenge_difficulty = challenge_difficulty;
+        }
+        if let Some(challenge_ttl_secs) = overrides.challenge_ttl_secs {
+            self.challenge_ttl_secs = challenge_ttl_secs;
+        }
+        if let Some(max_devices_per_user) = overrides.max_devices_per_user {
+            self.max_devices_per_user = max_devices_per_user;
+        }
+        if let Some(transfer_code_ttl_secs) = overrides.transfer_code_ttl_secs {
+            self.transfer_code_ttl_secs = transfer_code_ttl_secs;
+        }
+        if let Some(admin_token) = overrides.admin_token {
+            self.admin_token = Some(admin_token);
+        }
+        if let Some(stats_min_answers) = overrides.stats_min_answers {
+            self.stats_min_answers = stats_min_answers;
+        }
+        if let Some(alias_secret) = overrides.alias_secret {
+            self.alias_secret = Some(alias_secret);
+        }
+        if let Some(enabled) = overrides.rate_limit_enabled {
+            self.rate_limit.enabled = enabled;
+        }
+        if let Some(store) = overrides.rate_limit_store {
+            self.rate_limit.store = store;
+        }
+        match (overrides.tls_cert, overrides.tls_key, &mut self.tls) {
+            (Some(cert), Some(key), None) => {
+                self.tls = Some(TlsConfig {
+                    cert,
+                    key,
+                    http_redirect_port: None,
+                    reload_interval_secs: DEFAULT_CERT_RELOAD_INTERVAL_SECS,
+                });
+            }
+            (cert, key, Some(tls)) => {
+                if let Some(cert) = cert {
+                    tls.cert = cert;
+                }
+                if let Some(key) = key {
+                    tls.key = key;
+                }
+            }
+            _ => {}
+        }
+        if let (Some(port), Some(tls)) = (overrides.http_redirect_port, &mut self.tls) {
+            tls.http_redirect_port = Some(port);
+        }
+    }
+}
+
+#[derive(Default)]
+#[cfg_attr(test, derive(Clone))]
+pub struct Overrides {
+    pub listen_address: Option<String>,
+    pub port: Option<u16>,
+    pub allowed_origins: Option<Vec<String>>,
+    pub max_body_size: Option<usize>,
+    pub workers: Option<usize>,
+    pub session_ttl_secs: Option<u64>,
+    pub challenge_difficulty: Option<u8>,
+    pub challenge_ttl_secs: Option<u64>,
+    pub max_devices_per_user: Option<usize>,
+    pub transfer_code_ttl_secs: Option<u64>,
+    pub admin_token: Option<String>,
+    pub stats_min_answers: Option<usize>,
+    pub alias_secret: Option<String>,
+    pub rate_limit_enabled: Option<bool>,
+    pub rate_limit_store: Option<RateLimitStoreKind>,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    pub http_redirect_port: Option<u16>,
+}
+
+impl From<Overrides> for Config {
+    fn from(overrides: Overrides) -> Self {
+        let mut config = Config::default();
+        config.apply_overrides(overrides);
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tls_config() -> TlsConfig {
+        TlsConfig {
+            cert: PathBuf::from("cert.pem"),
+            key: PathBuf::from("key.pem"),
+            http_redirect_port: None,
+            reload_interval_secs: DEFAULT_CERT_RELOAD_INTERVAL_SECS,
+        }
+    }
+
+    #[test]
+    fn test_tls_is_disabled_by_default() {
+        assert!(Config::default().tls.is_none());
+    }
+
+    #[test]
+    fn test_defaults_listen_on_all_interfaces_and_allow_published_and_local_app() {
+        let config = Config::default();
+
+        assert_eq!(config.listen_address, "0.0.0.0");
+        assert_eq!(config.port, 8081);
+        assert_eq!(
+            config.allowed_origins,
+            [
+                "https://jaques-sam.github.io",
+                "https://prono-app.samagali.myds.me",
+                "http://127.0.0.1:8080",
+                "http://localhost:8080"
+            ]
+        );
+        assert_eq!(config.max_body_size, 16 * 1024);
+        assert!(config.workers.is_none());
+        assert_eq!(config.session_ttl_secs, 30 * 24 * 60 * 60);
+        assert_eq!(config.challenge_difficulty, 16);
+        assert_eq!(config.challenge_ttl_secs, 300);
+        assert_eq!(config.max_devices_per_user, 3);
+        assert_eq!(config.transfer_code_ttl_secs, 600);
+        assert!(config.admin_token.is_none());
+        assert_eq!(config.stats_min_answers, 5);
+        assert!(config.alias_secret.is_none());
+        assert!(config.rate_limit.enabled);
+        assert_eq!(config.rate_limit.store, RateLimitStoreKind::Memory);
+        assert!(!config.rate_limit.trust_forwarded_headers);
+    }
+
+    #[test]
+    fn test_server_overrides_are_applied() {
+        let mut config = Config::default();
+
+        config.apply_overrides(Overrides {
+            listen_address: Some("127.0.0.1".to_string()),
+            port: Some(9000),
+            allowed_origins: Some(vec!["https://prono.example".to_string()]),
+            max_body_size: Some(1024),
+            workers: Some(2),
+            session_ttl_secs: Some(3600),
+            challenge_difficulty: Some(20),
+            challenge_ttl_secs: Some(60),
+            max_devices_per_user: Some(1),
+            transfer_code_ttl_secs: Some(120),
+            admin_token: Some("admin-secret".to_string()),
+            stats_min_answers: Some(1),
+            alias_secret: Some("alias-secret".to_string()),
+            rate_limit_enabled: Some(false),
+            rate_limit_store: Some(RateLimitStoreKind::Database),
+            ..Overrides::default()
+        });
+
+        assert_eq!(config.listen_address, "127.0.0.1");
+        assert_eq!(config.port, 9000);
+        assert_eq!(config.allowed_origins, ["https://prono.example"]);
+        assert_eq!(config.max_body_size, 1024);
+        assert_eq!(config.workers, Some(2));
+        assert_eq!(config.session_ttl_secs, 3600);
+        assert_eq!(config.challenge_difficulty, 20);
+        assert_eq!(config.challenge_ttl_secs, 60);
+        assert_eq!(config.max_devices_per_user, 1);
+        assert_eq!(config.transfer_code_ttl_secs, 120);
+        assert_eq!(config.admin_token.as_deref(), Some("admin-secret"));
+        assert_eq!(config.stats_min_answers, 1);
+        assert_eq!(config.alias_secret.as_deref(), Some("alias-secret"));
+        assert!(!config.rate_limit.enabled);
+        assert_eq!(config.rate_limit.store, RateLimitStoreKind::Database);
+    }
+
+    #[test]
+    fn test_deserialize_partial_section_keeps_defaults() {
+        let config: Config = toml::from_str(
+            r#"
+            port = 9000
+            allowed_origins = ["https://prono.example"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config,
+            Config {
+                port: 9000,
+                allowed_origins: vec!["https://prono.example".to_string()],
+                ..Config::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_tls_is_enabled_when_cert_and_key_overrides_are_set() {
+        let config: Config = Overrides {
+            tls_cert: Some(PathBuf::from("cert.pem")),
+            tls_key: Some(PathBuf::from("key.pem")),
+            http_redirect_port: Some(8080),
+            ..Overrides::default()
+        }
+        .into();
+
+        assert_eq!(
+            config.tls,
+            Some(TlsConfig {
+                http_redirect_port: Some(8080),
+                ..tls_config()
+            })
+        );
+    }
+
+    #[test]
+    fn test_tls_stays_disabled_when_only_cert_override_is_set() {
+        let config: Config = Overrides {
+            tls_cert: Some(PathBuf::from("cert.pem")),
+            ..Overrides::default()
+        }
+        .into();
+
+        assert!(config.tls.is_none());
+    }
+
+    #[test]
+    fn test_overrides_replace_configured_tls_paths() {
+        let mut config = Config {
+            tls: Some(tls_config()),
+            ..Config::default()
+        };
+
+        config.apply_overrides(Overrides {
+            tls_key: Some(PathBuf::from("renewed.pem")),
+            ..Overrides::default()
+        });
+
+        let tls = config.tls.unwrap();
+        assert_eq!(tls.cert, PathBuf::from("cert.pem"));
+        assert_eq!(tls.key, PathBuf::from("renewed.pem"));
+    }
+
+    #[test]
+    fn test_deserialize_uses_default_reload_interval() {
+        let config: Config = toml::from_str(
+            r#"
+            [tls]
+            cert = "cert.pem"
+            key = "key.pem"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.tls, Some(tls_config()));
+    }
+
+    #[test]
+    fn test_deserialize_partial_rate_limit_section_keeps_defaults() {
+        let config: Config = toml::from_str(
+            r#"
+            [rate_limit]
+            store = "database"
+            per_user = { burst = 4, per_minute = 1 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.rate_limit,
+            RateLimitConfig {
+                store: RateLimitStoreKind::Database,
+                per_user: RateLimit {
+                    burst: 4,
+                    per_minute: NonZeroU32::MIN
+                },
+                ..RateLimitConfig::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_rate_limit_accepts_per_device_of_older_configs() {
+        let config: Config = toml::from_str("[rate_limit]\nper_device = { burst = 4, per_minute = 1 }").unwrap();
+
+        assert_eq!(config.rate_limit.per_user.burst, 4);
+    }
+
+    #[test]
+    fn test_deserialize_rate_limit_without_refill_fails() {
+        assert!(toml::from_str::<Config>("[rate_limit]\nper_ip = { burst = 4, per_minute = 0 }").is_err());
+    }
+
+    #[test]
+    fn test_rate_limit_store_parses_case_insensitively() {
+        assert_eq!("Database".parse(), Ok(RateLimitStoreKind::Database));
+        assert_eq!("memory".parse(), Ok(RateLimitStoreKind::Memory));
+        assert!("redis".parse::<RateLimitStoreKind>().is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use serde::Deserialize;
+
+/// Settings of `prono-cli`, read from the `[cli]` section of the config file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Base URL of a backend to manage through its admin API instead of connecting to the database.
+    pub remote: Option<String>,
+    /// Admin token of the backend at `remote`.
+    pub admin_token: Option<String>,
+}
+
+impl Config {
+    pub fn apply_overrides(&mut self, overrides: Overrides) {
+        if let Some(remote) = overrides.remote {
+            self.remote = Some(remote);
+        }
+        if let Some(admin_token) = overrides.admin_token {
+            self.admin_token = Some(admin_token);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Overrides {
+    pub remote: Option<String>,
+    pub admin_token: Option<String>,
+}
+
+/// The config file, of which only the `[cli]` section is read, so the other sections may be incomplete.
+#[derive(Default, Deserialize)]
+pub(crate) struct ConfigFile {
+    #[serde(default)]
+    pub cli: Config,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_replace_configured_values() {
+        let mut config = Config {
+            remote: Some("https://nas.local:8081".to_string()),
+            admin_token: Some("from-file".to_string()),
+        };
+
+        config.apply_overrides(Overrides {
+            admin_token: Some("from-env".to_string()),
+            ..Overrides::default()
+        });
+
+        assert_eq!(config.remote.as_deref(), Some("https://nas.local:8081"));
+        assert_eq!(config.admin_token.as_deref(), Some("from-env"));
+    }
+
+    #[test]
+    fn test_other_sections_are_ignored() {
+        let file: ConfigFile = toml::from_str(
+            r#"
+            [db]
+            host = "host"
+
+            [cli]
+            remote = "https://nas.local:8081"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(file.cli.remote.as_deref(), Some("https://nas.local:8081"));
+        assert!(file.cli.admin_token.is_none());
+    }
+}
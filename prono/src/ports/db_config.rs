@@ -1,3 +1,6 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
 use secure_string::SecureString;
 use serde::Deserialize;
 
@@ -9,6 +12,46 @@ pub struct Config {
     pub port: SecureString,
     pub user: SecureString,
     pub pass: SecureString,
+    #[serde(default)]
+    pub ssl_mode: SslMode,
+    /// PEM file with the CA certificate(s) used to verify the database server.
+    pub ca_cert: Option<PathBuf>,
+    /// PEM file with the client certificate, only needed when the server requires client authentication.
+    pub client_cert: Option<PathBuf>,
+    /// PEM file with the private key belonging to `client_cert`.
+    pub client_key: Option<PathBuf>,
+}
+
+/// Security state of the database connection, following the MySQL `ssl-mode` option.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SslMode {
+    /// Unencrypted connection.
+    Disabled,
+    /// Encrypted if the server supports it, unencrypted otherwise.
+    #[default]
+    Preferred,
+    /// Encrypted, fails if the server does not support TLS.
+    Required,
+    /// Like `Required`, and the server certificate must be signed by `ca_cert`.
+    VerifyCa,
+    /// Like `VerifyCa`, and the server certificate must match the host name.
+    VerifyIdentity,
+}
+
+impl FromStr for SslMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "_").as_str() {
+            "disabled" => Ok(Self::Disabled),
+            "preferred" => Ok(Self::Preferred),
+            "required" => Ok(Self::Required),
+            "verify_ca" => Ok(Self::VerifyCa),
+            "verify_identity" => Ok(Self::VerifyIdentity),
+            _ => Err(format!("unknown ssl mode '{s}'")),
+        }
+    }
 }
 
 fn deserialize_as_u16<'de, D>(deserializer: D) -> Result<SecureString, D::Error>
@@ -33,15 +76,32 @@ impl Config {
         if let Some(pass) = overrides.pass {
             self.pass = pass;
         }
+        if let Some(ssl_mode) = overrides.ssl_mode {
+            self.ssl_mode = ssl_mode;
+        }
+        if let Some(ca_cert) = overrides.ca_cert {
+            self.ca_cert = Some(ca_cert);
+        }
+        if let Some(client_cert) = overrides.client_cert {
+            self.client_cert = Some(client_cert);
+        }
+        if let Some(client_key) = overrides.client_key {
+            self.client_key = Some(client_key);
+        }
     }
 }
 
-#[cfg_attr(test, derive(Clone, Default))]
+#[derive(Default)]
+#[cfg_attr(test, derive(Clone))]
 pub struct Overrides {
     pub host: Option<SecureString>,
     pub port: Option<SecureString>,
     pub user: Option<SecureString>,
     pub pass: Option<SecureString>,
+    pub ssl_mode: Option<SslMode>,
+    pub ca_cert: Option<PathBuf>,
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
 }
 
 impl TryFrom<Overrides> for Config {
@@ -53,6 +113,10 @@ impl TryFrom<Overrides> for Config {
             port: overrides.port.ok_or("port override is missing")?,
             user: overrides.user.ok_or("user override is missing")?,
             pass: overrides.pass.ok_or("pass override is missing")?,
+            ssl_mode: overrides.ssl_mode.unwrap_or_default(),
+            ca_cert: overrides.ca_cert,
+            client_cert: overrides.client_cert,
+            client_key: overrides.client_key,
         })
     }
 }
@@ -81,6 +145,7 @@ mod tests {
             port: port_override.map(Into::into),
             user: user_override.map(Into::into),
             pass: pass_override.map(Into::into),
+            ..Overrides::default()
         };
 
         let mut config = Config {
@@ -88,6 +153,10 @@ mod tests {
             port: SecureString::from("5555"),
             user: SecureString::from("user"),
             pass: SecureString::from("password"),
+            ssl_mode: SslMode::default(),
+            ca_cert: None,
+            client_cert: None,
+            client_key: None,
         };
 
         config.apply_overrides(overrides);
@@ -125,9 +194,63 @@ mod tests {
             port: port_override.map(Into::into),
             user: user_override.map(Into::into),
             pass: pass_override.map(Into::into),
+            ..Overrides::default()
         };
 
         let mut config: Config = overrides.clone().try_into().unwrap();
         config.apply_overrides(overrides);
     }
+
+    fn full_overrides() -> Overrides {
+        Overrides {
+            host: Some("localhost".into()),
+            port: Some("5555".into()),
+            user: Some("user".into()),
+            pass: Some("password".into()),
+            ..Overrides::default()
+        }
+    }
+
+    #[test]
+    fn test_tls_options_default_to_preferred_without_certificates() {
+        let config: Config = full_overrides().try_into().unwrap();
+
+        assert_eq!(config.ssl_mode, SslMode::Preferred);
+        assert!(config.ca_cert.is_none());
+        assert!(config.client_cert.is_none());
+        assert!(config.client_key.is_none());
+    }
+
+    #[test]
+    fn test_tls_overrides_are_applied() {
+        let mut config: Config = full_overrides().try_into().unwrap();
+
+        config.apply_overrides(Overrides {
+            ssl_mode: Some(SslMode::VerifyCa),
+            ca_cert: Some(PathBuf::from("/etc/prono/ca.pem")),
+            client_cert: Some(PathBuf::from("/etc/prono/client.pem")),
+            client_key: Some(PathBuf::from("/etc/prono/client.key")),
+            ..Overrides::default()
+        });
+
+        assert_eq!(config.ssl_mode, SslMode::VerifyCa);
+        assert_eq!(config.ca_cert, Some(PathBuf::from("/etc/prono/ca.pem")));
+        assert_eq!(config.client_cert, Some(PathBuf::from("/etc/prono/client.pem")));
+        assert_eq!(config.client_key, Some(PathBuf::from("/etc/prono/client.key")));
+    }
+
+    #[rstest]
+    #[case("disabled", SslMode::Disabled)]
+    #[case("PREFERRED", SslMode::Preferred)]
+    #[case("required", SslMode::Required)]
+    #[case("verify_ca", SslMode::VerifyCa)]
+    #[case("VERIFY-IDENTITY", SslMode::VerifyIdentity)]
+    fn test_ssl_mode_from_str(#[case] input: &str, #[case] expected: SslMode) {
+        assert_eq!(input.parse::<SslMode>(), Ok(expected));
+    }
+
+    #[test]
+    fn test_ssl_mode_from_str_rejects_unknown_value() {
+        assert!("sometimes".parse::<SslMode>().is_err());
+    }
 }
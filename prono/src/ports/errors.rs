@@ -9,10 +9,44 @@ pub enum Error {
 
     #[error("Device mismatch: username is registered to a different device")]
     DeviceMismatch,
+
+    #[error("Username is already taken")]
+    UserExists,
+
+    /// Carries the ids of the questions both users answered.
+    #[error("Both users answered questions {}", .0.join(", "))]
+    AnswersConflict(Vec<String>),
+
+    #[error("Invalid username or password")]
+    InvalidCredentials,
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("Repository unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("System random number generator failed")]
+    RandomFailed,
 }
 
 pub type PronoResult<T> = std::result::Result<T, Error>;
 
+impl From<Error> for prono_api::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Repository(msg) => prono_api::Error::Server(msg),
+            Error::AnswerExists | Error::AnswersConflict(_) => prono_api::Error::AnswerExists,
+            Error::DeviceMismatch => prono_api::Error::DeviceMismatch,
+            Error::UserExists => prono_api::Error::UserExists,
+            Error::InvalidCredentials => prono_api::Error::InvalidCredentials,
+            Error::InvalidInput(msg) => prono_api::Error::InvalidRequest(msg),
+            Error::Unavailable(msg) => prono_api::Error::Unavailable(msg),
+            err @ Error::RandomFailed => prono_api::Error::Server(err.to_string()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -35,6 +69,12 @@ mod tests {
         assert!(format!("{error}").contains("Device mismatch"));
     }
 
+    #[test]
+    fn test_answers_conflict_error_display() {
+        let error = Error::AnswersConflict(vec!["q1".to_string(), "q2".to_string()]);
+        assert_eq!(format!("{error}"), "Both users answered questions q1, q2");
+    }
+
     #[test]
     fn test_error_equality() {
         assert_eq!(Error::AnswerExists, Error::AnswerExists);
@@ -45,4 +85,37 @@ mod tests {
         );
         assert_ne!(Error::Repository("a".to_string()), Error::Repository("b".to_string()));
     }
+
+    #[test]
+    fn test_into_prono_api_error() {
+        assert_eq!(
+            prono_api::Error::from(Error::AnswerExists),
+            prono_api::Error::AnswerExists
+        );
+        assert_eq!(
+            prono_api::Error::from(Error::DeviceMismatch),
+            prono_api::Error::DeviceMismatch
+        );
+        assert_eq!(prono_api::Error::from(Error::UserExists), prono_api::Error::UserExists);
+        assert_eq!(
+            prono_api::Error::from(Error::AnswersConflict(vec!["q1".to_string()])),
+            prono_api::Error::AnswerExists
+        );
+        assert_eq!(
+            prono_api::Error::from(Error::InvalidCredentials),
+            prono_api::Error::InvalidCredentials
+        );
+        assert_eq!(
+            prono_api::Error::from(Error::InvalidInput("too short".to_string())),
+            prono_api::Error::InvalidRequest("too short".to_string())
+        );
+        assert_eq!(
+            prono_api::Error::from(Error::Repository("db down".to_string())),
+            prono_api::Error::Server("db down".to_string())
+        );
+        assert_eq!(
+            prono_api::Error::from(Error::Unavailable("connecting".to_string())),
+            prono_api::Error::Unavailable("connecting".to_string())
+        );
+    }
 }
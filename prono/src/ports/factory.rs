@@ -1,14 +1,24 @@
-use crate::{ConfigReader, ReadConfig, SecureConfig};
+use crate::{ConfigReader, ReadConfig, SecureConfig, cli_config};
 
 #[must_use]
 pub fn create_config_reader() -> impl ReadConfig<SecureConfig> {
     ConfigReader {}
 }
 
+#[must_use]
+pub fn create_cli_config_reader() -> impl ReadConfig<cli_config::Config> {
+    ConfigReader {}
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
     fn test_can_create_config_reader() {
         let _config_reader = super::create_config_reader();
     }
+
+    #[test]
+    fn test_can_create_cli_config_reader() {
+        let _config_reader = super::create_cli_config_reader();
+    }
 }
@@ -1,11 +1,21 @@
 mod answer;
+mod device;
+mod privacy;
 mod question;
+mod rate_limit;
+mod session;
 mod survey;
 
 pub use answer::*;
+pub use device::*;
+pub use privacy::*;
 pub use question::*;
+pub use rate_limit::*;
+pub use session::*;
 pub use survey::*;
 
+use std::time::Duration;
+
 use async_trait::async_trait;
 
 use crate::PronoResult;
@@ -15,19 +25,84 @@ pub trait Surveys: Send + Sync {
     async fn answer(&self, user: &str, question_id: String) -> Option<Answer>;
     async fn response(&self, user: &str, survey_id: u64) -> Option<Survey>;
     async fn add_answer(&self, user: &str, question_id: String, answer: Answer) -> PronoResult<()>;
+    /// Stores the answer of the user, replacing their previous answer to the question in the same step.
+    async fn replace_answer(&self, user: &str, question_id: String, answer: Answer) -> PronoResult<()>;
+    /// Returns whether the user had answered the question.
+    async fn remove_answer(&self, user: &str, question_id: String) -> PronoResult<bool>;
     async fn all_answers(&self, question_id: String) -> Vec<(String, Answer)>;
 }
 
+/// Answers with when they were given, for exports.
+#[async_trait]
+pub trait AnswerRecords: Send + Sync {
+    /// All answers to the survey, oldest first when the time is known. Answers are not stored by survey, so
+    /// answers to questions of other surveys are included and left to the caller to skip.
+    async fn answer_records(&self, survey_id: u64) -> PronoResult<Vec<AnswerRecord>>;
+}
+
 #[async_trait]
 pub trait Users: Send + Sync {
     async fn all_users(&self) -> PronoResult<Vec<String>>;
+    /// Every name with answers, devices or an account, each spelling once, e.g. both `Sam` and `sam`.
+    async fn all_names(&self) -> PronoResult<Vec<String>>;
+    /// Deletes the answers, devices, account and sessions of the user in one step.
     async fn delete_user(&self, name: &str) -> PronoResult<()>;
+    /// Gives the answers, devices and account of `old` to `new`, normalized as a [`crate::Username`], and ends the
+    /// sessions of `old`. Fails with [`crate::Error::UserExists`] when `new` already answered, registered a device
+    /// or has an account, use [`Self::merge_users`] then, and with [`crate::Error::InvalidInput`] when `new` is
+    /// invalid or `old` itself, or `old` is unknown.
+    async fn rename_user(&self, old: &str, new: &str) -> PronoResult<()>;
+    /// Moves the answers, devices and account of `from` to `into`, which may already exist, and ends the sessions
+    /// of `from`. Fails, changing nothing, with [`crate::Error::AnswersConflict`] when both answered the same
+    /// question, with [`crate::Error::UserExists`] when both have an account, and like [`Self::rename_user`] for
+    /// invalid names.
+    async fn merge_users(&self, from: &str, into: &str) -> PronoResult<()>;
+}
+
+/// Whether surveys accept answers, and who sees their answers. A survey that was never closed is open.
+#[async_trait]
+pub trait SurveyStates: Send + Sync {
+    async fn is_closed(&self, survey_id: u64) -> PronoResult<bool>;
+    async fn set_closed(&self, survey_id: u64, closed: bool) -> PronoResult<()>;
+    async fn privacy(&self, survey_id: u64) -> PronoResult<Privacy>;
+    async fn set_privacy(&self, survey_id: u64, privacy: Privacy) -> PronoResult<()>;
+    /// Records the outcome of a question, resolving a question again replaces its outcome.
+    async fn resolve_question(&self, survey_id: u64, question_id: &str, answer: Answer) -> PronoResult<()>;
+    /// Outcomes of the resolved questions of the survey, in the order they were last resolved.
+    async fn resolutions(&self, survey_id: u64) -> PronoResult<Vec<(String, Answer)>>;
 }
 
 #[async_trait]
 pub trait DeviceRegistry: Send + Sync {
+    /// Adds the device to the devices of the user, registering a device twice keeps the first registration.
     async fn register_device(&self, user: &str, device_id: &str) -> PronoResult<()>;
-    async fn verify_device(&self, user: &str, device_id: &str) -> PronoResult<bool>;
+    async fn list_devices(&self, user: &str) -> PronoResult<Vec<Device>>;
+    /// Returns whether the device was registered.
+    async fn unregister_device(&self, user: &str, device_id: &str) -> PronoResult<bool>;
+    /// Removes all devices of the user at once, returns how many were removed.
+    async fn unregister_devices(&self, user: &str) -> PronoResult<usize>;
+    /// Registers the device if `policy` allows it next to the devices of the user, checked and registered in
+    /// one step so concurrent requests cannot exceed the limit. Returns whether the device is registered.
+    async fn admit_device(&self, user: &str, device_id: &str, policy: DevicePolicy) -> PronoResult<bool>;
+}
+
+/// Token buckets shared by all backend instances using the same store.
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Takes a token from each of the `buckets` by key, or from none of them when one is empty. A bucket seen
+    /// for the first time is full. `now` is the Unix time in milliseconds. Returns the time until the empty
+    /// buckets have a token again if one is empty.
+    async fn take_tokens(&self, buckets: &[(String, RateLimit)], now: u64) -> PronoResult<Option<Duration>>;
+}
+
+#[async_trait]
+pub trait Accounts: Send + Sync {
+    /// Fails with [`crate::Error::UserExists`] when the name is already taken.
+    async fn create_account(&self, user: &str, password_hash: &str) -> PronoResult<()>;
+    async fn password_hash(&self, user: &str) -> PronoResult<Option<String>>;
+    async fn create_session(&self, session: &Session) -> PronoResult<()>;
+    async fn session(&self, token_hash: &str) -> PronoResult<Option<Session>>;
+    async fn delete_session(&self, token_hash: &str) -> PronoResult<()>;
 }
 
 #[async_trait]
@@ -38,4 +113,7 @@ pub trait Db: Surveys + Sized + Send + Sync {
     /// Initialize the DB instance from the provided config. Runs on an
     /// async runtime and returns the constructed DB instance.
     async fn init(config: Self::Config) -> PronoResult<Self>;
+
+    /// Fails unless the repository can currently serve requests.
+    async fn health_check(&self) -> PronoResult<()>;
 }
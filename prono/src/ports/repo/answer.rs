@@ -4,6 +4,16 @@ pub enum Answer {
     PredictionDate { day: Option<u8>, month: u8, year: u16 },
 }
 
+/// An answer of a user to a question.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnswerRecord {
+    pub user: String,
+    pub question_id: String,
+    pub answer: Answer,
+    /// Unix time in seconds, `None` for answers stored before the time was recorded.
+    pub answered_at: Option<u64>,
+}
+
 #[cfg(test)]
 impl Default for Answer {
     fn default() -> Self {
@@ -0,0 +1,47 @@
+/// Device a user submits answers from, identified by the id the client generated on first run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Device {
+    pub id: String,
+    /// Unix time in seconds.
+    pub registered_at: u64,
+}
+
+/// How many devices a user may submit answers from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DevicePolicy {
+    pub max_devices: usize,
+}
+
+impl DevicePolicy {
+    /// Whether `device_id` may be used next to the already registered `devices`.
+    #[must_use]
+    pub fn allows(&self, devices: &[Device], device_id: &str) -> bool {
+        devices.iter().any(|device| device.id == device_id) || devices.len() < self.max_devices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(id: &str) -> Device {
+        Device {
+            id: id.to_string(),
+            registered_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_registered_device_is_always_allowed() {
+        let policy = DevicePolicy { max_devices: 1 };
+        assert!(policy.allows(&[device("laptop")], "laptop"));
+    }
+
+    #[test]
+    fn test_new_device_is_allowed_below_limit() {
+        let policy = DevicePolicy { max_devices: 2 };
+        assert!(policy.allows(&[], "laptop"));
+        assert!(policy.allows(&[device("laptop")], "phone"));
+        assert!(!policy.allows(&[device("laptop"), device("phone")], "tablet"));
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Who sees the individual answers of a survey. A survey without a stored privacy is public.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Privacy {
+    #[default]
+    Public,
+    Pseudonymous,
+    AggregateOnly,
+}
+
+impl Privacy {
+    /// Name under which the privacy is stored.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Privacy::Public => "public",
+            Privacy::Pseudonymous => "pseudonymous",
+            Privacy::AggregateOnly => "aggregate_only",
+        }
+    }
+}
+
+impl fmt::Display for Privacy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Privacy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "public" => Ok(Privacy::Public),
+            "pseudonymous" => Ok(Privacy::Pseudonymous),
+            "aggregate_only" => Ok(Privacy::AggregateOnly),
+            _ => Err(format!("unknown survey privacy '{s}'")),
+        }
+    }
+}
+
+impl From<prono_api::Privacy> for Privacy {
+    fn from(privacy: prono_api::Privacy) -> Self {
+        match privacy {
+            prono_api::Privacy::Public => Privacy::Public,
+            prono_api::Privacy::Pseudonymous => Privacy::Pseudonymous,
+            prono_api::Privacy::AggregateOnly => Privacy::AggregateOnly,
+        }
+    }
+}
+
+impl From<Privacy> for prono_api::Privacy {
+    fn from(privacy: Privacy) -> Self {
+        match privacy {
+            Privacy::Public => prono_api::Privacy::Public,
+            Privacy::Pseudonymous => prono_api::Privacy::Pseudonymous,
+            Privacy::AggregateOnly => prono_api::Privacy::AggregateOnly,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stored_name_round_trips() {
+        for privacy in [Privacy::Public, Privacy::Pseudonymous, Privacy::AggregateOnly] {
+            assert_eq!(privacy.as_str().parse(), Ok(privacy));
+        }
+        assert!("secret".parse::<Privacy>().is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// Thousandths of a token, so that a bucket refills in whole numbers.
+const MILLI_TOKENS_PER_TOKEN: u64 = 1000;
+const MILLIS_PER_MINUTE: u64 = 60 * 1000;
+
+/// Token bucket limit: a bucket holds up to `burst` tokens and regains `per_minute` tokens a minute.
+/// Every request takes one token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub struct RateLimit {
+    pub burst: u32,
+    /// A bucket that never refills would block for good, so a config with `per_minute = 0` is rejected.
+    pub per_minute: NonZeroU32,
+}
+
+/// Fill state of a token bucket.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TokenBucket {
+    /// Tokens left, in thousandths of a token.
+    pub milli_tokens: u64,
+    /// Unix time in milliseconds of the last refill.
+    pub updated_at: u64,
+}
+
+impl TokenBucket {
+    #[must_use]
+    pub fn full(limit: RateLimit, now: u64) -> Self {
+        Self {
+            milli_tokens: u64::from(limit.burst) * MILLI_TOKENS_PER_TOKEN,
+            updated_at: now,
+        }
+    }
+
+    /// Refills the bucket up to `now` (Unix time in milliseconds) and takes a token.
+    ///
+    /// Returns the new fill state, which stays the refilled state when the bucket is empty, and for an
+    /// empty bucket the time until the next token is available.
+    #[must_use]
+    pub fn take(self, limit: RateLimit, now: u64) -> (Self, Option<Duration>) {
+        let refilled = self.refill(limit, now);
+        if let Some(milli_tokens) = refilled.milli_tokens.checked_sub(MILLI_TOKENS_PER_TOKEN) {
+            return (
+                Self {
+                    milli_tokens,
+                    ..refilled
+                },
+                None,
+            );
+        }
+        let missing = MILLI_TOKENS_PER_TOKEN - refilled.milli_tokens;
+        let refill_rate = u64::from(limit.per_minute.get()) * MILLI_TOKENS_PER_TOKEN;
+        let wait = (missing * MILLIS_PER_MINUTE).div_ceil(refill_rate);
+        (refilled, Some(Duration::from_millis(wait)))
+    }
+
+    /// Whether the bucket is full again at `now`, so that forgetting it changes nothing.
+    #[must_use]
+    pub fn is_full_at(self, limit: RateLimit, now: u64) -> bool {
+        self.refill(limit, now).milli_tokens >= u64::from(limit.burst) * MILLI_TOKENS_PER_TOKEN
+    }
+
+    /// Takes a token from each of the `buckets`, or from none of them when one is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns the longest time until an empty bucket has a token again if any bucket is empty.
+    pub fn take_all(buckets: &[(Self, RateLimit)], now: u64) -> Result<Vec<Self>, Duration> {
+        let (taken, waits): (Vec<_>, Vec<_>) = buckets.iter().map(|(bucket, limit)| bucket.take(*limit, now)).unzip();
+        match waits.into_iter().flatten().max() {
+            Some(wait) => Err(wait),
+            None => Ok(taken),
+        }
+    }
+
+    fn refill(self, limit: RateLimit, now: u64) -> Self {
+        let capacity = u64::from(limit.burst) * MILLI_TOKENS_PER_TOKEN;
+        let refill_rate = u64::from(limit.per_minute.get()) * MILLI_TOKENS_PER_TOKEN;
+        let elapsed = now.saturating_sub(self.updated_at);
+        Self {
+            milli_tokens: self
+                .milli_tokens
+                .saturating_add(elapsed.saturating_mul(refill_rate) / MILLIS_PER_MINUTE)
+                .min(capacity),
+            updated_at: now.max(self.updated_at),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: RateLimit = RateLimit {
+        burst: 2,
+        per_minute: NonZeroU32::new(6).unwrap(),
+    };
+
+    #[test]
+    fn test_full_bucket_allows_burst_then_asks_to_wait() {
+        let bucket = TokenBucket::full(LIMIT, 0);
+
+        let (bucket, wait) = bucket.take(LIMIT, 0);
+        assert!(wait.is_none());
+        let (bucket, wait) = bucket.take(LIMIT, 0);
+        assert!(wait.is_none());
+        let (_, wait) = bucket.take(LIMIT, 0);
+        assert_eq!(wait, Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_bucket_refills_over_time_up_to_burst() {
+        let empty = TokenBucket {
+            milli_tokens: 0,
+            updated_at: 0,
+        };
+
+        let (_, wait) = empty.take(LIMIT, 4_000);
+        assert_eq!(wait, Some(Duration::from_secs(6)));
+        let (bucket, wait) = empty.take(LIMIT, 10_000);
+        assert!(wait.is_none());
+        assert_eq!(bucket.milli_tokens, 0);
+
+        let (bucket, _) = empty.take(LIMIT, 3_600_000);
+        assert_eq!(bucket.milli_tokens, 1000);
+    }
+
+    #[test]
+    fn test_bucket_is_full_after_refill() {
+        let (bucket, _) = TokenBucket::full(LIMIT, 0).take(LIMIT, 0);
+
+        assert!(!bucket.is_full_at(LIMIT, 0));
+        assert!(bucket.is_full_at(LIMIT, 10_000));
+    }
+
+    #[test]
+    fn test_take_all_takes_from_no_bucket_when_one_is_empty() {
+        let empty = TokenBucket {
+            milli_tokens: 0,
+            updated_at: 0,
+        };
+        let full = TokenBucket::full(LIMIT, 0);
+
+        assert_eq!(
+            TokenBucket::take_all(&[(full, LIMIT), (empty, LIMIT)], 4_000),
+            Err(Duration::from_secs(6))
+        );
+        let taken = TokenBucket::take_all(&[(full, LIMIT), (empty, LIMIT)], 10_000).unwrap();
+        assert_eq!(taken[0].milli_tokens, 1000);
+        assert_eq!(taken[1].milli_tokens, 0);
+    }
+
+    #[test]
+    fn test_clock_going_backwards_does_not_refill() {
+        let bucket = TokenBucket {
+            milli_tokens: 0,
+            updated_at: 10_000,
+        };
+
+        let (bucket, wait) = bucket.take(LIMIT, 0);
+        assert_eq!(wait, Some(Duration::from_secs(10)));
+        assert_eq!(bucket.updated_at, 10_000);
+    }
+}
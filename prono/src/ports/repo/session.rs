@@ -0,0 +1,31 @@
+/// Login session, identified by the hash of the token handed out to the client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Session {
+    pub token_hash: String,
+    pub user: String,
+    /// Unix time in seconds.
+    pub expires_at: u64,
+}
+
+impl Session {
+    #[must_use]
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_expires_at_expiry_time() {
+        let session = Session {
+            token_hash: "hash".to_string(),
+            user: "sam".to_string(),
+            expires_at: 100,
+        };
+        assert!(!session.is_expired(99));
+        assert!(session.is_expired(100));
+    }
+}
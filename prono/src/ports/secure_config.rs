@@ -1,9 +1,11 @@
-use crate::db_config;
+use crate::{backend_config, db_config};
 use serde::Deserialize;
 
 #[derive(Deserialize)]
 pub struct SecureConfig {
     pub db: db_config::Config,
+    #[serde(default)]
+    pub backend: backend_config::Config,
 }
 
 impl SecureConfig {
@@ -12,6 +14,12 @@ impl SecureConfig {
         self.db.apply_overrides(overrides);
         self
     }
+
+    #[must_use]
+    pub fn override_backend_config(mut self, overrides: backend_config::Overrides) -> Self {
+        self.backend.apply_overrides(overrides);
+        self
+    }
 }
 
 #[cfg(test)]
@@ -26,8 +34,10 @@ mod tests {
                 port: Some(SecureString::from("3306")),
                 user: Some(SecureString::from("testuser")),
                 pass: Some(SecureString::from("testpass")),
+                ..db_config::Overrides::default()
             })
             .unwrap(),
+            backend: backend_config::Config::default(),
         }
     }
 
@@ -42,4 +52,15 @@ mod tests {
         // Test that the method returns self for chaining
         let _result = config.override_db_config(overrides);
     }
+
+    #[test]
+    fn test_override_backend_config_enables_tls() {
+        let config = create_test_config().override_backend_config(backend_config::Overrides {
+            tls_cert: Some("cert.pem".into()),
+            tls_key: Some("key.pem".into()),
+            ..backend_config::Overrides::default()
+        });
+
+        assert!(config.backend.tls.is_some());
+    }
 }
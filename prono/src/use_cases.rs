@@ -1,2 +1,6 @@
+pub mod credentials;
 #[cfg(debug_assertions)]
 pub mod fake_db;
+pub mod privacy;
+pub mod stats;
+pub mod users;
@@ -0,0 +1,261 @@
+use std::num::NonZeroU32;
+
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{digest, hmac, pbkdf2};
+
+use crate::repo;
+use crate::{Error, PronoResult, Username};
+
+static PBKDF2_ALGORITHM: pbkdf2::Algorithm = pbkdf2::PBKDF2_HMAC_SHA256;
+const PBKDF2_SCHEME: &str = "pbkdf2-sha256";
+const PBKDF2_ITERATIONS: NonZeroU32 = NonZeroU32::new(100_000).unwrap();
+/// Well-formed hash no password matches, verified for unknown users so a login takes as long as for a known one.
+const DUMMY_PASSWORD_HASH: &str = "pbkdf2-sha256$100000$00000000000000000000000000000000$\
+                                   0000000000000000000000000000000000000000000000000000000000000000";
+const SALT_LEN: usize = 16;
+const TOKEN_LEN: usize = 32;
+/// Letters and digits that cannot be mistaken for each other when typed over, 32 of them.
+const CODE_ALPHABET: &[u8; 32] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_LEN: usize = 8;
+pub const MIN_PASSWORD_LEN: usize = 8;
+pub const MAX_USER_LEN: usize = Username::MAX_LEN;
+
+/// The username the account is created under.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidInput`] if the username breaks the rules of [`Username`], or the password is too short.
+pub fn validate_new_account(user: &str, password: &str) -> PronoResult<Username> {
+    let username = parse_username(user)?;
+    if password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(Error::InvalidInput(format!(
+            "Password must have at least {MIN_PASSWORD_LEN} characters"
+        )));
+    }
+    Ok(username)
+}
+
+/// # Errors
+///
+/// Returns [`Error::InvalidInput`] if the username breaks the rules of [`Username`].
+pub fn parse_username(user: &str) -> PronoResult<Username> {
+    Username::parse(user).map_err(|e| Error::InvalidInput(e.to_string()))
+}
+
+/// Salted PBKDF2 hash of the password, encoded as `pbkdf2-sha256$<iterations>$<salt>$<hash>`.
+///
+/// # Errors
+///
+/// Returns an error if the system random number generator fails.
+pub fn hash_password(password: &str) -> PronoResult<String> {
+    let salt = random_bytes::<SALT_LEN>()?;
+    let mut hash = [0u8; digest::SHA256_OUTPUT_LEN];
+    pbkdf2::derive(
+        PBKDF2_ALGORITHM,
+        PBKDF2_ITERATIONS,
+        &salt,
+        password.as_bytes(),
+        &mut hash,
+    );
+    Ok(format!(
+        "{PBKDF2_SCHEME}${PBKDF2_ITERATIONS}${}${}",
+        hex::encode(salt),
+        hex::encode(hash)
+    ))
+}
+
+/// Checks the password against a hash created by [`hash_password`], in constant time.
+#[must_use]
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    let mut parts = password_hash.split('$');
+    let (Some(PBKDF2_SCHEME), Some(iterations), Some(salt), Some(hash), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+    let (Ok(iterations), Ok(salt), Ok(hash)) = (iterations.parse(), hex::decode(salt), hex::decode(hash)) else {
+        return false;
+    };
+    pbkdf2::verify(PBKDF2_ALGORITHM, iterations, &salt, password.as_bytes(), &hash).is_ok()
+}
+
+/// New random session token, handed out to the client.
+///
+/// # Errors
+///
+/// Returns an error if the system random number generator fails.
+pub fn new_token() -> PronoResult<String> {
+    Ok(hex::encode(random_bytes::<TOKEN_LEN>()?))
+}
+
+/// New random one-time code, short enough for a user to type it over on another device.
+///
+/// # Errors
+///
+/// Returns an error if the system random number generator fails.
+pub fn new_one_time_code() -> PronoResult<String> {
+    Ok(random_bytes::<CODE_LEN>()?
+        .iter()
+        .map(|byte| char::from(CODE_ALPHABET[usize::from(byte % 32)]))
+        .collect())
+}
+
+/// Hex encoded HMAC-SHA256 of `message`, for the server to recognize data it handed out itself.
+#[must_use]
+pub fn sign(secret: &str, message: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hex::encode(hmac::sign(&key, message.as_bytes()))
+}
+
+/// Checks a signature created by [`sign`], in constant time.
+#[must_use]
+pub fn verify_signature(secret: &str, message: &str, signature: &str) -> bool {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hex::decode(signature).is_ok_and(|tag| hmac::verify(&key, message.as_bytes(), &tag).is_ok())
+}
+
+/// Hash under which a session token is stored, so a leaked database does not leak usable tokens.
+#[must_use]
+pub fn token_hash(token: &str) -> String {
+    hex::encode(digest::digest(&digest::SHA256, token.as_bytes()))
+}
+
+/// Creates an account with a hashed password, returns the username the account was created under.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidInput`] for an invalid username or password, [`Error::UserExists`] if the
+/// name is taken, or a repository error.
+pub async fn register(accounts: &dyn repo::Accounts, user: &str, password: &str) -> PronoResult<Username> {
+    let username = validate_new_account(user, password)?;
+    accounts
+        .create_account(username.as_str(), &hash_password(password)?)
+        .await?;
+    Ok(username)
+}
+
+/// Returns the username of the account.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidCredentials`] if the account does not exist or the password is wrong,
+/// or a repository error.
+pub async fn verify_login(accounts: &dyn repo::Accounts, user: &str, password: &str) -> PronoResult<Username> {
+    let username = Username::parse(user).map_err(|_| Error::InvalidCredentials)?;
+    match accounts.password_hash(username.as_str()).await? {
+        Some(hash) if verify_password(password, &hash) => Ok(username),
+        Some(_) => Err(Error::InvalidCredentials),
+        None => {
+            std::hint::black_box(verify_password(password, DUMMY_PASSWORD_HASH));
+            Err(Error::InvalidCredentials)
+        }
+    }
+}
+
+fn random_bytes<const N: usize>() -> PronoResult<[u8; N]> {
+    let mut bytes = [0u8; N];
+    SystemRandom::new().fill(&mut bytes).map_err(|_| Error::RandomFailed)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[test]
+    fn test_password_verifies_against_its_hash() {
+        let hash = hash_password("correct horse").unwrap();
+        assert!(hash.starts_with("pbkdf2-sha256$100000$"));
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("wrong horse", &hash));
+    }
+
+    #[test]
+    fn test_dummy_hash_is_as_costly_as_a_real_one() {
+        let hash = hash_password("password").unwrap();
+        assert_eq!(DUMMY_PASSWORD_HASH.len(), hash.len());
+        assert_eq!(DUMMY_PASSWORD_HASH.rsplitn(3, '$').last(), hash.rsplitn(3, '$').last());
+        assert!(!verify_password("", DUMMY_PASSWORD_HASH));
+    }
+
+    #[test]
+    fn test_same_password_gets_different_salt() {
+        assert_ne!(hash_password("password").unwrap(), hash_password("password").unwrap());
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("plain")]
+    #[case("bcrypt$1$00$00")]
+    #[case("pbkdf2-sha256$x$00$00")]
+    #[case("pbkdf2-sha256$1$zz$00")]
+    #[case("pbkdf2-sha256$1$00$00$extra")]
+    fn test_malformed_hash_never_verifies(#[case] hash: &str) {
+        assert!(!verify_password("password", hash));
+    }
+
+    #[test]
+    fn test_tokens_are_random_and_stored_hashed() {
+        let token = new_token().unwrap();
+        assert_eq!(token.len(), 2 * TOKEN_LEN);
+        assert_ne!(token, new_token().unwrap());
+        assert_eq!(token_hash(&token), token_hash(&token));
+        assert_ne!(token_hash(&token), token);
+    }
+
+    #[test]
+    fn test_signature_verifies_only_for_its_secret_and_message() {
+        let signature = sign("secret", "message");
+        assert!(verify_signature("secret", "message", &signature));
+        assert!(!verify_signature("other", "message", &signature));
+        assert!(!verify_signature("secret", "other", &signature));
+        assert!(!verify_signature("secret", "message", "not hex"));
+    }
+
+    #[test]
+    fn test_one_time_codes_are_random_and_typeable() {
+        let code = new_one_time_code().unwrap();
+        assert_eq!(code.len(), CODE_LEN);
+        assert!(code.bytes().all(|c| CODE_ALPHABET.contains(&c)));
+        assert_ne!(code, new_one_time_code().unwrap());
+    }
+
+    #[rstest]
+    #[case("", "long enough")]
+    #[case("  ", "long enough")]
+    #[case("sam", "short")]
+    #[case(&"x".repeat(MAX_USER_LEN + 1), "long enough")]
+    #[case("sam@home", "long enough")]
+    fn test_invalid_new_account_is_rejected(#[case] user: &str, #[case] password: &str) {
+        assert!(matches!(
+            validate_new_account(user, password),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[cfg(debug_assertions)]
+    #[tokio::test]
+    async fn test_register_and_verify_login() {
+        use crate::fake_db::FakeRepo;
+        use crate::repo::Db as _;
+
+        let repo = FakeRepo::init(()).await.unwrap();
+        let sam = register(&repo, " Sam", "long enough").await.unwrap();
+        assert_eq!(sam.as_str(), "sam");
+
+        assert_eq!(register(&repo, "sam", "another one").await, Err(Error::UserExists));
+        assert_eq!(register(&repo, "SAM ", "another one").await, Err(Error::UserExists));
+        assert_eq!(verify_login(&repo, "sam", "long enough").await, Ok(sam.clone()));
+        assert_eq!(verify_login(&repo, "SAM", "long enough").await, Ok(sam));
+        assert_eq!(
+            verify_login(&repo, "sam", "wrong password").await,
+            Err(Error::InvalidCredentials)
+        );
+        assert_eq!(
+            verify_login(&repo, "nobody", "long enough").await,
+            Err(Error::InvalidCredentials)
+        );
+    }
+}
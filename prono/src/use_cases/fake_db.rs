@@ -3,12 +3,28 @@ use async_trait::async_trait;
 use log::{error, info};
 
 use crate::repo::{self, Answer, Survey};
-use std::collections::HashMap;
+use crate::users;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::Mutex;
 
 pub struct FakeRepo {
     surveys: Mutex<HashMap<String, Survey>>,
-    devices: Mutex<HashMap<String, String>>,
+    devices: Mutex<HashMap<String, Vec<repo::Device>>>,
+    accounts: Mutex<HashMap<String, String>>,
+    sessions: Mutex<HashMap<String, repo::Session>>,
+    closed_surveys: Mutex<HashSet<u64>>,
+    privacy: Mutex<HashMap<u64, repo::Privacy>>,
+    /// Outcomes of resolved questions per survey, oldest first.
+    resolutions: Mutex<HashMap<u64, Vec<(String, Answer)>>>,
+    /// When each answer was given, by user and question id.
+    answered_at: Mutex<HashMap<(String, String), u64>>,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
 }
 
 #[async_trait]
@@ -21,8 +37,18 @@ impl repo::Db for FakeRepo {
         Ok(Self {
             surveys: Mutex::new(HashMap::new()),
             devices: Mutex::new(HashMap::new()),
+            accounts: Mutex::new(HashMap::new()),
+            sessions: Mutex::new(HashMap::new()),
+            closed_surveys: Mutex::new(HashSet::new()),
+            privacy: Mutex::new(HashMap::new()),
+            resolutions: Mutex::new(HashMap::new()),
+            answered_at: Mutex::new(HashMap::new()),
         })
     }
+
+    async fn health_check(&self) -> PronoResult<()> {
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -57,6 +83,10 @@ impl repo::Surveys for FakeRepo {
         }
 
         info!("Adding answer from user {user} for Q:{question_id}");
+        self.answered_at
+            .lock()
+            .await
+            .insert((user.to_string(), question_id.clone()), unix_now());
         user_surveys.questions.push(crate::repo::Question {
             id: question_id,
             answer,
@@ -65,6 +95,39 @@ impl repo::Surveys for FakeRepo {
         Ok(())
     }
 
+    async fn replace_answer(&self, user: &str, question_id: String, answer: Answer) -> PronoResult<()> {
+        let mut surveys = self.surveys.lock().await;
+        let user_surveys = surveys.entry(user.to_string()).or_insert_with(|| Survey {
+            questions: vec![],
+            id: 0,
+            description: None,
+        });
+
+        info!("Replacing answer from user {user} for Q:{question_id}");
+        user_surveys.questions.retain(|q| q.id != question_id);
+        self.answered_at
+            .lock()
+            .await
+            .insert((user.to_string(), question_id.clone()), unix_now());
+        user_surveys.questions.push(crate::repo::Question {
+            id: question_id,
+            answer,
+        });
+        Ok(())
+    }
+
+    async fn remove_answer(&self, user: &str, question_id: String) -> PronoResult<bool> {
+        let mut surveys = self.surveys.lock().await;
+        let Some(survey) = surveys.get_mut(user) else {
+            return Ok(false);
+        };
+        info!("Removing answer from user {user} for Q:{question_id}");
+        let before = survey.questions.len();
+        survey.questions.retain(|q| q.id != question_id);
+        self.answered_at.lock().await.remove(&(user.to_string(), question_id));
+        Ok(survey.questions.len() < before)
+    }
+
     async fn all_answers(&self, question_id: String) -> Vec<(String, Answer)> {
         info!("Fetching all answers for Q:{question_id}");
         self.surveys
@@ -90,36 +153,305 @@ impl repo::Users for FakeRepo {
         Ok(surveys.keys().cloned().collect())
     }
 
+    async fn all_names(&self) -> crate::PronoResult<Vec<String>> {
+        let surveys = self.surveys.lock().await;
+        let devices = self.devices.lock().await;
+        let accounts = self.accounts.lock().await;
+        let names: BTreeSet<&String> = surveys
+            .keys()
+            .chain(
+                devices
+                    .iter()
+                    .filter(|(_, devices)| !devices.is_empty())
+                    .map(|(name, _)| name),
+            )
+            .chain(accounts.keys())
+            .collect();
+        Ok(names.into_iter().cloned().collect())
+    }
+
     async fn delete_user(&self, name: &str) -> crate::PronoResult<()> {
         info!("Deleting user {name}");
         self.surveys.lock().await.remove(name);
+        self.answered_at.lock().await.retain(|(user, _), _| user != name);
+        self.devices.lock().await.remove(name);
+        self.accounts.lock().await.remove(name);
+        self.sessions.lock().await.retain(|_, session| session.user != name);
+        Ok(())
+    }
+
+    async fn rename_user(&self, old: &str, new: &str) -> crate::PronoResult<()> {
+        let new = users::new_name(old, new)?;
+        let new = new.as_str();
+        let mut surveys = self.surveys.lock().await;
+        let mut devices = self.devices.lock().await;
+        let mut accounts = self.accounts.lock().await;
+        if surveys.contains_key(new)
+            || devices.get(new).is_some_and(|devices| !devices.is_empty())
+            || accounts.contains_key(new)
+        {
+            error!("User {new} already exists");
+            return Err(Error::UserExists);
+        }
+        if !Self::is_known(old, &surveys, &devices, &accounts) {
+            return Err(users::unknown_user(old));
+        }
+        info!("Renaming user {old} to {new}");
+        if let Some(survey) = surveys.remove(old) {
+            surveys.insert(new.to_string(), survey);
+        }
+        if let Some(old_devices) = devices.remove(old) {
+            devices.insert(new.to_string(), old_devices);
+        }
+        if let Some(password_hash) = accounts.remove(old) {
+            accounts.insert(new.to_string(), password_hash);
+        }
+        self.move_answer_times(old, new).await;
+        self.sessions.lock().await.retain(|_, session| session.user != old);
+        Ok(())
+    }
+
+    async fn merge_users(&self, from: &str, into: &str) -> crate::PronoResult<()> {
+        let into = users::new_name(from, into)?;
+        let into = into.as_str();
+        let mut surveys = self.surveys.lock().await;
+        let mut devices = self.devices.lock().await;
+        let mut accounts = self.accounts.lock().await;
+        if let (Some(from_survey), Some(into_survey)) = (surveys.get(from), surveys.get(into)) {
+            let conflicts: Vec<String> = from_survey
+                .questions
+                .iter()
+                .filter(|q| into_survey.questions.iter().any(|other| other.id == q.id))
+                .map(|q| q.id.clone())
+                .collect();
+            if !conflicts.is_empty() {
+                error!("Users {from} and {into} both answered {conflicts:?}");
+                return Err(Error::AnswersConflict(conflicts));
+            }
+        }
+        if accounts.contains_key(from) && accounts.contains_key(into) {
+            error!("Users {from} and {into} both have an account");
+            return Err(Error::UserExists);
+        }
+        if !Self::is_known(from, &surveys, &devices, &accounts) {
+            return Err(users::unknown_user(from));
+        }
+        info!("Merging user {from} into {into}");
+        if let Some(from_survey) = surveys.remove(from) {
+            match surveys.get_mut(into) {
+                Some(into_survey) => into_survey.questions.extend(from_survey.questions),
+                None => {
+                    surveys.insert(into.to_string(), from_survey);
+                }
+            }
+        }
+        let from_devices = devices.remove(from).unwrap_or_default();
+        let into_devices = devices.entry(into.to_string()).or_default();
+        for device in from_devices {
+            if !into_devices.iter().any(|known| known.id == device.id) {
+                into_devices.push(device);
+            }
+        }
+        if let Some(password_hash) = accounts.remove(from) {
+            accounts.insert(into.to_string(), password_hash);
+        }
+        self.move_answer_times(from, into).await;
+        self.sessions.lock().await.retain(|_, session| session.user != from);
         Ok(())
     }
 }
 
+impl FakeRepo {
+    /// Whether `user` answered, registered a device or has an account.
+    fn is_known(
+        user: &str,
+        surveys: &HashMap<String, Survey>,
+        devices: &HashMap<String, Vec<repo::Device>>,
+        accounts: &HashMap<String, String>,
+    ) -> bool {
+        surveys.contains_key(user)
+            || devices.get(user).is_some_and(|devices| !devices.is_empty())
+            || accounts.contains_key(user)
+    }
+
+    async fn move_answer_times(&self, from: &str, to: &str) {
+        let mut answered_at = self.answered_at.lock().await;
+        let moved: Vec<_> = answered_at.keys().filter(|(user, _)| user == from).cloned().collect();
+        for key in moved {
+            if let Some(time) = answered_at.remove(&key) {
+                answered_at.insert((to.to_string(), key.1), time);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl repo::AnswerRecords for FakeRepo {
+    async fn answer_records(&self, survey_id: u64) -> PronoResult<Vec<repo::AnswerRecord>> {
+        info!("Fetching all answers to survey [{survey_id}]");
+        let answered_at = self.answered_at.lock().await;
+        let mut records: Vec<repo::AnswerRecord> = self
+            .surveys
+            .lock()
+            .await
+            .iter()
+            .flat_map(|(user, survey)| {
+                survey.questions.iter().map(|question| repo::AnswerRecord {
+                    user: user.clone(),
+                    question_id: question.id.clone(),
+                    answer: question.answer.clone(),
+                    answered_at: answered_at.get(&(user.clone(), question.id.clone())).copied(),
+                })
+            })
+            .collect();
+        records.sort_by(|a, b| (a.answered_at, &a.user).cmp(&(b.answered_at, &b.user)));
+        Ok(records)
+    }
+}
+
+#[async_trait]
+impl repo::SurveyStates for FakeRepo {
+    async fn is_closed(&self, survey_id: u64) -> crate::PronoResult<bool> {
+        Ok(self.closed_surveys.lock().await.contains(&survey_id))
+    }
+
+    async fn set_closed(&self, survey_id: u64, closed: bool) -> crate::PronoResult<()> {
+        info!(
+            "Marking survey [{survey_id}] as {}",
+            if closed { "closed" } else { "open" }
+        );
+        let mut closed_surveys = self.closed_surveys.lock().await;
+        if closed {
+            closed_surveys.insert(survey_id);
+        } else {
+            closed_surveys.remove(&survey_id);
+        }
+        Ok(())
+    }
+
+    async fn privacy(&self, survey_id: u64) -> crate::PronoResult<repo::Privacy> {
+        Ok(self.privacy.lock().await.get(&survey_id).copied().unwrap_or_default())
+    }
+
+    async fn set_privacy(&self, survey_id: u64, privacy: repo::Privacy) -> crate::PronoResult<()> {
+        info!("Setting privacy of survey [{survey_id}] to {privacy}");
+        self.privacy.lock().await.insert(survey_id, privacy);
+        Ok(())
+    }
+
+    async fn resolve_question(&self, survey_id: u64, question_id: &str, answer: Answer) -> crate::PronoResult<()> {
+        info!("Resolving question {question_id} of survey [{survey_id}] as {answer}");
+        let mut resolutions = self.resolutions.lock().await;
+        let resolutions = resolutions.entry(survey_id).or_default();
+        resolutions.retain(|(id, _)| id != question_id);
+        resolutions.push((question_id.to_string(), answer));
+        Ok(())
+    }
+
+    async fn resolutions(&self, survey_id: u64) -> crate::PronoResult<Vec<(String, Answer)>> {
+        Ok(self
+            .resolutions
+            .lock()
+            .await
+            .get(&survey_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
 #[async_trait]
 impl repo::DeviceRegistry for FakeRepo {
     async fn register_device(&self, user: &str, device_id: &str) -> crate::PronoResult<()> {
+        let mut devices = self.devices.lock().await;
+        let devices = devices.entry(user.to_string()).or_default();
+        if devices.iter().any(|device| device.id == device_id) {
+            return Ok(());
+        }
         info!("Registering device {device_id} for user {user}");
-        self.devices
+        devices.push(repo::Device {
+            id: device_id.to_string(),
+            registered_at: unix_now(),
+        });
+        Ok(())
+    }
+
+    async fn list_devices(&self, user: &str) -> crate::PronoResult<Vec<repo::Device>> {
+        Ok(self.devices.lock().await.get(user).cloned().unwrap_or_default())
+    }
+
+    async fn unregister_device(&self, user: &str, device_id: &str) -> crate::PronoResult<bool> {
+        let mut devices = self.devices.lock().await;
+        let Some(devices) = devices.get_mut(user) else {
+            return Ok(false);
+        };
+        let before = devices.len();
+        devices.retain(|device| device.id != device_id);
+        Ok(devices.len() < before)
+    }
+
+    async fn unregister_devices(&self, user: &str) -> crate::PronoResult<usize> {
+        Ok(self
+            .devices
             .lock()
             .await
-            .insert(user.to_string(), device_id.to_string());
-        Ok(())
+            .remove(user)
+            .map_or(0, |devices| devices.len()))
     }
 
-    async fn verify_device(&self, user: &str, device_id: &str) -> crate::PronoResult<bool> {
-        let devices = self.devices.lock().await;
-        match devices.get(user) {
-            Some(registered) => Ok(registered == device_id),
-            None => Ok(true), // No device registered yet, allow
+    async fn admit_device(&self, user: &str, device_id: &str, policy: repo::DevicePolicy) -> crate::PronoResult<bool> {
+        let mut devices = self.devices.lock().await;
+        let devices = devices.entry(user.to_string()).or_default();
+        if !policy.allows(devices, device_id) {
+            return Ok(false);
+        }
+        if !devices.iter().any(|device| device.id == device_id) {
+            info!("Registering device {device_id} for user {user}");
+            devices.push(repo::Device {
+                id: device_id.to_string(),
+                registered_at: unix_now(),
+            });
         }
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl repo::Accounts for FakeRepo {
+    async fn create_account(&self, user: &str, password_hash: &str) -> PronoResult<()> {
+        let mut accounts = self.accounts.lock().await;
+        if accounts.contains_key(user) {
+            return Err(Error::UserExists);
+        }
+        info!("Creating account {user}");
+        accounts.insert(user.to_string(), password_hash.to_string());
+        Ok(())
+    }
+
+    async fn password_hash(&self, user: &str) -> PronoResult<Option<String>> {
+        Ok(self.accounts.lock().await.get(user).cloned())
+    }
+
+    async fn create_session(&self, session: &repo::Session) -> PronoResult<()> {
+        self.sessions
+            .lock()
+            .await
+            .insert(session.token_hash.clone(), session.clone());
+        Ok(())
+    }
+
+    async fn session(&self, token_hash: &str) -> PronoResult<Option<repo::Session>> {
+        Ok(self.sessions.lock().await.get(token_hash).cloned())
+    }
+
+    async fn delete_session(&self, token_hash: &str) -> PronoResult<()> {
+        self.sessions.lock().await.remove(token_hash);
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::repo::{Db, DeviceRegistry, Surveys};
+    use crate::repo::{Accounts, AnswerRecords, Db, DeviceRegistry, SurveyStates, Surveys};
 
     use super::*;
 
@@ -127,9 +459,91 @@ mod tests {
         FakeRepo {
             surveys: Mutex::new(HashMap::new()),
             devices: Mutex::new(HashMap::new()),
+            accounts: Mutex::new(HashMap::new()),
+            sessions: Mutex::new(HashMap::new()),
+            closed_surveys: Mutex::new(HashSet::new()),
+            privacy: Mutex::new(HashMap::new()),
+            resolutions: Mutex::new(HashMap::new()),
+            answered_at: Mutex::new(HashMap::new()),
         }
     }
 
+    #[tokio::test]
+    async fn test_replace_answer_stores_new_and_replaces_existing_answers() {
+        let repo = setup();
+        let replaced = Answer::Text("replaced".to_string());
+
+        repo.replace_answer("user1", "q1".to_string(), Answer::default())
+            .await
+            .unwrap();
+        repo.replace_answer("user1", "q1".to_string(), replaced.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            repo.all_answers("q1".to_string()).await,
+            [("user1".to_string(), replaced)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_survey_is_open_until_closed() {
+        let repo = setup();
+        assert!(!repo.is_closed(1).await.unwrap());
+
+        repo.set_closed(1, true).await.unwrap();
+        assert!(repo.is_closed(1).await.unwrap());
+        assert!(!repo.is_closed(2).await.unwrap());
+
+        repo.set_closed(1, false).await.unwrap();
+        assert!(!repo.is_closed(1).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_survey_is_public_until_privacy_is_set() {
+        let repo = setup();
+        assert_eq!(repo.privacy(1).await.unwrap(), repo::Privacy::Public);
+
+        repo.set_privacy(1, repo::Privacy::AggregateOnly).await.unwrap();
+        assert_eq!(repo.privacy(1).await.unwrap(), repo::Privacy::AggregateOnly);
+        assert_eq!(repo.privacy(2).await.unwrap(), repo::Privacy::Public);
+    }
+
+    #[tokio::test]
+    async fn test_answer_records_include_time_of_answer() {
+        let repo = setup();
+        repo.add_answer("user1", "q1".to_string(), Answer::default())
+            .await
+            .unwrap();
+        repo.add_answer("user2", "q1".to_string(), Answer::default())
+            .await
+            .unwrap();
+        repo::Users::delete_user(&repo, "user2").await.unwrap();
+
+        let records = repo.answer_records(1).await.unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].user, "user1");
+        assert_eq!(records[0].question_id, "q1");
+        assert!(records[0].answered_at.is_some_and(|time| time > 0));
+    }
+
+    #[tokio::test]
+    async fn test_removed_answer_can_be_given_again() {
+        let repo = setup();
+        repo.add_answer("user1", "q1".to_string(), Answer::default())
+            .await
+            .unwrap();
+
+        assert!(repo.remove_answer("user1", "q1".to_string()).await.unwrap());
+        assert!(!repo.remove_answer("user1", "q1".to_string()).await.unwrap());
+        assert!(!repo.remove_answer("user2", "q1".to_string()).await.unwrap());
+
+        let text = Answer::Text("again".to_string());
+        repo.add_answer("user1", "q1".to_string(), text.clone()).await.unwrap();
+        assert_eq!(repo.answer("user1", "q1".to_string()).await, Some(text));
+    }
+
     #[tokio::test]
     async fn test_init() {
         let repo = FakeRepo::init(()).await;
@@ -269,6 +683,173 @@ mod tests {
         assert!(users.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_rename_user_moves_answers_and_devices() {
+        let repo = setup();
+        repo.add_answer("Sam", "q1".to_string(), Answer::Text("yes".to_string()))
+            .await
+            .unwrap();
+        repo.register_device("Sam", "device-1").await.unwrap();
+
+        repo::Users::rename_user(&repo, "Sam", "sam").await.unwrap();
+
+        assert_eq!(repo::Users::all_users(&repo,).await.unwrap(), ["sam"]);
+        assert_eq!(
+            repo.answer("sam", "q1".to_string()).await,
+            Some(Answer::Text("yes".to_string()))
+        );
+        assert!(repo.list_devices("Sam").await.unwrap().is_empty());
+        assert_eq!(repo.list_devices("sam").await.unwrap()[0].id, "device-1");
+        assert!(repo.answer_records(0).await.unwrap()[0].answered_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_rename_user_to_existing_user_fails() {
+        let repo = setup();
+        repo.add_answer("Sam", "q1".to_string(), Answer::Text("yes".to_string()))
+            .await
+            .unwrap();
+        repo.register_device("sam", "device-1").await.unwrap();
+
+        assert_eq!(
+            repo::Users::rename_user(&repo, "Sam", "sam").await,
+            Err(Error::UserExists)
+        );
+        assert_eq!(repo::Users::all_users(&repo,).await.unwrap(), ["Sam"]);
+    }
+
+    #[tokio::test]
+    async fn test_rename_user_moves_account_and_ends_sessions() {
+        let repo = setup();
+        repo.create_account("Sam", "hash").await.unwrap();
+        let session = repo::Session {
+            token_hash: "token".to_string(),
+            user: "Sam".to_string(),
+            expires_at: u64::MAX,
+        };
+        repo.create_session(&session).await.unwrap();
+
+        repo::Users::rename_user(&repo, "Sam", "sam").await.unwrap();
+
+        assert_eq!(repo.password_hash("Sam").await.unwrap(), None);
+        assert_eq!(repo.password_hash("sam").await.unwrap().as_deref(), Some("hash"));
+        assert_eq!(repo.session("token").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_rename_user_to_name_with_account_fails() {
+        let repo = setup();
+        repo.add_answer("Sam", "q1".to_string(), Answer::Text("yes".to_string()))
+            .await
+            .unwrap();
+        repo.create_account("bob", "hash").await.unwrap();
+
+        assert_eq!(
+            repo::Users::rename_user(&repo, "Sam", "bob").await,
+            Err(Error::UserExists)
+        );
+        assert_eq!(repo::Users::all_users(&repo).await.unwrap(), ["Sam"]);
+    }
+
+    #[tokio::test]
+    async fn test_rename_unknown_user_or_to_same_name_fails() {
+        let repo = setup();
+        repo.add_answer("sam", "q1".to_string(), Answer::Text("yes".to_string()))
+            .await
+            .unwrap();
+
+        for (old, new) in [("bob", "rob"), ("sam", "Sam"), ("sam", " ")] {
+            assert!(
+                matches!(
+                    repo::Users::rename_user(&repo, old, new).await,
+                    Err(Error::InvalidInput(_))
+                ),
+                "renaming {old} to {new:?} should fail"
+            );
+            assert!(matches!(
+                repo::Users::merge_users(&repo, old, new).await,
+                Err(Error::InvalidInput(_))
+            ));
+        }
+        assert_eq!(repo::Users::all_users(&repo).await.unwrap(), ["sam"]);
+    }
+
+    #[tokio::test]
+    async fn test_merge_users_combines_answers_and_devices() {
+        let repo = setup();
+        repo.add_answer("Sam", "q1".to_string(), Answer::Text("yes".to_string()))
+            .await
+            .unwrap();
+        repo.add_answer("sam", "q2".to_string(), Answer::Text("no".to_string()))
+            .await
+            .unwrap();
+        repo.register_device("Sam", "device-1").await.unwrap();
+        repo.register_device("Sam", "device-2").await.unwrap();
+        repo.register_device("sam", "device-1").await.unwrap();
+
+        repo::Users::merge_users(&repo, "Sam", "sam").await.unwrap();
+
+        assert_eq!(repo::Users::all_users(&repo,).await.unwrap(), ["sam"]);
+        assert_eq!(repo.response("sam", 0).await.unwrap().questions.len(), 2);
+        let devices: Vec<String> = repo
+            .list_devices("sam")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|device| device.id)
+            .collect();
+        assert_eq!(devices, ["device-1", "device-2"]);
+    }
+
+    #[tokio::test]
+    async fn test_merge_users_with_same_question_answered_changes_nothing() {
+        let repo = setup();
+        repo.add_answer("Sam", "q1".to_string(), Answer::Text("yes".to_string()))
+            .await
+            .unwrap();
+        repo.add_answer("sam", "q1".to_string(), Answer::Text("no".to_string()))
+            .await
+            .unwrap();
+        repo.register_device("Sam", "device-1").await.unwrap();
+
+        assert_eq!(
+            repo::Users::merge_users(&repo, "Sam", "sam").await,
+            Err(Error::AnswersConflict(vec!["q1".to_string()]))
+        );
+        assert_eq!(
+            repo.answer("Sam", "q1".to_string()).await,
+            Some(Answer::Text("yes".to_string()))
+        );
+        assert_eq!(repo.list_devices("Sam").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_merge_users_with_two_accounts_changes_nothing() {
+        let repo = setup();
+        repo.create_account("Sam", "old").await.unwrap();
+        repo.create_account("sam", "new").await.unwrap();
+
+        assert_eq!(
+            repo::Users::merge_users(&repo, "Sam", "sam").await,
+            Err(Error::UserExists)
+        );
+        assert_eq!(repo.password_hash("Sam").await.unwrap().as_deref(), Some("old"));
+    }
+
+    #[tokio::test]
+    async fn test_merge_users_moves_the_only_account() {
+        let repo = setup();
+        repo.create_account("Sam", "hash").await.unwrap();
+        repo.add_answer("sam", "q1".to_string(), Answer::Text("yes".to_string()))
+            .await
+            .unwrap();
+
+        repo::Users::merge_users(&repo, "Sam", "sam").await.unwrap();
+
+        assert_eq!(repo.password_hash("Sam").await.unwrap(), None);
+        assert_eq!(repo.password_hash("sam").await.unwrap().as_deref(), Some("hash"));
+    }
+
     #[tokio::test]
     async fn test_all_answers_multiple_users() {
         let repo = setup();
@@ -303,23 +884,91 @@ mod tests {
         assert_eq!(results.len(), 2);
     }
 
+    const ONE_DEVICE: repo::DevicePolicy = repo::DevicePolicy { max_devices: 1 };
+
+    #[tokio::test]
+    async fn test_register_and_admit_device() {
+        let repo = setup();
+        repo.register_device("alice", "device-123").await.unwrap();
+        assert!(repo.admit_device("alice", "device-123", ONE_DEVICE).await.unwrap());
+        assert_eq!(repo.list_devices("alice").await.unwrap().len(), 1);
+    }
+
     #[tokio::test]
-    async fn test_register_and_verify_device() {
+    async fn test_admit_device_wrong_id() {
         let repo = setup();
         repo.register_device("alice", "device-123").await.unwrap();
-        assert!(repo.verify_device("alice", "device-123").await.unwrap());
+        assert!(!repo.admit_device("alice", "device-456", ONE_DEVICE).await.unwrap());
+        assert_eq!(repo.list_devices("alice").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_admit_device_no_registration_registers_it() {
+        let repo = setup();
+        assert!(repo.admit_device("alice", "any-device", ONE_DEVICE).await.unwrap());
+        assert_eq!(repo.list_devices("alice").await.unwrap()[0].id, "any-device");
     }
 
     #[tokio::test]
-    async fn test_verify_device_wrong_id() {
+    async fn test_unregister_devices_removes_all() {
         let repo = setup();
         repo.register_device("alice", "device-123").await.unwrap();
-        assert!(!repo.verify_device("alice", "device-456").await.unwrap());
+        repo.register_device("alice", "device-456").await.unwrap();
+        repo.register_device("bob", "device-789").await.unwrap();
+
+        assert_eq!(repo.unregister_devices("alice").await.unwrap(), 2);
+        assert_eq!(repo.unregister_devices("alice").await.unwrap(), 0);
+        assert_eq!(repo.list_devices("bob").await.unwrap().len(), 1);
     }
 
     #[tokio::test]
-    async fn test_verify_device_no_registration() {
+    async fn test_register_device_twice_keeps_one_registration() {
         let repo = setup();
-        assert!(repo.verify_device("alice", "any-device").await.unwrap());
+        repo.register_device("alice", "device-123").await.unwrap();
+        repo.register_device("alice", "device-123").await.unwrap();
+        repo.register_device("alice", "device-456").await.unwrap();
+
+        let ids: Vec<String> = repo
+            .list_devices("alice")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|device| device.id)
+            .collect();
+        assert_eq!(ids, ["device-123", "device-456"]);
+    }
+
+    #[tokio::test]
+    async fn test_unregister_device_frees_a_slot() {
+        let repo = setup();
+        repo.register_device("alice", "device-123").await.unwrap();
+
+        assert!(repo.unregister_device("alice", "device-123").await.unwrap());
+        assert!(!repo.unregister_device("alice", "device-123").await.unwrap());
+        assert!(repo.list_devices("alice").await.unwrap().is_empty());
+        assert!(repo.admit_device("alice", "device-456", ONE_DEVICE).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_create_account_twice_fails() {
+        let repo = setup();
+        repo.create_account("sam", "hash").await.unwrap();
+        assert_eq!(repo.create_account("sam", "other").await, Err(Error::UserExists));
+        assert_eq!(repo.password_hash("sam").await.unwrap().as_deref(), Some("hash"));
+    }
+
+    #[tokio::test]
+    async fn test_session_lifecycle() {
+        let repo = setup();
+        let session = repo::Session {
+            token_hash: "token-hash".to_string(),
+            user: "sam".to_string(),
+            expires_at: 100,
+        };
+        repo.create_session(&session).await.unwrap();
+        assert_eq!(repo.session("token-hash").await.unwrap(), Some(session));
+
+        repo.delete_session("token-hash").await.unwrap();
+        assert_eq!(repo.session("token-hash").await.unwrap(), None);
     }
 }
@@ -0,0 +1,72 @@
+use ring::hmac;
+
+use crate::repo;
+
+const ALIAS_PREFIX: &str = "anonymous-";
+/// Bytes of the HMAC in an alias, aliases of two users in a survey of a few thousand users are unlikely to collide.
+const ALIAS_LEN: usize = 4;
+
+/// Alias of `user` in survey `survey_id`, the same for all answers of the user to the survey. Without `secret`,
+/// the alias cannot be traced back to the user, nor matched with the alias of the same user in another survey.
+#[must_use]
+pub fn alias(secret: &str, survey_id: u64, user: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, format!("{survey_id}:{user}").as_bytes());
+    format!("{ALIAS_PREFIX}{}", hex::encode(&tag.as_ref()[..ALIAS_LEN]))
+}
+
+/// Answers of users to a question of survey `survey_id` as `privacy` allows to show them: with usernames, with
+/// aliases, or not at all.
+#[must_use]
+pub fn visible_answers<T>(
+    privacy: repo::Privacy,
+    secret: &str,
+    survey_id: u64,
+    answers: Vec<(String, T)>,
+) -> Option<Vec<(String, T)>> {
+    match privacy {
+        repo::Privacy::Public => Some(answers),
+        repo::Privacy::Pseudonymous => Some(
+            answers
+                .into_iter()
+                .map(|(user, answer)| (alias(secret, survey_id, &user), answer))
+                .collect(),
+        ),
+        repo::Privacy::AggregateOnly => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alias_is_stable_per_survey_and_secret() {
+        let alias = alias("secret", 1, "sam");
+
+        assert!(alias.starts_with(ALIAS_PREFIX));
+        assert_eq!(alias.len(), ALIAS_PREFIX.len() + 2 * ALIAS_LEN);
+        assert_eq!(super::alias("secret", 1, "sam"), alias);
+        assert_ne!(super::alias("secret", 1, "alex"), alias);
+        assert_ne!(super::alias("secret", 2, "sam"), alias);
+        assert_ne!(super::alias("other secret", 1, "sam"), alias);
+    }
+
+    #[test]
+    fn test_visible_answers_follow_privacy() {
+        let answers = vec![("sam".to_string(), 1)];
+
+        assert_eq!(
+            visible_answers(repo::Privacy::Public, "secret", 1, answers.clone()),
+            Some(answers.clone())
+        );
+        assert_eq!(
+            visible_answers(repo::Privacy::Pseudonymous, "secret", 1, answers.clone()),
+            Some(vec![(alias("secret", 1, "sam"), 1)])
+        );
+        assert_eq!(
+            visible_answers(repo::Privacy::AggregateOnly, "secret", 1, answers),
+            None
+        );
+    }
+}
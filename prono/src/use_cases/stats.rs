@@ -0,0 +1,287 @@
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::{Error, PronoResult, repo};
+
+/// Day of the month a prediction without a day counts as, the middle of the month.
+const MID_MONTH_DAY: u32 = 15;
+
+/// Years a prediction may name, wide enough for any honest guess.
+pub const PREDICTION_YEARS: RangeInclusive<u16> = 2000..=2200;
+
+/// Statistics of the answers to one question.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuestionStats {
+    pub question_id: String,
+    /// Answers of any kind.
+    pub answers: usize,
+    /// Statistics of the predicted dates, `None` when no answer is a date.
+    pub dates: Option<DateStats>,
+}
+
+/// Statistics of predicted dates. Predictions without a day count as the 15th of their month.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DateStats {
+    /// Answers that predict an existing date.
+    pub predictions: usize,
+    pub median: NaiveDate,
+    pub mean: NaiveDate,
+    pub first_quartile: NaiveDate,
+    pub third_quartile: NaiveDate,
+    pub earliest: NaiveDate,
+    pub latest: NaiveDate,
+    /// Predictions per year, in order, for every year with a prediction.
+    pub histogram: Vec<(i32, usize)>,
+    /// Predictions per `(year, month)`, in order, for every month with a prediction.
+    pub monthly_histogram: Vec<(i32, u32, usize)>,
+}
+
+impl DateStats {
+    /// # Panics
+    ///
+    /// Panics if `dates` is empty.
+    fn new(mut dates: Vec<NaiveDate>) -> Self {
+        dates.sort_unstable();
+        let days: Vec<i64> = dates.iter().map(|date| i64::from(date.num_days_from_ce())).collect();
+        #[allow(clippy::cast_precision_loss)]
+        let mean = days.iter().sum::<i64>() as f64 / days.len() as f64;
+        let earliest = dates[0];
+        let latest = dates[dates.len() - 1];
+        let mut months = BTreeMap::<(i32, u32), usize>::new();
+        for date in &dates {
+            *months.entry((date.year(), date.month())).or_default() += 1;
+        }
+        let mut years = BTreeMap::<i32, usize>::new();
+        for ((year, _month), count) in &months {
+            *years.entry(*year).or_default() += count;
+        }
+        let histogram = years.into_iter().collect();
+        let monthly_histogram = months
+            .into_iter()
+            .map(|((year, month), count)| (year, month, count))
+            .collect();
+        Self {
+            predictions: dates.len(),
+            median: from_days(quantile(&days, 0.5)),
+            mean: from_days(mean),
+            first_quartile: from_days(quantile(&days, 0.25)),
+            third_quartile: from_days(quantile(&days, 0.75)),
+            earliest,
+            latest,
+            histogram,
+            monthly_histogram,
+        }
+    }
+
+    /// Days from the first to the third quartile, the spread of the middle half of the predictions.
+    #[must_use]
+    pub fn interquartile_days(&self) -> i64 {
+        (self.third_quartile - self.first_quartile).num_days()
+    }
+}
+
+impl QuestionStats {
+    /// Statistics of `answers` to the question `question_id`. Dates that do not exist are left out of the date
+    /// statistics.
+    #[must_use]
+    pub fn new(question_id: String, answers: &[repo::Answer]) -> Self {
+        let dates: Vec<NaiveDate> = answers.iter().filter_map(predicted_date).collect();
+        Self {
+            question_id,
+            answers: answers.len(),
+            dates: (!dates.is_empty()).then(|| DateStats::new(dates)),
+        }
+    }
+}
+
+/// Checks that a predicted date exists and its year is within [`PREDICTION_YEARS`]. Text answers always pass.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidInput`] for a date that does not exist or is out of range.
+pub fn check_answer(answer: &repo::Answer) -> PronoResult<()> {
+    let repo::Answer::PredictionDate { year, .. } = *answer else {
+        return Ok(());
+    };
+    if predicted_date(answer).is_none() {
+        return Err(Error::InvalidInput("The predicted date does not exist".to_string()));
+    }
+    if !PREDICTION_YEARS.contains(&year) {
+        return Err(Error::InvalidInput(format!(
+            "The predicted year must be from {} to {}",
+            PREDICTION_YEARS.start(),
+            PREDICTION_YEARS.end()
+        )));
+    }
+    Ok(())
+}
+
+/// Statistics of the answers to each question of `survey`, in the order of its questions.
+pub async fn survey_stats(db: &(impl repo::Surveys + ?Sized), survey: &prono_api::Survey) -> Vec<QuestionStats> {
+    let mut stats = Vec::with_capacity(survey.questions.len());
+    for question in &survey.questions {
+        let answers: Vec<repo::Answer> = db
+            .all_answers(question.id.clone())
+            .await
+            .into_iter()
+            .map(|(_user, answer)| answer)
+            .collect();
+        stats.push(QuestionStats::new(question.id.clone(), &answers));
+    }
+    stats
+}
+
+fn predicted_date(answer: &repo::Answer) -> Option<NaiveDate> {
+    match *answer {
+        repo::Answer::Text(_) => None,
+        repo::Answer::PredictionDate { day, month, year } => {
+            NaiveDate::from_ymd_opt(i32::from(year), u32::from(month), day.map_or(MID_MONTH_DAY, u32::from))
+        }
+    }
+}
+
+/// Quantile `q` of the sorted `days`, interpolating linearly between the closest ranks.
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+fn quantile(days: &[i64], q: f64) -> f64 {
+    let rank = q * (days.len() - 1) as f64;
+    let below = rank.floor() as usize;
+    let above = rank.ceil() as usize;
+    days[below] as f64 + (days[above] - days[below]) as f64 * (rank - rank.floor())
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn from_days(days: f64) -> NaiveDate {
+    NaiveDate::from_num_days_from_ce_opt(days.round() as i32).expect("within the range of the predicted dates")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn prediction(day: Option<u8>, month: u8, year: u16) -> repo::Answer {
+        repo::Answer::PredictionDate { day, month, year }
+    }
+
+    #[test]
+    fn test_stats_of_predictions() {
+        let answers = [
+            prediction(Some(1), 1, 2030),
+            prediction(Some(1), 1, 2031),
+            prediction(Some(1), 1, 2032),
+            prediction(Some(1), 1, 2035),
+            repo::Answer::Text("never".to_string()),
+        ];
+
+        let stats = QuestionStats::new("q1".to_string(), &answers);
+
+        assert_eq!(stats.answers, 5);
+        let dates = stats.dates.unwrap();
+        assert_eq!(dates.predictions, 4);
+        assert_eq!(dates.earliest, date(2030, 1, 1));
+        assert_eq!(dates.latest, date(2035, 1, 1));
+        // Halfway between 2031-01-01 and 2032-01-01, 182.5 days after the first, rounded up.
+        assert_eq!(dates.median, date(2031, 7, 3));
+        assert_eq!(dates.first_quartile, date(2030, 10, 2));
+        assert_eq!(dates.third_quartile, date(2032, 10, 1));
+        assert_eq!(dates.mean, date(2032, 1, 1));
+        assert_eq!(dates.histogram, [(2030, 1), (2031, 1), (2032, 1), (2035, 1)]);
+        assert_eq!(dates.interquartile_days(), 730);
+    }
+
+    #[test]
+    fn test_prediction_without_day_counts_as_middle_of_month() {
+        let stats = QuestionStats::new("q1".to_string(), &[prediction(None, 5, 2030)]);
+
+        let dates = stats.dates.unwrap();
+        assert_eq!(dates.median, date(2030, 5, 15));
+        assert_eq!(dates.interquartile_days(), 0);
+        assert_eq!(dates.histogram, [(2030, 1)]);
+        assert_eq!(dates.monthly_histogram, [(2030, 5, 1)]);
+    }
+
+    #[test]
+    fn test_histograms_leave_out_months_without_predictions() {
+        let answers = [
+            prediction(Some(30), 11, 2030),
+            prediction(Some(1), 11, 2030),
+            prediction(None, 2, 2031),
+        ];
+
+        let dates = QuestionStats::new("q1".to_string(), &answers).dates.unwrap();
+
+        assert_eq!(dates.monthly_histogram, [(2030, 11, 2), (2031, 2, 1)]);
+        assert_eq!(dates.histogram, [(2030, 2), (2031, 1)]);
+    }
+
+    #[test]
+    fn test_predictions_far_apart_have_small_histograms() {
+        let answers = [prediction(Some(1), 1, 0), prediction(Some(31), 12, u16::MAX)];
+
+        let dates = QuestionStats::new("q1".to_string(), &answers).dates.unwrap();
+
+        assert_eq!(dates.histogram, [(0, 1), (i32::from(u16::MAX), 1)]);
+        assert_eq!(dates.monthly_histogram, [(0, 1, 1), (i32::from(u16::MAX), 12, 1)]);
+    }
+
+    #[test]
+    fn test_check_answer_rejects_impossible_and_out_of_range_dates() {
+        assert!(check_answer(&repo::Answer::Text("soon".to_string())).is_ok());
+        assert!(check_answer(&prediction(None, 5, 2030)).is_ok());
+        assert!(check_answer(&prediction(Some(29), 2, 2032)).is_ok());
+
+        for answer in [
+            prediction(Some(31), 2, 2030),
+            prediction(None, 13, 2030),
+            prediction(None, 0, 2030),
+            prediction(Some(1), 1, 1999),
+            prediction(Some(1), 1, 2201),
+            prediction(None, 12, u16::MAX),
+        ] {
+            assert!(
+                matches!(check_answer(&answer), Err(Error::InvalidInput(_))),
+                "{answer:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_text_and_impossible_dates_have_no_date_stats() {
+        let answers = [repo::Answer::Text("soon".to_string()), prediction(Some(31), 2, 2030)];
+
+        let stats = QuestionStats::new("q1".to_string(), &answers);
+
+        assert_eq!(stats.answers, 2);
+        assert_eq!(stats.dates, None);
+    }
+
+    #[cfg(debug_assertions)]
+    #[tokio::test]
+    async fn test_survey_stats_follow_question_order() {
+        use crate::fake_db::FakeRepo;
+        use crate::repo::{Db as _, Surveys as _};
+
+        let db = FakeRepo::init(()).await.unwrap();
+        let survey = crate::empty_survey();
+        let second = survey.questions[1].id.clone();
+        db.add_answer("sam", second.clone(), prediction(None, 5, 2030))
+            .await
+            .unwrap();
+
+        let stats = survey_stats(&db, &survey).await;
+
+        assert_eq!(stats.len(), survey.questions.len());
+        assert_eq!(stats[0].answers, 0);
+        assert_eq!(stats[1].question_id, second);
+        assert_eq!(stats[1].answers, 1);
+    }
+}
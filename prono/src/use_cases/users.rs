@@ -0,0 +1,152 @@
+use crate::{Error, PronoResult, Username, credentials, repo};
+
+/// The name to rename `old` to or merge `old` into: `new` parsed as a [`Username`], so that users end up under the
+/// name they sign in with.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidInput`] if `new` breaks the rules of [`Username`], or already is `old`.
+pub fn new_name(old: &str, new: &str) -> PronoResult<Username> {
+    let username = credentials::parse_username(new)?;
+    if username.as_str() == old {
+        return Err(Error::InvalidInput(format!("User {old} already has the name {new}")));
+    }
+    Ok(username)
+}
+
+/// Error for renaming or merging `user` when it has no answers, devices or account.
+#[must_use]
+pub fn unknown_user(user: &str) -> Error {
+    Error::InvalidInput(format!("User {user} has no answers, devices or account"))
+}
+
+/// Merges the rows of every name that is not in the form of a [`Username`] into the normalized name, e.g. of
+/// names stored before usernames were normalized. Returns each such name with the name it was merged into, or
+/// why it was left as it is: it is no valid username, or merging failed, e.g. on an answer both gave.
+///
+/// # Errors
+///
+/// Returns an error if the names cannot be read.
+pub async fn normalize_names(users: &(impl repo::Users + ?Sized)) -> PronoResult<Vec<(String, PronoResult<Username>)>> {
+    let mut outcomes = Vec::new();
+    for name in users.all_names().await? {
+        let normalized = match credentials::parse_username(&name) {
+            Ok(normalized) if normalized.as_str() == name => continue,
+            Ok(normalized) => users.merge_users(&name, normalized.as_str()).await.map(|()| normalized),
+            Err(e) => Err(e),
+        };
+        outcomes.push((name, normalized));
+    }
+    Ok(outcomes)
+}
+
+/// What the repositories know about `user`: whether it has an account or answers, and whether it answered every
+/// question of `survey`, read in a single query of the response. `this_device` is left `false`, devices are not
+/// known here.
+///
+/// # Errors
+///
+/// Returns an error if the account cannot be read.
+pub async fn user_status(
+    accounts: &(impl repo::Accounts + ?Sized),
+    surveys: &(impl repo::Surveys + ?Sized),
+    survey: &prono_api::Survey,
+    user: &str,
+) -> PronoResult<prono_api::UserStatus> {
+    let response = surveys.response(user, survey.id).await;
+    let given = response.as_ref().map_or(&[][..], |response| &response.questions[..]);
+    let answered = survey
+        .questions
+        .iter()
+        .filter(|question| given.iter().any(|answer| answer.id == question.id))
+        .count();
+    let completed = !survey.questions.is_empty() && answered == survey.questions.len();
+    Ok(prono_api::UserStatus {
+        exists: answered > 0 || accounts.password_hash(user).await?.is_some(),
+        this_device: false,
+        completed_surveys: if completed { vec![survey.id] } else { Vec::new() },
+    })
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+
+    use crate::fake_db::FakeRepo;
+    use crate::repo::{Accounts as _, Db as _, Surveys as _, Users as _};
+
+    fn answer() -> repo::Answer {
+        repo::Answer::Text("never".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_unknown_user_does_not_exist() {
+        let db = FakeRepo::init(()).await.unwrap();
+
+        let status = user_status(&db, &db, &crate::empty_survey(), "sam").await.unwrap();
+
+        assert_eq!(status, prono_api::UserStatus::default());
+    }
+
+    #[tokio::test]
+    async fn test_account_without_answers_exists() {
+        let db = FakeRepo::init(()).await.unwrap();
+        db.create_account("sam", "hash").await.unwrap();
+
+        let status = user_status(&db, &db, &crate::empty_survey(), "sam").await.unwrap();
+
+        assert!(status.exists);
+        assert!(status.completed_surveys.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_survey_is_completed_once_every_question_is_answered() {
+        let db = FakeRepo::init(()).await.unwrap();
+        let survey = crate::empty_survey();
+        let (last, others) = survey.questions.split_last().unwrap();
+        for question in others {
+            db.add_answer("sam", question.id.clone(), answer()).await.unwrap();
+        }
+
+        let partial = user_status(&db, &db, &survey, "sam").await.unwrap();
+        db.add_answer("sam", last.id.clone(), answer()).await.unwrap();
+        let complete = user_status(&db, &db, &survey, "sam").await.unwrap();
+
+        assert!(partial.exists);
+        assert!(partial.completed_surveys.is_empty());
+        assert_eq!(complete.completed_surveys, vec![survey.id]);
+    }
+
+    #[tokio::test]
+    async fn test_normalize_names_merges_other_spellings() {
+        let db = FakeRepo::init(()).await.unwrap();
+        db.add_answer("Sam", "q1".to_string(), answer()).await.unwrap();
+        db.add_answer("sam", "q2".to_string(), answer()).await.unwrap();
+        db.create_account("STRASSE", "hash").await.unwrap();
+        db.add_answer("Bob", "q1".to_string(), answer()).await.unwrap();
+        db.add_answer("bob", "q1".to_string(), answer()).await.unwrap();
+        db.add_answer("bob@home", "q1".to_string(), answer()).await.unwrap();
+
+        let outcomes = normalize_names(&db).await.unwrap();
+
+        let outcomes: Vec<(&str, Result<&str, &Error>)> = outcomes
+            .iter()
+            .map(|(name, outcome)| (name.as_str(), outcome.as_ref().map(Username::as_str)))
+            .collect();
+        assert!(matches!(outcomes[0], ("Bob", Err(Error::AnswersConflict(_)))));
+        assert_eq!(outcomes[1], ("STRASSE", Ok("strasse")));
+        assert_eq!(outcomes[2], ("Sam", Ok("sam")));
+        assert!(matches!(outcomes[3], ("bob@home", Err(Error::InvalidInput(_)))));
+        assert_eq!(outcomes.len(), 4);
+        assert_eq!(db.password_hash("strasse").await.unwrap().as_deref(), Some("hash"));
+        assert_eq!(db.response("sam", 0).await.unwrap().questions.len(), 2);
+        assert!(db.all_names().await.unwrap().contains(&"Bob".to_string()));
+    }
+
+    #[test]
+    fn test_new_name_is_normalized_and_differs_from_old() {
+        assert_eq!(new_name("Sam", "Sam").unwrap().as_str(), "sam");
+        assert!(matches!(new_name("sam", "Sam"), Err(Error::InvalidInput(_))));
+        assert!(matches!(new_name("sam", ""), Err(Error::InvalidInput(_))));
+    }
+}